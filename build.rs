@@ -0,0 +1,7 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // gRPC query service; needs `protoc` on the PATH
+    tonic_build::compile_protos("proto/vaulta/indexer/v1/indexer.proto")?;
+    // Yellowstone-compatible re-publisher
+    tonic_build::compile_protos("proto/geyser/geyser.proto")?;
+    Ok(())
+}
@@ -0,0 +1,115 @@
+-- Baseline schema; IF NOT EXISTS adopts databases created before migrations
+
+CREATE TABLE IF NOT EXISTS vault_states (
+    vault_address TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    balance BIGINT NOT NULL,
+    assets JSONB NOT NULL DEFAULT '{}',
+    permissions JSONB NOT NULL DEFAULT '[]',
+    last_updated TIMESTAMPTZ NOT NULL,
+    slot BIGINT NOT NULL,
+    write_version BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_states_owner ON vault_states(owner);
+CREATE INDEX IF NOT EXISTS idx_vault_states_slot ON vault_states(slot);
+CREATE INDEX IF NOT EXISTS idx_vault_states_updated ON vault_states(last_updated);
+
+CREATE TABLE IF NOT EXISTS account_updates (
+    id BIGSERIAL PRIMARY KEY,
+    pubkey TEXT NOT NULL,
+    slot BIGINT NOT NULL,
+    write_version BIGINT NOT NULL,
+    data BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_account_updates_pubkey ON account_updates(pubkey);
+CREATE INDEX IF NOT EXISTS idx_account_updates_slot ON account_updates(slot);
+
+CREATE TABLE IF NOT EXISTS vault_tags (
+    vault_address TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (vault_address, tag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_tags_tag ON vault_tags(tag);
+
+CREATE TABLE IF NOT EXISTS saved_filters (
+    name TEXT PRIMARY KEY,
+    description TEXT,
+    filter JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS dead_letter (
+    id BIGSERIAL PRIMARY KEY,
+    pubkey TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    lamports BIGINT NOT NULL,
+    executable BOOLEAN NOT NULL,
+    rent_epoch BIGINT NOT NULL,
+    data BYTEA NOT NULL,
+    slot BIGINT NOT NULL,
+    write_version BIGINT NOT NULL,
+    is_startup BOOLEAN NOT NULL,
+    stage TEXT NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    replayed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_dead_letter_pending ON dead_letter(id) WHERE replayed_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS indexed_slot_ranges (
+    id BIGSERIAL PRIMARY KEY,
+    start_slot BIGINT NOT NULL,
+    end_slot BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_indexed_slot_ranges_end ON indexed_slot_ranges(end_slot);
+
+CREATE TABLE IF NOT EXISTS shard_checkpoints (
+    shard INTEGER PRIMARY KEY,
+    slot BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS vault_repairs (
+    id BIGSERIAL PRIMARY KEY,
+    vault_address TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    previous JSONB,
+    repaired JSONB,
+    repaired_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_repairs_vault ON vault_repairs(vault_address);
+
+CREATE TABLE IF NOT EXISTS backfill_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    program_id TEXT NOT NULL,
+    prefix_offset INTEGER NOT NULL,
+    next_prefix INTEGER NOT NULL DEFAULT 0,
+    pages_done INTEGER NOT NULL DEFAULT 0,
+    accounts_done BIGINT NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'running',
+    error_count INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS slot_gaps (
+    id BIGSERIAL PRIMARY KEY,
+    start_slot BIGINT NOT NULL,
+    end_slot BIGINT NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reconciled_at TIMESTAMPTZ
+);
@@ -0,0 +1,16 @@
+-- Append-only record of every vault state version
+
+CREATE TABLE IF NOT EXISTS vault_state_history (
+    vault_address TEXT NOT NULL,
+    slot BIGINT NOT NULL,
+    write_version BIGINT NOT NULL,
+    owner TEXT NOT NULL,
+    balance BIGINT NOT NULL,
+    assets JSONB NOT NULL DEFAULT '{}',
+    permissions JSONB NOT NULL DEFAULT '[]',
+    last_updated TIMESTAMPTZ NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (vault_address, slot, write_version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_state_history_slot ON vault_state_history(slot);
@@ -0,0 +1,49 @@
+-- Range-partition account_updates by slot, one partition per epoch
+-- (432,000 slots). Existing rows are moved into their partitions.
+
+ALTER TABLE account_updates RENAME TO account_updates_legacy;
+ALTER TABLE account_updates_legacy RENAME CONSTRAINT account_updates_pkey TO account_updates_legacy_pkey;
+ALTER SEQUENCE account_updates_id_seq RENAME TO account_updates_legacy_id_seq;
+DROP INDEX IF EXISTS idx_account_updates_pubkey;
+DROP INDEX IF EXISTS idx_account_updates_slot;
+
+CREATE TABLE account_updates (
+    id BIGSERIAL,
+    pubkey TEXT NOT NULL,
+    slot BIGINT NOT NULL,
+    write_version BIGINT NOT NULL,
+    data BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (id, slot)
+) PARTITION BY RANGE (slot);
+
+CREATE INDEX idx_account_updates_pubkey ON account_updates(pubkey);
+CREATE INDEX idx_account_updates_slot ON account_updates(slot);
+
+-- Create the partition covering a slot if it doesn't exist yet; partitions
+-- are named account_updates_p<n> and cover [n * 432000, (n + 1) * 432000)
+CREATE OR REPLACE FUNCTION ensure_account_updates_partition(p_slot BIGINT) RETURNS TEXT AS $$
+DECLARE
+    width CONSTANT BIGINT := 432000;
+    n BIGINT := p_slot / width;
+    partition_name TEXT := format('account_updates_p%s', n);
+BEGIN
+    IF to_regclass(partition_name) IS NULL THEN
+        EXECUTE format(
+            'CREATE TABLE IF NOT EXISTS %I PARTITION OF account_updates FOR VALUES FROM (%s) TO (%s)',
+            partition_name, n * width, (n + 1) * width
+        );
+    END IF;
+    RETURN partition_name;
+END
+$$ LANGUAGE plpgsql;
+
+SELECT ensure_account_updates_partition(slot)
+FROM (SELECT DISTINCT slot / 432000 * 432000 AS slot FROM account_updates_legacy) AS legacy_slots;
+
+INSERT INTO account_updates (id, pubkey, slot, write_version, data, created_at)
+SELECT id, pubkey, slot, write_version, data, created_at FROM account_updates_legacy;
+
+SELECT setval('account_updates_id_seq', COALESCE((SELECT MAX(id) FROM account_updates_legacy), 0) + 1, false);
+
+DROP TABLE account_updates_legacy;
@@ -0,0 +1,3 @@
+-- Supports pruning vault_state_history by age
+
+CREATE INDEX IF NOT EXISTS idx_vault_state_history_recorded ON vault_state_history(recorded_at);
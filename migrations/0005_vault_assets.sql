@@ -0,0 +1,17 @@
+-- Normalized copy of vault_states.assets, one row per vault and mint,
+-- seeded from the existing JSONB (assets are keyed by mint address)
+
+CREATE TABLE IF NOT EXISTS vault_assets (
+    vault_address TEXT NOT NULL REFERENCES vault_states(vault_address) ON DELETE CASCADE,
+    mint TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    decimals SMALLINT NOT NULL,
+    PRIMARY KEY (vault_address, mint)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_assets_mint ON vault_assets(mint, amount DESC);
+
+INSERT INTO vault_assets (vault_address, mint, amount, decimals)
+SELECT s.vault_address, a.key, (a.value->>'amount')::numeric::bigint, (a.value->>'decimals')::smallint
+FROM vault_states s, jsonb_each(s.assets) AS a
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,4 @@
+-- Index asset keys so containment lookups on vault_states.assets avoid
+-- sequential scans
+
+CREATE INDEX IF NOT EXISTS idx_vault_states_assets ON vault_states USING GIN (assets);
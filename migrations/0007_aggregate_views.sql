@@ -0,0 +1,27 @@
+-- Dashboard aggregates, refreshed concurrently by the indexer; each view
+-- needs a unique index for REFRESH ... CONCURRENTLY
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS mv_owner_totals AS
+SELECT owner, COUNT(*) AS vault_count, SUM(balance) AS total_balance
+FROM vault_states
+GROUP BY owner;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mv_owner_totals_owner ON mv_owner_totals(owner);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS mv_vault_counts AS
+SELECT
+    1 AS id,
+    COUNT(*) AS vault_count,
+    COUNT(DISTINCT owner) AS owner_count,
+    COALESCE(SUM(balance), 0) AS total_balance,
+    COALESCE(MAX(slot), 0) AS max_slot
+FROM vault_states;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mv_vault_counts_id ON mv_vault_counts(id);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS mv_mint_totals AS
+SELECT mint, COUNT(*) AS holder_count, SUM(amount) AS total_amount
+FROM vault_assets
+GROUP BY mint;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mv_mint_totals_mint ON mv_mint_totals(mint);
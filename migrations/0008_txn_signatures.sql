@@ -0,0 +1,10 @@
+-- Record the transaction that produced each account write, where the
+-- validator reports it
+
+ALTER TABLE account_updates ADD COLUMN IF NOT EXISTS txn_signature TEXT;
+ALTER TABLE dead_letter ADD COLUMN IF NOT EXISTS txn_signature TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_account_updates_txn_signature
+    ON account_updates(txn_signature) WHERE txn_signature IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_account_updates_version
+    ON account_updates(pubkey, slot, write_version);
@@ -0,0 +1,15 @@
+-- Run partition creation and aggregate refreshes with the owner's
+-- privileges, so an indexer role without CREATE or ownership can still
+-- perform them. The search path is pinned to the schema being migrated.
+
+ALTER FUNCTION ensure_account_updates_partition(BIGINT)
+    SECURITY DEFINER SET search_path FROM CURRENT;
+
+CREATE OR REPLACE FUNCTION refresh_aggregate_view(p_view TEXT) RETURNS VOID AS $$
+BEGIN
+    IF p_view NOT IN ('mv_owner_totals', 'mv_vault_counts', 'mv_mint_totals') THEN
+        RAISE EXCEPTION 'unknown aggregate view %', p_view;
+    END IF;
+    EXECUTE format('REFRESH MATERIALIZED VIEW CONCURRENTLY %I', p_view);
+END
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path FROM CURRENT;
@@ -0,0 +1,3 @@
+-- Content hash of the parsed state, so writes that leave a vault unchanged
+-- can be skipped
+ALTER TABLE vault_states ADD COLUMN IF NOT EXISTS state_hash BYTEA;
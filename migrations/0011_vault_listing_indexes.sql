@@ -0,0 +1,6 @@
+-- Keyset pagination over vault_states: each sort column paired with the
+-- address tie-breaker, so a page is one backward index range scan
+
+CREATE INDEX IF NOT EXISTS idx_vault_states_balance_address ON vault_states(balance, vault_address);
+CREATE INDEX IF NOT EXISTS idx_vault_states_updated_address ON vault_states(last_updated, vault_address);
+CREATE INDEX IF NOT EXISTS idx_vault_states_slot_address ON vault_states(slot, vault_address);
@@ -0,0 +1,4 @@
+-- Incremental sync reads vault_states in (slot, write_version, address)
+-- order from a cursor
+
+CREATE INDEX IF NOT EXISTS idx_vault_states_sync ON vault_states(slot, write_version, vault_address);
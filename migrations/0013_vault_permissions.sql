@@ -0,0 +1,31 @@
+-- Normalized copy of vault_states.permissions, one row per vault, holder and
+-- role, so access reviews can find a key's vaults by index. Keys are stored
+-- as raw bytes because the JSONB holds them as arrays of 32 byte values;
+-- role_rank orders roles from Viewer (1) to Owner (4)
+
+CREATE TABLE IF NOT EXISTS vault_permissions (
+    vault_address TEXT NOT NULL REFERENCES vault_states(vault_address) ON DELETE CASCADE,
+    pubkey BYTEA NOT NULL,
+    role TEXT NOT NULL,
+    role_rank SMALLINT NOT NULL,
+    PRIMARY KEY (vault_address, pubkey, role)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_permissions_pubkey ON vault_permissions(pubkey, role_rank);
+
+INSERT INTO vault_permissions (vault_address, pubkey, role, role_rank)
+SELECT
+    s.vault_address,
+    (
+        SELECT decode(string_agg(lpad(to_hex(b.value::int), 2, '0'), '' ORDER BY b.ordinality), 'hex')
+        FROM jsonb_array_elements_text(p.value->'pubkey') WITH ORDINALITY AS b
+    ),
+    p.value->>'permission_type',
+    CASE p.value->>'permission_type'
+        WHEN 'Owner' THEN 4
+        WHEN 'Admin' THEN 3
+        WHEN 'Operator' THEN 2
+        ELSE 1
+    END
+FROM vault_states s, jsonb_array_elements(s.permissions) AS p
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,11 @@
+-- Block times reported by the validator, mapping wall-clock time to slots
+-- for time-travel queries
+
+CREATE TABLE IF NOT EXISTS block_metadata (
+    slot BIGINT PRIMARY KEY,
+    blockhash TEXT NOT NULL,
+    block_height BIGINT,
+    block_time TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_block_metadata_time ON block_metadata(block_time, slot);
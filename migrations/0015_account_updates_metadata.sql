@@ -0,0 +1,8 @@
+-- Keep the account metadata delivered with each write, so a raw update can
+-- be replayed through the parser exactly as received. Rows recorded before
+-- this migration have NULLs here
+
+ALTER TABLE account_updates ADD COLUMN IF NOT EXISTS owner TEXT;
+ALTER TABLE account_updates ADD COLUMN IF NOT EXISTS lamports BIGINT;
+ALTER TABLE account_updates ADD COLUMN IF NOT EXISTS executable BOOLEAN;
+ALTER TABLE account_updates ADD COLUMN IF NOT EXISTS rent_epoch BIGINT;
@@ -0,0 +1,29 @@
+-- Owner transitions of vault_states, recorded by trigger so every write
+-- path (upserts, COPY merges, repairs, replication) is covered
+
+CREATE TABLE IF NOT EXISTS owner_changes (
+    id BIGSERIAL PRIMARY KEY,
+    vault_address TEXT NOT NULL,
+    previous_owner TEXT NOT NULL,
+    new_owner TEXT NOT NULL,
+    slot BIGINT NOT NULL,
+    write_version BIGINT NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_owner_changes_vault ON owner_changes(vault_address, slot, write_version);
+
+CREATE OR REPLACE FUNCTION owner_changes_record() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO owner_changes (vault_address, previous_owner, new_owner, slot, write_version)
+    VALUES (NEW.vault_address, OLD.owner, NEW.owner, NEW.slot, NEW.write_version);
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql SET search_path FROM CURRENT;
+
+DROP TRIGGER IF EXISTS owner_changes_on_update ON vault_states;
+CREATE TRIGGER owner_changes_on_update
+    AFTER UPDATE OF owner ON vault_states
+    FOR EACH ROW
+    WHEN (OLD.owner IS DISTINCT FROM NEW.owner)
+    EXECUTE FUNCTION owner_changes_record();
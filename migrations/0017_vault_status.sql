@@ -0,0 +1,35 @@
+-- Closed vaults stay in vault_states as tombstones holding their last
+-- state, rather than being deleted or left looking active. History rows
+-- carry the status too, so point-in-time reads see the closure.
+
+ALTER TABLE vault_states
+    ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'closed')),
+    ADD COLUMN IF NOT EXISTS closed_at_slot BIGINT;
+
+ALTER TABLE vault_state_history
+    ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'active',
+    ADD COLUMN IF NOT EXISTS closed_at_slot BIGINT;
+
+-- Aggregates only count active vaults
+
+DROP MATERIALIZED VIEW IF EXISTS mv_owner_totals;
+CREATE MATERIALIZED VIEW mv_owner_totals AS
+SELECT owner, COUNT(*) AS vault_count, SUM(balance) AS total_balance
+FROM vault_states
+WHERE status = 'active'
+GROUP BY owner;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mv_owner_totals_owner ON mv_owner_totals(owner);
+
+DROP MATERIALIZED VIEW IF EXISTS mv_vault_counts;
+CREATE MATERIALIZED VIEW mv_vault_counts AS
+SELECT
+    1 AS id,
+    COUNT(*) AS vault_count,
+    COUNT(DISTINCT owner) AS owner_count,
+    COALESCE(SUM(balance), 0) AS total_balance,
+    COALESCE(MAX(slot), 0) AS max_slot
+FROM vault_states
+WHERE status = 'active';
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mv_vault_counts_id ON mv_vault_counts(id);
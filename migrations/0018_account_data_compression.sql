@@ -0,0 +1,5 @@
+-- account_updates.data may be stored zstd-compressed; the flag says which
+-- form each row is in, so rows written before and after the setting
+-- changes stay readable
+
+ALTER TABLE account_updates ADD COLUMN IF NOT EXISTS data_compressed BOOLEAN NOT NULL DEFAULT FALSE;
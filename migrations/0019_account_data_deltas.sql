@@ -0,0 +1,6 @@
+-- account_updates.data may be stored as a binary diff against an earlier
+-- write of the same account, named by these columns; NULL means the row
+-- holds the full data
+
+ALTER TABLE account_updates ADD COLUMN IF NOT EXISTS delta_base_slot BIGINT;
+ALTER TABLE account_updates ADD COLUMN IF NOT EXISTS delta_base_write_version BIGINT;
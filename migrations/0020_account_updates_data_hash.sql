@@ -0,0 +1,5 @@
+-- blake3 hash of each write's data bytes, so a redelivered write with the
+-- same version and data can be skipped. Rows recorded before this
+-- migration have NULL here and are never matched
+
+ALTER TABLE account_updates ADD COLUMN IF NOT EXISTS data_hash BYTEA;
@@ -0,0 +1,20 @@
+-- Cold tier for old vault_state_history versions, filled by the retention
+-- task. History reads go through vault_state_history_all, which spans the
+-- hot and archived versions
+
+CREATE TABLE IF NOT EXISTS vault_state_history_archive (
+    LIKE vault_state_history INCLUDING DEFAULTS,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (vault_address, slot, write_version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_state_history_archive_slot ON vault_state_history_archive(slot);
+
+CREATE OR REPLACE VIEW vault_state_history_all AS
+SELECT vault_address, slot, write_version, owner, balance, assets, permissions,
+       last_updated, recorded_at, status, closed_at_slot
+FROM vault_state_history
+UNION ALL
+SELECT vault_address, slot, write_version, owner, balance, assets, permissions,
+       last_updated, recorded_at, status, closed_at_slot
+FROM vault_state_history_archive;
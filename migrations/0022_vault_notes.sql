@@ -0,0 +1,9 @@
+-- Free-form operator notes per vault. The note may be stored encrypted
+-- by the indexer when [encryption] covers vault_notes
+
+CREATE TABLE IF NOT EXISTS vault_notes (
+    vault_address TEXT PRIMARY KEY,
+    note TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
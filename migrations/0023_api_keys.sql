@@ -0,0 +1,10 @@
+-- API keys accepted by the query servers with [auth] database_keys. Only
+-- the SHA-256 of each key is stored; revoked keys are kept for audit
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    name TEXT PRIMARY KEY,
+    key_sha256 BYTEA NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
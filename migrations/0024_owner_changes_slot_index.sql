@@ -0,0 +1,4 @@
+-- The change log reads owner_changes in (slot, write_version, address)
+-- order from a cursor
+
+CREATE INDEX IF NOT EXISTS idx_owner_changes_slot ON owner_changes(slot, write_version);
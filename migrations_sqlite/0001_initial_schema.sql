@@ -0,0 +1,49 @@
+-- SQLite schema for `SqliteStore` (`sqlite` feature): vault states and
+-- shard checkpoints only, everything else stays in Postgres. JSON columns
+-- hold text, and timestamps fixed-width RFC 3339 text in UTC, so they
+-- compare and sort as strings.
+
+CREATE TABLE IF NOT EXISTS vault_states (
+    vault_address TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    balance INTEGER NOT NULL,
+    assets TEXT NOT NULL DEFAULT '{}',
+    permissions TEXT NOT NULL DEFAULT '[]',
+    last_updated TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    write_version INTEGER NOT NULL,
+    state_hash BLOB,
+    status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'closed')),
+    closed_at_slot INTEGER,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f000000Z', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f000000Z', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_states_owner ON vault_states(owner, vault_address);
+CREATE INDEX IF NOT EXISTS idx_vault_states_balance_address ON vault_states(balance, vault_address);
+CREATE INDEX IF NOT EXISTS idx_vault_states_updated_address ON vault_states(last_updated, vault_address);
+CREATE INDEX IF NOT EXISTS idx_vault_states_slot_address ON vault_states(slot, vault_address);
+
+CREATE TABLE IF NOT EXISTS vault_assets (
+    vault_address TEXT NOT NULL REFERENCES vault_states(vault_address) ON DELETE CASCADE,
+    mint TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    decimals INTEGER NOT NULL,
+    PRIMARY KEY (vault_address, mint)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vault_assets_mint ON vault_assets(mint, amount DESC);
+
+CREATE TABLE IF NOT EXISTS vault_permissions (
+    vault_address TEXT NOT NULL REFERENCES vault_states(vault_address) ON DELETE CASCADE,
+    pubkey BLOB NOT NULL,
+    role TEXT NOT NULL,
+    role_rank INTEGER NOT NULL,
+    PRIMARY KEY (vault_address, pubkey, role)
+);
+
+CREATE TABLE IF NOT EXISTS shard_checkpoints (
+    shard INTEGER PRIMARY KEY,
+    slot INTEGER NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f000000Z', 'now'))
+);
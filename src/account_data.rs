@@ -0,0 +1,292 @@
+use crate::database::ACCOUNT_UPDATES_PARTITION_SLOTS;
+use crate::types::{AccountUpdate, RawAccount};
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use std::borrow::{Borrow, Cow};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// zstd level for `account_updates.data`; higher levels gain little on
+/// account data and slow the write path
+const ZSTD_LEVEL: i32 = 3;
+
+/// Unchanged bytes shorter than this between two changed runs are copied
+/// into one patch, since a patch header costs eight bytes
+const MIN_PATCH_GAP: usize = 8;
+
+/// How an account write's data bytes are stored in `account_updates`
+///
+/// Data is optionally stored as a binary delta against the account's
+/// previous write, then optionally zstd-compressed. A delta row names the
+/// version it was diffed against, and a full keyframe is stored every
+/// `keyframe_interval` writes, on the first write of each
+/// `account_updates` partition, and whenever the previous write isn't
+/// known, so a chain never outlives the partition it started in.
+pub struct AccountDataCodec {
+    compress: bool,
+    keyframe_interval: Option<u32>,
+    /// Last stored write per account, the base of its next delta
+    bases: Mutex<HashMap<Pubkey, DeltaBase>>,
+}
+
+struct DeltaBase {
+    slot: u64,
+    write_version: u64,
+    data: Vec<u8>,
+    /// Deltas stored since the last keyframe
+    chain: u32,
+}
+
+/// Data bytes of one write, ready to store
+pub struct StoredAccountData<'a> {
+    pub data: Cow<'a, [u8]>,
+    pub compressed: bool,
+    /// `(slot, write_version)` of the write this is a delta against
+    pub delta_base: Option<(u64, u64)>,
+}
+
+/// An encoded batch; its bases only take effect once `commit` is called
+/// after the rows are stored
+pub struct EncodedBatch<'a> {
+    pub rows: Vec<StoredAccountData<'a>>,
+    bases: HashMap<Pubkey, DeltaBase>,
+}
+
+impl AccountDataCodec {
+    /// `keyframe_interval` enables delta storage
+    pub fn new(compress: bool, keyframe_interval: Option<u32>) -> Self {
+        Self {
+            compress,
+            keyframe_interval: keyframe_interval.map(|interval| interval.max(1)),
+            bases: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Encode the data bytes of a batch of writes, in batch order
+    ///
+    /// A write older than the account's last stored one is kept whole and
+    /// doesn't become a base, so deltas always point backwards.
+    pub fn encode<'a, U: Borrow<AccountUpdate>>(&self, updates: &'a [U]) -> Result<EncodedBatch<'a>> {
+        let mut batch = EncodedBatch {
+            rows: Vec::with_capacity(updates.len()),
+            bases: HashMap::new(),
+        };
+        
+        let Some(keyframe_interval) = self.keyframe_interval else {
+            for update in updates {
+                batch.rows.push(self.finish(Cow::Borrowed(&update.borrow().data[..]), None)?);
+            }
+            self.record_ratio(updates, &batch);
+            return Ok(batch);
+        };
+        
+        let bases = self.bases.lock().unwrap();
+        for update in updates {
+            let update = update.borrow();
+            let version = (update.slot, update.write_version);
+            let base = batch.bases.get(&update.pubkey).or_else(|| bases.get(&update.pubkey));
+            
+            if base.is_some_and(|base| version <= (base.slot, base.write_version)) {
+                batch.rows.push(self.finish(Cow::Borrowed(&update.data[..]), None)?);
+                continue;
+            }
+            
+            let delta = base
+                .filter(|base| base.chain < keyframe_interval)
+                .filter(|base| {
+                    base.slot / ACCOUNT_UPDATES_PARTITION_SLOTS == update.slot / ACCOUNT_UPDATES_PARTITION_SLOTS
+                })
+                .map(|base| (base, diff(&base.data, &update.data)))
+                .filter(|(_, delta)| delta.len() < update.data.len());
+            
+            let (row, chain) = match delta {
+                Some((base, delta)) => (
+                    self.finish(Cow::Owned(delta), Some((base.slot, base.write_version)))?,
+                    base.chain + 1,
+                ),
+                None => (self.finish(Cow::Borrowed(&update.data[..]), None)?, 0),
+            };
+            batch.rows.push(row);
+            batch.bases.insert(
+                update.pubkey,
+                DeltaBase {
+                    slot: update.slot,
+                    write_version: update.write_version,
+                    data: update.data.clone(),
+                    chain,
+                },
+            );
+        }
+        
+        self.record_ratio(updates, &batch);
+        Ok(batch)
+    }
+    
+    /// Make a stored batch's writes the bases of later deltas; a batch
+    /// that failed to store is dropped instead, so nothing refers to it
+    pub fn commit(&self, batch: EncodedBatch<'_>) {
+        if !batch.bases.is_empty() {
+            self.bases.lock().unwrap().extend(batch.bases);
+        }
+    }
+    
+    fn finish<'a>(&self, data: Cow<'a, [u8]>, delta_base: Option<(u64, u64)>) -> Result<StoredAccountData<'a>> {
+        if !self.compress {
+            return Ok(StoredAccountData { data, compressed: false, delta_base });
+        }
+        
+        Ok(StoredAccountData {
+            data: Cow::Owned(zstd::bulk::compress(&data, ZSTD_LEVEL)?),
+            compressed: true,
+            delta_base,
+        })
+    }
+    
+    /// Account data bytes per stored byte across a batch
+    fn record_ratio<U: Borrow<AccountUpdate>>(&self, updates: &[U], batch: &EncodedBatch<'_>) {
+        if !self.compress && self.keyframe_interval.is_none() {
+            return;
+        }
+        
+        let raw_bytes: usize = updates.iter().map(|update| update.borrow().data.len()).sum();
+        let stored_bytes: usize = batch.rows.iter().map(|row| row.data.len()).sum();
+        if stored_bytes > 0 {
+            metrics::histogram!("indexer_account_data_compression_ratio")
+                .record(raw_bytes as f64 / stored_bytes as f64);
+        }
+    }
+}
+
+/// A stored write: account, slot, write version and data hash
+pub type StoredWrite = (Pubkey, u64, u64, [u8; 32]);
+
+/// blake3 hash of a write's data bytes, stored with the row so a
+/// redelivery of the same write can be recognised
+pub fn data_hash(data: &[u8]) -> [u8; 32] {
+    *blake3::hash(data).as_bytes()
+}
+
+/// Drop writes that are byte-identical redeliveries, of a write already
+/// `stored` or earlier in the batch, keeping each write's data hash
+///
+/// Validators redeliver writes on same-slot replays and while the startup
+/// snapshot overlaps the live stream. Only the same version with the same
+/// data counts as a redelivery.
+pub fn drop_redeliveries<'a>(
+    updates: Vec<(&'a AccountUpdate, [u8; 32])>,
+    stored: &HashSet<StoredWrite>,
+) -> Vec<(&'a AccountUpdate, [u8; 32])> {
+    let total = updates.len();
+    let mut seen = HashSet::with_capacity(total);
+    let kept: Vec<_> = updates
+        .into_iter()
+        .filter(|(update, hash)| {
+            let write = (update.pubkey, update.slot, update.write_version, *hash);
+            !stored.contains(&write) && seen.insert(write)
+        })
+        .collect();
+    
+    metrics::counter!("indexer_account_updates_checked_total").increment(total as u64);
+    metrics::counter!("indexer_account_updates_duplicate_total").increment((total - kept.len()) as u64);
+    kept
+}
+
+/// The stored bytes of a write, decompressed; still a delta if the row is
+pub fn decompress(data: Vec<u8>, compressed: bool) -> Result<Vec<u8>> {
+    if compressed {
+        Ok(zstd::stream::decode_all(data.as_slice())?)
+    } else {
+        Ok(data)
+    }
+}
+
+/// Rebuild the data of delta rows from the rows they were diffed against
+///
+/// `rows` holds decompressed writes of one account with their delta
+/// bases, including every base a delta needs; bases sort before the rows
+/// built on them. Only the rows in `slot` are returned.
+pub fn resolve_deltas(mut rows: Vec<(RawAccount, Option<(u64, u64)>)>, slot: u64) -> Result<Vec<RawAccount>> {
+    rows.sort_by_key(|(raw, _)| (raw.slot, raw.write_version));
+    
+    let mut rebuilt: HashMap<(u64, u64), Vec<u8>> = HashMap::new();
+    let mut resolved = Vec::new();
+    for (mut raw, delta_base) in rows {
+        if let Some(base) = delta_base {
+            let Some(base_data) = rebuilt.get(&base) else {
+                anyhow::bail!(
+                    "Write {} of {} at slot {} is a delta against {:?}, which is no longer stored",
+                    raw.write_version, raw.pubkey, raw.slot, base
+                );
+            };
+            raw.data = apply_delta(base_data, &raw.data)?;
+        }
+        
+        rebuilt.insert((raw.slot, raw.write_version), raw.data.clone());
+        if raw.slot == slot {
+            resolved.push(raw);
+        }
+    }
+    
+    Ok(resolved)
+}
+
+/// Patches turning `base` into `data`: the length of `data`, then each
+/// changed run as offset, length and bytes, all lengths little-endian u32
+fn diff(base: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    delta.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    
+    let changed = |i: usize| base.get(i) != Some(&data[i]);
+    let mut i = 0;
+    while i < data.len() {
+        if !changed(i) {
+            i += 1;
+            continue;
+        }
+        
+        let start = i;
+        let mut end = i + 1;
+        let mut probe = end;
+        while probe < data.len() && probe - end < MIN_PATCH_GAP {
+            if changed(probe) {
+                end = probe + 1;
+            }
+            probe += 1;
+        }
+        
+        delta.extend_from_slice(&(start as u32).to_le_bytes());
+        delta.extend_from_slice(&((end - start) as u32).to_le_bytes());
+        delta.extend_from_slice(&data[start..end]);
+        i = end;
+    }
+    
+    delta
+}
+
+fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let read_u32 = |at: usize| -> Result<usize> {
+        let bytes = delta
+            .get(at..at + 4)
+            .ok_or_else(|| anyhow::anyhow!("Truncated account data delta"))?;
+        Ok(u32::from_le_bytes(bytes.try_into()?) as usize)
+    };
+    
+    let len = read_u32(0)?;
+    let mut data = base[..base.len().min(len)].to_vec();
+    data.resize(len, 0);
+    
+    let mut at = 4;
+    while at < delta.len() {
+        let offset = read_u32(at)?;
+        let patch_len = read_u32(at + 4)?;
+        let patch = delta
+            .get(at + 8..at + 8 + patch_len)
+            .ok_or_else(|| anyhow::anyhow!("Truncated account data delta"))?;
+        data.get_mut(offset..offset + patch_len)
+            .ok_or_else(|| anyhow::anyhow!("Account data delta patches past the end of the data"))?
+            .copy_from_slice(patch);
+        at += 8 + patch_len;
+    }
+    
+    Ok(data)
+}
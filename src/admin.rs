@@ -0,0 +1,125 @@
+use crate::api::{ApiError, ClientKey};
+use crate::indexer::Indexer;
+use crate::reload::ReloadReport;
+use crate::utils::parse_pubkey;
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+/// Target of the admin audit log, so it can be filtered or shipped apart
+pub(crate) const AUDIT_TARGET: &str = "audit";
+
+/// Most vaults and owners one eviction may name
+const MAX_EVICTIONS: usize = 1000;
+
+/// Body of `POST /admin/cache/evict`
+#[derive(Deserialize)]
+struct EvictRequest {
+    #[serde(default)]
+    vault_addresses: Vec<String>,
+    #[serde(default)]
+    owners: Vec<String>,
+}
+
+/// Operator routes of the HTTP API
+///
+/// `POST /admin/backfill` starts a `[backfill]` in the background,
+/// `POST /admin/cache/evict` drops vaults and owner indexes from the
+/// caches, `POST /admin/keys/reload` reloads `api_keys` ahead of its
+/// refresh and `POST /admin/reload` re-reads the config file, applying its
+/// hot-safe settings (see `ConfigReloader`). They need the `admin` scope,
+/// which query keys don't carry, so they're only served with `[auth]` on.
+/// Every request is logged under the `audit` target with the key that
+/// made it, as is every one refused for want of an admin key.
+pub(crate) fn router<S: Clone + Send + Sync + 'static>(indexer: Arc<Indexer>) -> Router<S> {
+    Router::new()
+        .route("/admin/backfill", post(start_backfill))
+        .route("/admin/cache/evict", post(evict_cache))
+        .route("/admin/keys/reload", post(reload_keys))
+        .route("/admin/reload", post(reload_config))
+        .route_layer(middleware::from_fn(audit))
+        .with_state(indexer)
+}
+
+async fn start_backfill(State(indexer): State<Arc<Indexer>>) -> Result<Response, ApiError> {
+    match indexer.start_backfill() {
+        Ok(true) => Ok((StatusCode::ACCEPTED, Json(json!({ "status": "started" }))).into_response()),
+        Ok(false) => Err(ApiError(StatusCode::CONFLICT, "A backfill is already running".to_string())),
+        Err(e) => Err(ApiError(StatusCode::CONFLICT, e.to_string())),
+    }
+}
+
+async fn evict_cache(
+    State(indexer): State<Arc<Indexer>>,
+    Json(request): Json<EvictRequest>,
+) -> Result<Json<Value>, ApiError> {
+    if request.vault_addresses.len() + request.owners.len() > MAX_EVICTIONS {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("An eviction may name at most {} vaults and owners", MAX_EVICTIONS),
+        ));
+    }
+    for address in request.vault_addresses.iter().chain(&request.owners) {
+        parse_pubkey(address).map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?;
+    }
+    
+    indexer.evict_cached(&request.vault_addresses, &request.owners).await?;
+    Ok(Json(json!({
+        "evicted_vaults": request.vault_addresses.len(),
+        "evicted_owners": request.owners.len(),
+    })))
+}
+
+async fn reload_keys(State(indexer): State<Arc<Indexer>>) -> Result<Json<Value>, ApiError> {
+    let Some(keys) = indexer.api_keys() else {
+        return Err(ApiError(StatusCode::CONFLICT, "[auth] is off".to_string()));
+    };
+    match keys.reload_stored().await? {
+        Some(count) => Ok(Json(json!({ "database_keys": count }))),
+        None => Err(ApiError(StatusCode::CONFLICT, "[auth] database_keys is off".to_string())),
+    }
+}
+
+async fn reload_config(State(indexer): State<Arc<Indexer>>) -> Result<Json<ReloadReport>, ApiError> {
+    let Some(reloader) = indexer.config_reloader() else {
+        return Err(ApiError(StatusCode::CONFLICT, "The indexer wasn't started from a config file".to_string()));
+    };
+    // The file is the operator's to fix, so its errors are theirs to see
+    let report = reloader
+        .reload(&indexer)
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    Ok(Json(report))
+}
+
+/// Log each admin request with its key and outcome; refusals are logged by
+/// the authentication middleware, since they never get here
+async fn audit(request: Request, next: Next) -> Response {
+    let key = request
+        .extensions()
+        .get::<ClientKey>()
+        .map(|ClientKey(name)| name.clone())
+        .unwrap_or_default();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    
+    let response = next.run(request).await;
+    info!(
+        target: AUDIT_TARGET,
+        "Admin {} {} by key '{}': {}",
+        method,
+        path,
+        key,
+        response.status()
+    );
+    metrics::counter!("indexer_admin_requests_total", "status" => response.status().as_str().to_string())
+        .increment(1);
+    
+    response
+}
@@ -0,0 +1,86 @@
+use crate::database::{Database, AGGREGATE_VIEWS};
+use crate::types::{AggregatesConfig, TotalValueLocked};
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, error};
+
+/// Refreshes the aggregate materialized views on a schedule
+///
+/// Views are refreshed concurrently, so dashboards keep reading the previous
+/// contents while a refresh runs. Each refresh's duration is recorded in the
+/// `indexer_aggregate_refresh_seconds` histogram, labelled by view.
+pub struct AggregateRefresher {
+    database: Arc<Database>,
+    refresh_interval: Duration,
+}
+
+impl AggregateRefresher {
+    pub fn new(config: &AggregatesConfig, database: Arc<Database>) -> Self {
+        Self {
+            database,
+            refresh_interval: Duration::from_secs(config.refresh_interval_secs.max(1)),
+        }
+    }
+    
+    pub async fn run(self) {
+        let mut ticker = interval(self.refresh_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        
+        loop {
+            ticker.tick().await;
+            
+            for view in AGGREGATE_VIEWS {
+                let start = Instant::now();
+                match self.database.refresh_aggregate_view(view).await {
+                    Ok(()) => {
+                        let elapsed = start.elapsed();
+                        debug!("Refreshed {} in {:?}", view, elapsed);
+                        metrics::histogram!("indexer_aggregate_refresh_seconds", "view" => view)
+                            .record(elapsed.as_secs_f64());
+                    }
+                    Err(e) => {
+                        error!("Failed to refresh {}: {}", view, e);
+                        metrics::counter!("indexer_aggregate_refresh_failures_total", "view" => view)
+                            .increment(1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Total value locked, recomputed at most once per `tvl_cache_secs`
+///
+/// The lock is held while recomputing, so concurrent callers with an
+/// expired entry wait for one query instead of each scanning the tables.
+pub(crate) struct TvlCache {
+    database: Arc<Database>,
+    ttl: Duration,
+    latest: Mutex<Option<(Instant, TotalValueLocked)>>,
+}
+
+impl TvlCache {
+    pub fn new(config: &AggregatesConfig, database: Arc<Database>) -> Self {
+        Self {
+            database,
+            ttl: Duration::from_secs(config.tvl_cache_secs),
+            latest: Mutex::new(None),
+        }
+    }
+    
+    pub async fn get(&self) -> Result<TotalValueLocked> {
+        let mut latest = self.latest.lock().await;
+        if let Some((computed, tvl)) = latest.as_ref() {
+            if computed.elapsed() < self.ttl {
+                return Ok(tvl.clone());
+            }
+        }
+        
+        let tvl = self.database.get_total_value_locked().await?;
+        *latest = Some((Instant::now(), tvl.clone()));
+        Ok(tvl)
+    }
+}
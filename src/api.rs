@@ -0,0 +1,609 @@
+use crate::admin::{self, AUDIT_TARGET};
+use crate::auth::{self, ApiKeys, AuthError};
+use crate::graphql;
+use crate::indexer::Indexer;
+use crate::openapi::{self, ApiDoc};
+use crate::projection::{self, FieldSelection};
+use crate::rate_limit::{self, Client, RateLimits};
+use crate::readiness::WarmingUp;
+use crate::rpc;
+use crate::sse::{self, EventsQuery};
+use crate::tls::TlsFiles;
+use crate::types::{ApiConfig, ApiScope, ChangeCursor, IndexingStats, PageCursor};
+use crate::utils::parse_pubkey;
+use crate::watermark::SlotNotFlushed;
+use anyhow::{Context, Result};
+use async_graphql_axum::GraphQL;
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, Path, Query, Request, State, WebSocketUpgrade};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Page size of `/owners/{pubkey}/vaults` when none is given
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Read-only HTTP API over the indexer's cache and database read path
+///
+/// Serves `GET /vaults/{address}`, `POST /vaults/batch`,
+/// `GET /owners/{pubkey}/vaults`, `GET /changes` and `GET /stats` as
+/// JSON, so consumers need no Postgres or Redis access of their own.
+/// Vault responses take a `fields=balance,slot` selection, and vault and
+/// owner lookups a `min_slot` to wait for (see `Indexer::read_at_slot`).
+/// `/ws` upgrades to a WebSocket subscription to vault changes, and
+/// `/events` streams the same changes as server-sent events. With
+/// `graphql` on, `/graphql` serves the schema in `graphql.rs`; with
+/// `json_rpc` on, `POST /rpc` answers Solana account methods for vaults.
+/// The REST routes are described at `/openapi.json`, and browsable at
+/// `/docs` with `swagger_ui` on. With `[auth]` on, every route but the
+/// spec and `/docs` needs an API key with the route's scope, and with
+/// `[rate_limit]` on, those routes answer `429` to clients over their
+/// limit. The server speaks HTTPS when given a certificate, and answers
+/// CORS requests from `cors_allowed_origins`. With `[auth]` on, the
+/// operator routes in `admin.rs` are served under `/admin`.
+pub struct ApiServer {
+    config: ApiConfig,
+    indexer: Arc<Indexer>,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    indexer: Arc<Indexer>,
+    max_page_size: i64,
+    max_batch_size: usize,
+    /// Longest wait for a `min_slot`
+    min_slot_timeout: Duration,
+}
+
+#[derive(Deserialize, IntoParams)]
+struct VaultQuery {
+    /// Comma-separated fields to render, such as `balance,slot`; all
+    /// fields when absent
+    fields: Option<String>,
+    /// Answer only once every update up to this slot is indexed, so a
+    /// client sees the transaction it just sent
+    min_slot: Option<u64>,
+}
+
+/// Body of `POST /vaults/batch`
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct BatchRequest {
+    addresses: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BatchResponse {
+    found: Vec<Value>,
+    /// Requested addresses the indexer has no vault for
+    missing: Vec<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+struct OwnerQuery {
+    /// Comma-separated fields to render; all fields when absent
+    fields: Option<String>,
+    /// Page size, 100 by default and at most `max_page_size`
+    limit: Option<i64>,
+    /// `next_cursor` of the previous page
+    cursor: Option<String>,
+    /// Answer only once every update up to this slot is indexed
+    min_slot: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct OwnerVaultsResponse {
+    vaults: Vec<Value>,
+    /// Cursor for the next page; null on the last page
+    next_cursor: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+struct ChangesQuery {
+    /// `next_cursor` of the previous poll
+    cursor: Option<String>,
+    /// Without a cursor, start with the changes after this slot rather
+    /// than at the start of the log
+    after_slot: Option<u64>,
+    /// Most events to return, 100 by default and at most `max_page_size`
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ChangesResponse {
+    events: Vec<Value>,
+    /// Where the next poll continues from, even when no events were found
+    next_cursor: String,
+}
+
+/// Key of a stream request; browsers can't set headers on `EventSource`
+/// or WebSocket connections
+#[derive(Deserialize)]
+struct KeyQuery {
+    api_key: Option<String>,
+}
+
+/// Name of the API key a request presented, for the rate limiter and the
+/// admin audit log
+#[derive(Clone)]
+pub(crate) struct ClientKey(pub(crate) String);
+
+#[derive(Serialize)]
+struct StatsResponse {
+    #[serde(flatten)]
+    stats: IndexingStats,
+    ready: bool,
+}
+
+/// An error response: `{"error": "..."}` with its status
+pub(crate) struct ApiError(pub(crate) StatusCode, pub(crate) String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        if e.is::<WarmingUp>() || e.is::<SlotNotFlushed>() {
+            return ApiError(StatusCode::SERVICE_UNAVAILABLE, e.to_string());
+        }
+        
+        // Query failures stay in the log; clients only learn it failed
+        error!("API query failed: {:#}", e);
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, "internal error".to_string())
+    }
+}
+
+fn bad_request(e: anyhow::Error) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, e.to_string())
+}
+
+impl ApiServer {
+    pub fn new(config: &ApiConfig, indexer: Arc<Indexer>) -> Self {
+        Self {
+            config: config.clone(),
+            indexer,
+        }
+    }
+    
+    /// Serve until the process exits; a failure to bind is logged
+    pub async fn run(self) {
+        if let Err(e) = self.serve().await {
+            error!("API server on {} stopped: {:#}", self.config.bind_address, e);
+        }
+    }
+    
+    async fn serve(&self) -> Result<()> {
+        let state = ApiState {
+            indexer: self.indexer.clone(),
+            max_page_size: self.config.max_page_size.max(1),
+            max_batch_size: self.config.max_batch_size.max(1),
+            min_slot_timeout: Duration::from_millis(self.config.min_slot_timeout_ms),
+        };
+        let mut app = Router::new()
+            .route("/vaults/batch", post(get_vaults_batch))
+            .route("/vaults/:address", get(get_vault))
+            .route("/owners/:owner/vaults", get(get_owner_vaults))
+            .route("/changes", get(get_changes))
+            .route("/stats", get(get_stats))
+            .route("/ws", get(subscribe))
+            .route("/events", get(stream_events));
+        app = if self.config.swagger_ui {
+            app.merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()))
+        } else {
+            app.route("/openapi.json", get(openapi_json))
+        };
+        if self.config.json_rpc {
+            app = app.route("/rpc", post(json_rpc));
+        }
+        if self.config.graphql {
+            let schema = graphql::schema(&self.config, self.indexer.clone());
+            app = app.route_service("/graphql", GraphQL::new(schema));
+        }
+        // Without keys, nothing would tell an operator from anyone else
+        if self.indexer.api_keys().is_some() {
+            app = app.merge(admin::router(self.indexer.clone()));
+        }
+        // Layers added later run first, so keys are checked before limits;
+        // requests refused for their key are charged while authenticating
+        if let Some(limits) = self.indexer.rate_limits() {
+            app = app.route_layer(middleware::from_fn_with_state(limits, limit_rate));
+        }
+        if let Some(keys) = self.indexer.api_keys() {
+            let state = (keys, self.indexer.rate_limits());
+            app = app.route_layer(middleware::from_fn_with_state(state, authenticate));
+        }
+        // Outermost, so preflight requests are answered without a key
+        if let Some(cors) = self.cors()? {
+            app = app.layer(cors);
+        }
+        let app = app.with_state(state).into_make_service_with_connect_info::<SocketAddr>();
+        
+        match (&self.config.tls_cert_path, &self.config.tls_key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                let address: SocketAddr = self
+                    .config
+                    .bind_address
+                    .parse()
+                    .with_context(|| format!("Invalid [api] bind_address {}", self.config.bind_address))?;
+                let tls = TlsFiles::new(cert_path, key_path)
+                    .load(Duration::from_secs(self.config.tls_reload_interval_secs))
+                    .await?;
+                info!("API server listening on {} (TLS)", address);
+                axum_server::bind_rustls(address, tls).serve(app).await?;
+            }
+            (None, None) => {
+                let listener = tokio::net::TcpListener::bind(&self.config.bind_address)
+                    .await
+                    .with_context(|| format!("Failed to bind {}", self.config.bind_address))?;
+                info!("API server listening on {}", self.config.bind_address);
+                axum::serve(listener, app).await?;
+            }
+            _ => anyhow::bail!("[api] tls_cert_path and tls_key_path must be set together"),
+        }
+        Ok(())
+    }
+    
+    /// CORS policy for `cors_allowed_origins`; `None` when it's empty
+    fn cors(&self) -> Result<Option<CorsLayer>> {
+        let origins = &self.config.cors_allowed_origins;
+        if origins.is_empty() {
+            return Ok(None);
+        }
+        
+        let allow_origin = if origins.iter().any(|origin| origin == "*") {
+            AllowOrigin::any()
+        } else {
+            let origins = origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin).with_context(|| format!("Invalid CORS origin {}", origin)))
+                .collect::<Result<Vec<_>>>()?;
+            AllowOrigin::list(origins)
+        };
+        Ok(Some(
+            CorsLayer::new()
+                .allow_origin(allow_origin)
+                .allow_methods([Method::GET, Method::POST])
+                .allow_headers([
+                    header::AUTHORIZATION,
+                    header::CONTENT_TYPE,
+                    header::IF_NONE_MATCH,
+                    HeaderName::from_static("x-api-key"),
+                    HeaderName::from_static("last-event-id"),
+                ])
+                .expose_headers([header::ETAG, header::RETRY_AFTER]),
+        ))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/vaults/{address}",
+    tag = "vaults",
+    params(
+        ("address" = String, Path, description = "Vault address"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a copy the client already has"),
+        VaultQuery
+    ),
+    responses(
+        (status = 200, description = "The vault", body = openapi::Vault,
+            headers(("ETag" = String, description = "Version of the vault, from its slot and write version"))),
+        (status = 304, description = "The vault is unchanged since the given ETag"),
+        (status = 400, description = "Invalid address or field", body = openapi::ErrorBody),
+        (status = 404, description = "Unknown vault", body = openapi::ErrorBody),
+        (status = 503, description = "Warming up, or min_slot not indexed in time", body = openapi::ErrorBody)
+    )
+)]
+pub(crate) async fn get_vault(
+    State(state): State<ApiState>,
+    Path(address): Path<String>,
+    Query(query): Query<VaultQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    parse_pubkey(&address).map_err(bad_request)?;
+    let fields = FieldSelection::parse(query.fields.as_deref()).map_err(bad_request)?;
+    
+    let read = state.indexer.get_vault_state(&address);
+    let Some(vault) = state.indexer.read_at_slot(query.min_slot, state.min_slot_timeout, read).await? else {
+        return Err(ApiError(StatusCode::NOT_FOUND, format!("Vault {} not found", address)));
+    };
+    // Every write to a vault has a new (slot, write_version), so it names
+    // the state; `fields` is part of the URL, so it needn't be included
+    let etag = format!("\"{}-{}\"", vault.slot, vault.write_version);
+    if if_none_match(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+    Ok(([(header::ETAG, etag)], Json(fields.project(&vault))).into_response())
+}
+
+/// Whether `If-None-Match` lists `etag` or is `*`, comparing weakly as
+/// RFC 9110 asks of it
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// Look up several vaults at once, through one cache `MGET` and one
+/// database query for the misses; duplicates are looked up once
+#[utoipa::path(
+    post,
+    path = "/vaults/batch",
+    tag = "vaults",
+    params(VaultQuery),
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "Found vaults and missing addresses", body = openapi::BatchResponse),
+        (status = 400, description = "Invalid address or field, or too many", body = openapi::ErrorBody),
+        (status = 503, description = "Warming up, or min_slot not indexed in time", body = openapi::ErrorBody)
+    )
+)]
+pub(crate) async fn get_vaults_batch(
+    State(state): State<ApiState>,
+    Query(query): Query<VaultQuery>,
+    Json(request): Json<BatchRequest>,
+) -> Result<Json<BatchResponse>, ApiError> {
+    let fields = FieldSelection::parse(query.fields.as_deref()).map_err(bad_request)?;
+    let mut addresses = request.addresses;
+    addresses.sort();
+    addresses.dedup();
+    if addresses.len() > state.max_batch_size {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("A batch may look up at most {} vaults", state.max_batch_size),
+        ));
+    }
+    for address in &addresses {
+        parse_pubkey(address).map_err(bad_request)?;
+    }
+    
+    let read = state.indexer.get_vault_states(&addresses);
+    let vaults = state.indexer.read_at_slot(query.min_slot, state.min_slot_timeout, read).await?;
+    let found: HashSet<String> = vaults.iter().map(|vault| vault.vault_address.to_string()).collect();
+    Ok(Json(BatchResponse {
+        found: vaults.iter().map(|vault| fields.project(vault)).collect(),
+        missing: addresses.into_iter().filter(|address| !found.contains(address)).collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/owners/{owner}/vaults",
+    tag = "vaults",
+    params(("owner" = String, Path, description = "Owner address"), OwnerQuery),
+    responses(
+        (status = 200, description = "A page of the owner's active vaults, by address", body = openapi::VaultPage),
+        (status = 400, description = "Invalid address, field or cursor", body = openapi::ErrorBody),
+        (status = 503, description = "Warming up, or min_slot not indexed in time", body = openapi::ErrorBody)
+    )
+)]
+pub(crate) async fn get_owner_vaults(
+    State(state): State<ApiState>,
+    Path(owner): Path<String>,
+    Query(query): Query<OwnerQuery>,
+) -> Result<Json<OwnerVaultsResponse>, ApiError> {
+    parse_pubkey(&owner).map_err(bad_request)?;
+    let fields = FieldSelection::parse(query.fields.as_deref()).map_err(bad_request)?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, state.max_page_size);
+    let after = query
+        .cursor
+        .as_deref()
+        .map(PageCursor::decode_owner_vaults)
+        .transpose()
+        .map_err(bad_request)?;
+    
+    let read = state.indexer.get_vaults_by_owner(&owner, after.as_deref(), limit);
+    let vaults = state.indexer.read_at_slot(query.min_slot, state.min_slot_timeout, read).await?;
+    Ok(Json(OwnerVaultsResponse {
+        vaults: vaults.iter().map(|vault| fields.project(vault)).collect(),
+        next_cursor: PageCursor::next_owner_vaults(&vaults, limit),
+    }))
+}
+
+/// Owner changes and recorded vault states in log order, for consumers
+/// that must process every change exactly once
+#[utoipa::path(
+    get,
+    path = "/changes",
+    tag = "vaults",
+    params(ChangesQuery),
+    responses(
+        (status = 200, description = "The next events of the change log", body = openapi::ChangeLogPage),
+        (status = 400, description = "Invalid cursor", body = openapi::ErrorBody),
+        (status = 503, description = "Startup ingestion in progress", body = openapi::ErrorBody)
+    )
+)]
+pub(crate) async fn get_changes(
+    State(state): State<ApiState>,
+    Query(query): Query<ChangesQuery>,
+) -> Result<Json<ChangesResponse>, ApiError> {
+    let since = match (&query.cursor, query.after_slot) {
+        (Some(cursor), _) => PageCursor::decode_changes(cursor).map_err(bad_request)?,
+        (None, Some(slot)) => ChangeCursor::after_slot(slot),
+        (None, None) => ChangeCursor::default(),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, state.max_page_size);
+    
+    let page = state.indexer.get_changes_since(&since, limit).await?;
+    Ok(Json(ChangesResponse {
+        events: page.events.iter().map(projection::render_event).collect(),
+        next_cursor: PageCursor::Changes(page.next_cursor).encode(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "stats",
+    responses((status = 200, description = "Indexing statistics and readiness", body = openapi::Stats))
+)]
+pub(crate) async fn get_stats(State(state): State<ApiState>) -> Json<StatsResponse> {
+    Json(StatsResponse {
+        stats: state.indexer.get_stats(),
+        ready: state.indexer.is_ready(),
+    })
+}
+
+async fn subscribe(State(state): State<ApiState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| crate::ws::serve(socket, state.indexer))
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "vaults",
+    params(
+        EventsQuery,
+        ("Last-Event-ID" = Option<String>, Header, description = "Resume after this event id")
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Server-sent `update` and `owner_changed` events",
+            content_type = "text/event-stream"
+        ),
+        (status = 400, description = "Invalid filter or event id", body = openapi::ErrorBody)
+    )
+)]
+pub(crate) async fn stream_events(
+    State(state): State<ApiState>,
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Result<sse::EventStream, ApiError> {
+    let last_event_id = headers.get("last-event-id").and_then(|id| id.to_str().ok());
+    sse::stream(state.indexer, query, last_event_id).map_err(bad_request)
+}
+
+/// JSON-RPC errors are reported in the body, so this always answers 200
+async fn json_rpc(State(state): State<ApiState>, body: Bytes) -> Json<Value> {
+    let max_accounts = usize::try_from(state.max_page_size).unwrap_or(usize::MAX);
+    Json(rpc::handle(&state.indexer, max_accounts, &body).await)
+}
+
+async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Refuse a request without a key holding its route's scope
+///
+/// With `[rate_limit]` on, a refused request is charged to its client IP,
+/// and an IP out of tokens is refused before its key is checked, so keys
+/// can't be guessed faster than `per_ip` allows.
+async fn authenticate(
+    State((keys, limits)): State<(Arc<ApiKeys>, Option<Arc<RateLimits>>)>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(scope) = required_scope(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let client_ip = limits.as_ref().and_then(|limits| client_ip(limits, &request));
+    if let (Some(limits), Some(ip)) = (&limits, client_ip) {
+        if let Err(wait) = limits.check_penalty(ip) {
+            return too_many_requests(wait);
+        }
+    }
+    
+    let query_key = match scope {
+        ApiScope::Stream => Query::<KeyQuery>::try_from_uri(request.uri())
+            .ok()
+            .and_then(|Query(query)| query.api_key),
+        _ => None,
+    };
+    let headers = request.headers();
+    let presented = auth::presented_key(|name| headers.get(name).and_then(|value| value.to_str().ok()));
+    
+    let result = keys.authorize(presented.or(query_key.as_deref()), scope);
+    if let (Err(e), ApiScope::Admin) = (&result, scope) {
+        warn!(target: AUDIT_TARGET, "Refused admin {} {}: {}", request.method(), request.uri().path(), e);
+    }
+    if let (Err(_), Some(limits), Some(ip)) = (&result, &limits, client_ip) {
+        if let Err(wait) = limits.charge_rejected(ip) {
+            return too_many_requests(wait);
+        }
+    }
+    
+    match result {
+        Ok(name) => {
+            request.extensions_mut().insert(ClientKey(name));
+            next.run(request).await
+        }
+        Err(e @ AuthError::Forbidden(_)) => ApiError(StatusCode::FORBIDDEN, e.to_string()).into_response(),
+        Err(e) => {
+            let mut response = ApiError(StatusCode::UNAUTHORIZED, e.to_string()).into_response();
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            response
+        }
+    }
+}
+
+/// Refuse a request over its client's rate limit: its API key's, or its
+/// IP's without one
+async fn limit_rate(State(limits): State<Arc<RateLimits>>, request: Request, next: Next) -> Response {
+    if required_scope(request.uri().path()).is_none() {
+        return next.run(request).await;
+    }
+    let client = match request.extensions().get::<ClientKey>() {
+        Some(ClientKey(name)) => Client::Key(name),
+        None => match client_ip(&limits, &request) {
+            Some(ip) => Client::Ip(ip),
+            None => return next.run(request).await,
+        },
+    };
+    
+    match limits.check(client) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => too_many_requests(wait),
+    }
+}
+
+/// Client IP of `request` as `limits` sees it
+fn client_ip(limits: &RateLimits, request: &Request) -> Option<IpAddr> {
+    let headers = request.headers();
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| *peer);
+    limits.client_ip(|name| headers.get(name).and_then(|value| value.to_str().ok()), peer)
+}
+
+fn too_many_requests(wait: Duration) -> Response {
+    let mut response = ApiError(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded".to_string()).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(rate_limit::retry_after_secs(wait)));
+    response
+}
+
+/// Scope a route needs; the spec and Swagger UI need none
+fn required_scope(path: &str) -> Option<ApiScope> {
+    match path {
+        "/openapi.json" => None,
+        path if path.starts_with("/docs") => None,
+        "/stats" => Some(ApiScope::Stats),
+        "/ws" | "/events" => Some(ApiScope::Stream),
+        "/graphql" => Some(ApiScope::Graphql),
+        "/rpc" => Some(ApiScope::Rpc),
+        path if path.starts_with("/admin/") => Some(ApiScope::Admin),
+        _ => Some(ApiScope::Read),
+    }
+}
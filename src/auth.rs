@@ -0,0 +1,226 @@
+use crate::database::Database;
+use crate::types::{ApiScope, AuthConfig};
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{info, warn};
+
+/// Headers a key is read from, in order; `x-token` is what Yellowstone
+/// clients send
+const KEY_HEADERS: [&str; 2] = ["x-api-key", "x-token"];
+
+/// Why a request was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// No key was presented
+    Missing,
+    /// The key isn't configured, or was revoked
+    Invalid,
+    /// The key lacks the scope the request needs
+    Forbidden(ApiScope),
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::Missing => write!(f, "API key required"),
+            AuthError::Invalid => write!(f, "Invalid API key"),
+            AuthError::Forbidden(scope) => write!(f, "API key lacks the '{}' scope", scope.as_str()),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+struct ApiKey {
+    name: String,
+    scopes: HashSet<ApiScope>,
+}
+
+/// API keys accepted by the HTTP, WebSocket and gRPC servers
+///
+/// Keys are matched by their SHA-256, so configured plain keys, configured
+/// hashes and the hashes in `api_keys` are looked up alike and plain keys
+/// aren't kept in memory. With `database_keys`, the table is reloaded every
+/// `refresh_interval_secs`; a failed reload keeps the previous keys.
+pub struct ApiKeys {
+    configured: HashMap<[u8; 32], ApiKey>,
+    stored: RwLock<HashMap<[u8; 32], ApiKey>>,
+    /// Where `stored` is loaded from, with `database_keys`
+    database: Option<Arc<Database>>,
+}
+
+impl ApiKeys {
+    /// Load the keys and, with `database_keys`, keep `api_keys` in sync in
+    /// the background
+    pub async fn start(config: &AuthConfig, database: Arc<Database>) -> Result<Arc<Self>> {
+        let mut configured = HashMap::with_capacity(config.keys.len());
+        for key in &config.keys {
+            let hash = match (&key.key, &key.key_sha256) {
+                (Some(plain), None) => sha256(plain),
+                (None, Some(hex)) => {
+                    parse_sha256(hex).with_context(|| format!("Invalid key_sha256 of API key '{}'", key.name))?
+                }
+                _ => anyhow::bail!("API key '{}' must set exactly one of key and key_sha256", key.name),
+            };
+            let previous = configured.insert(
+                hash,
+                ApiKey {
+                    name: key.name.clone(),
+                    scopes: key.scopes.iter().copied().collect(),
+                },
+            );
+            if let Some(previous) = previous {
+                anyhow::bail!("API keys '{}' and '{}' are the same key", previous.name, key.name);
+            }
+        }
+        if configured.is_empty() && !config.database_keys {
+            warn!("[auth] is enabled without keys; every query will be refused");
+        }
+        
+        let keys = Arc::new(Self {
+            configured,
+            stored: RwLock::new(HashMap::new()),
+            database: config.database_keys.then_some(database),
+        });
+        if let Some(database) = &keys.database {
+            keys.reload(database).await;
+            tokio::spawn(
+                keys.clone()
+                    .refresh(database.clone(), Duration::from_secs(config.refresh_interval_secs.max(1))),
+            );
+        }
+        
+        Ok(keys)
+    }
+    
+    /// Check a presented key for `scope`, returning the key's name
+    pub fn authorize(&self, presented: Option<&str>, scope: ApiScope) -> Result<String, AuthError> {
+        let result = self.check(presented, scope);
+        if let Err(e) = &result {
+            let reason = match e {
+                AuthError::Missing => "missing",
+                AuthError::Invalid => "invalid",
+                AuthError::Forbidden(_) => "forbidden",
+            };
+            metrics::counter!("indexer_auth_rejections_total", "reason" => reason).increment(1);
+        }
+        result
+    }
+    
+    fn check(&self, presented: Option<&str>, scope: ApiScope) -> Result<String, AuthError> {
+        let hash = sha256(presented.ok_or(AuthError::Missing)?);
+        let stored = self.stored.read().unwrap();
+        let key = self
+            .configured
+            .get(&hash)
+            .or_else(|| stored.get(&hash))
+            .ok_or(AuthError::Invalid)?;
+        
+        if !key.scopes.contains(&scope) {
+            return Err(AuthError::Forbidden(scope));
+        }
+        Ok(key.name.clone())
+    }
+    
+    async fn refresh(self: Arc<Self>, database: Arc<Database>, every: Duration) {
+        let mut ticker = interval(every);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+        
+        loop {
+            ticker.tick().await;
+            self.reload(&database).await;
+        }
+    }
+    
+    /// Reload `api_keys` now rather than at the next refresh, returning how
+    /// many keys it holds; `None` without `database_keys`
+    pub async fn reload_stored(&self) -> Result<Option<usize>> {
+        let Some(database) = &self.database else {
+            return Ok(None);
+        };
+        self.load(database).await.map(Some)
+    }
+    
+    async fn reload(&self, database: &Database) {
+        if let Err(e) = self.load(database).await {
+            warn!("Failed to load API keys, keeping the previous ones: {:#}", e);
+        }
+    }
+    
+    async fn load(&self, database: &Database) -> Result<usize> {
+        let rows = database.get_api_keys().await?;
+        
+        let mut stored = HashMap::with_capacity(rows.len());
+        for row in rows {
+            let Ok(hash) = <[u8; 32]>::try_from(row.key_sha256.as_slice()) else {
+                warn!("Skipping API key '{}': key_sha256 is not a SHA-256", row.name);
+                continue;
+            };
+            stored.insert(
+                hash,
+                ApiKey {
+                    name: row.name,
+                    scopes: row.scopes.into_iter().collect(),
+                },
+            );
+        }
+        
+        let count = stored.len();
+        let mut current = self.stored.write().unwrap();
+        if current.len() != count {
+            info!("Loaded {} API keys from the database", count);
+        }
+        *current = stored;
+        Ok(count)
+    }
+}
+
+/// The key a request presents: a bearer token in `authorization`, else
+/// `x-api-key` or `x-token`; `header` looks a header up by name
+pub fn presented_key<'a>(header: impl Fn(&str) -> Option<&'a str>) -> Option<&'a str> {
+    if let Some(token) = header("authorization").and_then(|value| value.strip_prefix("Bearer ")) {
+        return Some(token.trim());
+    }
+    KEY_HEADERS.iter().find_map(|name| header(name)).map(str::trim)
+}
+
+/// Check the key in a gRPC request's metadata, returning the key's name;
+/// `None` keys let it through
+pub fn authorize_grpc(
+    keys: Option<&ApiKeys>,
+    metadata: &tonic::metadata::MetadataMap,
+    scope: ApiScope,
+) -> Result<Option<String>, tonic::Status> {
+    let Some(keys) = keys else {
+        return Ok(None);
+    };
+    let presented = presented_key(|name| metadata.get(name).and_then(|value| value.to_str().ok()));
+    
+    match keys.authorize(presented, scope) {
+        Ok(name) => Ok(Some(name)),
+        Err(e @ AuthError::Forbidden(_)) => Err(tonic::Status::permission_denied(e.to_string())),
+        Err(e) => Err(tonic::Status::unauthenticated(e.to_string())),
+    }
+}
+
+fn sha256(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+fn parse_sha256(hex: &str) -> Result<[u8; 32]> {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.is_ascii() {
+        anyhow::bail!("expected 64 hex digits");
+    }
+    
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+    }
+    Ok(hash)
+}
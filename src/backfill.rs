@@ -0,0 +1,211 @@
+use crate::database::Database;
+use crate::indexer::{Indexer, WriteMode};
+use crate::known_vaults::KnownVaults;
+use crate::redis_cache::RedisCache;
+use crate::store::VaultStore;
+use crate::types::{AccountUpdate, BackfillConfig, BackfillJob, BackfillJobStatus};
+use anyhow::{Context, Result};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Summary of a completed backfill job
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BackfillReport {
+    pub job_id: i64,
+    /// Totals include pages done by earlier attempts of a resumed job
+    pub pages: usize,
+    pub accounts: usize,
+    /// Slot the snapshot was taken at
+    pub slot: u64,
+    /// Checkpointed slot live indexing resumed from, if any
+    pub resume_slot: Option<u64>,
+    pub elapsed: Duration,
+}
+
+/// Seeds Postgres and Redis from `getProgramAccounts`
+///
+/// A single unfiltered call for a large program can exceed RPC response
+/// limits, so the program is split into 256 pages by the value of the data
+/// byte at `prefix_offset` (the first byte of the vault owner by default).
+pub struct Backfiller {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    prefix_offset: usize,
+    batch_size: usize,
+    resume_slot: Option<u64>,
+    database: Arc<Database>,
+    store: Arc<dyn VaultStore>,
+    cache: Option<Arc<RedisCache>>,
+    /// Learns the vaults found, so their closures are recognized
+    known_vaults: Arc<KnownVaults>,
+}
+
+impl Backfiller {
+    pub fn new(
+        config: &BackfillConfig,
+        program_id: Pubkey,
+        batch_size: usize,
+        resume_slot: Option<u64>,
+        database: Arc<Database>,
+        store: Arc<dyn VaultStore>,
+        cache: Option<Arc<RedisCache>>,
+        known_vaults: Arc<KnownVaults>,
+    ) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(config.rpc_url.clone(), CommitmentConfig::confirmed()),
+            program_id,
+            prefix_offset: config.prefix_offset,
+            batch_size: batch_size.max(1),
+            resume_slot,
+            database,
+            store,
+            cache,
+            known_vaults,
+        }
+    }
+    
+    /// Page through every program account and write it through the indexer
+    ///
+    /// Progress is persisted in `backfill_jobs` after every page, so an
+    /// unfinished job for the same program and paging filter is resumed
+    /// from its cursor instead of starting over.
+    pub async fn run(&self) -> Result<BackfillReport> {
+        let program_id = self.program_id.to_string();
+        let job = match self
+            .database
+            .find_resumable_backfill_job(&program_id, self.prefix_offset)
+            .await?
+        {
+            Some(job) => {
+                info!(
+                    "Resuming backfill job {} at page {}/256 ({} accounts done)",
+                    job.id, job.next_prefix, job.accounts_done
+                );
+                job
+            }
+            None => self.database.create_backfill_job(&program_id, self.prefix_offset).await?,
+        };
+        
+        match self.run_job(&job).await {
+            Ok(report) => {
+                self.database
+                    .set_backfill_job_status(job.id, BackfillJobStatus::Completed, None)
+                    .await?;
+                Ok(report)
+            }
+            Err(e) => {
+                let error = format!("{:#}", e);
+                if let Err(status_err) = self
+                    .database
+                    .set_backfill_job_status(job.id, BackfillJobStatus::Failed, Some(&error))
+                    .await
+                {
+                    warn!("Failed to record backfill job {} failure: {}", job.id, status_err);
+                }
+                Err(e)
+            }
+        }
+    }
+    
+    async fn run_job(&self, job: &BackfillJob) -> Result<BackfillReport> {
+        let start = Instant::now();
+        let slot = self.rpc.get_slot().await.context("Failed to fetch current slot")?;
+        let mut report = BackfillReport {
+            job_id: job.id,
+            pages: job.pages_done as usize,
+            accounts: job.accounts_done as usize,
+            slot,
+            resume_slot: self.resume_slot,
+            ..BackfillReport::default()
+        };
+        
+        match self.resume_slot {
+            Some(resume_slot) => info!(
+                "Backfilling program {} from RPC at slot {} (live indexing resumed after slot {})",
+                self.program_id, slot, resume_slot
+            ),
+            None => info!("Backfilling program {} from RPC at slot {}", self.program_id, slot),
+        }
+        
+        for prefix in job.next_prefix..=u8::MAX as u16 {
+            let accounts = self.fetch_page(prefix as u8).await?;
+            
+            let updates: Vec<AccountUpdate> = accounts
+                .into_iter()
+                .inspect(|(pubkey, _)| self.known_vaults.insert(*pubkey))
+                .map(|(pubkey, account)| AccountUpdate {
+                    pubkey,
+                    lamports: account.lamports,
+                    owner: account.owner,
+                    executable: account.executable,
+                    rent_epoch: account.rent_epoch,
+                    data: account.data,
+                    write_version: 0,
+                    slot,
+                    is_startup: true,
+                    txn_signature: None,
+                })
+                .collect();
+            
+            for chunk in updates.chunks(self.batch_size) {
+                Indexer::process_batch(
+                    self.store.as_ref(),
+                    &self.database,
+                    self.cache.as_deref(),
+                    None,
+                    chunk,
+                    WriteMode::Copy,
+                ).await?;
+            }
+            
+            self.database
+                .advance_backfill_job(job.id, prefix + 1, updates.len() as u64)
+                .await?;
+            
+            report.pages += 1;
+            report.accounts += updates.len();
+            metrics::counter!("indexer_backfill_accounts_total").increment(updates.len() as u64);
+        }
+        
+        report.elapsed = start.elapsed();
+        info!(
+            "Backfill job {} complete: {} accounts in {} pages ({:?})",
+            job.id, report.accounts, report.pages, report.elapsed
+        );
+        
+        Ok(report)
+    }
+    
+    async fn fetch_page(&self, prefix: u8) -> Result<Vec<(Pubkey, solana_sdk::account::Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                self.prefix_offset,
+                vec![prefix],
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        
+        self.rpc
+            .get_program_accounts_with_config(&self.program_id, config)
+            .await
+            .with_context(|| format!("getProgramAccounts failed for prefix {:#04x}", prefix))
+    }
+}
+
+/// Run a backfill in the background, logging instead of failing startup
+pub(crate) async fn run_in_background(backfiller: Backfiller) {
+    if let Err(e) = backfiller.run().await {
+        warn!("Background backfill failed: {:#}", e);
+    }
+}
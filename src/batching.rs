@@ -0,0 +1,119 @@
+use crate::types::AdaptiveBatchConfig;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tracing::debug;
+
+/// Rolling window of recent flush latencies
+#[derive(Debug, Clone)]
+pub struct LatencyWindow {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl LatencyWindow {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+    
+    /// Record a sample, evicting the oldest once the window is full
+    pub fn record(&mut self, latency: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+    
+    /// Latency at percentile `p` (0.0..=1.0) using nearest-rank
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        
+        let rank = (p.clamp(0.0, 1.0) * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1).min(sorted.len() - 1)])
+    }
+    
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Batch size controller targeting a p99 flush latency
+///
+/// Every `adjust_every` flushes the controller looks at the p99 of the
+/// latency window: above target it halves the batch (DB under pressure),
+/// well below target it grows by 25%, otherwise it holds.
+#[derive(Debug, Clone)]
+pub struct AdaptiveBatchSizer {
+    current: usize,
+    min: usize,
+    max: usize,
+    target: Duration,
+    window: LatencyWindow,
+    adjust_every: usize,
+    flushes_since_adjust: usize,
+}
+
+impl AdaptiveBatchSizer {
+    pub fn new(config: &AdaptiveBatchConfig, initial: usize) -> Self {
+        let min = config.min_batch_size.max(1);
+        let max = config.max_batch_size.max(min);
+        let window_size = config.window_size.max(1);
+        
+        Self {
+            current: initial.clamp(min, max),
+            min,
+            max,
+            target: Duration::from_millis(config.target_p99_flush_ms),
+            window: LatencyWindow::new(window_size),
+            adjust_every: (window_size / 4).max(1),
+            flushes_since_adjust: 0,
+        }
+    }
+    
+    pub fn batch_size(&self) -> usize {
+        self.current
+    }
+    
+    /// Feed an observed flush latency and return the (possibly new) batch size
+    pub fn record_flush(&mut self, latency: Duration) -> usize {
+        self.window.record(latency);
+        self.flushes_since_adjust += 1;
+        
+        if self.flushes_since_adjust < self.adjust_every {
+            return self.current;
+        }
+        self.flushes_since_adjust = 0;
+        
+        let Some(p99) = self.window.percentile(0.99) else {
+            return self.current;
+        };
+        
+        let previous = self.current;
+        if p99 > self.target {
+            self.current = (self.current / 2).max(self.min);
+        } else if p99 < self.target / 2 {
+            self.current = (self.current + self.current / 4 + 1).min(self.max);
+        }
+        
+        if self.current != previous {
+            debug!(
+                "Adaptive batch size {} -> {} (p99 flush {:?}, target {:?})",
+                previous, self.current, p99, self.target
+            );
+        }
+        
+        self.current
+    }
+}
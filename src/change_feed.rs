@@ -0,0 +1,46 @@
+use crate::namespace::Namespace;
+use crate::types::VaultChange;
+use anyhow::Result;
+use sqlx::postgres::{PgListener, PgPool};
+
+/// Notification channel announcing applied vault writes, before the table
+/// prefix
+pub const VAULT_CHANGES_CHANNEL: &str = "vault_changes";
+
+/// Listener for the notifications sent with `database.notify_changes`
+///
+/// Every vault write the indexer applies is announced on the prefixed
+/// `vault_changes` channel as a JSON `VaultChange`, once its transaction
+/// commits. Delivery is at most once: notifications sent while the listener
+/// is reconnecting are lost, so consumers that need every change should
+/// reconcile with `get_vaults_updated_since` after a gap. LISTEN holds
+/// session state, so connect to Postgres directly rather than through a
+/// transaction-pooling PgBouncer.
+pub struct ChangeFeed {
+    listener: PgListener,
+}
+
+impl ChangeFeed {
+    /// Listen on a dedicated connection to `connection_string`
+    pub async fn connect(connection_string: &str, namespace: &Namespace) -> Result<Self> {
+        let listener = PgListener::connect(connection_string).await?;
+        Self::listen(listener, namespace).await
+    }
+    
+    /// Listen on a connection taken from `pool`, which it then keeps
+    pub async fn from_pool(pool: &PgPool, namespace: &Namespace) -> Result<Self> {
+        let listener = PgListener::connect_with(pool).await?;
+        Self::listen(listener, namespace).await
+    }
+    
+    async fn listen(mut listener: PgListener, namespace: &Namespace) -> Result<Self> {
+        listener.listen(&namespace.table(VAULT_CHANGES_CHANNEL)).await?;
+        Ok(Self { listener })
+    }
+    
+    /// Wait for the next change, reconnecting if the connection drops
+    pub async fn recv(&mut self) -> Result<VaultChange> {
+        let notification = self.listener.recv().await?;
+        Ok(serde_json::from_str(notification.payload())?)
+    }
+}
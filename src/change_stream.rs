@@ -0,0 +1,135 @@
+use crate::redis_cache::RedisCache;
+use crate::types::{RedisConfig, VaultChange};
+use anyhow::{Context, Result};
+use redis::aio::ConnectionManager;
+use redis::streams::{StreamId, StreamReadOptions, StreamReadReply};
+use redis::AsyncCommands;
+use std::time::Duration;
+
+/// Stream vault writes are appended to with `redis.stream_changes`, after
+/// the key namespace
+pub const VAULT_CHANGE_STREAM: &str = "vault_change_stream";
+
+/// A change read from the stream, to be acknowledged by `id`
+#[derive(Debug, Clone)]
+pub struct StreamChange {
+    pub id: String,
+    pub change: VaultChange,
+}
+
+/// Consumer-group reader of the change stream written with
+/// `redis.stream_changes`
+///
+/// Every vault write the indexer applies is appended with XADD, so the
+/// stream is ordered and can be replayed from any retained entry; it is
+/// capped at about `stream_max_len` entries. Consumers in one group share
+/// the entries between them. An entry stays pending until acknowledged,
+/// and a restarted consumer is handed its pending entries again before
+/// new ones, so processing is at least once.
+pub struct ChangeStreamConsumer {
+    conn: ConnectionManager,
+    stream: String,
+    group: String,
+    consumer: String,
+    block: Duration,
+    /// Still re-reading entries delivered before a restart
+    replaying: bool,
+}
+
+impl ChangeStreamConsumer {
+    /// Join `group` as `consumer`, creating the group at the end of the
+    /// stream if it doesn't exist yet
+    pub async fn connect(
+        config: &RedisConfig,
+        vault_program_id: &str,
+        group: &str,
+        consumer: &str,
+        block: Duration,
+    ) -> Result<Self> {
+        let client = RedisCache::client(config)?;
+        let mut conn = tokio::time::timeout(
+            Duration::from_secs(config.connection_timeout_seconds),
+            ConnectionManager::new(client),
+        )
+        .await
+        .context("Timed out connecting to Redis")??;
+        let stream = format!("{}{}", RedisCache::namespace_for(config, vault_program_id), VAULT_CHANGE_STREAM);
+        
+        let created: redis::RedisResult<()> = conn.xgroup_create_mkstream(&stream, group, "$").await;
+        if let Err(e) = created {
+            if e.code() != Some("BUSYGROUP") {
+                return Err(e.into());
+            }
+        }
+        
+        Ok(Self {
+            conn,
+            stream,
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+            block,
+            replaying: true,
+        })
+    }
+    
+    /// Up to `count` changes, oldest first, waiting up to the block time
+    /// for new ones; empty if none arrived
+    ///
+    /// Entries delivered to this consumer but never acknowledged come
+    /// first, so acknowledge a batch before asking for the next.
+    pub async fn recv(&mut self, count: usize) -> Result<Vec<StreamChange>> {
+        if self.replaying {
+            let pending = self.read("0", count, None).await?;
+            if !pending.is_empty() {
+                return Ok(pending);
+            }
+            self.replaying = false;
+        }
+        
+        self.read(">", count, Some(self.block)).await
+    }
+    
+    /// Acknowledge processed changes so they aren't delivered again
+    pub async fn ack(&mut self, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        
+        let _: u64 = self.conn.xack(&self.stream, &self.group, ids).await?;
+        Ok(())
+    }
+    
+    async fn read(&mut self, start: &str, count: usize, block: Option<Duration>) -> Result<Vec<StreamChange>> {
+        let mut options = StreamReadOptions::default()
+            .group(&self.group, &self.consumer)
+            .count(count);
+        if let Some(block) = block {
+            options = options.block(block.as_millis() as usize);
+        }
+        
+        let reply: Option<StreamReadReply> = self
+            .conn
+            .xread_options(&[&self.stream], &[start], &options)
+            .await?;
+        
+        reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .map(|entry| Self::parse(&entry))
+            .collect()
+    }
+    
+    fn parse(entry: &StreamId) -> Result<StreamChange> {
+        let field = |name: &str| format!("Change stream entry {} has no {}", entry.id, name);
+        
+        Ok(StreamChange {
+            id: entry.id.clone(),
+            change: VaultChange {
+                vault_address: entry.get("vault_address").with_context(|| field("vault_address"))?,
+                slot: entry.get("slot").with_context(|| field("slot"))?,
+                write_version: entry.get("write_version").with_context(|| field("write_version"))?,
+            },
+        })
+    }
+}
@@ -0,0 +1,126 @@
+use crate::types::CircuitBreakerConfig;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Breaker state, exported as the `indexer_circuit_breaker_state` gauge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Writes flow normally
+    Closed,
+    /// A single probe write is allowed through
+    HalfOpen,
+    /// Writes are rejected without touching the database
+    Open,
+}
+
+impl CircuitState {
+    fn as_gauge(self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::HalfOpen => 1.0,
+            CircuitState::Open => 2.0,
+        }
+    }
+}
+
+/// Circuit breaker shared by all shard workers around Postgres writes
+///
+/// Opens after `failure_threshold` consecutive failures. Once `open_ms` has
+/// passed, one caller is let through as a half-open probe: success closes
+/// the breaker, failure re-opens it for another `open_ms`.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_duration: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+struct BreakerInner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Instant,
+    probe_in_flight: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        metrics::gauge!("indexer_circuit_breaker_state").set(CircuitState::Closed.as_gauge());
+        
+        Self {
+            failure_threshold: config.failure_threshold.max(1),
+            open_duration: Duration::from_millis(config.open_ms),
+            inner: Mutex::new(BreakerInner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: Instant::now(),
+                probe_in_flight: false,
+            }),
+        }
+    }
+    
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().unwrap().state
+    }
+    
+    /// Whether a write may be attempted now
+    ///
+    /// Every allowed call must be followed by `record_success` or
+    /// `record_failure`.
+    pub fn allow(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        
+        match inner.state {
+            CircuitState::Closed => true,
+            CircuitState::Open if inner.opened_at.elapsed() >= self.open_duration => {
+                info!("Circuit breaker half-open, probing database");
+                Self::transition(&mut inner, CircuitState::HalfOpen);
+                inner.probe_in_flight = true;
+                true
+            }
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if !inner.probe_in_flight => {
+                inner.probe_in_flight = true;
+                true
+            }
+            CircuitState::HalfOpen => false,
+        }
+    }
+    
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = 0;
+        inner.probe_in_flight = false;
+        
+        if inner.state != CircuitState::Closed {
+            info!("Circuit breaker closed, database writes resumed");
+            Self::transition(&mut inner, CircuitState::Closed);
+        }
+    }
+    
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        inner.probe_in_flight = false;
+        
+        let trip = match inner.state {
+            CircuitState::Closed => inner.consecutive_failures >= self.failure_threshold,
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+        };
+        
+        if trip {
+            warn!(
+                "Circuit breaker open after {} consecutive failures, pausing writes for {:?}",
+                inner.consecutive_failures, self.open_duration
+            );
+            inner.opened_at = Instant::now();
+            Self::transition(&mut inner, CircuitState::Open);
+            metrics::counter!("indexer_circuit_breaker_trips_total").increment(1);
+        }
+    }
+    
+    fn transition(inner: &mut BreakerInner, state: CircuitState) {
+        inner.state = state;
+        metrics::gauge!("indexer_circuit_breaker_state").set(state.as_gauge());
+    }
+}
@@ -0,0 +1,319 @@
+use crate::database::Database;
+use crate::indexer::Indexer;
+use crate::projection::{FieldSelection, VaultField};
+use crate::redis_cache::RedisCache;
+use crate::types::{AccountUpdate, ConsistencyConfig, VaultClosure, VaultState, VaultStatus};
+use anyhow::{Context, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval, sleep, MissedTickBehavior};
+use tracing::{error, info, warn};
+
+/// `getMultipleAccounts` accepts at most 100 keys per call
+const RPC_CHUNK: usize = 100;
+
+/// Fields compared between Postgres and the chain; slot, write version and
+/// timestamps legitimately differ between the two reads
+const COMPARED_FIELDS: [VaultField; 4] = [
+    VaultField::Owner,
+    VaultField::Balance,
+    VaultField::Assets,
+    VaultField::Permissions,
+];
+
+/// A vault whose indexed state doesn't match the chain
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub vault_address: Pubkey,
+    pub kind: DivergenceKind,
+    /// State as indexed in Postgres
+    pub indexed: VaultState,
+    /// State as parsed from RPC, if the account still parses as a vault
+    pub onchain: Option<VaultState>,
+    /// Slot RPC read the on-chain side at
+    pub observed_slot: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// The account no longer exists or is no longer a vault
+    MissingOnChain,
+    /// Listed fields differ
+    Fields(Vec<VaultField>),
+}
+
+impl DivergenceKind {
+    pub fn describe(&self) -> String {
+        match self {
+            DivergenceKind::MissingOnChain => "missing on chain".to_string(),
+            DivergenceKind::Fields(fields) => {
+                let names: Vec<&str> = fields.iter().map(VaultField::name).collect();
+                format!("fields differ: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+/// Result of one sampling round
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencyReport {
+    pub checked: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+/// Samples indexed vaults and compares them against RPC
+///
+/// A mismatch can simply mean an update is still in flight, so candidates
+/// are re-read from both sides after `recheck_delay_ms` and only reported
+/// if they still differ. In repair mode, confirmed divergences are then
+/// overwritten from RPC, and vaults missing on chain are closed.
+pub struct ConsistencyChecker {
+    rpc: RpcClient,
+    database: Arc<Database>,
+    cache: Option<Arc<RedisCache>>,
+    repair: bool,
+    program_id: Pubkey,
+    sample_size: i64,
+    check_interval: Duration,
+    recheck_delay: Duration,
+}
+
+impl ConsistencyChecker {
+    pub fn new(
+        config: &ConsistencyConfig,
+        rpc_url: &str,
+        program_id: Pubkey,
+        database: Arc<Database>,
+        cache: Option<Arc<RedisCache>>,
+    ) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed()),
+            database,
+            cache,
+            repair: config.repair,
+            program_id,
+            sample_size: config.sample_size as i64,
+            check_interval: Duration::from_secs(config.interval_secs.max(1)),
+            recheck_delay: Duration::from_millis(config.recheck_delay_ms),
+        }
+    }
+    
+    /// Check a sample every interval until the task is dropped
+    pub async fn run(self) {
+        let mut ticker = interval(self.check_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        
+        loop {
+            ticker.tick().await;
+            
+            let report = match self.check_sample().await {
+                Ok(report) => report,
+                Err(e) => {
+                    error!("Consistency check failed: {:#}", e);
+                    continue;
+                }
+            };
+            
+            if self.repair && !report.divergences.is_empty() {
+                let repaired = self.repair(&report.divergences).await;
+                info!("Repaired {} of {} divergent vaults", repaired, report.divergences.len());
+            }
+        }
+    }
+    
+    /// Sample vaults and report those that still diverge after a recheck
+    pub async fn check_sample(&self) -> Result<ConsistencyReport> {
+        let sample = self.database.sample_vault_states(self.sample_size).await?;
+        if sample.is_empty() {
+            return Ok(ConsistencyReport::default());
+        }
+        
+        let candidates = self.compare(&sample).await?;
+        let divergences = if candidates.is_empty() {
+            candidates
+        } else {
+            sleep(self.recheck_delay).await;
+            
+            let addresses: Vec<String> = candidates
+                .iter()
+                .map(|divergence| divergence.vault_address.to_string())
+                .collect();
+            let mut current = self.database.get_vault_states(&addresses).await?;
+            // The indexer already caught up with vaults closed in the meantime
+            current.retain(|state| state.status == VaultStatus::Active);
+            self.compare(&current).await?
+        };
+        
+        let report = ConsistencyReport {
+            checked: sample.len(),
+            divergences,
+        };
+        
+        metrics::counter!("indexer_consistency_checked_total").increment(report.checked as u64);
+        metrics::counter!("indexer_consistency_divergent_total").increment(report.divergences.len() as u64);
+        metrics::gauge!("indexer_consistency_divergence_ratio")
+            .set(report.divergences.len() as f64 / report.checked as f64);
+        
+        for divergence in &report.divergences {
+            warn!(
+                "Vault {} diverges from chain: {}",
+                divergence.vault_address,
+                divergence.kind.describe()
+            );
+        }
+        info!(
+            "Consistency check: {} of {} sampled vaults diverge",
+            report.divergences.len(),
+            report.checked
+        );
+        
+        Ok(report)
+    }
+    
+    /// Overwrite diverged rows and cache entries from RPC, auditing each fix
+    ///
+    /// Returns the number of vaults repaired.
+    pub async fn repair(&self, divergences: &[Divergence]) -> usize {
+        let mut repaired = 0;
+        
+        for divergence in divergences {
+            match self.repair_one(divergence).await {
+                Ok(()) => {
+                    repaired += 1;
+                    metrics::counter!("indexer_consistency_repairs_total").increment(1);
+                }
+                Err(e) => error!("Failed to repair vault {}: {:#}", divergence.vault_address, e),
+            }
+        }
+        
+        repaired
+    }
+    
+    async fn repair_one(&self, divergence: &Divergence) -> Result<()> {
+        let address = divergence.vault_address.to_string();
+        let fields = FieldSelection::all();
+        let previous = fields.project(&divergence.indexed);
+        
+        match &divergence.onchain {
+            Some(state) => {
+                self.database.upsert_vault_state(state).await?;
+                if let Some(cache) = &self.cache {
+                    cache.batch_set_changes(std::slice::from_ref(state)).await?;
+                }
+            }
+            None => {
+                let closure = VaultClosure {
+                    vault_address: divergence.vault_address,
+                    slot: divergence.observed_slot,
+                    write_version: 0,
+                };
+                let closed = self.database.close_vaults(&[closure]).await?;
+                if let Some(cache) = &self.cache {
+                    cache.batch_set_changes(&closed).await?;
+                }
+            }
+        }
+        
+        let repaired = divergence.onchain.as_ref().map(|state| fields.project(state));
+        self.database
+            .insert_vault_repair(&address, &divergence.kind.describe(), Some(&previous), repaired.as_ref())
+            .await?;
+        
+        info!("Repaired vault {} ({})", address, divergence.kind.describe());
+        Ok(())
+    }
+    
+    /// Compare indexed states with their current on-chain accounts
+    async fn compare(&self, states: &[VaultState]) -> Result<Vec<Divergence>> {
+        let (onchain, observed_slot) = self.fetch(states).await?;
+        let mut divergences = Vec::new();
+        
+        for state in states {
+            let kind = match onchain.get(&state.vault_address) {
+                None => Some(DivergenceKind::MissingOnChain),
+                Some(current) => {
+                    let fields = diff_fields(state, current);
+                    (!fields.is_empty()).then_some(DivergenceKind::Fields(fields))
+                }
+            };
+            
+            if let Some(kind) = kind {
+                divergences.push(Divergence {
+                    vault_address: state.vault_address,
+                    kind,
+                    indexed: state.clone(),
+                    onchain: onchain.get(&state.vault_address).cloned(),
+                    observed_slot,
+                });
+            }
+        }
+        
+        Ok(divergences)
+    }
+    
+    /// Fetch and parse the current on-chain state of each vault, with the
+    /// newest slot RPC answered at
+    async fn fetch(&self, states: &[VaultState]) -> Result<(HashMap<Pubkey, VaultState>, u64)> {
+        let mut parsed = HashMap::with_capacity(states.len());
+        let mut observed_slot = 0;
+        
+        for chunk in states.chunks(RPC_CHUNK) {
+            let pubkeys: Vec<Pubkey> = chunk.iter().map(|state| state.vault_address).collect();
+            let response = self
+                .rpc
+                .get_multiple_accounts_with_commitment(&pubkeys, CommitmentConfig::confirmed())
+                .await
+                .context("getMultipleAccounts failed")?;
+            let slot = response.context.slot;
+            observed_slot = observed_slot.max(slot);
+            
+            for (pubkey, account) in pubkeys.into_iter().zip(response.value) {
+                let Some(account) = account.filter(|account| account.owner == self.program_id) else {
+                    continue;
+                };
+                
+                let update = AccountUpdate {
+                    pubkey,
+                    lamports: account.lamports,
+                    owner: account.owner,
+                    executable: account.executable,
+                    rent_epoch: account.rent_epoch,
+                    data: account.data,
+                    write_version: 0,
+                    slot,
+                    is_startup: false,
+                    txn_signature: None,
+                };
+                
+                if let Ok(Some(state)) = Indexer::parse_vault_state(&update) {
+                    parsed.insert(pubkey, state);
+                }
+            }
+        }
+        
+        Ok((parsed, observed_slot))
+    }
+}
+
+/// Compared fields whose values differ
+fn diff_fields(indexed: &VaultState, onchain: &VaultState) -> Vec<VaultField> {
+    COMPARED_FIELDS
+        .into_iter()
+        .filter(|field| match field {
+            VaultField::Owner => indexed.owner != onchain.owner,
+            VaultField::Balance => indexed.balance != onchain.balance,
+            VaultField::Assets => {
+                serde_json::to_value(&indexed.assets).ok() != serde_json::to_value(&onchain.assets).ok()
+            }
+            VaultField::Permissions => {
+                serde_json::to_value(&indexed.permissions).ok()
+                    != serde_json::to_value(&onchain.permissions).ok()
+            }
+            _ => false,
+        })
+        .collect()
+}
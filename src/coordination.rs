@@ -0,0 +1,105 @@
+use crate::database::Database;
+use crate::types::CoordinationConfig;
+use anyhow::Result;
+use sqlx::PgConnection;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval, timeout, MissedTickBehavior};
+use tracing::{info, warn};
+
+/// Single-writer election between instances sharing one database
+///
+/// The writer holds a session-level Postgres advisory lock on a dedicated
+/// connection. Standbys retry the lock every `check_interval_ms`; the
+/// writer pings its session on the same interval and steps down as soon as
+/// the session is lost, since Postgres releases the lock with it.
+pub struct WriterElection {
+    database: Arc<Database>,
+    lock_key: i64,
+    check_interval: Duration,
+    leader: AtomicBool,
+}
+
+impl WriterElection {
+    /// Make a first attempt at the lock, then keep contending in the background
+    pub async fn start(config: &CoordinationConfig, database: Arc<Database>) -> Arc<Self> {
+        let election = Arc::new(Self {
+            database,
+            lock_key: config.lock_key,
+            check_interval: Duration::from_millis(config.check_interval_ms.max(1)),
+            leader: AtomicBool::new(false),
+        });
+        metrics::gauge!("indexer_writer_leader").set(0.0);
+        
+        let session = election.acquire().await;
+        tokio::spawn(election.clone().run(session));
+        
+        election
+    }
+    
+    /// Whether this instance currently holds the writer lock
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Acquire)
+    }
+    
+    async fn run(self: Arc<Self>, mut session: Option<PgConnection>) {
+        let mut ticker = interval(self.check_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+        
+        loop {
+            ticker.tick().await;
+            
+            session = match session {
+                Some(mut conn) => {
+                    let ping = timeout(self.check_interval, sqlx::query("SELECT 1").execute(&mut conn)).await;
+                    if matches!(ping, Ok(Ok(_))) {
+                        Some(conn)
+                    } else {
+                        warn!("Lost writer lock session, stepping down to standby");
+                        self.set_leader(false);
+                        None
+                    }
+                }
+                None => self.acquire().await,
+            };
+        }
+    }
+    
+    /// Try the lock once, returning the session that holds it on success
+    async fn acquire(&self) -> Option<PgConnection> {
+        match self.try_lock().await {
+            Ok(Some(conn)) => {
+                self.set_leader(true);
+                Some(conn)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                warn!("Failed to contend for writer lock: {}", e);
+                None
+            }
+        }
+    }
+    
+    async fn try_lock(&self) -> Result<Option<PgConnection>> {
+        let mut conn = self.database.dedicated_connection().await?;
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(self.lock_key)
+            .fetch_one(&mut conn)
+            .await?;
+        
+        Ok(acquired.then_some(conn))
+    }
+    
+    fn set_leader(&self, leader: bool) {
+        if self.leader.swap(leader, Ordering::AcqRel) != leader {
+            if leader {
+                info!("Acquired writer lock {}, writing to the database", self.lock_key);
+            } else {
+                metrics::counter!("indexer_writer_demotions_total").increment(1);
+            }
+            metrics::gauge!("indexer_writer_leader").set(if leader { 1.0 } else { 0.0 });
+        }
+    }
+}
@@ -1,78 +1,569 @@
-use crate::types::VaultState;
-use std::str::FromStr;
+use crate::account_data::{self, AccountDataCodec};
+use crate::types::{
+    AccountUpdate, AssetBalance, BackfillJob, BackfillJobStatus, ChangeCursor, ChangePage, DatabaseConfig,
+    DeadLetter, DeadLetterStage, EncryptedColumn, MintTotals, OwnerChange, OwnerTotals, Permission, PermissionType,
+    RawAccount, SavedFilter, SchemaCheck, SchemaVersion, ShardCheckpoint, SlotGap, SlotPartition, SlotRange, SslMode,
+    StoredApiKey, SyncCursor, SyncPage, TotalValueLocked, VaultAsset, VaultChange, VaultClosure, VaultCounts,
+    VaultCursor, VaultFilter, VaultPage, VaultSort, VaultState, VaultStateVersion, VaultStatus,
+};
+use crate::change_feed::VAULT_CHANGES_CHANNEL;
+use crate::encryption::{self, ColumnCipher};
+use crate::namespace::Namespace;
+use crate::replicas::ReadReplicas;
+use crate::watermark;
 use anyhow::Result;
-use sqlx::PgPool;
-use std::collections::HashMap;
+use solana_sdk::pubkey::Pubkey;
+use backoff::backoff::Backoff;
+use backoff::ExponentialBackoff;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use sqlx::types::Json;
+use sqlx::{Executor, PgConnection, PgPool, Postgres, QueryBuilder, Row};
+use std::borrow::{Borrow, Cow};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
+use tracing::{info, warn};
+
+/// Column list matching `Database::vault_state_from_row`; also valid for
+/// `vault_state_history` and `vault_state_history_all`
+const VAULT_STATE_COLUMNS: &str = "vault_address, owner, balance, assets, permissions, \
+    last_updated, slot, write_version, status, closed_at_slot";
+
+/// Slots per `account_updates` partition (one epoch); must match
+/// `ensure_account_updates_partition` in the migrations
+pub const ACCOUNT_UPDATES_PARTITION_SLOTS: u64 = 432_000;
+
+/// Aggregate materialized views, refreshed by `AggregateRefresher`
+pub const AGGREGATE_VIEWS: [&str; 3] = ["mv_owner_totals", "mv_vault_counts", "mv_mint_totals"];
+
+/// Slots behind the newest indexed slot that `get_vaults_updated_since`
+/// holds back, about a minute, so a flush that is retried or commits late
+/// still lands ahead of consumers' cursors
+pub const SYNC_SETTLE_SLOTS: u64 = 150;
+
+/// Column list matching `Database::backfill_job_from_row`
+const BACKFILL_JOB_COLUMNS: &str = "id, program_id, prefix_offset, next_prefix, pages_done, \
+    accounts_done, status, error_count, last_error, started_at, updated_at";
+
+/// Column list matching `Database::owner_change_from_row`
+const OWNER_CHANGE_COLUMNS: &str = "id, vault_address, previous_owner, new_owner, slot, write_version, \
+    changed_at";
+
+/// Column list matching `Database::dead_letter_from_row`
+const DEAD_LETTER_COLUMNS: &str = "id, pubkey, owner, lamports, executable, rent_epoch, data, \
+    slot, write_version, is_startup, stage, error, attempts, created_at, replayed_at, txn_signature";
+
+/// Connection pool sizing, liveness, startup retry and TLS settings
+#[derive(Debug, Clone)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    pub idle_timeout: Option<Duration>,
+    pub test_before_acquire: bool,
+    pub connect_attempts: u32,
+    pub connect_max_backoff: Duration,
+    /// Connections go through a transaction-pooling PgBouncer
+    pub pgbouncer: bool,
+    pub statement_timeout: Option<Duration>,
+    pub lock_timeout: Option<Duration>,
+    /// Calls taking longer than this are logged and counted
+    pub slow_query_threshold: Option<Duration>,
+    /// Applied vault writes are announced on the `vault_changes` channel
+    pub notify_changes: bool,
+    /// `account_updates.data` is written zstd-compressed
+    pub compress_account_data: bool,
+    /// `account_updates.data` is written as deltas against each account's
+    /// previous write, with a full copy every this many writes
+    pub account_data_keyframe_interval: Option<u32>,
+    /// Overrides any `sslmode` in the connection string
+    pub ssl_mode: Option<SslMode>,
+    pub ssl_root_cert: Option<String>,
+    pub ssl_client_cert: Option<String>,
+    pub ssl_client_key: Option<String>,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+            test_before_acquire: true,
+            connect_attempts: 1,
+            connect_max_backoff: Duration::from_secs(10),
+            pgbouncer: false,
+            statement_timeout: None,
+            lock_timeout: None,
+            slow_query_threshold: None,
+            notify_changes: false,
+            compress_account_data: false,
+            account_data_keyframe_interval: None,
+            ssl_mode: None,
+            ssl_root_cert: None,
+            ssl_client_cert: None,
+            ssl_client_key: None,
+        }
+    }
+}
+
+impl PoolSettings {
+    pub fn from_config(config: &DatabaseConfig) -> Self {
+        Self {
+            max_connections: config.max_connections,
+            min_connections: config.min_connections.min(config.max_connections),
+            acquire_timeout: match config.acquire_timeout_ms {
+                Some(ms) => Duration::from_millis(ms.max(1)),
+                None => Duration::from_secs(config.connection_timeout_seconds.max(1)),
+            },
+            idle_timeout: (config.idle_timeout_seconds > 0)
+                .then(|| Duration::from_secs(config.idle_timeout_seconds)),
+            test_before_acquire: config.test_before_acquire,
+            connect_attempts: config.connect_attempts.max(1),
+            connect_max_backoff: Duration::from_millis(config.connect_max_backoff_ms),
+            pgbouncer: config.pgbouncer,
+            statement_timeout: (config.statement_timeout_ms > 0)
+                .then(|| Duration::from_millis(config.statement_timeout_ms)),
+            lock_timeout: (config.lock_timeout_ms > 0)
+                .then(|| Duration::from_millis(config.lock_timeout_ms)),
+            slow_query_threshold: (config.slow_query_threshold_ms > 0)
+                .then(|| Duration::from_millis(config.slow_query_threshold_ms)),
+            notify_changes: config.notify_changes,
+            compress_account_data: config.compress_account_data,
+            account_data_keyframe_interval: config
+                .delta_account_data
+                .then_some(config.account_data_keyframe_interval.max(1)),
+            ssl_mode: config.ssl_mode,
+            ssl_root_cert: config.ssl_root_cert.clone(),
+            ssl_client_cert: config.ssl_client_cert.clone(),
+            ssl_client_key: config.ssl_client_key.clone(),
+        }
+    }
+    
+    /// Connection options for `connection_string` in `namespace`
+    ///
+    /// Behind PgBouncer no statement is prepared under a name, since the
+    /// next transaction may run on another server connection, and no
+    /// startup options are sent, since PgBouncer rejects them. The schema
+    /// then has to come from the role's or database's `search_path`, and
+    /// the timeouts from the role's settings; configured timeouts are
+    /// refused rather than silently left unset.
+    pub fn connect_options(&self, namespace: &Namespace, connection_string: &str) -> Result<PgConnectOptions> {
+        let options = if self.pgbouncer {
+            if self.statement_timeout.is_some() || self.lock_timeout.is_some() {
+                anyhow::bail!(
+                    "statement_timeout_ms and lock_timeout_ms can't be applied with pgbouncer = true; \
+                     set them on the database role instead"
+                );
+            }
+            PgConnectOptions::from_str(connection_string)?.statement_cache_capacity(0)
+        } else {
+            let timeouts: Vec<(&str, String)> = [
+                ("statement_timeout", self.statement_timeout),
+                ("lock_timeout", self.lock_timeout),
+            ]
+            .into_iter()
+            .filter_map(|(name, timeout)| Some((name, timeout?.as_millis().to_string())))
+            .collect();
+            
+            namespace.connect_options(connection_string)?.options(timeouts)
+        };
+        
+        self.apply_tls(options)
+    }
+    
+    /// TLS settings on top of whatever the connection string specified
+    fn apply_tls(&self, mut options: PgConnectOptions) -> Result<PgConnectOptions> {
+        if let Some(mode) = self.ssl_mode {
+            options = options.ssl_mode(match mode {
+                SslMode::Disable => PgSslMode::Disable,
+                SslMode::Allow => PgSslMode::Allow,
+                SslMode::Prefer => PgSslMode::Prefer,
+                SslMode::Require => PgSslMode::Require,
+                SslMode::VerifyCa => PgSslMode::VerifyCa,
+                SslMode::VerifyFull => PgSslMode::VerifyFull,
+            });
+        }
+        if let Some(path) = &self.ssl_root_cert {
+            if !Path::new(path).is_file() {
+                anyhow::bail!("ssl_root_cert '{}' does not exist", path);
+            }
+            options = options.ssl_root_cert(path);
+        }
+        
+        match (&self.ssl_client_cert, &self.ssl_client_key) {
+            (Some(cert), Some(key)) => {
+                for path in [cert, key] {
+                    if !Path::new(path).is_file() {
+                        anyhow::bail!("TLS client certificate or key '{}' does not exist", path);
+                    }
+                }
+                Ok(options.ssl_client_cert(cert).ssl_client_key(key))
+            }
+            (None, None) => Ok(options),
+            _ => anyhow::bail!("ssl_client_cert and ssl_client_key must be set together"),
+        }
+    }
+}
 
 /// PostgreSQL database interface
 pub struct Database {
     pool: PgPool,
+    namespace: Namespace,
+    replicas: Option<Arc<ReadReplicas>>,
+    slow_query_threshold: Option<Duration>,
+    notify_changes: bool,
+    account_data: AccountDataCodec,
+    cipher: Option<ColumnCipher>,
 }
 
 impl Database {
     /// Create a new database connection pool
+    ///
+    /// Pending migrations are applied, and a schema that doesn't match this
+    /// binary afterwards is refused.
     pub async fn new(connection_string: &str, max_connections: u32) -> Result<Self> {
-        let options = sqlx::postgres::PgConnectOptions::from_str(connection_string)?;
-        let pool = PgPool::connect_with(options).await?;
-        
-        // Initialize schema
-        Self::init_schema(&pool).await?;
-        
-        Ok(Self { pool })
-    }
-    
-    /// Initialize database schema
-    async fn init_schema(pool: &PgPool) -> Result<()> {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS vault_states (
-                vault_address TEXT PRIMARY KEY,
-                owner TEXT NOT NULL,
-                balance BIGINT NOT NULL,
-                assets JSONB NOT NULL DEFAULT '{}',
-                permissions JSONB NOT NULL DEFAULT '[]',
-                last_updated TIMESTAMPTZ NOT NULL,
-                slot BIGINT NOT NULL,
-                write_version BIGINT NOT NULL,
-                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        let settings = PoolSettings {
+            max_connections,
+            ..PoolSettings::default()
+        };
+        
+        Self::connect(
+            connection_string,
+            &settings,
+            Namespace::default(),
+            true,
+            SchemaCheck::Strict,
+        ).await
+    }
+    
+    /// Create a connection pool with explicit pool, namespace, migration
+    /// and schema check settings
+    ///
+    /// The first connection is retried with exponential backoff, so a
+    /// Postgres that is still starting up doesn't fail the caller.
+    pub async fn connect(
+        connection_string: &str,
+        settings: &PoolSettings,
+        namespace: Namespace,
+        run_migrations: bool,
+        schema_check: SchemaCheck,
+    ) -> Result<Self> {
+        let options = settings.connect_options(&namespace, connection_string)?;
+        if settings.pgbouncer && namespace.schema() != "public" {
+            info!(
+                "PgBouncer mode: expecting search_path to start with '{}' for the indexer role",
+                namespace.schema()
             );
+        }
+        let pool_options = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(settings.max_connections)
+            .min_connections(settings.min_connections)
+            .acquire_timeout(settings.acquire_timeout)
+            .idle_timeout(settings.idle_timeout)
+            .test_before_acquire(settings.test_before_acquire);
+        
+        let mut backoff = ExponentialBackoff {
+            max_interval: settings.connect_max_backoff,
+            max_elapsed_time: None,
+            ..ExponentialBackoff::default()
+        };
+        let mut attempt = 1;
+        let pool = loop {
+            match pool_options.clone().connect_with(options.clone()).await {
+                Ok(pool) => break pool,
+                Err(e) if attempt < settings.connect_attempts => {
+                    let delay = backoff.next_backoff().unwrap_or(settings.connect_max_backoff);
+                    warn!(
+                        "Database connection attempt {}/{} failed: {}; retrying in {:?}",
+                        attempt, settings.connect_attempts, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+        
+        let database = Self {
+            pool,
+            namespace,
+            replicas: None,
+            slow_query_threshold: settings.slow_query_threshold,
+            notify_changes: settings.notify_changes,
+            account_data: AccountDataCodec::new(
+                settings.compress_account_data,
+                settings.account_data_keyframe_interval,
+            ),
+            cipher: None,
+        };
+        database.prepare_schema(run_migrations, schema_check).await?;
+        
+        Ok(database)
+    }
+    
+    /// Wrap an existing pool without touching the schema; the pool's
+    /// connections must already have the namespace's schema on their
+    /// search path
+    pub fn from_pool(pool: PgPool, namespace: Namespace) -> Self {
+        Self {
+            pool,
+            namespace,
+            replicas: None,
+            slow_query_threshold: None,
+            notify_changes: false,
+            account_data: AccountDataCodec::new(false, None),
+            cipher: None,
+        }
+    }
+    
+    /// Route read queries to replicas that lag the primary by at most
+    /// `max_lag`; writes and anything that must see them stay on the primary
+    pub async fn attach_read_replicas(
+        &mut self,
+        urls: &[String],
+        settings: &PoolSettings,
+        max_lag: Duration,
+    ) -> Result<()> {
+        self.replicas = Some(ReadReplicas::connect(urls, settings, &self.namespace, max_lag).await?);
+        Ok(())
+    }
+    
+    /// Encrypt the cipher's designated columns from now on; values already
+    /// stored encrypted need it to be read
+    pub fn set_column_cipher(&mut self, cipher: ColumnCipher) {
+        self.cipher = Some(cipher);
+    }
+    
+    /// Pool for a read query: a fresh enough replica, else the primary;
+    /// always the primary for reads that must see the latest flush
+    fn read_pool(&self) -> &PgPool {
+        self.replicas
+            .as_ref()
+            .filter(|_| !watermark::reads_own_writes())
+            .and_then(|replicas| replicas.pick())
+            .unwrap_or(&self.pool)
+    }
+    
+    /// Schema and table prefix this database's tables live under
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+    
+    /// SQL with the table prefix applied
+    fn sql<'a>(&self, sql: &'a str) -> Cow<'a, str> {
+        self.namespace.render(sql)
+    }
+    
+    /// Timer for one call, reported when dropped; `binds` summarizes the
+    /// call's parameters and is only evaluated for slow calls
+    fn time_query<F: FnOnce() -> String>(&self, query: &'static str, binds: F) -> QueryTimer<F> {
+        QueryTimer::new(query, binds, self.slow_query_threshold)
+    }
+    
+    /// Connection taken out of the pool, for session-scoped state such as
+    /// advisory locks; closing it ends the session
+    pub async fn dedicated_connection(&self) -> Result<PgConnection> {
+        Ok(self.pool.acquire().await?.detach())
+    }
+    
+    /// Round trip to the database through the pool
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+    
+    /// Latest migration applied to the database and embedded in the binary
+    pub async fn schema_version(&self) -> Result<SchemaVersion> {
+        let _timer = self.time_query("schema_version", String::new);
+        let binary = Self::migrator()
+            .iter()
+            .map(|migration| migration.version)
+            .max()
+            .unwrap_or(0);
+        
+        let tracked: bool = sqlx::query_scalar(&self.sql("SELECT to_regclass('_sqlx_migrations') IS NOT NULL"))
+            .fetch_one(&self.pool)
+            .await?;
+        let database = if tracked {
+            sqlx::query_scalar(&self.sql("SELECT MAX(version) FROM _sqlx_migrations WHERE success"))
+                .fetch_one(&self.pool)
+                .await?
+        } else {
+            None
+        };
+        
+        Ok(SchemaVersion { database, binary })
+    }
+    
+    /// Embedded migrations; they're written against the unprefixed table
+    /// names and rendered for the namespace when applied
+    fn migrator() -> Migrator {
+        sqlx::migrate!("./migrations")
+    }
+    
+    /// Apply pending migrations
+    ///
+    /// Mirrors sqlx's own runner, but keeps the history in the namespace's
+    /// `_sqlx_migrations` table so prefixed instances sharing a schema each
+    /// track their own. Without a prefix the table is the one sqlx uses.
+    /// Applied versions the binary doesn't know are left to the schema
+    /// check instead of failing the run.
+    ///
+    /// The run is one transaction under a transaction-scoped advisory lock,
+    /// so concurrent instances apply each migration once and nothing
+    /// depends on session state, which a transaction-pooling PgBouncer
+    /// wouldn't preserve.
+    async fn run_migrations(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        
+        // Migrations may rewrite whole tables
+        sqlx::query("SET LOCAL statement_timeout = 0")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(self.namespace.qualified_table("_sqlx_migrations"))
+            .execute(&mut *tx)
+            .await?;
+        
+        let schema_exists: bool = sqlx::query_scalar("SELECT to_regnamespace($1) IS NOT NULL")
+            .bind(self.namespace.schema())
+            .fetch_one(&mut *tx)
+            .await?;
+        if !schema_exists {
+            sqlx::query(&format!("CREATE SCHEMA {}", self.namespace.schema()))
+                .execute(&mut *tx)
+                .await?;
+        }
+        
+        self.apply_migrations(&mut tx).await?;
+        tx.commit().await?;
+        
+        Ok(())
+    }
+    
+    async fn apply_migrations(&self, conn: &mut PgConnection) -> Result<()> {
+        sqlx::query(&self.sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS _sqlx_migrations (
+                version BIGINT PRIMARY KEY,
+                description TEXT NOT NULL,
+                installed_on TIMESTAMPTZ NOT NULL DEFAULT now(),
+                success BOOLEAN NOT NULL,
+                checksum BYTEA NOT NULL,
+                execution_time BIGINT NOT NULL
+            )
+            "#
+        ))
+        .execute(&mut *conn)
+        .await?;
+        
+        let dirty: Option<i64> = sqlx::query_scalar(&self.sql(
+            "SELECT version FROM _sqlx_migrations WHERE NOT success ORDER BY version LIMIT 1"
+        ))
+        .fetch_optional(&mut *conn)
+        .await?;
+        if let Some(version) = dirty {
+            anyhow::bail!("migration {} is partially applied; fix and remove it from _sqlx_migrations", version);
+        }
+        
+        let applied: HashMap<i64, Vec<u8>> = sqlx::query_as::<_, (i64, Vec<u8>)>(&self.sql(
+            "SELECT version, checksum FROM _sqlx_migrations"
+        ))
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .collect();
+        
+        for migration in Self::migrator().iter() {
+            if migration.migration_type.is_down_migration() {
+                continue;
+            }
             
-            CREATE INDEX IF NOT EXISTS idx_vault_states_owner ON vault_states(owner);
-            CREATE INDEX IF NOT EXISTS idx_vault_states_slot ON vault_states(slot);
-            CREATE INDEX IF NOT EXISTS idx_vault_states_updated ON vault_states(last_updated);
+            if let Some(checksum) = applied.get(&migration.version) {
+                if checksum[..] != migration.checksum[..] {
+                    anyhow::bail!("migration {} was modified after it was applied", migration.version);
+                }
+                continue;
+            }
             
-            CREATE TABLE IF NOT EXISTS account_updates (
-                id BIGSERIAL PRIMARY KEY,
-                pubkey TEXT NOT NULL,
-                slot BIGINT NOT NULL,
-                write_version BIGINT NOT NULL,
-                data BYTEA,
-                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
-            );
+            let start = std::time::Instant::now();
+            (&mut *conn).execute(&*self.sql(&migration.sql)).await?;
+            sqlx::query(&self.sql(
+                r#"
+                INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+                VALUES ($1, $2, TRUE, $3, $4)
+                "#
+            ))
+            .bind(migration.version)
+            .bind(&*migration.description)
+            .bind(&*migration.checksum)
+            .bind(start.elapsed().as_nanos() as i64)
+            .execute(&mut *conn)
+            .await?;
             
-            CREATE INDEX IF NOT EXISTS idx_account_updates_pubkey ON account_updates(pubkey);
-            CREATE INDEX IF NOT EXISTS idx_account_updates_slot ON account_updates(slot);
-            "#
-        )
-        .execute(pool)
-        .await?;
+            info!("Applied migration {} ({})", migration.version, migration.description);
+        }
         
         Ok(())
     }
     
-    /// Upsert vault state
+    /// Bring the schema up to date and apply the version policy
+    ///
+    /// A schema ahead of the binary is never migrated, since an older
+    /// binary could apply a migration out of order.
+    async fn prepare_schema(&self, run_migrations: bool, check: SchemaCheck) -> Result<()> {
+        let mut version = self.schema_version().await?;
+        
+        if run_migrations && !version.is_ahead() {
+            self.run_migrations().await?;
+            version = self.schema_version().await?;
+        }
+        
+        let problem = if version.is_ahead() {
+            format!(
+                "database schema version {} is ahead of this binary ({}); upgrade the indexer",
+                version.database.unwrap_or(0),
+                version.binary
+            )
+        } else if version.is_behind() {
+            format!(
+                "database schema version {} is behind this binary ({}); run migrations",
+                version.database.unwrap_or(0),
+                version.binary
+            )
+        } else {
+            return Ok(());
+        };
+        
+        match check {
+            SchemaCheck::Strict => anyhow::bail!(problem),
+            SchemaCheck::Warn => {
+                warn!("{}", problem);
+                Ok(())
+            }
+        }
+    }
+    
+    /// Upsert vault state and record the version in its history
     pub async fn upsert_vault_state(&self, state: &VaultState) -> Result<()> {
-        let assets_json = serde_json::to_string(&state.assets)?;
-        let permissions_json = serde_json::to_string(&state.permissions)?;
+        let _timer = self.time_query("upsert_vault_state", || {
+            format!("vault_address={}", state.vault_address)
+        });
+        let mut tx = self.pool.begin().await?;
         
-        sqlx::query(
+        sqlx::query(&self.sql(
             r#"
             INSERT INTO vault_states (
                 vault_address, owner, balance, assets, permissions,
-                last_updated, slot, write_version, updated_at
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+                last_updated, slot, write_version, state_hash, status, closed_at_slot, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW())
             ON CONFLICT (vault_address) DO UPDATE SET
                 owner = EXCLUDED.owner,
                 balance = EXCLUDED.balance,
@@ -81,116 +572,2855 @@ impl Database {
                 last_updated = EXCLUDED.last_updated,
                 slot = EXCLUDED.slot,
                 write_version = EXCLUDED.write_version,
+                state_hash = EXCLUDED.state_hash,
+                status = EXCLUDED.status,
+                closed_at_slot = EXCLUDED.closed_at_slot,
                 updated_at = NOW()
             "#
-        )
+        ))
         .bind(state.vault_address.to_string())
         .bind(state.owner.to_string())
         .bind(state.balance as i64)
-        .bind(assets_json)
-        .bind(permissions_json)
+        .bind(Json(&state.assets))
+        .bind(Json(&state.permissions))
         .bind(state.last_updated)
         .bind(state.slot as i64)
         .bind(state.write_version as i64)
-        .execute(&self.pool)
+        .bind(&state.content_hash()?[..])
+        .bind(state.status.as_str())
+        .bind(state.closed_at_slot.map(|slot| slot as i64))
+        .execute(&mut *tx)
         .await?;
         
+        self.insert_history(&mut tx, state).await?;
+        self.replace_vault_assets(&mut tx, &[state]).await?;
+        self.replace_vault_permissions(&mut tx, &[state]).await?;
+        self.notify_vault_changes(&mut tx, &[state]).await?;
+        
+        tx.commit().await?;
         Ok(())
     }
     
-    /// Get vault state by address
-    pub async fn get_vault_state(&self, vault_address: &str) -> Result<Option<VaultState>> {
-        let row = sqlx::query(
+    /// Rewrite the `vault_assets` rows of the given vaults from their states
+    async fn replace_vault_assets(&self, conn: &mut PgConnection, states: &[&VaultState]) -> Result<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+        
+        let addresses: Vec<String> = states.iter().map(|state| state.vault_address.to_string()).collect();
+        let mut vaults = Vec::new();
+        let mut mints = Vec::new();
+        let mut amounts = Vec::new();
+        let mut decimals = Vec::new();
+        let active = states.iter().zip(&addresses).filter(|(state, _)| state.status == VaultStatus::Active);
+        for (state, address) in active {
+            for (mint, asset) in &state.assets {
+                vaults.push(address.clone());
+                mints.push(mint.clone());
+                amounts.push(asset.amount as i64);
+                decimals.push(asset.decimals as i16);
+            }
+        }
+        
+        sqlx::query(&self.sql("DELETE FROM vault_assets WHERE vault_address = ANY($1)"))
+            .bind(&addresses)
+            .execute(&mut *conn)
+            .await?;
+        
+        sqlx::query(&self.sql(
             r#"
-            SELECT vault_address, owner, balance, assets, permissions,
-                   last_updated, slot, write_version
-            FROM vault_states
-            WHERE vault_address = $1
+            INSERT INTO vault_assets (vault_address, mint, amount, decimals)
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::bigint[], $4::smallint[])
             "#
-        )
-        .bind(vault_address)
-        .fetch_optional(&self.pool)
+        ))
+        .bind(&vaults)
+        .bind(&mints)
+        .bind(&amounts)
+        .bind(&decimals)
+        .execute(&mut *conn)
         .await?;
         
-        if let Some(row) = row {
-            let vault_address_str: String = row.try_get(0)?;
-            let owner_str: String = row.try_get(1)?;
-            let balance: i64 = row.try_get(2)?;
-            let assets_json: String = row.try_get(3)?;
-            let permissions_json: String = row.try_get(4)?;
-            let last_updated: OffsetDateTime = row.try_get(5)?;
-            let slot: i64 = row.try_get(6)?;
-            let write_version: i64 = row.try_get(7)?;
-            
-            let vault_address: Pubkey = vault_address_str.parse()?;
-            let owner: Pubkey = owner_str.parse()?;
-            
-            let assets: HashMap<String, AssetBalance> = serde_json::from_str(&assets_json)?;
-            let permissions: Vec<Permission> = serde_json::from_str(&permissions_json)?;
-            
-            Ok(Some(VaultState {
-                vault_address,
-                owner,
-                balance: balance as u64,
-                assets,
-                permissions,
-                last_updated,
-                slot: slot as u64,
-                write_version: write_version as u64,
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(())
     }
     
-    /// Batch upsert vault states
-    pub async fn batch_upsert_vault_states(&self, states: &[VaultState]) -> Result<()> {
-        let mut tx = self.pool.begin().await?;
+    /// Rewrite the `vault_permissions` rows of the given vaults from their
+    /// states
+    async fn replace_vault_permissions(&self, conn: &mut PgConnection, states: &[&VaultState]) -> Result<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
         
-        for state in states {
-            let assets_json = serde_json::to_string(&state.assets)?;
-            let permissions_json = serde_json::to_string(&state.permissions)?;
-            
-            sqlx::query(
-                r#"
-                INSERT INTO vault_states (
-                    vault_address, owner, balance, assets, permissions,
-                    last_updated, slot, write_version, updated_at
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
-                ON CONFLICT (vault_address) DO UPDATE SET
-                    owner = EXCLUDED.owner,
-                    balance = EXCLUDED.balance,
-                    assets = EXCLUDED.assets,
-                    permissions = EXCLUDED.permissions,
-                    last_updated = EXCLUDED.last_updated,
-                    slot = EXCLUDED.slot,
-                    write_version = EXCLUDED.write_version,
-                    updated_at = NOW()
-                "#
-            )
-            .bind(state.vault_address.to_string())
-            .bind(state.owner.to_string())
-            .bind(state.balance as i64)
-            .bind(assets_json)
-            .bind(permissions_json)
-            .bind(state.last_updated)
-            .bind(state.slot as i64)
-            .bind(state.write_version as i64)
-            .execute(&mut *tx)
+        let addresses: Vec<String> = states.iter().map(|state| state.vault_address.to_string()).collect();
+        let mut vaults = Vec::new();
+        let mut pubkeys = Vec::new();
+        let mut roles = Vec::new();
+        let mut ranks = Vec::new();
+        let active = states.iter().zip(&addresses).filter(|(state, _)| state.status == VaultStatus::Active);
+        for (state, address) in active {
+            for permission in &state.permissions {
+                vaults.push(address.clone());
+                pubkeys.push(permission.pubkey.to_bytes().to_vec());
+                roles.push(permission.permission_type.as_str());
+                ranks.push(permission.permission_type.rank());
+            }
+        }
+        
+        sqlx::query(&self.sql("DELETE FROM vault_permissions WHERE vault_address = ANY($1)"))
+            .bind(&addresses)
+            .execute(&mut *conn)
             .await?;
+        
+        sqlx::query(&self.sql(
+            r#"
+            INSERT INTO vault_permissions (vault_address, pubkey, role, role_rank)
+            SELECT * FROM UNNEST($1::text[], $2::bytea[], $3::text[], $4::smallint[])
+            ON CONFLICT DO NOTHING
+            "#
+        ))
+        .bind(&vaults)
+        .bind(&pubkeys)
+        .bind(&roles)
+        .bind(&ranks)
+        .execute(&mut *conn)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Queue a `vault_changes` notification per state, delivered to
+    /// listeners when the transaction commits; a no-op unless
+    /// `notify_changes` is set
+    async fn notify_vault_changes(&self, conn: &mut PgConnection, states: &[&VaultState]) -> Result<()> {
+        if !self.notify_changes || states.is_empty() {
+            return Ok(());
         }
         
-        tx.commit().await?;
+        let payloads = states
+            .iter()
+            .map(|state| {
+                serde_json::to_string(&VaultChange {
+                    vault_address: state.vault_address.to_string(),
+                    slot: state.slot,
+                    write_version: state.write_version,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        sqlx::query("SELECT pg_notify($1, payload) FROM UNNEST($2::text[]) AS payload")
+            .bind(self.namespace.table(VAULT_CHANGES_CHANNEL))
+            .bind(&payloads)
+            .execute(&mut *conn)
+            .await?;
+        
         Ok(())
     }
     
-    /// Get latest slot indexed
-    pub async fn get_latest_slot(&self) -> Result<u64> {
-        let row = sqlx::query("SELECT COALESCE(MAX(slot), 0) FROM vault_states")
-            .fetch_one(&self.pool)
+    /// Append a state version to `vault_state_history`; versions already
+    /// recorded are ignored
+    async fn insert_history(&self, conn: &mut PgConnection, state: &VaultState) -> Result<()> {
+        sqlx::query(&self.sql(
+            r#"
+            INSERT INTO vault_state_history (
+                vault_address, owner, balance, assets, permissions,
+                last_updated, slot, write_version, status, closed_at_slot
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT DO NOTHING
+            "#
+        ))
+        .bind(state.vault_address.to_string())
+        .bind(state.owner.to_string())
+        .bind(state.balance as i64)
+        .bind(Json(&state.assets))
+        .bind(Json(&state.permissions))
+        .bind(state.last_updated)
+        .bind(state.slot as i64)
+        .bind(state.write_version as i64)
+        .bind(state.status.as_str())
+        .bind(state.closed_at_slot.map(|slot| slot as i64))
+        .execute(conn)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Append many state versions to `vault_state_history` with `COPY`
+    ///
+    /// Rows go through a staging table so versions already recorded are
+    /// still ignored.
+    async fn copy_history(&self, conn: &mut PgConnection, states: &[VaultState]) -> Result<()> {
+        let mut csv = String::with_capacity(states.len() * 256);
+        for state in states {
+            Self::push_csv_record(&mut csv, &[
+                Some(state.vault_address.to_string()),
+                Some(state.owner.to_string()),
+                Some(state.balance.to_string()),
+                Some(serde_json::to_string(&state.assets)?),
+                Some(serde_json::to_string(&state.permissions)?),
+                Some(state.last_updated.format(&Rfc3339)?),
+                Some(state.slot.to_string()),
+                Some(state.write_version.to_string()),
+                Some(state.status.as_str().to_string()),
+                state.closed_at_slot.map(|slot| slot.to_string()),
+            ]);
+        }
+        
+        sqlx::query(&self.sql(
+            r#"
+            CREATE TEMP TABLE vault_state_history_staging
+                (LIKE vault_state_history INCLUDING DEFAULTS) ON COMMIT DROP
+            "#
+        ))
+        .execute(&mut *conn)
+        .await?;
+        
+        let mut copy = conn
+            .copy_in_raw(&self.sql(
+                "COPY vault_state_history_staging (vault_address, owner, balance, assets, permissions, \
+                 last_updated, slot, write_version, status, closed_at_slot) FROM STDIN (FORMAT csv)"
+            ))
             .await?;
+        copy.send(csv.as_bytes()).await?;
+        copy.finish().await?;
         
-        let slot: i64 = row.try_get(0)?;
-        Ok(slot as u64)
+        sqlx::query(&self.sql(
+            r#"
+            INSERT INTO vault_state_history (
+                vault_address, owner, balance, assets, permissions,
+                last_updated, slot, write_version, status, closed_at_slot
+            )
+            SELECT vault_address, owner, balance, assets, permissions,
+                last_updated, slot, write_version, status, closed_at_slot
+            FROM vault_state_history_staging
+            ON CONFLICT DO NOTHING
+            "#
+        ))
+        .execute(&mut *conn)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Append one CSV record for `COPY ... (FORMAT csv)`; every value is
+    /// quoted, and `None` is written unquoted and empty, which reads as NULL
+    fn push_csv_record(csv: &mut String, fields: &[Option<String>]) {
+        for (index, field) in fields.iter().enumerate() {
+            if index > 0 {
+                csv.push(',');
+            }
+            if let Some(field) = field {
+                csv.push('"');
+                csv.push_str(&field.replace('"', "\"\""));
+                csv.push('"');
+            }
+        }
+        csv.push('\n');
+    }
+    
+    /// `bytea` input in hex format
+    fn bytea_literal(bytes: &[u8]) -> String {
+        let mut literal = String::with_capacity(2 + bytes.len() * 2);
+        literal.push_str("\\x");
+        for byte in bytes {
+            literal.push_str(&format!("{:02x}", byte));
+        }
+        literal
+    }
+    
+    /// Get vault state by address
+    pub async fn get_vault_state(&self, vault_address: &str) -> Result<Option<VaultState>> {
+        let _timer = self.time_query("get_vault_state", || {
+            format!("vault_address={}", vault_address)
+        });
+        let row = sqlx::query(&self.sql(&format!(
+            "SELECT {} FROM vault_states WHERE vault_address = $1",
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(vault_address)
+        .fetch_optional(self.read_pool())
+        .await?;
+        
+        row.map(|row| Self::vault_state_from_row(&row)).transpose()
+    }
+    
+    /// Get vault states for several addresses in one query
+    ///
+    /// Addresses that are not indexed are simply absent from the result.
+    pub async fn get_vault_states(&self, vault_addresses: &[String]) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_vault_states", || {
+            format!("vault_addresses=[{} items]", vault_addresses.len())
+        });
+        let rows = sqlx::query(&self.sql(&format!(
+            "SELECT {} FROM vault_states WHERE vault_address = ANY($1)",
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(vault_addresses)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// One page of the active vaults owned by `owner`, ordered by address
+    ///
+    /// Addresses are compared bytewise, matching the Redis owner index.
+    pub async fn get_vaults_by_owner(&self, owner: &str, after: Option<&str>, limit: i64) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_vaults_by_owner", || {
+            format!("owner={}, after={:?}, limit={}", owner, after, limit)
+        });
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM vault_states
+            WHERE owner = $1 AND status = 'active' AND vault_address COLLATE "C" > $2
+            ORDER BY vault_address COLLATE "C"
+            LIMIT $3
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(owner)
+        .bind(after.unwrap_or_default())
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// One page of the vaults matching `filter`, largest `sort_by` first
+    ///
+    /// Pages are resumed from the previous page's `next_cursor` rather than
+    /// an offset, so every page costs the same however deep the listing.
+    /// The cursor must come from a listing with the same `sort_by`. Closed
+    /// vaults are left out unless `filter.include_closed` is set.
+    pub async fn list_vaults(
+        &self,
+        filter: &VaultFilter,
+        sort_by: VaultSort,
+        cursor: Option<&VaultCursor>,
+        limit: i64,
+    ) -> Result<VaultPage> {
+        let _timer = self.time_query("list_vaults", || {
+            format!("sort_by={}, cursor={:?}, limit={}", sort_by.as_str(), cursor, limit)
+        });
+        let mut query = QueryBuilder::<Postgres>::new(self.sql(&format!(
+            "SELECT {} FROM vault_states WHERE TRUE",
+            VAULT_STATE_COLUMNS
+        )));
+        
+        if !filter.include_closed {
+            query.push(" AND status = 'active'");
+        }
+        if !filter.vault_addresses.is_empty() {
+            query.push(" AND vault_address = ANY(")
+                .push_bind(filter.vault_addresses.as_slice())
+                .push(")");
+        }
+        if !filter.owners.is_empty() {
+            query.push(" AND owner = ANY(")
+                .push_bind(filter.owners.as_slice())
+                .push(")");
+        }
+        if !filter.tags.is_empty() {
+            let mut tags = filter.tags.clone();
+            tags.sort();
+            tags.dedup();
+            let tag_count = tags.len() as i64;
+            query.push(self.sql(" AND vault_address IN (SELECT vault_address FROM vault_tags WHERE tag = ANY("))
+                .push_bind(tags)
+                .push(") GROUP BY vault_address HAVING COUNT(*) = ")
+                .push_bind(tag_count)
+                .push(")");
+        }
+        if let Some(min_balance) = filter.min_balance {
+            query.push(" AND balance >= ").push_bind(min_balance as i64);
+        }
+        
+        if let Some(cursor) = cursor {
+            if cursor.sort_by() != sort_by {
+                anyhow::bail!(
+                    "cursor continues a listing sorted by {}, not {}",
+                    cursor.sort_by().as_str(),
+                    sort_by.as_str()
+                );
+            }
+            query.push(format!(" AND ({}, vault_address) < (", sort_by.as_str()));
+            match cursor {
+                VaultCursor::Balance { balance, .. } => query.push_bind(*balance as i64),
+                VaultCursor::LastUpdated { last_updated, .. } => query.push_bind(*last_updated),
+                VaultCursor::Slot { slot, .. } => query.push_bind(*slot as i64),
+            };
+            query.push(", ").push_bind(cursor.vault_address()).push(")");
+        }
+        
+        query.push(format!(" ORDER BY {} DESC, vault_address DESC LIMIT ", sort_by.as_str()))
+            .push_bind(limit);
+        
+        let rows = query.build().fetch_all(self.read_pool()).await?;
+        let vaults = rows
+            .iter()
+            .map(Self::vault_state_from_row)
+            .collect::<Result<Vec<_>>>()?;
+        let next_cursor = if vaults.len() as i64 == limit {
+            vaults.last().map(|last| VaultCursor::after(sort_by, last))
+        } else {
+            None
+        };
+        
+        Ok(VaultPage { vaults, next_cursor })
+    }
+    
+    /// Vaults whose latest state comes after `since`, oldest first
+    ///
+    /// A vault updated again after being returned moves forward and is
+    /// returned again, so polling with each page's `next_cursor` sees every
+    /// vault's latest state. Updates within `SYNC_SETTLE_SLOTS` of the
+    /// newest indexed slot are held back until they settle. Closures come
+    /// through as closed states, so consumers can drop those vaults.
+    pub async fn get_vaults_updated_since(&self, since: &SyncCursor, limit: i64) -> Result<SyncPage> {
+        let _timer = self.time_query("get_vaults_updated_since", || {
+            format!(
+                "slot={}, write_version={}, vault_address={}, limit={}",
+                since.slot, since.write_version, since.vault_address, limit
+            )
+        });
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM vault_states
+            WHERE (slot, write_version, vault_address) > ($1, $2, $3)
+              AND slot <= (SELECT MAX(slot) FROM vault_states) - $4
+            ORDER BY slot, write_version, vault_address
+            LIMIT $5
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(since.slot as i64)
+        .bind(since.write_version as i64)
+        .bind(&since.vault_address)
+        .bind(SYNC_SETTLE_SLOTS as i64)
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        let vaults = rows
+            .iter()
+            .map(Self::vault_state_from_row)
+            .collect::<Result<Vec<_>>>()?;
+        let next_cursor = vaults.last().map(SyncCursor::after).unwrap_or_else(|| since.clone());
+        
+        Ok(SyncPage { vaults, next_cursor })
+    }
+    
+    /// Owner changes and recorded vault states after `since`, in change
+    /// log order
+    ///
+    /// Unlike `get_vaults_updated_since`, every version is returned, read
+    /// from `vault_state_history_all`. Changes within `SYNC_SETTLE_SLOTS` of
+    /// the newest indexed slot are held back until they settle, so a cursor
+    /// never moves past a change that could still be committed behind it.
+    /// Addresses are compared bytewise, as `ChangeCursor` orders them.
+    pub async fn get_changes_since(&self, since: &ChangeCursor, limit: i64) -> Result<ChangePage> {
+        let _timer = self.time_query("get_changes_since", || {
+            format!(
+                "slot={}, write_version={}, vault_address={}, after_state={}, limit={}",
+                since.slot, since.write_version, since.vault_address, since.after_state, limit
+            )
+        });
+        let owner_rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM owner_changes
+            WHERE slot >= $1
+              AND (slot, write_version, vault_address COLLATE "C", 0) > ($1, $2, $3, $4)
+              AND slot <= (SELECT MAX(slot) FROM vault_states) - $5
+            ORDER BY slot, write_version, vault_address COLLATE "C", id
+            LIMIT $6
+            "#,
+            OWNER_CHANGE_COLUMNS
+        )))
+        .bind(since.slot as i64)
+        .bind(since.write_version as i64)
+        .bind(&since.vault_address)
+        .bind(since.after_state as i32)
+        .bind(SYNC_SETTLE_SLOTS as i64)
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        let state_rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM vault_state_history_all
+            WHERE slot >= $1
+              AND (slot, write_version, vault_address COLLATE "C", 1) > ($1, $2, $3, $4)
+              AND slot <= (SELECT MAX(slot) FROM vault_states) - $5
+            ORDER BY slot, write_version, vault_address COLLATE "C"
+            LIMIT $6
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(since.slot as i64)
+        .bind(since.write_version as i64)
+        .bind(&since.vault_address)
+        .bind(since.after_state as i32)
+        .bind(SYNC_SETTLE_SLOTS as i64)
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        let owner_changes = owner_rows
+            .iter()
+            .map(Self::owner_change_from_row)
+            .collect::<Result<Vec<_>>>()?;
+        let states = state_rows
+            .iter()
+            .map(Self::vault_state_from_row)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ChangePage::merge(since, owner_changes, states, limit))
+    }
+    
+    /// The `limit` most recently written active vaults, newest first
+    pub async fn get_recently_updated_vaults(&self, limit: i64) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_recently_updated_vaults", || format!("limit={}", limit));
+        let rows = sqlx::query(&self.sql(&format!(
+            "SELECT {} FROM vault_states WHERE status = 'active' \
+             ORDER BY slot DESC, write_version DESC LIMIT $1",
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// Recorded versions of a vault within an inclusive slot range, oldest first
+    pub async fn get_vault_history(
+        &self,
+        vault_address: &str,
+        from_slot: u64,
+        to_slot: u64,
+        limit: i64,
+    ) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_vault_history", || {
+            format!(
+                "vault_address={}, from_slot={}, to_slot={}, limit={}",
+                vault_address, from_slot, to_slot, limit
+            )
+        });
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM vault_state_history_all
+            WHERE vault_address = $1 AND slot BETWEEN $2 AND $3
+            ORDER BY slot, write_version
+            LIMIT $4
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(vault_address)
+        .bind(from_slot as i64)
+        .bind(to_slot as i64)
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// Recorded versions of a vault within a slot range, each with the
+    /// transaction that produced it when one was recorded
+    pub async fn get_vault_history_with_signatures(
+        &self,
+        vault_address: &str,
+        from_slot: u64,
+        to_slot: u64,
+        after: Option<(u64, u64)>,
+        limit: i64,
+    ) -> Result<Vec<VaultStateVersion>> {
+        let _timer = self.time_query("get_vault_history_with_signatures", || {
+            format!(
+                "vault_address={}, from_slot={}, to_slot={}, after={:?}, limit={}",
+                vault_address, from_slot, to_slot, after, limit
+            )
+        });
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT h.vault_address, h.owner, h.balance, h.assets, h.permissions,
+                   h.last_updated, h.slot, h.write_version, h.status, h.closed_at_slot, u.txn_signature
+            FROM vault_state_history_all h
+            LEFT JOIN LATERAL (
+                SELECT txn_signature FROM account_updates
+                WHERE pubkey = h.vault_address AND slot = h.slot AND write_version = h.write_version
+                LIMIT 1
+            ) u ON TRUE
+            WHERE h.vault_address = $1 AND h.slot BETWEEN $2 AND $3
+              AND (h.slot, h.write_version) > ($4, $5)
+            ORDER BY h.slot, h.write_version
+            LIMIT $6
+            "#
+        ))
+        .bind(vault_address)
+        .bind(from_slot as i64)
+        .bind(to_slot as i64)
+        // Stored versions are never negative, so this starts at the first
+        .bind(after.map_or(-1, |(slot, _)| slot as i64))
+        .bind(after.map_or(-1, |(_, write_version)| write_version as i64))
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_version_from_row).collect()
+    }
+    
+    /// Vault state versions produced by a transaction
+    pub async fn get_vault_versions_by_signature(&self, txn_signature: &str) -> Result<Vec<VaultStateVersion>> {
+        let _timer = self.time_query("get_vault_versions_by_signature", || {
+            format!("txn_signature={}", txn_signature)
+        });
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT h.vault_address, h.owner, h.balance, h.assets, h.permissions,
+                   h.last_updated, h.slot, h.write_version, h.status, h.closed_at_slot, u.txn_signature
+            FROM account_updates u
+            JOIN vault_state_history_all h
+              ON h.vault_address = u.pubkey AND h.slot = u.slot AND h.write_version = u.write_version
+            WHERE u.txn_signature = $1
+            ORDER BY h.slot, h.write_version
+            "#
+        ))
+        .bind(txn_signature)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_version_from_row).collect()
+    }
+    
+    /// Latest recorded version of a vault at or before a slot
+    pub async fn get_vault_state_at_slot(&self, vault_address: &str, slot: u64) -> Result<Option<VaultState>> {
+        let _timer = self.time_query("get_vault_state_at_slot", || {
+            format!("vault_address={}, slot={}", vault_address, slot)
+        });
+        let row = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM vault_state_history_all
+            WHERE vault_address = $1 AND slot <= $2
+            ORDER BY slot DESC, write_version DESC
+            LIMIT 1
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(vault_address)
+        .bind(slot as i64)
+        .fetch_optional(self.read_pool())
+        .await?;
+        
+        row.map(|row| Self::vault_state_from_row(&row)).transpose()
+    }
+    
+    /// State of a vault as of a wall-clock time
+    ///
+    /// The time is mapped to the last indexed block at or before it, so a
+    /// time inside a stretch the indexer was down resolves to the last
+    /// block before the outage. `None` if no block that early was indexed
+    /// or the vault didn't exist yet.
+    pub async fn get_vault_state_at_time(
+        &self,
+        vault_address: &str,
+        time: OffsetDateTime,
+    ) -> Result<Option<VaultState>> {
+        match self.get_slot_at_time(time).await? {
+            Some(slot) => self.get_vault_state_at_slot(vault_address, slot).await,
+            None => Ok(None),
+        }
+    }
+    
+    /// Last indexed slot whose block time is at or before `time`
+    pub async fn get_slot_at_time(&self, time: OffsetDateTime) -> Result<Option<u64>> {
+        let _timer = self.time_query("get_slot_at_time", || format!("time={}", time));
+        let slot: Option<i64> = sqlx::query_scalar(&self.sql(
+            r#"
+            SELECT slot FROM block_metadata
+            WHERE block_time <= $1
+            ORDER BY block_time DESC, slot DESC
+            LIMIT 1
+            "#
+        ))
+        .bind(time)
+        .fetch_optional(self.read_pool())
+        .await?;
+        
+        Ok(slot.map(|slot| slot as u64))
+    }
+    
+    /// Record a block's time; blocks already recorded are ignored
+    pub async fn insert_block_metadata(
+        &self,
+        slot: u64,
+        blockhash: &str,
+        block_height: Option<u64>,
+        block_time: OffsetDateTime,
+    ) -> Result<()> {
+        let _timer = self.time_query("insert_block_metadata", || format!("slot={}", slot));
+        sqlx::query(&self.sql(
+            r#"
+            INSERT INTO block_metadata (slot, blockhash, block_height, block_time)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (slot) DO NOTHING
+            "#
+        ))
+        .bind(slot as i64)
+        .bind(blockhash)
+        .bind(block_height.map(|height| height as i64))
+        .bind(block_time)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// State of every vault as of a slot, e.g. an end-of-epoch snapshot
+    ///
+    /// Pages run in descending address order, which lets Postgres walk the
+    /// history primary key backwards; pass the last address of a page as
+    /// `before` to get the next one. Vaults deleted since `slot` are
+    /// included, vaults created after it are not.
+    pub async fn get_vault_states_at_slot(
+        &self,
+        slot: u64,
+        before: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_vault_states_at_slot", || {
+            format!("slot={}, before={:?}, limit={}", slot, before, limit)
+        });
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT DISTINCT ON (vault_address) {} FROM vault_state_history_all
+            WHERE slot <= $1 AND ($2::text IS NULL OR vault_address < $2)
+            ORDER BY vault_address DESC, slot DESC, write_version DESC
+            LIMIT $3
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(slot as i64)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// Owner transitions of a vault, oldest first
+    pub async fn get_owner_history(&self, vault_address: &str) -> Result<Vec<OwnerChange>> {
+        let _timer = self.time_query("get_owner_history", || {
+            format!("vault_address={}", vault_address)
+        });
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM owner_changes
+            WHERE vault_address = $1
+            ORDER BY slot, write_version, id
+            "#,
+            OWNER_CHANGE_COLUMNS
+        )))
+        .bind(vault_address)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::owner_change_from_row).collect()
+    }
+    
+    /// Owner changes recorded after `after_id`, in id order
+    ///
+    /// Only rows at least `settle` old are returned: ids are assigned before
+    /// their transaction commits, so a younger row could still be followed
+    /// by a lower id. Reads the primary, since a lagging replica would let
+    /// the cursor move past rows it hasn't replayed yet.
+    pub async fn get_owner_changes_after(
+        &self,
+        after_id: i64,
+        settle: Duration,
+        limit: i64,
+    ) -> Result<Vec<OwnerChange>> {
+        let _timer = self.time_query("get_owner_changes_after", || {
+            format!("after_id={}, limit={}", after_id, limit)
+        });
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM owner_changes
+            WHERE id > $1 AND changed_at <= NOW() - $2 * INTERVAL '1 millisecond'
+            ORDER BY id
+            LIMIT $3
+            "#,
+            OWNER_CHANGE_COLUMNS
+        )))
+        .bind(after_id)
+        .bind(settle.as_millis() as i64)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter().map(Self::owner_change_from_row).collect()
+    }
+    
+    /// Highest recorded owner change id, 0 if there are none
+    pub async fn latest_owner_change_id(&self) -> Result<i64> {
+        let _timer = self.time_query("latest_owner_change_id", String::new);
+        let id: Option<i64> = sqlx::query_scalar(&self.sql("SELECT MAX(id) FROM owner_changes"))
+            .fetch_one(&self.pool)
+            .await?;
+        
+        Ok(id.unwrap_or(0))
+    }
+    
+    fn owner_change_from_row(row: &sqlx::postgres::PgRow) -> Result<OwnerChange> {
+        let slot: i64 = row.try_get(4)?;
+        let write_version: i64 = row.try_get(5)?;
+        
+        Ok(OwnerChange {
+            id: row.try_get(0)?,
+            vault_address: row.try_get(1)?,
+            previous_owner: row.try_get(2)?,
+            new_owner: row.try_get(3)?,
+            slot: slot as u64,
+            write_version: write_version as u64,
+            changed_at: row.try_get(6)?,
+        })
+    }
+    
+    /// Holdings of a mint across vaults, largest first
+    pub async fn get_vault_assets_by_mint(&self, mint: &str, limit: i64) -> Result<Vec<VaultAsset>> {
+        let _timer = self.time_query("get_vault_assets_by_mint", || {
+            format!("mint={}, limit={}", mint, limit)
+        });
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT vault_address, mint, amount, decimals
+            FROM vault_assets
+            WHERE mint = $1
+            ORDER BY amount DESC
+            LIMIT $2
+            "#
+        ))
+        .bind(mint)
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_asset_from_row).collect()
+    }
+    
+    /// The `n` largest vaults, by lamport balance or, with `mint`, by their
+    /// holding of that mint
+    ///
+    /// Both orders are read off an index (`vault_states(balance,
+    /// vault_address)` and `vault_assets(mint, amount DESC)`), so the cost
+    /// depends on `n`, not the number of vaults.
+    pub async fn get_top_vaults_by_balance(&self, n: i64, mint: Option<&str>) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_top_vaults_by_balance", || {
+            format!("n={}, mint={:?}", n, mint)
+        });
+        let rows = match mint {
+            None => {
+                sqlx::query(&self.sql(&format!(
+                    "SELECT {} FROM vault_states WHERE status = 'active' \
+                     ORDER BY balance DESC, vault_address DESC LIMIT $1",
+                    VAULT_STATE_COLUMNS
+                )))
+                .bind(n)
+                .fetch_all(self.read_pool())
+                .await?
+            }
+            Some(mint) => {
+                sqlx::query(&self.sql(&format!(
+                    r#"
+                    SELECT {} FROM vault_states
+                    JOIN (
+                        SELECT vault_address, amount FROM vault_assets
+                        WHERE mint = $1
+                        ORDER BY amount DESC
+                        LIMIT $2
+                    ) top USING (vault_address)
+                    ORDER BY top.amount DESC, vault_address
+                    "#,
+                    VAULT_STATE_COLUMNS
+                )))
+                .bind(mint)
+                .bind(n)
+                .fetch_all(self.read_pool())
+                .await?
+            }
+        };
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// Vaults where `pubkey` holds `min_role` or a higher role, for access
+    /// reviews
+    pub async fn get_vaults_by_permission(&self, pubkey: &str, min_role: PermissionType) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_vaults_by_permission", || {
+            format!("pubkey={}, min_role={}", pubkey, min_role.as_str())
+        });
+        let pubkey = Pubkey::from_str(pubkey)?;
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM vault_states
+            WHERE vault_address IN (
+                SELECT vault_address FROM vault_permissions
+                WHERE pubkey = $1 AND role_rank >= $2
+            )
+            ORDER BY vault_address
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(&pubkey.to_bytes()[..])
+        .bind(min_role.rank())
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// Vaults whose `assets` JSONB holds a mint
+    ///
+    /// Uses the GIN index on `vault_states.assets` via containment, so it
+    /// works without the normalized `vault_assets` table.
+    pub async fn find_vaults_holding_mint(&self, mint: &str) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("find_vaults_holding_mint", || format!("mint={}", mint));
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM vault_states
+            WHERE assets @> jsonb_build_object($1::text, '{{}}'::jsonb) AND status = 'active'
+            ORDER BY vault_address
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(mint)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// Vaults whose `assets` JSONB holds every listed mint
+    pub async fn find_vaults_holding_all_mints(&self, mints: &[String]) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("find_vaults_holding_all_mints", || {
+            format!("mints=[{} items]", mints.len())
+        });
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM vault_states
+            WHERE assets @> (
+                SELECT COALESCE(jsonb_object_agg(mint, '{{}}'::jsonb), '{{}}'::jsonb)
+                FROM UNNEST($1::text[]) AS mint
+            ) AND status = 'active'
+            ORDER BY vault_address
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(mints)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// Normalized asset rows of one vault
+    pub async fn get_vault_assets(&self, vault_address: &str) -> Result<Vec<VaultAsset>> {
+        let _timer = self.time_query("get_vault_assets", || {
+            format!("vault_address={}", vault_address)
+        });
+        let rows = sqlx::query(&self.sql(
+            "SELECT vault_address, mint, amount, decimals FROM vault_assets WHERE vault_address = $1 ORDER BY mint"
+        ))
+        .bind(vault_address)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::vault_asset_from_row).collect()
+    }
+    
+    fn vault_asset_from_row(row: &sqlx::postgres::PgRow) -> Result<VaultAsset> {
+        let amount: i64 = row.try_get(2)?;
+        let decimals: i16 = row.try_get(3)?;
+        
+        Ok(VaultAsset {
+            vault_address: row.try_get(0)?,
+            mint: row.try_get(1)?,
+            amount: amount as u64,
+            decimals: decimals as u8,
+        })
+    }
+    
+    /// Random sample of indexed vault states
+    pub async fn sample_vault_states(&self, limit: i64) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("sample_vault_states", || format!("limit={}", limit));
+        let rows = sqlx::query(&self.sql(&format!(
+            "SELECT {} FROM vault_states WHERE status = 'active' ORDER BY random() LIMIT $1",
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// Delete vault state by address
+    pub async fn delete_vault_state(&self, vault_address: &str) -> Result<bool> {
+        let _timer = self.time_query("delete_vault_state", || {
+            format!("vault_address={}", vault_address)
+        });
+        let result = sqlx::query(&self.sql("DELETE FROM vault_states WHERE vault_address = $1"))
+            .bind(vault_address)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+    
+    /// Expects `VAULT_STATE_COLUMNS` followed by `txn_signature`
+    fn vault_state_version_from_row(row: &sqlx::postgres::PgRow) -> Result<VaultStateVersion> {
+        Ok(VaultStateVersion {
+            state: Self::vault_state_from_row(row)?,
+            txn_signature: row.try_get(10)?,
+        })
+    }
+    
+    fn vault_state_from_row(row: &sqlx::postgres::PgRow) -> Result<VaultState> {
+        let vault_address_str: String = row.try_get(0)?;
+        let owner_str: String = row.try_get(1)?;
+        let balance: i64 = row.try_get(2)?;
+        let Json(assets): Json<HashMap<String, AssetBalance>> = row.try_get(3)?;
+        let Json(permissions): Json<Vec<Permission>> = row.try_get(4)?;
+        let last_updated: OffsetDateTime = row.try_get(5)?;
+        let slot: i64 = row.try_get(6)?;
+        let write_version: i64 = row.try_get(7)?;
+        let status: String = row.try_get(8)?;
+        let closed_at_slot: Option<i64> = row.try_get(9)?;
+        
+        let vault_address: Pubkey = vault_address_str.parse()?;
+        let owner: Pubkey = owner_str.parse()?;
+        
+        Ok(VaultState {
+            vault_address,
+            owner,
+            balance: balance as u64,
+            assets,
+            permissions,
+            last_updated,
+            slot: slot as u64,
+            write_version: write_version as u64,
+            status: status.parse()?,
+            closed_at_slot: closed_at_slot.map(|slot| slot as u64),
+        })
+    }
+    
+    /// Batch upsert vault states
+    ///
+    /// A row is only overwritten by a newer `(slot, write_version)`, so
+    /// late-arriving older versions can't regress it, though every version
+    /// is still recorded in `vault_state_history`. `vault_assets` follows the
+    /// applied states in the same transaction. Returns whether each state
+    /// was applied.
+    pub async fn batch_upsert_vault_states(&self, states: &[VaultState]) -> Result<Vec<bool>> {
+        let _timer = self.time_query("batch_upsert_vault_states", || {
+            format!("states=[{} items]", states.len())
+        });
+        let mut tx = self.pool.begin().await?;
+        let mut applied = Vec::with_capacity(states.len());
+        
+        for state in states {
+            let row = sqlx::query(&self.sql(
+                r#"
+                INSERT INTO vault_states (
+                    vault_address, owner, balance, assets, permissions,
+                    last_updated, slot, write_version, state_hash, status, closed_at_slot, updated_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW())
+                ON CONFLICT (vault_address) DO UPDATE SET
+                    owner = EXCLUDED.owner,
+                    balance = EXCLUDED.balance,
+                    assets = EXCLUDED.assets,
+                    permissions = EXCLUDED.permissions,
+                    last_updated = EXCLUDED.last_updated,
+                    slot = EXCLUDED.slot,
+                    write_version = EXCLUDED.write_version,
+                    state_hash = EXCLUDED.state_hash,
+                    status = EXCLUDED.status,
+                    closed_at_slot = EXCLUDED.closed_at_slot,
+                    updated_at = NOW()
+                WHERE (vault_states.slot, vault_states.write_version)
+                    < (EXCLUDED.slot, EXCLUDED.write_version)
+                RETURNING 1
+                "#
+            ))
+            .bind(state.vault_address.to_string())
+            .bind(state.owner.to_string())
+            .bind(state.balance as i64)
+            .bind(Json(&state.assets))
+            .bind(Json(&state.permissions))
+            .bind(state.last_updated)
+            .bind(state.slot as i64)
+            .bind(state.write_version as i64)
+            .bind(&state.content_hash()?[..])
+            .bind(state.status.as_str())
+            .bind(state.closed_at_slot.map(|slot| slot as i64))
+            .fetch_optional(&mut *tx)
+            .await?;
+            
+            applied.push(row.is_some());
+        }
+        
+        self.copy_history(&mut tx, states).await?;
+        
+        let applied_states: Vec<&VaultState> = states
+            .iter()
+            .zip(&applied)
+            .filter_map(|(state, applied)| applied.then_some(state))
+            .collect();
+        self.replace_vault_assets(&mut tx, &applied_states).await?;
+        self.replace_vault_permissions(&mut tx, &applied_states).await?;
+        self.notify_vault_changes(&mut tx, &applied_states).await?;
+        
+        tx.commit().await?;
+        Ok(applied)
+    }
+    
+    /// Bulk upsert via COPY into a staging table and one merge statement
+    ///
+    /// Same version guard and return value as `batch_upsert_vault_states`,
+    /// but one round trip for the whole batch; used for startup snapshots
+    /// and backfills. `states` must not repeat a vault address.
+    pub async fn copy_upsert_vault_states(&self, states: &[VaultState]) -> Result<Vec<bool>> {
+        let _timer = self.time_query("copy_upsert_vault_states", || {
+            format!("states=[{} items]", states.len())
+        });
+        let mut csv = String::with_capacity(states.len() * 256);
+        for state in states {
+            Self::push_csv_record(&mut csv, &[
+                Some(state.vault_address.to_string()),
+                Some(state.owner.to_string()),
+                Some(state.balance.to_string()),
+                Some(serde_json::to_string(&state.assets)?),
+                Some(serde_json::to_string(&state.permissions)?),
+                Some(state.last_updated.format(&Rfc3339)?),
+                Some(state.slot.to_string()),
+                Some(state.write_version.to_string()),
+                Some(Self::bytea_literal(&state.content_hash()?)),
+                Some(state.status.as_str().to_string()),
+                state.closed_at_slot.map(|slot| slot.to_string()),
+            ]);
+        }
+        
+        let mut tx = self.pool.begin().await?;
+        
+        sqlx::query(&self.sql(
+            r#"
+            CREATE TEMP TABLE vault_states_staging
+                (LIKE vault_states INCLUDING DEFAULTS) ON COMMIT DROP
+            "#
+        ))
+        .execute(&mut *tx)
+        .await?;
+        
+        let mut copy = tx
+            .copy_in_raw(&self.sql(
+                "COPY vault_states_staging (vault_address, owner, balance, assets, permissions, \
+                 last_updated, slot, write_version, state_hash, status, closed_at_slot) FROM STDIN (FORMAT csv)"
+            ))
+            .await?;
+        copy.send(csv.as_bytes()).await?;
+        copy.finish().await?;
+        
+        let rows = sqlx::query(&self.sql(
+            r#"
+            INSERT INTO vault_states (
+                vault_address, owner, balance, assets, permissions,
+                last_updated, slot, write_version, state_hash, status, closed_at_slot, updated_at
+            )
+            SELECT vault_address, owner, balance, assets, permissions,
+                last_updated, slot, write_version, state_hash, status, closed_at_slot, NOW()
+            FROM vault_states_staging
+            ON CONFLICT (vault_address) DO UPDATE SET
+                owner = EXCLUDED.owner,
+                balance = EXCLUDED.balance,
+                assets = EXCLUDED.assets,
+                permissions = EXCLUDED.permissions,
+                last_updated = EXCLUDED.last_updated,
+                slot = EXCLUDED.slot,
+                write_version = EXCLUDED.write_version,
+                state_hash = EXCLUDED.state_hash,
+                status = EXCLUDED.status,
+                closed_at_slot = EXCLUDED.closed_at_slot,
+                updated_at = NOW()
+            WHERE (vault_states.slot, vault_states.write_version)
+                < (EXCLUDED.slot, EXCLUDED.write_version)
+            RETURNING vault_address
+            "#
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+        
+        sqlx::query(&self.sql(
+            r#"
+            INSERT INTO vault_state_history (
+                vault_address, owner, balance, assets, permissions,
+                last_updated, slot, write_version, status, closed_at_slot
+            )
+            SELECT vault_address, owner, balance, assets, permissions,
+                last_updated, slot, write_version, status, closed_at_slot
+            FROM vault_states_staging
+            ON CONFLICT DO NOTHING
+            "#
+        ))
+        .execute(&mut *tx)
+        .await?;
+        
+        let applied: HashSet<String> = rows
+            .iter()
+            .map(|row| row.try_get(0))
+            .collect::<Result<_, _>>()?;
+        let applied: Vec<bool> = states
+            .iter()
+            .map(|state| applied.contains(&state.vault_address.to_string()))
+            .collect();
+        
+        let applied_states: Vec<&VaultState> = states
+            .iter()
+            .zip(&applied)
+            .filter_map(|(state, applied)| applied.then_some(state))
+            .collect();
+        self.replace_vault_assets(&mut tx, &applied_states).await?;
+        self.replace_vault_permissions(&mut tx, &applied_states).await?;
+        self.notify_vault_changes(&mut tx, &applied_states).await?;
+        
+        tx.commit().await?;
+        Ok(applied)
+    }
+    
+    /// Mark vaults closed by the given writes, keeping their last state as
+    /// a tombstone; returns the states of the vaults that were closed
+    ///
+    /// A closure only applies to an active vault whose stored version is
+    /// older, so a late closure can't hide a vault that was recreated.
+    /// The tombstone is recorded in `vault_state_history`, and the vault's
+    /// `vault_assets` and `vault_permissions` rows are removed.
+    pub async fn close_vaults(&self, closures: &[VaultClosure]) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("close_vaults", || {
+            format!("closures=[{} items]", closures.len())
+        });
+        if closures.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let addresses: Vec<String> = closures.iter().map(|closure| closure.vault_address.to_string()).collect();
+        let slots: Vec<i64> = closures.iter().map(|closure| closure.slot as i64).collect();
+        let write_versions: Vec<i64> = closures.iter().map(|closure| closure.write_version as i64).collect();
+        
+        let mut tx = self.pool.begin().await?;
+        let rows = sqlx::query(&self.sql(&format!(
+            r#"
+            UPDATE vault_states SET
+                status = 'closed',
+                closed_at_slot = c.closed_slot,
+                slot = c.closed_slot,
+                write_version = c.closed_write_version,
+                last_updated = NOW(),
+                updated_at = NOW()
+            FROM UNNEST($1::text[], $2::bigint[], $3::bigint[])
+                AS c(closed_address, closed_slot, closed_write_version)
+            WHERE vault_address = c.closed_address
+              AND status = 'active'
+              AND (slot, write_version) < (c.closed_slot, c.closed_write_version)
+            RETURNING {}
+            "#,
+            VAULT_STATE_COLUMNS
+        )))
+        .bind(&addresses)
+        .bind(&slots)
+        .bind(&write_versions)
+        .fetch_all(&mut *tx)
+        .await?;
+        
+        let closed = rows
+            .iter()
+            .map(Self::vault_state_from_row)
+            .collect::<Result<Vec<_>>>()?;
+        for state in &closed {
+            self.insert_history(&mut tx, state).await?;
+        }
+        
+        let closed_refs: Vec<&VaultState> = closed.iter().collect();
+        self.replace_vault_assets(&mut tx, &closed_refs).await?;
+        self.replace_vault_permissions(&mut tx, &closed_refs).await?;
+        self.notify_vault_changes(&mut tx, &closed_refs).await?;
+        
+        tx.commit().await?;
+        Ok(closed)
+    }
+    
+    /// Stored content hashes of the given vaults, keyed by address; vaults
+    /// without a row or a hash are left out, as are closed vaults, so a
+    /// recreated vault is always written
+    pub async fn get_state_hashes(&self, vault_addresses: &[String]) -> Result<HashMap<String, Vec<u8>>> {
+        let _timer = self.time_query("get_state_hashes", || {
+            format!("vault_addresses=[{} items]", vault_addresses.len())
+        });
+        let rows: Vec<(String, Vec<u8>)> = sqlx::query_as(&self.sql(
+            "SELECT vault_address, state_hash FROM vault_states \
+             WHERE vault_address = ANY($1) AND state_hash IS NOT NULL AND status = 'active'"
+        ))
+        .bind(vault_addresses)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows.into_iter().collect())
+    }
+    
+    /// Get latest slot indexed
+    pub async fn get_latest_slot(&self) -> Result<u64> {
+        let _timer = self.time_query("get_latest_slot", String::new);
+        let row = sqlx::query(&self.sql("SELECT COALESCE(MAX(slot), 0) FROM vault_states"))
+            .fetch_one(&self.pool)
+            .await?;
+        
+        let slot: i64 = row.try_get(0)?;
+        Ok(slot as u64)
+    }
+    
+    /// Attach a tag to a vault (no-op if already present)
+    pub async fn add_vault_tag(&self, vault_address: &str, tag: &str) -> Result<()> {
+        let _timer = self.time_query("add_vault_tag", || {
+            format!("vault_address={}, tag={}", vault_address, tag)
+        });
+        sqlx::query(&self.sql(
+            "INSERT INTO vault_tags (vault_address, tag) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+        ))
+        .bind(vault_address)
+        .bind(tag)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Remove a tag from a vault, returning whether it was present
+    pub async fn remove_vault_tag(&self, vault_address: &str, tag: &str) -> Result<bool> {
+        let _timer = self.time_query("remove_vault_tag", || {
+            format!("vault_address={}, tag={}", vault_address, tag)
+        });
+        let result = sqlx::query(&self.sql("DELETE FROM vault_tags WHERE vault_address = $1 AND tag = $2"))
+            .bind(vault_address)
+            .bind(tag)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+    
+    /// Get all tags attached to a vault
+    pub async fn get_vault_tags(&self, vault_address: &str) -> Result<Vec<String>> {
+        let _timer = self.time_query("get_vault_tags", || {
+            format!("vault_address={}", vault_address)
+        });
+        let tags = sqlx::query_scalar(&self.sql(
+            "SELECT tag FROM vault_tags WHERE vault_address = $1 ORDER BY tag"
+        ))
+        .bind(vault_address)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        Ok(tags)
+    }
+    
+    /// Get addresses of all vaults carrying a tag
+    pub async fn get_vaults_by_tag(&self, tag: &str) -> Result<Vec<String>> {
+        let _timer = self.time_query("get_vaults_by_tag", || format!("tag={}", tag));
+        let addresses = sqlx::query_scalar(&self.sql(
+            "SELECT vault_address FROM vault_tags WHERE tag = $1 ORDER BY vault_address"
+        ))
+        .bind(tag)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        Ok(addresses)
+    }
+    
+    /// Set the operator note of a vault, replacing any previous one
+    pub async fn set_vault_note(&self, vault_address: &str, note: &str) -> Result<()> {
+        let _timer = self.time_query("set_vault_note", || format!("vault_address={}", vault_address));
+        let note = encryption::seal(self.cipher.as_ref(), EncryptedColumn::VaultNotes, vault_address, note)?;
+        sqlx::query(&self.sql(
+            r#"
+            INSERT INTO vault_notes (vault_address, note)
+            VALUES ($1, $2)
+            ON CONFLICT (vault_address) DO UPDATE SET
+                note = EXCLUDED.note,
+                updated_at = NOW()
+            "#
+        ))
+        .bind(vault_address)
+        .bind(note.as_ref())
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Operator note of a vault
+    pub async fn get_vault_note(&self, vault_address: &str) -> Result<Option<String>> {
+        let _timer = self.time_query("get_vault_note", || format!("vault_address={}", vault_address));
+        let note: Option<String> = sqlx::query_scalar(&self.sql(
+            "SELECT note FROM vault_notes WHERE vault_address = $1"
+        ))
+        .bind(vault_address)
+        .fetch_optional(self.read_pool())
+        .await?;
+        
+        note.map(|note| encryption::open(self.cipher.as_ref(), EncryptedColumn::VaultNotes, vault_address, note))
+            .transpose()
+    }
+    
+    /// Remove the note of a vault, returning whether it had one
+    pub async fn delete_vault_note(&self, vault_address: &str) -> Result<bool> {
+        let _timer = self.time_query("delete_vault_note", || format!("vault_address={}", vault_address));
+        let result = sqlx::query(&self.sql("DELETE FROM vault_notes WHERE vault_address = $1"))
+            .bind(vault_address)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+    
+    /// Get addresses of all vaults owned by any of the given owners
+    pub async fn get_vault_addresses_by_owners(&self, owners: &[String]) -> Result<Vec<String>> {
+        let _timer = self.time_query("get_vault_addresses_by_owners", || {
+            format!("owners=[{} items]", owners.len())
+        });
+        let addresses = sqlx::query_scalar(&self.sql(
+            "SELECT vault_address FROM vault_states \
+             WHERE owner = ANY($1) AND status = 'active' ORDER BY vault_address"
+        ))
+        .bind(owners)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        Ok(addresses)
+    }
+    
+    /// Address of every active vault, to tell their closures apart
+    pub async fn get_active_vault_addresses(&self) -> Result<Vec<String>> {
+        let _timer = self.time_query("get_active_vault_addresses", String::new);
+        let addresses = sqlx::query_scalar(&self.sql(
+            "SELECT vault_address FROM vault_states WHERE status = 'active'"
+        ))
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        Ok(addresses)
+    }
+    
+    /// Address and lamport balance of every active vault, to seed the
+    /// cache leaderboard
+    pub async fn get_vault_balances(&self) -> Result<Vec<(String, u64)>> {
+        let _timer = self.time_query("get_vault_balances", String::new);
+        let rows: Vec<(String, i64)> = sqlx::query_as(&self.sql(
+            "SELECT vault_address, balance FROM vault_states WHERE status = 'active'"
+        ))
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        Ok(rows.into_iter().map(|(address, balance)| (address, balance as u64)).collect())
+    }
+    
+    /// Create or replace a named filter
+    pub async fn save_filter(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        filter: &VaultFilter,
+    ) -> Result<()> {
+        let _timer = self.time_query("save_filter", || format!("name={}", name));
+        let description = description
+            .map(|description| {
+                encryption::seal(self.cipher.as_ref(), EncryptedColumn::SavedFilterDescriptions, name, description)
+            })
+            .transpose()?;
+        sqlx::query(&self.sql(
+            r#"
+            INSERT INTO saved_filters (name, description, filter)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO UPDATE SET
+                description = EXCLUDED.description,
+                filter = EXCLUDED.filter,
+                updated_at = NOW()
+            "#
+        ))
+        .bind(name)
+        .bind(description.as_deref())
+        .bind(Json(filter))
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Get a named filter
+    pub async fn get_saved_filter(&self, name: &str) -> Result<Option<SavedFilter>> {
+        let _timer = self.time_query("get_saved_filter", || format!("name={}", name));
+        let row = sqlx::query(&self.sql(
+            r#"
+            SELECT name, description, filter, created_at, updated_at
+            FROM saved_filters
+            WHERE name = $1
+            "#
+        ))
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        row.map(|row| self.saved_filter_from_row(&row)).transpose()
+    }
+    
+    /// List all named filters
+    pub async fn list_saved_filters(&self) -> Result<Vec<SavedFilter>> {
+        let _timer = self.time_query("list_saved_filters", String::new);
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT name, description, filter, created_at, updated_at
+            FROM saved_filters
+            ORDER BY name
+            "#
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter().map(|row| self.saved_filter_from_row(row)).collect()
+    }
+    
+    /// Delete a named filter, returning whether it existed
+    pub async fn delete_saved_filter(&self, name: &str) -> Result<bool> {
+        let _timer = self.time_query("delete_saved_filter", || format!("name={}", name));
+        let result = sqlx::query(&self.sql("DELETE FROM saved_filters WHERE name = $1"))
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.rows_affected() > 0)
+    }
+    
+    fn saved_filter_from_row(&self, row: &sqlx::postgres::PgRow) -> Result<SavedFilter> {
+        let description: Option<String> = row.try_get(1)?;
+        let Json(filter): Json<VaultFilter> = row.try_get(2)?;
+        let name: String = row.try_get(0)?;
+        let description = description
+            .map(|description| {
+                encryption::open(self.cipher.as_ref(), EncryptedColumn::SavedFilterDescriptions, &name, description)
+            })
+            .transpose()?;
+        
+        Ok(SavedFilter {
+            name,
+            description,
+            filter,
+            created_at: row.try_get(3)?,
+            updated_at: row.try_get(4)?,
+        })
+    }
+    
+    /// API keys of `api_keys` that haven't been revoked
+    pub async fn get_api_keys(&self) -> Result<Vec<StoredApiKey>> {
+        let _timer = self.time_query("get_api_keys", String::new);
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT name, key_sha256, scopes
+            FROM api_keys
+            WHERE revoked_at IS NULL
+            "#
+        ))
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter()
+            .map(|row| {
+                let scopes: Vec<String> = row.try_get(2)?;
+                Ok(StoredApiKey {
+                    name: row.try_get(0)?,
+                    key_sha256: row.try_get(1)?,
+                    scopes: scopes.iter().map(|scope| scope.parse()).collect::<Result<_>>()?,
+                })
+            })
+            .collect()
+    }
+    
+    /// Persist failed updates with their error context
+    pub async fn insert_dead_letters(
+        &self,
+        updates: &[&AccountUpdate],
+        stage: DeadLetterStage,
+        error: &str,
+        attempts: u32,
+    ) -> Result<()> {
+        let _timer = self.time_query("insert_dead_letters", || {
+            format!(
+                "updates=[{} items], stage={}, attempts={}",
+                updates.len(), stage.as_str(), attempts
+            )
+        });
+        let mut tx = self.pool.begin().await?;
+        
+        for update in updates {
+            sqlx::query(&self.sql(
+                r#"
+                INSERT INTO dead_letter (
+                    pubkey, owner, lamports, executable, rent_epoch, data,
+                    slot, write_version, is_startup, stage, error, attempts, txn_signature
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                "#
+            ))
+            .bind(update.pubkey.to_string())
+            .bind(update.owner.to_string())
+            .bind(update.lamports as i64)
+            .bind(update.executable)
+            .bind(update.rent_epoch as i64)
+            .bind(&update.data)
+            .bind(update.slot as i64)
+            .bind(update.write_version as i64)
+            .bind(update.is_startup)
+            .bind(stage.as_str())
+            .bind(error)
+            .bind(attempts as i32)
+            .bind(update.txn_signature.map(|signature| signature.to_string()))
+            .execute(&mut *tx)
+            .await?;
+        }
+        
+        tx.commit().await?;
+        Ok(())
+    }
+    
+    /// List dead letters, oldest first
+    pub async fn list_dead_letters(&self, limit: i64, include_replayed: bool) -> Result<Vec<DeadLetter>> {
+        let _timer = self.time_query("list_dead_letters", || {
+            format!("limit={}, include_replayed={}", limit, include_replayed)
+        });
+        let rows = sqlx::query(&self.sql(&format!(
+            "SELECT {} FROM dead_letter WHERE $2 OR replayed_at IS NULL ORDER BY id LIMIT $1",
+            DEAD_LETTER_COLUMNS
+        )))
+        .bind(limit)
+        .bind(include_replayed)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter().map(Self::dead_letter_from_row).collect()
+    }
+    
+    /// Get dead letters by id
+    pub async fn get_dead_letters(&self, ids: &[i64]) -> Result<Vec<DeadLetter>> {
+        let _timer = self.time_query("get_dead_letters", || format!("ids=[{} items]", ids.len()));
+        let rows = sqlx::query(&self.sql(&format!(
+            "SELECT {} FROM dead_letter WHERE id = ANY($1) ORDER BY id",
+            DEAD_LETTER_COLUMNS
+        )))
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter().map(Self::dead_letter_from_row).collect()
+    }
+    
+    /// Mark dead letters as replayed
+    pub async fn mark_dead_letters_replayed(&self, ids: &[i64]) -> Result<()> {
+        let _timer = self.time_query("mark_dead_letters_replayed", || {
+            format!("ids=[{} items]", ids.len())
+        });
+        sqlx::query(&self.sql("UPDATE dead_letter SET replayed_at = NOW() WHERE id = ANY($1)"))
+            .bind(ids)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    fn dead_letter_from_row(row: &sqlx::postgres::PgRow) -> Result<DeadLetter> {
+        let pubkey: String = row.try_get(1)?;
+        let owner: String = row.try_get(2)?;
+        let lamports: i64 = row.try_get(3)?;
+        let rent_epoch: i64 = row.try_get(5)?;
+        let slot: i64 = row.try_get(7)?;
+        let write_version: i64 = row.try_get(8)?;
+        let stage: String = row.try_get(10)?;
+        let attempts: i32 = row.try_get(12)?;
+        let txn_signature: Option<String> = row.try_get(15)?;
+        
+        Ok(DeadLetter {
+            id: row.try_get(0)?,
+            update: AccountUpdate {
+                pubkey: pubkey.parse()?,
+                lamports: lamports as u64,
+                owner: owner.parse()?,
+                executable: row.try_get(4)?,
+                rent_epoch: rent_epoch as u64,
+                data: row.try_get(6)?,
+                write_version: write_version as u64,
+                slot: slot as u64,
+                is_startup: row.try_get(9)?,
+                txn_signature: txn_signature.map(|signature| signature.parse()).transpose()?,
+            },
+            stage: stage.parse()?,
+            error: row.try_get(11)?,
+            attempts: attempts as u32,
+            created_at: row.try_get(13)?,
+            replayed_at: row.try_get(14)?,
+        })
+    }
+    
+    /// Most recently indexed slot range, if any
+    pub async fn latest_slot_range(&self) -> Result<Option<SlotRange>> {
+        let _timer = self.time_query("latest_slot_range", String::new);
+        let row = sqlx::query(&self.sql(
+            "SELECT id, start_slot, end_slot FROM indexed_slot_ranges ORDER BY end_slot DESC LIMIT 1"
+        ))
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        row.map(|row| Self::slot_range_from_row(&row)).transpose()
+    }
+    
+    /// Record a new contiguous range of indexed slots
+    pub async fn insert_slot_range(&self, start_slot: u64, end_slot: u64) -> Result<SlotRange> {
+        let _timer = self.time_query("insert_slot_range", || {
+            format!("start_slot={}, end_slot={}", start_slot, end_slot)
+        });
+        let row = sqlx::query(&self.sql(
+            r#"
+            INSERT INTO indexed_slot_ranges (start_slot, end_slot)
+            VALUES ($1, $2)
+            RETURNING id, start_slot, end_slot
+            "#
+        ))
+        .bind(start_slot as i64)
+        .bind(end_slot as i64)
+        .fetch_one(&self.pool)
+        .await?;
+        
+        Self::slot_range_from_row(&row)
+    }
+    
+    /// Move the end of an indexed range forward
+    pub async fn extend_slot_range(&self, id: i64, end_slot: u64) -> Result<()> {
+        let _timer = self.time_query("extend_slot_range", || {
+            format!("id={}, end_slot={}", id, end_slot)
+        });
+        sqlx::query(&self.sql(
+            r#"
+            UPDATE indexed_slot_ranges
+            SET end_slot = GREATEST(end_slot, $2), updated_at = NOW()
+            WHERE id = $1
+            "#
+        ))
+        .bind(id)
+        .bind(end_slot as i64)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    fn slot_range_from_row(row: &sqlx::postgres::PgRow) -> Result<SlotRange> {
+        let start_slot: i64 = row.try_get(1)?;
+        let end_slot: i64 = row.try_get(2)?;
+        
+        Ok(SlotRange {
+            id: row.try_get(0)?,
+            start_slot: start_slot as u64,
+            end_slot: end_slot as u64,
+        })
+    }
+    
+    /// Record slots that were never indexed
+    pub async fn insert_slot_gap(&self, start_slot: u64, end_slot: u64) -> Result<()> {
+        let _timer = self.time_query("insert_slot_gap", || {
+            format!("start_slot={}, end_slot={}", start_slot, end_slot)
+        });
+        sqlx::query(&self.sql("INSERT INTO slot_gaps (start_slot, end_slot) VALUES ($1, $2)"))
+            .bind(start_slot as i64)
+            .bind(end_slot as i64)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    /// Gaps that have not been reconciled yet, oldest first
+    pub async fn unreconciled_slot_gaps(&self) -> Result<Vec<SlotGap>> {
+        let _timer = self.time_query("unreconciled_slot_gaps", String::new);
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT id, start_slot, end_slot, detected_at, reconciled_at
+            FROM slot_gaps
+            WHERE reconciled_at IS NULL
+            ORDER BY start_slot
+            "#
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter()
+            .map(|row| {
+                let start_slot: i64 = row.try_get(1)?;
+                let end_slot: i64 = row.try_get(2)?;
+                
+                Ok(SlotGap {
+                    id: row.try_get(0)?,
+                    start_slot: start_slot as u64,
+                    end_slot: end_slot as u64,
+                    detected_at: row.try_get(3)?,
+                    reconciled_at: row.try_get(4)?,
+                })
+            })
+            .collect()
+    }
+    
+    /// Mark gaps as reconciled
+    pub async fn mark_slot_gaps_reconciled(&self, ids: &[i64]) -> Result<()> {
+        let _timer = self.time_query("mark_slot_gaps_reconciled", || {
+            format!("ids=[{} items]", ids.len())
+        });
+        sqlx::query(&self.sql("UPDATE slot_gaps SET reconciled_at = NOW() WHERE id = ANY($1)"))
+            .bind(ids)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    /// Advance a shard's checkpoint; never moves it backwards
+    pub async fn upsert_shard_checkpoint(&self, shard: usize, slot: u64) -> Result<()> {
+        let _timer = self.time_query("upsert_shard_checkpoint", || {
+            format!("shard={}, slot={}", shard, slot)
+        });
+        sqlx::query(&self.sql(
+            r#"
+            INSERT INTO shard_checkpoints (shard, slot)
+            VALUES ($1, $2)
+            ON CONFLICT (shard) DO UPDATE SET
+                slot = GREATEST(shard_checkpoints.slot, EXCLUDED.slot),
+                updated_at = NOW()
+            "#
+        ))
+        .bind(shard as i32)
+        .bind(slot as i64)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// All shard checkpoints, by shard
+    pub async fn get_shard_checkpoints(&self) -> Result<Vec<ShardCheckpoint>> {
+        let _timer = self.time_query("get_shard_checkpoints", String::new);
+        let rows = sqlx::query(&self.sql("SELECT shard, slot, updated_at FROM shard_checkpoints ORDER BY shard"))
+            .fetch_all(&self.pool)
+            .await?;
+        
+        rows.iter()
+            .map(|row| {
+                let shard: i32 = row.try_get(0)?;
+                let slot: i64 = row.try_get(1)?;
+                
+                Ok(ShardCheckpoint {
+                    shard: shard as usize,
+                    slot: slot as u64,
+                    updated_at: row.try_get(2)?,
+                })
+            })
+            .collect()
+    }
+    
+    /// Replace every checkpoint with one at `slot` for each of `shards`
+    /// shards, in one transaction
+    pub async fn reset_shard_checkpoints(&self, shards: usize, slot: u64) -> Result<()> {
+        let _timer = self.time_query("reset_shard_checkpoints", || {
+            format!("shards={}, slot={}", shards, slot)
+        });
+        let mut tx = self.pool.begin().await?;
+        
+        sqlx::query(&self.sql("DELETE FROM shard_checkpoints"))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&self.sql(
+            "INSERT INTO shard_checkpoints (shard, slot) SELECT generate_series(0, $1 - 1), $2"
+        ))
+        .bind(shards as i32)
+        .bind(slot as i64)
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        Ok(())
+    }
+    
+    /// Create the `account_updates` partition covering a slot if missing,
+    /// returning its name
+    pub async fn ensure_account_updates_partition(&self, slot: u64) -> Result<String> {
+        let _timer = self.time_query("ensure_account_updates_partition", || {
+            format!("slot={}", slot)
+        });
+        let name = sqlx::query_scalar(&self.sql("SELECT ensure_account_updates_partition($1)"))
+            .bind(slot as i64)
+            .fetch_one(&self.pool)
+            .await?;
+        
+        Ok(name)
+    }
+    
+    /// Append raw account writes to `account_updates` with `COPY`
+    ///
+    /// Takes owned or borrowed updates. The table is append-only, so the
+    /// rows are streamed straight into it without a staging table. The
+    /// data bytes are stored as `AccountDataCodec` encodes them, and
+    /// byte-identical redeliveries of a stored write are skipped.
+    pub async fn copy_account_updates<U>(&self, updates: &[U]) -> Result<()>
+    where
+        U: Borrow<AccountUpdate> + Sync,
+    {
+        let _timer = self.time_query("copy_account_updates", || {
+            format!("updates=[{} items]", updates.len())
+        });
+        if updates.is_empty() {
+            return Ok(());
+        }
+        
+        let hashed: Vec<(&AccountUpdate, [u8; 32])> = updates
+            .iter()
+            .map(|update| {
+                let update = update.borrow();
+                (update, account_data::data_hash(&update.data))
+            })
+            .collect();
+        let stored = self.stored_account_writes(&hashed).await?;
+        let (updates, hashes): (Vec<&AccountUpdate>, Vec<[u8; 32]>) =
+            account_data::drop_redeliveries(hashed, &stored).into_iter().unzip();
+        if updates.is_empty() {
+            return Ok(());
+        }
+        
+        let batch = self.account_data.encode(&updates)?;
+        let mut csv = String::with_capacity(updates.len() * 512);
+        for ((update, hash), stored) in updates.iter().zip(&hashes).zip(&batch.rows) {
+            Self::push_csv_record(&mut csv, &[
+                Some(update.pubkey.to_string()),
+                Some(update.slot.to_string()),
+                Some(update.write_version.to_string()),
+                Some(Self::bytea_literal(&stored.data)),
+                update.txn_signature.map(|signature| signature.to_string()),
+                Some(update.owner.to_string()),
+                Some(update.lamports.to_string()),
+                Some(update.executable.to_string()),
+                Some(update.rent_epoch.to_string()),
+                Some(stored.compressed.to_string()),
+                stored.delta_base.map(|(slot, _)| slot.to_string()),
+                stored.delta_base.map(|(_, write_version)| write_version.to_string()),
+                Some(Self::bytea_literal(hash)),
+            ]);
+        }
+        
+        let mut conn = self.pool.acquire().await?;
+        let mut copy = conn
+            .copy_in_raw(&self.sql(
+                "COPY account_updates (pubkey, slot, write_version, data, txn_signature, \
+                 owner, lamports, executable, rent_epoch, data_compressed, \
+                 delta_base_slot, delta_base_write_version, data_hash) FROM STDIN (FORMAT csv)"
+            ))
+            .await?;
+        copy.send(csv.as_bytes()).await?;
+        copy.finish().await?;
+        self.account_data.commit(batch);
+        
+        Ok(())
+    }
+    
+    /// The writes of a batch already in `account_updates` with a data hash
+    async fn stored_account_writes(
+        &self,
+        updates: &[(&AccountUpdate, [u8; 32])],
+    ) -> Result<HashSet<account_data::StoredWrite>> {
+        let pubkeys: Vec<String> = updates.iter().map(|(update, _)| update.pubkey.to_string()).collect();
+        let slots: Vec<i64> = updates.iter().map(|(update, _)| update.slot as i64).collect();
+        let write_versions: Vec<i64> = updates.iter().map(|(update, _)| update.write_version as i64).collect();
+        
+        // The slot bounds let Postgres skip partitions outside the batch
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT pubkey, slot, write_version, data_hash FROM account_updates
+            WHERE slot BETWEEN $4 AND $5
+              AND (pubkey, slot, write_version) IN (
+                  SELECT * FROM UNNEST($1::text[], $2::bigint[], $3::bigint[])
+              )
+              AND data_hash IS NOT NULL
+            "#
+        ))
+        .bind(&pubkeys)
+        .bind(&slots)
+        .bind(&write_versions)
+        .bind(slots.iter().min().copied().unwrap_or(0))
+        .bind(slots.iter().max().copied().unwrap_or(0))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter()
+            .map(|row| {
+                let pubkey: String = row.try_get(0)?;
+                let slot: i64 = row.try_get(1)?;
+                let write_version: i64 = row.try_get(2)?;
+                let hash: Vec<u8> = row.try_get(3)?;
+                Ok((pubkey.parse()?, slot as u64, write_version as u64, hash.as_slice().try_into()?))
+            })
+            .collect()
+    }
+    
+    /// Every write recorded for an account in a slot, as the validator
+    /// delivered it, in write order
+    ///
+    /// Writes stored as deltas are rebuilt from the chain of earlier writes
+    /// they were diffed against, fetched in the same query.
+    pub async fn get_raw_account(&self, pubkey: &str, slot: u64) -> Result<Vec<RawAccount>> {
+        let _timer = self.time_query("get_raw_account", || {
+            format!("pubkey={}, slot={}", pubkey, slot)
+        });
+        let rows = sqlx::query(&self.sql(
+            r#"
+            WITH RECURSIVE chain AS (
+                SELECT pubkey, slot, write_version, data, owner, lamports, executable, rent_epoch,
+                       txn_signature, created_at, data_compressed, delta_base_slot, delta_base_write_version
+                FROM account_updates
+                WHERE pubkey = $1 AND slot = $2
+                UNION
+                SELECT u.pubkey, u.slot, u.write_version, u.data, u.owner, u.lamports, u.executable,
+                       u.rent_epoch, u.txn_signature, u.created_at, u.data_compressed,
+                       u.delta_base_slot, u.delta_base_write_version
+                FROM account_updates u
+                JOIN chain c
+                  ON u.pubkey = c.pubkey
+                 AND u.slot = c.delta_base_slot
+                 AND u.write_version = c.delta_base_write_version
+            )
+            SELECT * FROM chain
+            ORDER BY slot, write_version
+            "#
+        ))
+        .bind(pubkey)
+        .bind(slot as i64)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        let rows = rows.iter()
+            .map(|row| {
+                let pubkey: String = row.try_get(0)?;
+                let slot: i64 = row.try_get(1)?;
+                let write_version: i64 = row.try_get(2)?;
+                let data: Option<Vec<u8>> = row.try_get(3)?;
+                let owner: Option<String> = row.try_get(4)?;
+                let lamports: Option<i64> = row.try_get(5)?;
+                let rent_epoch: Option<i64> = row.try_get(7)?;
+                let txn_signature: Option<String> = row.try_get(8)?;
+                let data_compressed: bool = row.try_get(10)?;
+                let delta_base_slot: Option<i64> = row.try_get(11)?;
+                let delta_base_write_version: Option<i64> = row.try_get(12)?;
+                
+                let raw = RawAccount {
+                    pubkey: pubkey.parse()?,
+                    slot: slot as u64,
+                    write_version: write_version as u64,
+                    data: account_data::decompress(data.unwrap_or_default(), data_compressed)?,
+                    owner: owner.map(|owner| owner.parse()).transpose()?,
+                    lamports: lamports.map(|lamports| lamports as u64),
+                    executable: row.try_get(6)?,
+                    rent_epoch: rent_epoch.map(|rent_epoch| rent_epoch as u64),
+                    txn_signature: txn_signature.map(|signature| signature.parse()).transpose()?,
+                    received_at: row.try_get(9)?,
+                };
+                let delta_base = delta_base_slot
+                    .zip(delta_base_write_version)
+                    .map(|(slot, write_version)| (slot as u64, write_version as u64));
+                Ok((raw, delta_base))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        
+        account_data::resolve_deltas(rows, slot)
+    }
+    
+    /// Turn `vault_state_history` and `account_updates` into TimescaleDB
+    /// hypertables chunked by epoch, compressing chunks that end more than
+    /// `compress_after_epochs` behind the newest indexed slot
+    ///
+    /// Safe to repeat: tables that already are hypertables keep their data
+    /// and chunks, and only the compression policy is replaced. Timescale
+    /// can't adopt a natively partitioned table, so `account_updates` is
+    /// copied into a new hypertable inside the same transaction, which
+    /// locks it for the duration. Needs the `timescaledb` extension and
+    /// ownership of both tables.
+    pub async fn setup_timescale(&self, compress_after_epochs: u32) -> Result<()> {
+        let _timer = self.time_query("setup_timescale", || {
+            format!("compress_after_epochs={}", compress_after_epochs)
+        });
+        let version: Option<String> = sqlx::query_scalar(
+            "SELECT extversion FROM pg_extension WHERE extname = 'timescaledb'"
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(version) = version else {
+            anyhow::bail!(
+                "[timescale] needs the timescaledb extension; run CREATE EXTENSION timescaledb first"
+            );
+        };
+        
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SET LOCAL statement_timeout = 0")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(self.namespace.qualified_table("_sqlx_migrations"))
+            .execute(&mut *tx)
+            .await?;
+        
+        // Compression policies on an integer dimension measure age against
+        // this; the newest vault slot is an index lookup
+        sqlx::query(&self.sql(
+            r#"
+            CREATE OR REPLACE FUNCTION timescale_current_slot() RETURNS BIGINT
+            LANGUAGE SQL STABLE AS $$ SELECT COALESCE(MAX(slot), 0) FROM vault_states $$
+            "#
+        ))
+        .execute(&mut *tx)
+        .await?;
+        
+        if self.hypertable_compression(&mut tx, "vault_state_history").await?.is_none() {
+            sqlx::query(&self.sql(
+                r#"
+                SELECT create_hypertable('vault_state_history', 'slot',
+                    chunk_time_interval => $1::bigint, migrate_data => TRUE)
+                "#
+            ))
+            .bind(ACCOUNT_UPDATES_PARTITION_SLOTS as i64)
+            .execute(&mut *tx)
+            .await?;
+            info!("Converted vault_state_history to a hypertable");
+        }
+        
+        if self.hypertable_compression(&mut tx, "account_updates").await?.is_none() {
+            (&mut *tx).execute(&*self.sql(&format!(
+                r#"
+                ALTER TABLE account_updates RENAME TO account_updates_partitioned;
+                ALTER TABLE account_updates_partitioned
+                    RENAME CONSTRAINT account_updates_pkey TO account_updates_partitioned_pkey;
+                ALTER SEQUENCE account_updates_id_seq OWNED BY NONE;
+                DROP INDEX idx_account_updates_pubkey, idx_account_updates_slot,
+                    idx_account_updates_txn_signature, idx_account_updates_version;
+                
+                CREATE TABLE account_updates (
+                    LIKE account_updates_partitioned INCLUDING DEFAULTS,
+                    PRIMARY KEY (id, slot)
+                );
+                ALTER SEQUENCE account_updates_id_seq OWNED BY account_updates.id;
+                SELECT create_hypertable('account_updates', 'slot',
+                    chunk_time_interval => {}::bigint, create_default_indexes => FALSE);
+                
+                INSERT INTO account_updates SELECT * FROM account_updates_partitioned;
+                DROP TABLE account_updates_partitioned;
+                
+                CREATE INDEX idx_account_updates_pubkey ON account_updates(pubkey);
+                CREATE INDEX idx_account_updates_slot ON account_updates(slot);
+                CREATE INDEX idx_account_updates_txn_signature
+                    ON account_updates(txn_signature) WHERE txn_signature IS NOT NULL;
+                CREATE INDEX idx_account_updates_version
+                    ON account_updates(pubkey, slot, write_version);
+                "#,
+                ACCOUNT_UPDATES_PARTITION_SLOTS
+            ))).await?;
+            info!("Converted account_updates to a hypertable");
+        }
+        
+        let compress_after = compress_after_epochs as i64 * ACCOUNT_UPDATES_PARTITION_SLOTS as i64;
+        let tables = [("vault_state_history", "vault_address"), ("account_updates", "pubkey")];
+        for (table, segment_by) in tables {
+            // Compression settings can't change once chunks are compressed
+            if self.hypertable_compression(&mut tx, table).await? == Some(false) {
+                (&mut *tx).execute(&*self.sql(&format!(
+                    "ALTER TABLE {} SET (timescaledb.compress, timescaledb.compress_segmentby = '{}', \
+                     timescaledb.compress_orderby = 'slot DESC, write_version DESC')",
+                    table, segment_by
+                ))).await?;
+            }
+            
+            (&mut *tx).execute(&*self.sql(&format!(
+                r#"
+                SELECT set_integer_now_func('{0}', 'timescale_current_slot', replace_if_exists => TRUE);
+                SELECT remove_compression_policy('{0}', if_exists => TRUE);
+                SELECT add_compression_policy('{0}', compress_after => {1}::bigint);
+                "#,
+                table, compress_after
+            ))).await?;
+        }
+        
+        tx.commit().await?;
+        info!(
+            "TimescaleDB {} hypertables ready, compressing after {} epochs",
+            version, compress_after_epochs
+        );
+        
+        Ok(())
+    }
+    
+    /// Whether `setup_timescale` has converted both tables
+    pub async fn timescale_ready(&self) -> Result<bool> {
+        let _timer = self.time_query("timescale_ready", String::new);
+        let mut conn = self.pool.acquire().await?;
+        
+        for table in ["vault_state_history", "account_updates"] {
+            if self.hypertable_compression(&mut conn, table).await?.is_none() {
+                return Ok(false);
+            }
+        }
+        
+        Ok(true)
+    }
+    
+    /// Whether an indexer table is a hypertable with compression enabled;
+    /// `None` if it isn't a hypertable, or Timescale isn't installed
+    async fn hypertable_compression(&self, conn: &mut PgConnection, table: &str) -> Result<Option<bool>> {
+        let installed: bool = sqlx::query_scalar(
+            "SELECT to_regclass('timescaledb_information.hypertables') IS NOT NULL"
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if !installed {
+            return Ok(None);
+        }
+        
+        let compression = sqlx::query_scalar(
+            r#"
+            SELECT compression_enabled FROM timescaledb_information.hypertables
+            WHERE hypertable_schema = $1 AND hypertable_name = $2
+            "#
+        )
+        .bind(self.namespace.schema())
+        .bind(self.namespace.table(table))
+        .fetch_optional(&mut *conn)
+        .await?;
+        
+        Ok(compression)
+    }
+    
+    /// Drop `account_updates` hypertable chunks lying entirely below the
+    /// slot indexed at `before`; returns the number of rows removed
+    ///
+    /// The cutoff comes from recorded block times, so nothing is dropped
+    /// until `block_metadata` reaches back that far.
+    pub async fn drop_account_updates_chunks(&self, before: OffsetDateTime) -> Result<u64> {
+        let _timer = self.time_query("drop_account_updates_chunks", || {
+            format!("before={}", before)
+        });
+        let Some(slot) = self.get_slot_at_time(before).await? else {
+            return Ok(0);
+        };
+        // Chunks start at multiples of the interval
+        let below_slot = slot - slot % ACCOUNT_UPDATES_PARTITION_SLOTS;
+        
+        let mut tx = self.pool.begin().await?;
+        let count: i64 = sqlx::query_scalar(&self.sql(
+            "SELECT COUNT(*) FROM account_updates WHERE slot < $1"
+        ))
+        .bind(below_slot as i64)
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query(&self.sql("SELECT drop_chunks('account_updates', older_than => $1::bigint)"))
+            .bind(below_slot as i64)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        
+        Ok(count as u64)
+    }
+    
+    /// Partitions currently attached to `account_updates`, oldest first
+    pub async fn list_account_updates_partitions(&self) -> Result<Vec<SlotPartition>> {
+        let _timer = self.time_query("list_account_updates_partitions", String::new);
+        let names: Vec<String> = sqlx::query_scalar(&self.sql(
+            r#"
+            SELECT c.relname::text
+            FROM pg_inherits i
+            JOIN pg_class c ON c.oid = i.inhrelid
+            WHERE i.inhparent = 'account_updates'::regclass
+            "#
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let partition_prefix = self.namespace.table("account_updates_p");
+        let mut partitions: Vec<SlotPartition> = names
+            .into_iter()
+            .filter_map(|name| {
+                let index: u64 = name.strip_prefix(partition_prefix.as_str())?.parse().ok()?;
+                Some(SlotPartition {
+                    start_slot: index * ACCOUNT_UPDATES_PARTITION_SLOTS,
+                    end_slot: (index + 1) * ACCOUNT_UPDATES_PARTITION_SLOTS,
+                    name,
+                })
+            })
+            .collect();
+        partitions.sort_by_key(|partition| partition.start_slot);
+        
+        Ok(partitions)
+    }
+    
+    /// Detach every `account_updates` partition lying entirely below a
+    /// slot, dropping it too if `drop` is set
+    ///
+    /// Detached partitions stay in the database as standalone tables for
+    /// archiving. Returns the partitions affected.
+    pub async fn detach_account_updates_partitions(
+        &self,
+        below_slot: u64,
+        drop: bool,
+    ) -> Result<Vec<SlotPartition>> {
+        let _timer = self.time_query("detach_account_updates_partitions", || {
+            format!("below_slot={}, drop={}", below_slot, drop)
+        });
+        let expired: Vec<SlotPartition> = self
+            .list_account_updates_partitions()
+            .await?
+            .into_iter()
+            .filter(|partition| partition.end_slot <= below_slot)
+            .collect();
+        
+        for partition in &expired {
+            sqlx::query(&self.sql(&format!(
+                "ALTER TABLE account_updates DETACH PARTITION \"{}\"",
+                partition.name
+            )))
+            .execute(&self.pool)
+            .await?;
+            
+            if drop {
+                sqlx::query(&self.sql(&format!("DROP TABLE \"{}\"", partition.name)))
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+        
+        Ok(expired)
+    }
+    
+    /// Delete `account_updates` rows created before a cutoff
+    ///
+    /// With `drop_partitions`, partitions holding only expired rows are
+    /// detached and dropped whole, which needs ownership of the table; the
+    /// rest are deleted `batch_size` rows at a time. Returns the number of
+    /// rows removed.
+    pub async fn prune_account_updates(
+        &self,
+        before: OffsetDateTime,
+        batch_size: i64,
+        drop_partitions: bool,
+    ) -> Result<u64> {
+        let _timer = self.time_query("prune_account_updates", || {
+            format!(
+                "before={}, batch_size={}, drop_partitions={}",
+                before, batch_size, drop_partitions
+            )
+        });
+        let mut pruned = 0;
+        
+        let partitions = if drop_partitions {
+            self.list_account_updates_partitions().await?
+        } else {
+            Vec::new()
+        };
+        
+        for partition in partitions {
+            let row = sqlx::query(&self.sql(&format!(
+                "SELECT COUNT(*), MAX(created_at) FROM \"{}\"",
+                partition.name
+            )))
+            .fetch_one(&self.pool)
+            .await?;
+            let count: i64 = row.try_get(0)?;
+            let newest: Option<OffsetDateTime> = row.try_get(1)?;
+            
+            // Empty partitions are usually pre-created for upcoming slots
+            if newest.is_some_and(|newest| newest < before) {
+                sqlx::query(&self.sql(&format!(
+                    "ALTER TABLE account_updates DETACH PARTITION \"{}\"",
+                    partition.name
+                )))
+                .execute(&self.pool)
+                .await?;
+                sqlx::query(&self.sql(&format!("DROP TABLE \"{}\"", partition.name)))
+                    .execute(&self.pool)
+                    .await?;
+                pruned += count as u64;
+            }
+        }
+        
+        loop {
+            let result = sqlx::query(&self.sql(
+                r#"
+                DELETE FROM account_updates
+                WHERE (id, slot) IN (
+                    SELECT id, slot FROM account_updates WHERE created_at < $1 LIMIT $2
+                )
+                "#
+            ))
+            .bind(before)
+            .bind(batch_size)
+            .execute(&self.pool)
+            .await?;
+            
+            pruned += result.rows_affected();
+            if result.rows_affected() < batch_size as u64 {
+                return Ok(pruned);
+            }
+        }
+    }
+    
+    /// Delete `vault_state_history` versions recorded before a cutoff,
+    /// `batch_size` rows at a time; returns the number of rows removed
+    ///
+    /// A version is only removed once a newer version of the vault was also
+    /// recorded before the cutoff, so the state at any slot since the cutoff
+    /// can still be reconstructed, even for vaults unchanged since.
+    pub async fn prune_vault_state_history(&self, before: OffsetDateTime, batch_size: i64) -> Result<u64> {
+        let _timer = self.time_query("prune_vault_state_history", || {
+            format!("before={}, batch_size={}", before, batch_size)
+        });
+        let mut pruned = 0;
+        
+        loop {
+            let result = sqlx::query(&self.sql(
+                r#"
+                DELETE FROM vault_state_history
+                WHERE (vault_address, slot, write_version) IN (
+                    SELECT h.vault_address, h.slot, h.write_version FROM vault_state_history h
+                    WHERE h.recorded_at < $1
+                      AND EXISTS (
+                          SELECT 1 FROM vault_state_history n
+                          WHERE n.vault_address = h.vault_address
+                            AND (n.slot, n.write_version) > (h.slot, h.write_version)
+                            AND n.recorded_at < $1
+                      )
+                    LIMIT $2
+                )
+                "#
+            ))
+            .bind(before)
+            .bind(batch_size)
+            .execute(&self.pool)
+            .await?;
+            
+            pruned += result.rows_affected();
+            if result.rows_affected() < batch_size as u64 {
+                return Ok(pruned);
+            }
+        }
+    }
+    
+    /// Move `vault_state_history` versions recorded before a cutoff to
+    /// `vault_state_history_archive`, `batch_size` rows at a time; returns
+    /// the number of rows moved
+    ///
+    /// Versions are chosen as in `prune_vault_state_history`, so each
+    /// vault's newest version before the cutoff stays in the hot table as a
+    /// stub, and point-in-time reads since the cutoff never need the
+    /// archive. History reads go through `vault_state_history_all` and see
+    /// both tables.
+    pub async fn archive_vault_state_history(&self, before: OffsetDateTime, batch_size: i64) -> Result<u64> {
+        let _timer = self.time_query("archive_vault_state_history", || {
+            format!("before={}, batch_size={}", before, batch_size)
+        });
+        let mut archived = 0;
+        
+        loop {
+            let moved: i64 = sqlx::query_scalar(&self.sql(
+                r#"
+                WITH moved AS (
+                    DELETE FROM vault_state_history
+                    WHERE (vault_address, slot, write_version) IN (
+                        SELECT h.vault_address, h.slot, h.write_version FROM vault_state_history h
+                        WHERE h.recorded_at < $1
+                          AND EXISTS (
+                              SELECT 1 FROM vault_state_history n
+                              WHERE n.vault_address = h.vault_address
+                                AND (n.slot, n.write_version) > (h.slot, h.write_version)
+                                AND n.recorded_at < $1
+                          )
+                        LIMIT $2
+                    )
+                    RETURNING vault_address, slot, write_version, owner, balance, assets, permissions,
+                              last_updated, recorded_at, status, closed_at_slot
+                ), archived AS (
+                    INSERT INTO vault_state_history_archive (
+                        vault_address, slot, write_version, owner, balance, assets, permissions,
+                        last_updated, recorded_at, status, closed_at_slot
+                    )
+                    SELECT * FROM moved
+                    ON CONFLICT DO NOTHING
+                )
+                SELECT COUNT(*) FROM moved
+                "#
+            ))
+            .bind(before)
+            .bind(batch_size)
+            .fetch_one(&self.pool)
+            .await?;
+            
+            archived += moved as u64;
+            if moved < batch_size {
+                return Ok(archived);
+            }
+        }
+    }
+    
+    /// Refresh one of `AGGREGATE_VIEWS` without blocking readers
+    pub async fn refresh_aggregate_view(&self, view: &str) -> Result<()> {
+        let _timer = self.time_query("refresh_aggregate_view", || format!("view={}", view));
+        if !AGGREGATE_VIEWS.contains(&view) {
+            anyhow::bail!("Unknown aggregate view '{}'", view);
+        }
+        
+        // Goes through a definer function, since refreshing needs ownership
+        sqlx::query(&self.sql("SELECT refresh_aggregate_view($1)"))
+            .bind(self.namespace.table(view))
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    /// Vault count and total balance of one owner, as of the last refresh
+    pub async fn get_owner_totals(&self, owner: &str) -> Result<Option<OwnerTotals>> {
+        let _timer = self.time_query("get_owner_totals", || format!("owner={}", owner));
+        let row = sqlx::query(&self.sql(
+            "SELECT owner, vault_count, total_balance::text FROM mv_owner_totals WHERE owner = $1"
+        ))
+        .bind(owner)
+        .fetch_optional(self.read_pool())
+        .await?;
+        
+        row.map(|row| Self::owner_totals_from_row(&row)).transpose()
+    }
+    
+    /// Owners with the largest total balance, as of the last refresh
+    pub async fn list_owner_totals(&self, limit: i64) -> Result<Vec<OwnerTotals>> {
+        let _timer = self.time_query("list_owner_totals", || format!("limit={}", limit));
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT owner, vault_count, total_balance::text
+            FROM mv_owner_totals
+            ORDER BY total_balance DESC
+            LIMIT $1
+            "#
+        ))
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::owner_totals_from_row).collect()
+    }
+    
+    fn owner_totals_from_row(row: &sqlx::postgres::PgRow) -> Result<OwnerTotals> {
+        let vault_count: i64 = row.try_get(1)?;
+        let total_balance: String = row.try_get(2)?;
+        
+        Ok(OwnerTotals {
+            owner: row.try_get(0)?,
+            vault_count: vault_count as u64,
+            total_balance: total_balance.parse()?,
+        })
+    }
+    
+    /// Overall vault and owner counts, as of the last refresh
+    pub async fn get_vault_counts(&self) -> Result<VaultCounts> {
+        let _timer = self.time_query("get_vault_counts", String::new);
+        let row = sqlx::query(&self.sql(
+            "SELECT vault_count, owner_count, total_balance::text, max_slot FROM mv_vault_counts"
+        ))
+        .fetch_optional(self.read_pool())
+        .await?;
+        
+        let Some(row) = row else {
+            return Ok(VaultCounts::default());
+        };
+        let vault_count: i64 = row.try_get(0)?;
+        let owner_count: i64 = row.try_get(1)?;
+        let total_balance: String = row.try_get(2)?;
+        let max_slot: i64 = row.try_get(3)?;
+        
+        Ok(VaultCounts {
+            vault_count: vault_count as u64,
+            owner_count: owner_count as u64,
+            total_balance: total_balance.parse()?,
+            max_slot: max_slot as u64,
+        })
+    }
+    
+    /// Mints with the largest total amount held, as of the last refresh
+    pub async fn list_mint_totals(&self, limit: i64) -> Result<Vec<MintTotals>> {
+        let _timer = self.time_query("list_mint_totals", || format!("limit={}", limit));
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT mint, holder_count, total_amount::text
+            FROM mv_mint_totals
+            ORDER BY total_amount DESC
+            LIMIT $1
+            "#
+        ))
+        .bind(limit)
+        .fetch_all(self.read_pool())
+        .await?;
+        
+        rows.iter().map(Self::mint_totals_from_row).collect()
+    }
+    
+    /// Total lamport balance and per-mint totals across all vaults, computed
+    /// from the base tables rather than the aggregate views
+    ///
+    /// Both sums are read from one snapshot. This scans `vault_states` and
+    /// `vault_assets`, so callers serving dashboards should go through
+    /// `Indexer::get_total_value_locked`, which caches the result.
+    pub async fn get_total_value_locked(&self) -> Result<TotalValueLocked> {
+        let _timer = self.time_query("get_total_value_locked", String::new);
+        let mut tx = self.read_pool().begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+        
+        let total_balance: String = sqlx::query_scalar(&self.sql(
+            "SELECT COALESCE(SUM(balance), 0)::text FROM vault_states WHERE status = 'active'"
+        ))
+        .fetch_one(&mut *tx)
+        .await?;
+        let rows = sqlx::query(&self.sql(
+            r#"
+            SELECT mint, COUNT(*), SUM(amount)::text
+            FROM vault_assets
+            GROUP BY mint
+            ORDER BY SUM(amount) DESC
+            "#
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        
+        Ok(TotalValueLocked {
+            total_balance: total_balance.parse()?,
+            mints: rows.iter().map(Self::mint_totals_from_row).collect::<Result<_>>()?,
+            computed_at: OffsetDateTime::now_utc(),
+        })
+    }
+    
+    /// Row of mint, holder count and total amount (as text)
+    fn mint_totals_from_row(row: &sqlx::postgres::PgRow) -> Result<MintTotals> {
+        let holder_count: i64 = row.try_get(1)?;
+        let total_amount: String = row.try_get(2)?;
+        
+        Ok(MintTotals {
+            mint: row.try_get(0)?,
+            holder_count: holder_count as u64,
+            total_amount: total_amount.parse()?,
+        })
+    }
+    
+    /// Record an automatic repair with the state before and after
+    pub async fn insert_vault_repair(
+        &self,
+        vault_address: &str,
+        reason: &str,
+        previous: Option<&serde_json::Value>,
+        repaired: Option<&serde_json::Value>,
+    ) -> Result<()> {
+        let _timer = self.time_query("insert_vault_repair", || {
+            format!("vault_address={}, reason={}", vault_address, reason)
+        });
+        sqlx::query(&self.sql(
+            r#"
+            INSERT INTO vault_repairs (vault_address, reason, previous, repaired)
+            VALUES ($1, $2, $3, $4)
+            "#
+        ))
+        .bind(vault_address)
+        .bind(reason)
+        .bind(previous)
+        .bind(repaired)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Most recent unfinished backfill job for a program and paging filter
+    pub async fn find_resumable_backfill_job(
+        &self,
+        program_id: &str,
+        prefix_offset: usize,
+    ) -> Result<Option<BackfillJob>> {
+        let _timer = self.time_query("find_resumable_backfill_job", || {
+            format!("program_id={}, prefix_offset={}", program_id, prefix_offset)
+        });
+        let row = sqlx::query(&self.sql(&format!(
+            r#"
+            SELECT {} FROM backfill_jobs
+            WHERE program_id = $1 AND prefix_offset = $2 AND status <> 'completed'
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+            BACKFILL_JOB_COLUMNS
+        )))
+        .bind(program_id)
+        .bind(prefix_offset as i32)
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        row.map(|row| Self::backfill_job_from_row(&row)).transpose()
+    }
+    
+    /// Start a new backfill job at the first page
+    pub async fn create_backfill_job(&self, program_id: &str, prefix_offset: usize) -> Result<BackfillJob> {
+        let _timer = self.time_query("create_backfill_job", || {
+            format!("program_id={}, prefix_offset={}", program_id, prefix_offset)
+        });
+        let row = sqlx::query(&self.sql(&format!(
+            "INSERT INTO backfill_jobs (program_id, prefix_offset) VALUES ($1, $2) RETURNING {}",
+            BACKFILL_JOB_COLUMNS
+        )))
+        .bind(program_id)
+        .bind(prefix_offset as i32)
+        .fetch_one(&self.pool)
+        .await?;
+        
+        Self::backfill_job_from_row(&row)
+    }
+    
+    /// Record a completed page and advance the cursor
+    pub async fn advance_backfill_job(&self, id: i64, next_prefix: u16, accounts: u64) -> Result<()> {
+        let _timer = self.time_query("advance_backfill_job", || {
+            format!("id={}, next_prefix={}, accounts={}", id, next_prefix, accounts)
+        });
+        sqlx::query(&self.sql(
+            r#"
+            UPDATE backfill_jobs SET
+                next_prefix = $2,
+                pages_done = pages_done + 1,
+                accounts_done = accounts_done + $3,
+                status = 'running',
+                updated_at = NOW()
+            WHERE id = $1
+            "#
+        ))
+        .bind(id)
+        .bind(next_prefix as i32)
+        .bind(accounts as i64)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Set a job's status, recording the error if it failed
+    pub async fn set_backfill_job_status(
+        &self,
+        id: i64,
+        status: BackfillJobStatus,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let _timer = self.time_query("set_backfill_job_status", || {
+            format!("id={}, status={}", id, status.as_str())
+        });
+        sqlx::query(&self.sql(
+            r#"
+            UPDATE backfill_jobs SET
+                status = $2,
+                error_count = error_count + CASE WHEN $3::TEXT IS NULL THEN 0 ELSE 1 END,
+                last_error = COALESCE($3, last_error),
+                updated_at = NOW()
+            WHERE id = $1
+            "#
+        ))
+        .bind(id)
+        .bind(status.as_str())
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Backfill jobs, newest first
+    pub async fn list_backfill_jobs(&self, limit: i64) -> Result<Vec<BackfillJob>> {
+        let _timer = self.time_query("list_backfill_jobs", || format!("limit={}", limit));
+        let rows = sqlx::query(&self.sql(&format!(
+            "SELECT {} FROM backfill_jobs ORDER BY id DESC LIMIT $1",
+            BACKFILL_JOB_COLUMNS
+        )))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter().map(Self::backfill_job_from_row).collect()
+    }
+    
+    fn backfill_job_from_row(row: &sqlx::postgres::PgRow) -> Result<BackfillJob> {
+        let prefix_offset: i32 = row.try_get(2)?;
+        let next_prefix: i32 = row.try_get(3)?;
+        let pages_done: i32 = row.try_get(4)?;
+        let accounts_done: i64 = row.try_get(5)?;
+        let status: String = row.try_get(6)?;
+        let error_count: i32 = row.try_get(7)?;
+        
+        Ok(BackfillJob {
+            id: row.try_get(0)?,
+            program_id: row.try_get(1)?,
+            prefix_offset: prefix_offset as usize,
+            next_prefix: next_prefix as u16,
+            pages_done: pages_done as u32,
+            accounts_done: accounts_done as u64,
+            status: status.parse()?,
+            error_count: error_count as u32,
+            last_error: row.try_get(8)?,
+            started_at: row.try_get(9)?,
+            updated_at: row.try_get(10)?,
+        })
+    }
+}
+
+/// Records a `Database` call's duration when dropped, so calls returning
+/// early through `?` are measured too
+pub(crate) struct QueryTimer<F: FnOnce() -> String> {
+    query: &'static str,
+    binds: Option<F>,
+    threshold: Option<Duration>,
+    started: Instant,
+}
+
+impl<F: FnOnce() -> String> QueryTimer<F> {
+    pub(crate) fn new(query: &'static str, binds: F, threshold: Option<Duration>) -> Self {
+        Self {
+            query,
+            binds: Some(binds),
+            threshold,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl<F: FnOnce() -> String> Drop for QueryTimer<F> {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        metrics::histogram!("indexer_db_query_seconds", "query" => self.query)
+            .record(elapsed.as_secs_f64());
+        
+        if self.threshold.is_some_and(|threshold| elapsed >= threshold) {
+            let binds = self.binds.take().map(|binds| binds()).unwrap_or_default();
+            warn!("Slow query {} took {:?} ({})", self.query, elapsed, binds);
+            metrics::counter!("indexer_db_slow_queries_total", "query" => self.query).increment(1);
+        }
     }
 }
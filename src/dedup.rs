@@ -0,0 +1,68 @@
+use crate::types::AccountUpdate;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tracing::info;
+
+/// Drops duplicate and superseded updates while the startup snapshot and the
+/// live stream overlap
+///
+/// During startup the same account can arrive from the snapshot and from
+/// early live updates, in either order. Each account's highest
+/// `(slot, write_version)` is remembered, and anything at or below it is
+/// rejected, so every version is written once and an older snapshot copy
+/// never lands after a newer live one. Once end-of-startup is signalled the
+/// live stream is ordered per account again and the map is dropped.
+pub struct UpdateDeduplicator {
+    active: AtomicBool,
+    /// Latest version per account, partitioned like the shard workers
+    seen: Vec<Mutex<HashMap<Pubkey, (u64, u64)>>>,
+}
+
+impl UpdateDeduplicator {
+    pub fn new(partitions: usize) -> Self {
+        Self {
+            active: AtomicBool::new(true),
+            seen: (0..partitions.max(1)).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+    
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+    
+    /// Whether the update is newer than any version already admitted
+    pub fn admit(&self, update: &AccountUpdate, partition: usize) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        
+        let version = (update.slot, update.write_version);
+        let mut seen = self.seen[partition].lock().unwrap();
+        
+        match seen.get(&update.pubkey) {
+            Some(&latest) if version <= latest => {
+                let reason = if version == latest { "duplicate" } else { "superseded" };
+                metrics::counter!("indexer_dedup_dropped_total", "reason" => reason).increment(1);
+                false
+            }
+            _ => {
+                seen.insert(update.pubkey, version);
+                true
+            }
+        }
+    }
+    
+    /// Stop deduplicating and free the version map
+    pub fn finish_startup(&self) {
+        if self.active.swap(false, Ordering::AcqRel) {
+            let tracked: usize = self
+                .seen
+                .iter()
+                .map(|partition| std::mem::take(&mut *partition.lock().unwrap()).len())
+                .sum();
+            info!("Startup dedup finished after tracking {} accounts", tracked);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::types::{AccountUpdate, DegradationConfig, DegradationLevel};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use tracing::warn;
+
+/// Emergency filtering driven by pipeline backlog
+///
+/// Levels are ordered by `min_pending_updates`. A level activates once the
+/// backlog reaches its threshold and deactivates when the backlog falls
+/// below half of it, so the mode doesn't flap around a single threshold.
+/// Level 0 means normal operation.
+pub struct DegradationController {
+    /// Replaced whole by a config reload
+    levels: RwLock<Vec<DegradationLevel>>,
+    active: AtomicUsize,
+    /// Per-account update counters used for hot-account sampling
+    hot_counters: Mutex<HashMap<Pubkey, u32>>,
+}
+
+impl DegradationController {
+    pub fn new(config: &DegradationConfig) -> Self {
+        metrics::gauge!("indexer_degradation_level").set(0.0);
+        
+        Self {
+            levels: RwLock::new(Self::sorted_levels(config)),
+            active: AtomicUsize::new(0),
+            hot_counters: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Swap in the levels of `config`, returning to normal operation until
+    /// the next backlog observation picks a level among them
+    pub fn set_levels(&self, config: &DegradationConfig) {
+        let mut levels = self.levels.write().unwrap();
+        *levels = Self::sorted_levels(config);
+        self.active.store(0, Ordering::Relaxed);
+        self.hot_counters.lock().unwrap().clear();
+        metrics::gauge!("indexer_degradation_level").set(0.0);
+    }
+    
+    fn sorted_levels(config: &DegradationConfig) -> Vec<DegradationLevel> {
+        let mut levels = config.levels.clone();
+        levels.sort_by_key(|level| level.min_pending_updates);
+        levels
+    }
+    
+    /// Currently active level (0 = normal)
+    pub fn active_level(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+    
+    /// Re-evaluate the active level against the current backlog
+    pub fn observe_backlog(&self, pending: u64) -> usize {
+        let levels = self.levels.read().unwrap();
+        let current = self.active_level();
+        let mut next = levels
+            .iter()
+            .rposition(|level| pending >= level.min_pending_updates)
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        
+        // Hysteresis: hold a level until the backlog halves
+        if next < current && pending >= levels[current - 1].min_pending_updates / 2 {
+            next = current;
+        }
+        
+        if next != current
+            && self
+                .active
+                .compare_exchange(current, next, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            warn!(
+                "Degradation level {} -> {} (pending updates: {})",
+                current, next, pending
+            );
+            metrics::gauge!("indexer_degradation_level").set(next as f64);
+            
+            if next == 0 {
+                self.hot_counters.lock().unwrap().clear();
+            }
+        }
+        
+        next
+    }
+    
+    /// Whether an update should enter the pipeline under the active level
+    pub fn admit(&self, update: &AccountUpdate) -> bool {
+        let level = self.active_level();
+        if level == 0 {
+            return true;
+        }
+        let levels = self.levels.read().unwrap();
+        let Some(rules) = levels.get(level - 1) else {
+            return true;
+        };
+        
+        // Closures only get this far for known vaults, and one dropped
+        // would leave its vault open for good
+        if update.lamports == 0 && !update.is_startup {
+            return true;
+        }
+        
+        if rules.drop_startup && update.is_startup {
+            return self.reject("startup");
+        }
+        
+        if !rules.essential_data_lens.is_empty()
+            && !rules.essential_data_lens.contains(&update.data.len())
+        {
+            return self.reject("non_essential");
+        }
+        
+        if rules.sample_every > 1 {
+            let mut counters = self.hot_counters.lock().unwrap();
+            let seen = counters.entry(update.pubkey).or_insert(0);
+            let count = *seen;
+            *seen = count.wrapping_add(1);
+            if count % rules.sample_every != 0 {
+                return self.reject("sampled");
+            }
+        }
+        
+        true
+    }
+    
+    fn reject(&self, reason: &'static str) -> bool {
+        metrics::counter!("indexer_degraded_drops_total", "reason" => reason).increment(1);
+        false
+    }
+}
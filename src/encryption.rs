@@ -0,0 +1,127 @@
+use crate::types::{EncryptedColumn, EncryptionConfig};
+use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// Prefix of an encrypted column value, sealed with its column and row as
+/// associated data; anything else is plaintext, so rows written before
+/// encryption was enabled stay readable
+const ENCRYPTED_PREFIX: &str = "enc:v2:";
+
+/// Prefix of a value sealed without associated data, still read so rows
+/// written before it was added stay readable
+const LEGACY_PREFIX: &str = "enc:v1:";
+
+/// AES-GCM nonce length in bytes
+const NONCE_LEN: usize = 12;
+
+/// Application-level AES-256-GCM encryption of designated text columns
+///
+/// Values are stored as `enc:v2:` followed by the base64 nonce and
+/// ciphertext, so the database never sees the plaintext or the key. The
+/// column and the row's key are authenticated along with the value, so a
+/// sealed value copied to another row or column fails to open. The key is
+/// read from an environment variable or a file, which is where a KMS or
+/// secrets agent is expected to place it.
+pub struct ColumnCipher {
+    cipher: Aes256Gcm,
+    columns: HashSet<EncryptedColumn>,
+}
+
+impl ColumnCipher {
+    /// `None` if encryption is disabled
+    pub fn from_config(config: &EncryptionConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        
+        let encoded = match (&config.key_env, &config.key_file) {
+            (Some(var), None) => std::env::var(var)
+                .with_context(|| format!("Encryption key variable {} is not set", var))?,
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read encryption key file {}", path))?,
+            _ => anyhow::bail!("[encryption] needs exactly one of key_env and key_file"),
+        };
+        let key = BASE64.decode(encoded.trim()).context("Encryption key is not valid base64")?;
+        if key.len() != 32 {
+            anyhow::bail!("Encryption key must be 32 bytes, got {}", key.len());
+        }
+        
+        Ok(Some(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+            columns: config.columns.iter().copied().collect(),
+        }))
+    }
+    
+    fn encrypt(&self, plaintext: &str, aad: &[u8]) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad })
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt column value"))?;
+        
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(sealed)))
+    }
+    
+    fn decrypt(&self, encoded: &str, aad: &[u8]) -> Result<String> {
+        let sealed = BASE64.decode(encoded).context("Encrypted column value is not valid base64")?;
+        if sealed.len() < NONCE_LEN {
+            anyhow::bail!("Encrypted column value is truncated");
+        }
+        
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt column value; wrong key or row?"))?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+/// Associated data binding a sealed value to its column and to the row
+/// keyed by `row_key`, the vault address or filter name
+fn associated_data(column: EncryptedColumn, row_key: &str) -> Vec<u8> {
+    let column = match column {
+        EncryptedColumn::VaultNotes => "vault_notes.note",
+        EncryptedColumn::SavedFilterDescriptions => "saved_filters.description",
+    };
+    format!("{}:{}", column, row_key).into_bytes()
+}
+
+/// The value to store in `column` of the row keyed by `row_key`:
+/// encrypted if `cipher` covers the column, as given otherwise
+pub fn seal<'a>(
+    cipher: Option<&ColumnCipher>,
+    column: EncryptedColumn,
+    row_key: &str,
+    value: &'a str,
+) -> Result<Cow<'a, str>> {
+    match cipher.filter(|cipher| cipher.columns.contains(&column)) {
+        Some(cipher) => Ok(Cow::Owned(cipher.encrypt(value, &associated_data(column, row_key))?)),
+        None => Ok(Cow::Borrowed(value)),
+    }
+}
+
+/// The plaintext of a value stored in `column` of the row keyed by
+/// `row_key`; plaintext values pass through, so a column can be designated
+/// after rows were written
+pub fn open(cipher: Option<&ColumnCipher>, column: EncryptedColumn, row_key: &str, value: String) -> Result<String> {
+    let (encoded, aad) = if let Some(encoded) = value.strip_prefix(ENCRYPTED_PREFIX) {
+        (encoded, associated_data(column, row_key))
+    } else if let Some(encoded) = value.strip_prefix(LEGACY_PREFIX) {
+        (encoded, Vec::new())
+    } else {
+        return Ok(value);
+    };
+    
+    match cipher {
+        Some(cipher) => cipher.decrypt(encoded, &aad),
+        None => anyhow::bail!("Column value is encrypted but [encryption] is not enabled"),
+    }
+}
@@ -0,0 +1,189 @@
+use crate::database::Database;
+use crate::indexer::Indexer;
+use crate::types::{EventsConfig, VaultEvent, VaultState};
+use crate::utils::parse_pubkey;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, warn};
+
+/// How old an `owner_changes` row must be before it is emitted, so rows
+/// from transactions still in flight aren't skipped by the id cursor
+const OWNER_CHANGE_SETTLE: Duration = Duration::from_secs(2);
+
+/// Owner changes read per poll
+const OWNER_CHANGE_PAGE: i64 = 1000;
+
+/// Most vault addresses and owners one client subscription may list
+pub const MAX_SUBSCRIPTION_KEYS: usize = 1000;
+
+/// Vaults read per owner page by `states_since`
+const REPLAY_PAGE: i64 = 500;
+
+/// Turns rows recorded by database triggers into `VaultEvent`s
+///
+/// Polls `owner_changes` by id, starting from the newest row at startup, and
+/// broadcasts each change as `VaultEvent::OwnerChanged`. Subscribers that
+/// fall more than `channel_capacity` events behind miss the oldest ones;
+/// `get_owner_history` remains the complete record.
+pub struct EventWatcher {
+    database: Arc<Database>,
+    sender: broadcast::Sender<VaultEvent>,
+    poll_interval: Duration,
+    last_owner_change: i64,
+}
+
+impl EventWatcher {
+    pub async fn new(
+        config: &EventsConfig,
+        database: Arc<Database>,
+        sender: broadcast::Sender<VaultEvent>,
+    ) -> Result<Self> {
+        let last_owner_change = database.latest_owner_change_id().await?;
+        
+        Ok(Self {
+            database,
+            sender,
+            poll_interval: Duration::from_millis(config.poll_interval_ms.max(100)),
+            last_owner_change,
+        })
+    }
+    
+    pub async fn run(mut self) {
+        let mut ticker = interval(self.poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        
+        loop {
+            ticker.tick().await;
+            
+            if let Err(e) = self.poll_owner_changes().await {
+                warn!("Failed to poll owner changes: {}", e);
+            }
+        }
+    }
+    
+    async fn poll_owner_changes(&mut self) -> Result<()> {
+        loop {
+            let changes = self.database
+                .get_owner_changes_after(self.last_owner_change, OWNER_CHANGE_SETTLE, OWNER_CHANGE_PAGE)
+                .await?;
+            let full_page = changes.len() as i64 == OWNER_CHANGE_PAGE;
+            
+            for change in changes {
+                debug!(
+                    "Vault {} owner changed from {} to {} at slot {}",
+                    change.vault_address, change.previous_owner, change.new_owner, change.slot
+                );
+                metrics::counter!("indexer_owner_changes_total").increment(1);
+                self.last_owner_change = change.id;
+                // No receivers is fine; the event is simply dropped
+                let _ = self.sender.send(VaultEvent::OwnerChanged(change));
+            }
+            
+            if !full_page {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Which vault events a subscriber receives
+///
+/// With neither list set every event matches; otherwise an event matches
+/// if its vault or its owner is listed. Owner changes match on either
+/// the previous or the new owner.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    vault_addresses: HashSet<String>,
+    owners: HashSet<String>,
+}
+
+impl EventFilter {
+    pub fn new(vault_addresses: impl IntoIterator<Item = String>, owners: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            vault_addresses: vault_addresses.into_iter().collect(),
+            owners: owners.into_iter().collect(),
+        }
+    }
+    
+    /// Filter for a client subscription, which must list at least one and
+    /// at most `MAX_SUBSCRIPTION_KEYS` valid vault addresses and owners
+    pub fn for_subscription(vault_addresses: Vec<String>, owners: Vec<String>) -> Result<Self> {
+        let keys = vault_addresses.len() + owners.len();
+        if keys == 0 {
+            anyhow::bail!("Subscribe to at least one vault address or owner");
+        }
+        if keys > MAX_SUBSCRIPTION_KEYS {
+            anyhow::bail!("A subscription may list at most {} vaults and owners", MAX_SUBSCRIPTION_KEYS);
+        }
+        for address in vault_addresses.iter().chain(&owners) {
+            parse_pubkey(address)?;
+        }
+        
+        Ok(Self::new(vault_addresses, owners))
+    }
+    
+    pub fn vault_addresses(&self) -> impl Iterator<Item = &String> {
+        self.vault_addresses.iter()
+    }
+    
+    pub fn owners(&self) -> impl Iterator<Item = &String> {
+        self.owners.iter()
+    }
+    
+    pub fn matches(&self, event: &VaultEvent) -> bool {
+        if self.vault_addresses.is_empty() && self.owners.is_empty() {
+            return true;
+        }
+        
+        match event {
+            VaultEvent::Updated(state) => {
+                self.vault_addresses.contains(&state.vault_address.to_string())
+                    || self.owners.contains(&state.owner.to_string())
+            }
+            VaultEvent::OwnerChanged(change) => {
+                self.vault_addresses.contains(&change.vault_address)
+                    || self.owners.contains(&change.previous_owner)
+                    || self.owners.contains(&change.new_owner)
+            }
+        }
+    }
+}
+
+/// Current states of the vaults `filter` lists or whose owner it lists,
+/// written after `from_slot`, oldest first
+///
+/// Lets a stream subscriber catch up on what it missed before following
+/// live events. Only current states are returned, so intermediate versions
+/// and owner changes in between aren't replayed, and a vault that has
+/// since left a listed owner isn't either.
+pub async fn states_since(indexer: &Indexer, filter: &EventFilter, from_slot: u64) -> Result<Vec<VaultState>> {
+    let addresses: Vec<&String> = filter.vault_addresses().collect();
+    let mut states = if addresses.is_empty() {
+        Vec::new()
+    } else {
+        indexer.get_vault_states(&addresses).await?
+    };
+    
+    for owner in filter.owners() {
+        let mut after = None;
+        loop {
+            let page = indexer.get_vaults_by_owner(owner, after.as_deref(), REPLAY_PAGE).await?;
+            let full_page = page.len() as i64 == REPLAY_PAGE;
+            after = page.last().map(|state| state.vault_address.to_string());
+            states.extend(page);
+            if !full_page {
+                break;
+            }
+        }
+    }
+    
+    // A listed vault can also belong to a listed owner
+    let mut seen = HashSet::new();
+    states.retain(|state| state.slot > from_slot && seen.insert(state.vault_address));
+    states.sort_by_key(|state| (state.slot, state.write_version));
+    Ok(states)
+}
@@ -0,0 +1,130 @@
+use crate::backfill::Backfiller;
+use crate::database::Database;
+use crate::stats::PipelineStats;
+use crate::types::{GapDetectionConfig, SlotRange};
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{error, info, warn};
+
+/// Persists the slot range this instance has indexed and detects gaps
+///
+/// On the first flushed slot after startup, the new range is compared with
+/// the latest range on record. Any slots in between were never seen, so the
+/// gap is recorded and, together with gaps left unreconciled by earlier
+/// runs, reconciled by re-reading program accounts over RPC.
+pub struct SlotCoverageTracker {
+    database: Arc<Database>,
+    stats: Arc<PipelineStats>,
+    backfiller: Option<Backfiller>,
+    checkpoint_interval: Duration,
+    min_gap_slots: u64,
+}
+
+impl SlotCoverageTracker {
+    pub fn new(
+        config: &GapDetectionConfig,
+        database: Arc<Database>,
+        stats: Arc<PipelineStats>,
+        backfiller: Backfiller,
+    ) -> Self {
+        Self {
+            database,
+            stats,
+            backfiller: Some(backfiller),
+            checkpoint_interval: Duration::from_millis(config.checkpoint_interval_ms.max(1)),
+            min_gap_slots: config.min_gap_slots.max(1),
+        }
+    }
+    
+    pub async fn run(mut self) {
+        let mut ticker = interval(self.checkpoint_interval);
+        let mut range: Option<SlotRange> = None;
+        
+        loop {
+            ticker.tick().await;
+            
+            let slot = self.stats.last_indexed_slot();
+            if slot == 0 {
+                continue;
+            }
+            
+            let result = match range {
+                Some(current) => self.database.extend_slot_range(current.id, slot).await,
+                None => self.open_range(slot).await.map(|opened| range = Some(opened)),
+            };
+            
+            if let Err(e) = result {
+                error!("Failed to checkpoint indexed slot range: {}", e);
+            }
+        }
+    }
+    
+    /// Start this run's range, recording and reconciling any gap before it
+    async fn open_range(&mut self, slot: u64) -> Result<SlotRange> {
+        let previous = self.database.latest_slot_range().await?;
+        
+        let range = match previous {
+            // Restarted before missing anything worth reconciling; keep extending
+            Some(previous) if slot < previous.end_slot + 1 + self.min_gap_slots => {
+                self.database.extend_slot_range(previous.id, slot).await?;
+                previous
+            }
+            Some(previous) => {
+                let (gap_start, gap_end) = (previous.end_slot + 1, slot - 1);
+                warn!(
+                    "Detected {} unindexed slots ({}..={}) since the last run",
+                    gap_end - gap_start + 1,
+                    gap_start,
+                    gap_end
+                );
+                metrics::counter!("indexer_slot_gaps_total").increment(1);
+                
+                self.database.insert_slot_gap(gap_start, gap_end).await?;
+                self.database.insert_slot_range(slot, slot).await?
+            }
+            None => self.database.insert_slot_range(slot, slot).await?,
+        };
+        
+        if let Some(backfiller) = self.backfiller.take() {
+            tokio::spawn(reconcile_gaps(self.database.clone(), backfiller));
+        }
+        
+        Ok(range)
+    }
+}
+
+/// Re-read program accounts over RPC and close out all pending gaps
+///
+/// Any account may have changed during a gap, so the whole program is
+/// re-read; only gaps pending before the read started are marked done.
+async fn reconcile_gaps(database: Arc<Database>, backfiller: Backfiller) {
+    let gaps = match database.unreconciled_slot_gaps().await {
+        Ok(gaps) if gaps.is_empty() => return,
+        Ok(gaps) => gaps,
+        Err(e) => {
+            error!("Failed to load slot gaps: {}", e);
+            return;
+        }
+    };
+    
+    info!("Reconciling {} slot gaps via RPC", gaps.len());
+    
+    match backfiller.run().await {
+        Ok(report) => {
+            let ids: Vec<i64> = gaps.iter().map(|gap| gap.id).collect();
+            if let Err(e) = database.mark_slot_gaps_reconciled(&ids).await {
+                error!("Failed to mark slot gaps reconciled: {}", e);
+                return;
+            }
+            info!(
+                "Reconciled {} slot gaps ({} accounts at slot {})",
+                ids.len(),
+                report.accounts,
+                report.slot
+            );
+        }
+        Err(e) => warn!("Gap reconciliation failed, will retry on next start: {:#}", e),
+    }
+}
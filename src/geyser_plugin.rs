@@ -1,10 +1,16 @@
+use crate::api::ApiServer;
 use crate::config::Config;
+use crate::grpc::GrpcServer;
 use crate::indexer::Indexer;
+use crate::reload::{self, ConfigReloader};
 use crate::types::AccountUpdate;
+use crate::yellowstone::YellowstoneServer;
 use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
 use solana_geyser_plugin_interface::geyser_plugin_interface::{
-    GeyserPlugin, ReplicaAccountInfo, ReplicaAccountInfoVersions, Result as GeyserResult,
+    GeyserPlugin, ReplicaAccountInfo, ReplicaAccountInfoVersions, ReplicaBlockInfoVersions, Result as GeyserResult,
 };
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use tracing::{error, info, warn};
 
@@ -12,6 +18,8 @@ use tracing::{error, info, warn};
 pub struct GeyserIndexerPlugin {
     indexer: Arc<Mutex<Option<Arc<Indexer>>>>,
     config: Arc<Mutex<Option<Config>>>,
+    /// End-of-startup can arrive before the indexer finishes initializing
+    startup_complete: Arc<AtomicBool>,
 }
 
 impl GeyserIndexerPlugin {
@@ -19,6 +27,7 @@ impl GeyserIndexerPlugin {
         Self {
             indexer: Arc::new(Mutex::new(None)),
             config: Arc::new(Mutex::new(None)),
+            startup_complete: Arc::new(AtomicBool::new(false)),
         }
     }
 }
@@ -43,9 +52,11 @@ impl GeyserPlugin for GeyserIndexerPlugin {
         info!("Loading Vaulta Geyser Indexer plugin...");
         
         // Load configuration
+        let mut config_path = Some(config_file.to_string());
         let config = match Config::from_file(config_file) {
             Ok(c) => c,
             Err(e) => {
+                config_path = None;
                 warn!("Failed to load config file, using environment variables: {}", e);
                 Config::from_env().map_err(|e| {
                     error!("Failed to load config: {}", e);
@@ -57,15 +68,35 @@ impl GeyserPlugin for GeyserIndexerPlugin {
         };
         
         let config_inner = config.inner().clone();
+        let log_filter = reload::init_logging(&config_inner.log_level);
         
         // Initialize indexer asynchronously
         let indexer_arc = self.indexer.clone();
         let config_arc = self.config.clone();
+        let startup_complete = self.startup_complete.clone();
         
         tokio::spawn(async move {
-            match initialize_indexer(&config_inner).await {
+            match Indexer::from_config(&config_inner).await {
                 Ok(indexer) => {
-                    *indexer_arc.lock().unwrap() = Some(Arc::new(indexer));
+                    let mut guard = indexer_arc.lock().unwrap();
+                    if startup_complete.load(Ordering::Acquire) {
+                        indexer.end_of_startup();
+                    }
+                    let indexer = Arc::new(indexer);
+                    if let Some(path) = config_path {
+                        indexer.set_config_reloader(Arc::new(ConfigReloader::new(path, &config_inner, log_filter)));
+                    }
+                    *guard = Some(indexer.clone());
+                    drop(guard);
+                    if config_inner.api.enabled {
+                        tokio::spawn(ApiServer::new(&config_inner.api, indexer.clone()).run());
+                    }
+                    if config_inner.grpc.enabled {
+                        tokio::spawn(GrpcServer::new(&config_inner.grpc, indexer.clone()).run());
+                    }
+                    if config_inner.yellowstone.enabled {
+                        tokio::spawn(YellowstoneServer::new(&config_inner.yellowstone, indexer).run());
+                    }
                     *config_arc.lock().unwrap() = Some(config);
                     info!("Vaulta Geyser Indexer initialized successfully");
                 }
@@ -111,73 +142,70 @@ impl GeyserPlugin for GeyserIndexerPlugin {
         Ok(())
     }
     
+    fn notify_block_metadata(&mut self, blockinfo: ReplicaBlockInfoVersions) -> GeyserResult<()> {
+        let (slot, blockhash, block_height, block_time) = match blockinfo {
+            ReplicaBlockInfoVersions::V0_0_1(info) => {
+                (info.slot, info.blockhash, info.block_height, info.block_time)
+            }
+            ReplicaBlockInfoVersions::V0_0_2(info) => {
+                (info.slot, info.blockhash, info.block_height, info.block_time)
+            }
+            ReplicaBlockInfoVersions::V0_0_3(info) => {
+                (info.slot, info.blockhash, info.block_height, info.block_time)
+            }
+        };
+        
+        if let Some(indexer) = self.indexer.lock().unwrap().as_ref() {
+            indexer.process_block_metadata(slot, blockhash, block_height, block_time);
+        }
+        
+        Ok(())
+    }
+    
     fn notify_end_of_startup(&mut self) -> GeyserResult<()> {
         info!("Startup complete, switching to real-time indexing mode");
+        
+        let guard = self.indexer.lock().unwrap();
+        self.startup_complete.store(true, Ordering::Release);
+        if let Some(indexer) = guard.as_ref() {
+            indexer.end_of_startup();
+        }
+        
         Ok(())
     }
 }
 
-/// Initialize indexer with configuration
-async fn initialize_indexer(config: &crate::types::PluginConfig) -> Result<Indexer> {
-    use crate::database::Database;
-    use crate::redis_cache::RedisCache;
-    
-    // Initialize database
-    let db_conn_string = format!(
-        "postgresql://{}:{}@{}:{}/{}",
-        config.database.username,
-        config.database.password,
-        config.database.host,
-        config.database.port,
-        config.database.database,
-    );
-    
-    let database = Database::new(
-        &db_conn_string,
-        config.database.max_connections,
-    ).await?;
-    
-    // Initialize Redis cache if enabled
-    let cache = if config.enable_cache {
-        Some(RedisCache::new(
-            &config.redis.url,
-            config.redis.ttl_seconds,
-        ).await?)
-    } else {
-        None
-    };
-    
-    // Create indexer
-    let indexer = Indexer::new(
-        database,
-        cache,
-        &config.vault_program_id,
-        config.batch_size,
-    ).await?;
-    
-    Ok(indexer)
-}
-
 /// Convert Geyser account info to our AccountUpdate type
+///
+/// V0_0_2 reports the transaction signature directly and V0_0_3 the whole
+/// transaction; V0_0_1 carries none.
 fn convert_account_info(
     account: ReplicaAccountInfoVersions,
     slot: u64,
     is_startup: bool,
 ) -> Result<AccountUpdate> {
-    let account_info = match account {
-        ReplicaAccountInfoVersions::V0_0_1(info) => info,
-        ReplicaAccountInfoVersions::V0_0_2(info) => info,
-    };
+    macro_rules! account_update {
+        ($info:expr, $txn_signature:expr) => {
+            AccountUpdate {
+                pubkey: Pubkey::try_from($info.pubkey)?,
+                lamports: $info.lamports,
+                owner: Pubkey::try_from($info.owner)?,
+                executable: $info.executable,
+                rent_epoch: $info.rent_epoch,
+                data: $info.data.to_vec(),
+                write_version: $info.write_version,
+                slot,
+                is_startup,
+                txn_signature: $txn_signature,
+            }
+        };
+    }
     
-    Ok(AccountUpdate {
-        pubkey: *account_info.pubkey,
-        lamports: account_info.lamports,
-        owner: *account_info.owner,
-        executable: account_info.executable,
-        rent_epoch: account_info.rent_epoch,
-        data: account_info.data.to_vec(),
-        write_version: account_info.write_version,
-        slot,
-        is_startup,
+    Ok(match account {
+        ReplicaAccountInfoVersions::V0_0_1(info) => account_update!(info, None),
+        ReplicaAccountInfoVersions::V0_0_2(info) => account_update!(info, info.txn_signature.copied()),
+        ReplicaAccountInfoVersions::V0_0_3(info) => {
+            account_update!(info, info.txn.map(|txn| *txn.signature()))
+        }
     })
 }
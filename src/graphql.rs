@@ -0,0 +1,338 @@
+use crate::indexer::Indexer;
+use crate::projection::format_timestamp;
+use crate::readiness::WarmingUp;
+use crate::types::{self, ApiConfig, PageCursor, VaultFilter, VaultState};
+use crate::utils::parse_pubkey;
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Enum, ErrorExtensions, InputObject, Object, Schema, SimpleObject,
+};
+use std::sync::Arc;
+use tracing::error;
+
+/// Page size of `vaults` and `history` when `first` isn't given
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+pub type VaultSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Build the schema served at `/graphql`
+///
+/// Depth and complexity are capped by `graphql_max_depth` and
+/// `graphql_max_complexity`; list fields count once per requested item, so
+/// a query nesting `history` inside a large `vaults` page is refused
+/// before it reaches the database.
+pub fn schema(config: &ApiConfig, indexer: Arc<Indexer>) -> VaultSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(QueryContext {
+            indexer,
+            max_page_size: config.max_page_size.max(1),
+        })
+        .limit_depth(config.graphql_max_depth)
+        .limit_complexity(config.graphql_max_complexity)
+        .finish()
+}
+
+struct QueryContext {
+    indexer: Arc<Indexer>,
+    max_page_size: i64,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// One vault by address, through the cache like `GET /vaults/{address}`
+    async fn vault(&self, ctx: &Context<'_>, address: String) -> async_graphql::Result<Option<Vault>> {
+        parse_pubkey(&address).map_err(bad_input)?;
+        let state = query(ctx).indexer.get_vault_state(&address).await.map_err(query_error)?;
+        Ok(state.map(Vault))
+    }
+    
+    /// One page of the vaults matching `filter`, largest `sortBy` first;
+    /// pass the previous page's `nextCursor` as `after` to continue
+    #[graphql(complexity = "page_size(first, i64::MAX) as usize * child_complexity")]
+    async fn vaults(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<VaultFilterInput>,
+        #[graphql(default_with = "VaultSortField::Balance")] sort_by: VaultSortField,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> async_graphql::Result<VaultConnection> {
+        let context = query(ctx);
+        let filter = match filter {
+            Some(filter) => filter.into_filter().map_err(bad_input)?,
+            None => VaultFilter::default(),
+        };
+        let sort_by = types::VaultSort::from(sort_by);
+        let cursor = after.as_deref().map(PageCursor::decode_vaults).transpose().map_err(bad_input)?;
+        if let Some(cursor) = &cursor {
+            if cursor.sort_by() != sort_by {
+                return Err(bad_input(anyhow::anyhow!(
+                    "Cursor continues a listing sorted by {}",
+                    cursor.sort_by().as_str()
+                )));
+            }
+        }
+        
+        let page = context
+            .indexer
+            .list_vaults(&filter, sort_by, cursor.as_ref(), page_size(first, context.max_page_size))
+            .await
+            .map_err(query_error)?;
+        Ok(VaultConnection {
+            nodes: page.vaults.into_iter().map(Vault).collect(),
+            next_cursor: page.next_cursor.map(|cursor| PageCursor::Vaults(cursor).encode()),
+        })
+    }
+}
+
+/// A vault state; only the selected fields are rendered
+pub struct Vault(VaultState);
+
+#[Object]
+impl Vault {
+    async fn address(&self) -> String {
+        self.0.vault_address.to_string()
+    }
+    
+    async fn owner(&self) -> String {
+        self.0.owner.to_string()
+    }
+    
+    /// Lamports
+    async fn balance(&self) -> u64 {
+        self.0.balance
+    }
+    
+    /// Token balances ordered by mint; `mints` keeps only those listed
+    async fn assets(&self, mints: Option<Vec<String>>) -> Vec<Asset> {
+        let mut assets: Vec<Asset> = self
+            .0
+            .assets
+            .values()
+            .map(|asset| Asset {
+                mint: asset.mint.to_string(),
+                amount: asset.amount,
+                decimals: asset.decimals,
+            })
+            .filter(|asset| mints.as_ref().map_or(true, |mints| mints.contains(&asset.mint)))
+            .collect();
+        assets.sort_by(|a, b| a.mint.cmp(&b.mint));
+        assets
+    }
+    
+    /// Granted permissions; `permissionType` keeps only that role
+    async fn permissions(&self, permission_type: Option<PermissionKind>) -> Vec<Permission> {
+        self.0
+            .permissions
+            .iter()
+            .filter(|permission| {
+                permission_type.map_or(true, |kind| permission.permission_type == types::PermissionType::from(kind))
+            })
+            .map(|permission| Permission {
+                pubkey: permission.pubkey.to_string(),
+                permission_type: permission.permission_type.into(),
+                granted_at: format_timestamp(permission.granted_at),
+            })
+            .collect()
+    }
+    
+    /// RFC 3339 timestamp
+    async fn last_updated(&self) -> String {
+        format_timestamp(self.0.last_updated)
+    }
+    
+    async fn slot(&self) -> u64 {
+        self.0.slot
+    }
+    
+    async fn write_version(&self) -> u64 {
+        self.0.write_version
+    }
+    
+    async fn status(&self) -> VaultStatusKind {
+        self.0.status.into()
+    }
+    
+    async fn closed_at_slot(&self) -> Option<u64> {
+        self.0.closed_at_slot
+    }
+    
+    /// Recorded versions within an inclusive slot range, oldest first;
+    /// pass the previous page's `nextCursor` as `after` to continue
+    #[graphql(complexity = "page_size(first, i64::MAX) as usize * child_complexity")]
+    async fn history(
+        &self,
+        ctx: &Context<'_>,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> async_graphql::Result<VaultVersionConnection> {
+        let context = query(ctx);
+        // Slots are stored as BIGINT
+        let to_slot = to_slot.unwrap_or(i64::MAX as u64).min(i64::MAX as u64);
+        let after = after.as_deref().map(PageCursor::decode_history).transpose().map_err(bad_input)?;
+        let limit = page_size(first, context.max_page_size);
+        let versions = context
+            .indexer
+            .get_vault_history(&self.0.vault_address.to_string(), from_slot.unwrap_or(0), to_slot, after, limit)
+            .await
+            .map_err(query_error)?;
+        
+        Ok(VaultVersionConnection {
+            next_cursor: PageCursor::next_history(&versions, limit),
+            nodes: versions
+                .into_iter()
+                .map(|version| VaultVersion {
+                    state: Vault(version.state),
+                    txn_signature: version.txn_signature,
+                })
+                .collect(),
+        })
+    }
+    
+    /// Owner transitions, oldest first
+    async fn owner_changes(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<OwnerChange>> {
+        let changes = query(ctx)
+            .indexer
+            .get_owner_history(&self.0.vault_address.to_string())
+            .await
+            .map_err(query_error)?;
+        
+        Ok(changes
+            .into_iter()
+            .map(|change| OwnerChange {
+                previous_owner: change.previous_owner,
+                new_owner: change.new_owner,
+                slot: change.slot,
+                write_version: change.write_version,
+                changed_at: format_timestamp(change.changed_at),
+            })
+            .collect())
+    }
+}
+
+#[derive(SimpleObject)]
+struct VaultConnection {
+    nodes: Vec<Vault>,
+    /// Cursor for the next page; null on the last page
+    next_cursor: Option<String>,
+}
+
+#[derive(SimpleObject)]
+struct VaultVersionConnection {
+    nodes: Vec<VaultVersion>,
+    /// Cursor for the next page; null on the last page
+    next_cursor: Option<String>,
+}
+
+#[derive(SimpleObject)]
+struct VaultVersion {
+    state: Vault,
+    /// Null for snapshot, backfill and repair writes
+    txn_signature: Option<String>,
+}
+
+#[derive(SimpleObject)]
+struct Asset {
+    mint: String,
+    amount: u64,
+    decimals: u8,
+}
+
+#[derive(SimpleObject)]
+struct Permission {
+    pubkey: String,
+    permission_type: PermissionKind,
+    /// RFC 3339 timestamp
+    granted_at: String,
+}
+
+#[derive(SimpleObject)]
+struct OwnerChange {
+    previous_owner: String,
+    new_owner: String,
+    slot: u64,
+    write_version: u64,
+    /// RFC 3339 timestamp
+    changed_at: String,
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(name = "PermissionType", remote = "crate::types::PermissionType")]
+enum PermissionKind {
+    Owner,
+    Admin,
+    Operator,
+    Viewer,
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(name = "VaultStatus", remote = "crate::types::VaultStatus")]
+enum VaultStatusKind {
+    Active,
+    Closed,
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(name = "VaultSort", remote = "crate::types::VaultSort")]
+enum VaultSortField {
+    Balance,
+    LastUpdated,
+    Slot,
+}
+
+/// Vault selection; every given criterion must match
+#[derive(InputObject)]
+#[graphql(name = "VaultFilter")]
+struct VaultFilterInput {
+    addresses: Option<Vec<String>>,
+    owners: Option<Vec<String>>,
+    /// Vault must carry every listed tag
+    tags: Option<Vec<String>>,
+    min_balance: Option<u64>,
+    /// Also match closed vaults, which are left out by default
+    include_closed: Option<bool>,
+}
+
+impl VaultFilterInput {
+    fn into_filter(self) -> anyhow::Result<VaultFilter> {
+        let vault_addresses = self.addresses.unwrap_or_default();
+        let owners = self.owners.unwrap_or_default();
+        for address in vault_addresses.iter().chain(&owners) {
+            parse_pubkey(address)?;
+        }
+        
+        Ok(VaultFilter {
+            vault_addresses,
+            owners,
+            tags: self.tags.unwrap_or_default(),
+            min_balance: self.min_balance,
+            include_closed: self.include_closed.unwrap_or(false),
+        })
+    }
+}
+
+fn query<'a>(ctx: &Context<'a>) -> &'a QueryContext {
+    ctx.data_unchecked::<QueryContext>()
+}
+
+fn page_size(first: Option<i32>, max_page_size: i64) -> i64 {
+    first.map_or(DEFAULT_PAGE_SIZE, i64::from).clamp(1, max_page_size)
+}
+
+fn bad_input(e: anyhow::Error) -> async_graphql::Error {
+    async_graphql::Error::new(e.to_string()).extend_with(|_, extensions| extensions.set("code", "BAD_USER_INPUT"))
+}
+
+/// Error for a failed query; details stay in the log
+fn query_error(e: anyhow::Error) -> async_graphql::Error {
+    if e.is::<WarmingUp>() {
+        return async_graphql::Error::new(e.to_string())
+            .extend_with(|_, extensions| extensions.set("code", "UNAVAILABLE"));
+    }
+    
+    error!("GraphQL query failed: {:#}", e);
+    async_graphql::Error::new("internal error")
+}
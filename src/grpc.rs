@@ -0,0 +1,268 @@
+use crate::events::EventFilter;
+use crate::indexer::Indexer;
+use crate::rate_limit;
+use crate::readiness::WarmingUp;
+use crate::types::{self, ApiScope, GrpcConfig, PageCursor, VaultEvent};
+use crate::utils::parse_pubkey;
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info};
+
+/// Types and service generated from `proto/vaulta/indexer/v1/indexer.proto`
+pub mod proto {
+    tonic::include_proto!("vaulta.indexer.v1");
+}
+
+use proto::vault_indexer_server::{VaultIndexer, VaultIndexerServer};
+
+/// Page size of `ListVaultsByOwner` when the request leaves it at 0
+const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Events queued per subscription while the client reads slowly
+const SUBSCRIPTION_BUFFER: usize = 256;
+
+/// gRPC counterpart of the HTTP API, for internal services that prefer
+/// typed RPC; the schema ships in `proto/`
+pub struct GrpcServer {
+    config: GrpcConfig,
+    indexer: Arc<Indexer>,
+}
+
+struct VaultIndexerService {
+    indexer: Arc<Indexer>,
+    max_page_size: i64,
+}
+
+impl GrpcServer {
+    pub fn new(config: &GrpcConfig, indexer: Arc<Indexer>) -> Self {
+        Self {
+            config: config.clone(),
+            indexer,
+        }
+    }
+    
+    /// Serve until the process exits; a failure to bind is logged
+    pub async fn run(self) {
+        if let Err(e) = self.serve().await {
+            error!("gRPC server on {} stopped: {:#}", self.config.bind_address, e);
+        }
+    }
+    
+    async fn serve(&self) -> Result<()> {
+        let address: SocketAddr = self
+            .config
+            .bind_address
+            .parse()
+            .with_context(|| format!("Invalid [grpc] bind_address {}", self.config.bind_address))?;
+        let service = VaultIndexerService {
+            indexer: self.indexer.clone(),
+            max_page_size: self.config.max_page_size.max(1),
+        };
+        
+        info!("gRPC server listening on {}", address);
+        tonic::transport::Server::builder()
+            .add_service(VaultIndexerServer::new(service))
+            .serve(address)
+            .await?;
+        Ok(())
+    }
+}
+
+impl VaultIndexerService {
+    /// Refuse a call without a key holding `scope`, with `[auth]` on, or
+    /// over its client's rate limit, with `[rate_limit]` on
+    fn admit<T>(&self, request: &Request<T>, scope: ApiScope) -> Result<(), Status> {
+        rate_limit::admit_grpc(
+            self.indexer.api_keys().as_deref(),
+            self.indexer.rate_limits().as_deref(),
+            request,
+            scope,
+        )
+    }
+}
+
+#[tonic::async_trait]
+impl VaultIndexer for VaultIndexerService {
+    type SubscribeVaultUpdatesStream = ReceiverStream<Result<proto::VaultEvent, Status>>;
+    
+    async fn get_vault_state(
+        &self,
+        request: Request<proto::GetVaultStateRequest>,
+    ) -> Result<Response<proto::GetVaultStateResponse>, Status> {
+        self.admit(&request, ApiScope::Read)?;
+        let address = request.into_inner().vault_address;
+        parse_pubkey(&address).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        
+        match self.indexer.get_vault_state(&address).await.map_err(status)? {
+            Some(vault) => Ok(Response::new(proto::GetVaultStateResponse {
+                vault: Some(vault.into()),
+            })),
+            None => Err(Status::not_found(format!("Vault {} not found", address))),
+        }
+    }
+    
+    async fn list_vaults_by_owner(
+        &self,
+        request: Request<proto::ListVaultsByOwnerRequest>,
+    ) -> Result<Response<proto::ListVaultsByOwnerResponse>, Status> {
+        self.admit(&request, ApiScope::Read)?;
+        let request = request.into_inner();
+        parse_pubkey(&request.owner).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let limit = match request.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => i64::from(limit),
+        }
+        .min(self.max_page_size);
+        let after = match request.cursor.as_str() {
+            "" => None,
+            cursor => Some(
+                PageCursor::decode_owner_vaults(cursor).map_err(|e| Status::invalid_argument(e.to_string()))?,
+            ),
+        };
+        
+        let vaults = self
+            .indexer
+            .get_vaults_by_owner(&request.owner, after.as_deref(), limit)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::ListVaultsByOwnerResponse {
+            next_cursor: PageCursor::next_owner_vaults(&vaults, limit).unwrap_or_default(),
+            vaults: vaults.into_iter().map(Into::into).collect(),
+        }))
+    }
+    
+    async fn get_stats(
+        &self,
+        request: Request<proto::GetStatsRequest>,
+    ) -> Result<Response<proto::GetStatsResponse>, Status> {
+        self.admit(&request, ApiScope::Stats)?;
+        let stats = self.indexer.get_stats();
+        Ok(Response::new(proto::GetStatsResponse {
+            total_accounts_indexed: stats.total_accounts_indexed,
+            accounts_per_second: stats.accounts_per_second,
+            average_latency_ms: stats.average_latency_ms,
+            cache_hit_rate: stats.cache_hit_rate,
+            last_indexed_slot: stats.last_indexed_slot,
+            uptime_seconds: stats.uptime_seconds,
+            ready: self.indexer.is_ready(),
+        }))
+    }
+    
+    async fn subscribe_vault_updates(
+        &self,
+        request: Request<proto::VaultUpdateFilter>,
+    ) -> Result<Response<Self::SubscribeVaultUpdatesStream>, Status> {
+        self.admit(&request, ApiScope::Stream)?;
+        let request = request.into_inner();
+        if !request.program_id.is_empty() && request.program_id != self.indexer.vault_program_id().to_string() {
+            return Err(Status::invalid_argument(format!(
+                "This indexer only indexes program {}",
+                self.indexer.vault_program_id()
+            )));
+        }
+        for address in request.vault_addresses.iter().chain(&request.owners) {
+            parse_pubkey(address).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        }
+        
+        let filter = EventFilter::new(request.vault_addresses, request.owners);
+        let events = self.indexer.subscribe_events();
+        let (tx, rx) = mpsc::channel(SUBSCRIPTION_BUFFER);
+        tokio::spawn(forward_events(events, filter, tx));
+        
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Pass a subscription's matching events to its client until the client
+/// goes away
+async fn forward_events(
+    mut events: broadcast::Receiver<VaultEvent>,
+    filter: EventFilter,
+    tx: mpsc::Sender<Result<proto::VaultEvent, Status>>,
+) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                debug!("gRPC subscriber fell behind and skipped {} events", skipped);
+                metrics::counter!("indexer_grpc_events_skipped_total").increment(skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        
+        if !filter.matches(&event) {
+            continue;
+        }
+        if tx.send(Ok(event.into())).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Status for a failed query; details stay in the log
+fn status(e: anyhow::Error) -> Status {
+    if e.is::<WarmingUp>() {
+        return Status::unavailable(e.to_string());
+    }
+    
+    error!("gRPC query failed: {:#}", e);
+    Status::internal("internal error")
+}
+
+impl From<VaultEvent> for proto::VaultEvent {
+    fn from(event: VaultEvent) -> Self {
+        let event = match event {
+            VaultEvent::Updated(state) => proto::vault_event::Event::Updated(state.into()),
+            VaultEvent::OwnerChanged(change) => proto::vault_event::Event::OwnerChanged(proto::OwnerChanged {
+                vault_address: change.vault_address,
+                previous_owner: change.previous_owner,
+                new_owner: change.new_owner,
+                slot: change.slot,
+                write_version: change.write_version,
+                changed_at: change.changed_at.unix_timestamp(),
+            }),
+        };
+        Self { event: Some(event) }
+    }
+}
+
+impl From<types::VaultState> for proto::VaultState {
+    fn from(state: types::VaultState) -> Self {
+        Self {
+            vault_address: state.vault_address.to_string(),
+            owner: state.owner.to_string(),
+            balance: state.balance,
+            assets: state
+                .assets
+                .into_iter()
+                .map(|(key, asset)| {
+                    let asset = proto::AssetBalance {
+                        mint: asset.mint.to_string(),
+                        amount: asset.amount,
+                        decimals: u32::from(asset.decimals),
+                    };
+                    (key, asset)
+                })
+                .collect(),
+            permissions: state
+                .permissions
+                .into_iter()
+                .map(|permission| proto::Permission {
+                    pubkey: permission.pubkey.to_string(),
+                    permission_type: permission.permission_type.as_str().to_string(),
+                    granted_at: permission.granted_at.unix_timestamp(),
+                })
+                .collect(),
+            last_updated: state.last_updated.unix_timestamp(),
+            slot: state.slot,
+            write_version: state.write_version,
+            status: state.status.as_str().to_string(),
+            closed_at_slot: state.closed_at_slot,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::database::Database;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::{interval, timeout, MissedTickBehavior};
+use tracing::{info, warn};
+
+/// Background liveness check of the database
+///
+/// Pings Postgres every `check_interval`; a ping that fails or takes longer
+/// than `ping_timeout` marks the database unhealthy until the next one
+/// succeeds. The state is exported as the `indexer_database_healthy` gauge
+/// and folded into `Indexer::is_ready`.
+pub struct DatabaseHealth {
+    database: Arc<Database>,
+    check_interval: Duration,
+    ping_timeout: Duration,
+    healthy: AtomicBool,
+}
+
+impl DatabaseHealth {
+    /// Start checking in the background; the database counts as healthy
+    /// until the first check says otherwise, since it was just connected
+    pub fn start(database: Arc<Database>, check_interval: Duration, ping_timeout: Duration) -> Arc<Self> {
+        let health = Arc::new(Self {
+            database,
+            check_interval: check_interval.max(Duration::from_secs(1)),
+            ping_timeout,
+            healthy: AtomicBool::new(true),
+        });
+        metrics::gauge!("indexer_database_healthy").set(1.0);
+        
+        tokio::spawn(health.clone().run());
+        
+        health
+    }
+    
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Acquire)
+    }
+    
+    async fn run(self: Arc<Self>) {
+        let mut ticker = interval(self.check_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+        
+        loop {
+            ticker.tick().await;
+            
+            let start = Instant::now();
+            let healthy = match timeout(self.ping_timeout, self.database.ping()).await {
+                Ok(Ok(())) => {
+                    metrics::histogram!("indexer_database_ping_seconds").record(start.elapsed().as_secs_f64());
+                    true
+                }
+                Ok(Err(e)) => {
+                    warn!("Database health check failed: {}", e);
+                    false
+                }
+                Err(_) => {
+                    warn!("Database health check timed out after {:?}", self.ping_timeout);
+                    false
+                }
+            };
+            
+            if !healthy {
+                metrics::counter!("indexer_database_health_failures_total").increment(1);
+            }
+            if self.healthy.swap(healthy, Ordering::AcqRel) != healthy {
+                if healthy {
+                    info!("Database is healthy again");
+                }
+                metrics::gauge!("indexer_database_healthy").set(if healthy { 1.0 } else { 0.0 });
+            }
+        }
+    }
+}
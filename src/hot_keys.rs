@@ -0,0 +1,79 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Length of the windows reads are counted over
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Per-key read counts over one-minute windows, to find hot cache keys
+///
+/// A key read at least `threshold` times within a window is hot for the
+/// rest of it and the whole of the next, so a key that stays busy never
+/// cools between windows. Only keys read in the current window are held.
+pub struct HotKeys {
+    threshold: u64,
+    window: Mutex<Window>,
+}
+
+struct Window {
+    started: Instant,
+    reads: HashMap<String, u64>,
+    hot: HashSet<String>,
+    /// Keys that were hot in the previous window
+    previous: HashSet<String>,
+}
+
+impl HotKeys {
+    pub fn new(threshold: u64) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            window: Mutex::new(Window {
+                started: Instant::now(),
+                reads: HashMap::new(),
+                hot: HashSet::new(),
+                previous: HashSet::new(),
+            }),
+        }
+    }
+    
+    /// Count a read of `key`; true if the read made it hot
+    pub fn record(&self, key: &str) -> bool {
+        let mut window = self.window.lock().unwrap();
+        window.roll();
+        
+        let reads = window.reads.entry(key.to_string()).or_insert(0);
+        *reads += 1;
+        if *reads != self.threshold {
+            return false;
+        }
+        
+        let was_hot = window.previous.contains(key);
+        window.hot.insert(key.to_string());
+        !was_hot
+    }
+    
+    pub fn is_hot(&self, key: &str) -> bool {
+        let mut window = self.window.lock().unwrap();
+        window.roll();
+        window.hot.contains(key) || window.previous.contains(key)
+    }
+}
+
+impl Window {
+    fn roll(&mut self) {
+        let elapsed = self.started.elapsed();
+        if elapsed < WINDOW {
+            return;
+        }
+        
+        // A gap of more than a window leaves nothing hot
+        self.previous = if elapsed < WINDOW * 2 {
+            std::mem::take(&mut self.hot)
+        } else {
+            HashSet::new()
+        };
+        self.hot.clear();
+        self.reads.clear();
+        self.started = Instant::now();
+    }
+}
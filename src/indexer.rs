@@ -1,23 +1,126 @@
-use crate::database::Database;
+use crate::aggregates::{AggregateRefresher, TvlCache};
+use crate::auth::ApiKeys;
+use crate::backfill::{self, BackfillReport, Backfiller};
+use crate::batching::AdaptiveBatchSizer;
+use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+use crate::consistency::ConsistencyChecker;
+use crate::coordination::WriterElection;
+use crate::database::{Database, PoolSettings};
+use crate::dedup::UpdateDeduplicator;
+use crate::encryption::ColumnCipher;
+use crate::degradation::DegradationController;
+use crate::events::EventWatcher;
+use crate::gaps::SlotCoverageTracker;
+use crate::health::DatabaseHealth;
+use crate::known_vaults::KnownVaults;
+use crate::local_cache::LocalCache;
+use crate::memory::MemoryBudget;
+use crate::namespace::Namespace;
+use crate::partitions::PartitionMaintainer;
+use crate::rate_limit::RateLimits;
+use crate::readiness::{Readiness, ReadinessGate};
 use crate::redis_cache::RedisCache;
-use crate::types::{AccountUpdate, AssetBalance, VaultState};
+use crate::reload::ConfigReloader;
+use crate::replication::ReplicationConsumer;
+use crate::retention::RetentionPruner;
+use crate::single_flight::SingleFlight;
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteStore;
+use crate::stats::PipelineStats;
+use crate::store::VaultStore;
+use crate::types::{
+    AccountUpdate, AssetBalance, BackfillConfig, CacheWarmupConfig, ChangeCursor, ChangePage, DeadLetter,
+    DeadLetterStage, DegradationConfig, IndexerMode, IndexingStats, OwnerChange, PluginConfig, RawAccount,
+    RedisConfig, ReplicationConfig, RetryConfig, ShardCheckpoint, StoreBackend, SyncCursor, SyncPage,
+    TotalValueLocked, VaultClosure, VaultCursor, VaultEvent, VaultFilter, VaultPage, VaultSort, VaultState,
+    VaultStateVersion, VaultStatus,
+};
+use crate::utils::shard_for;
+use crate::wal::WriteAheadLog;
+use crate::watermark::{self, FlushWatermark, SlotNotFlushed};
 use anyhow::Result;
+use backoff::backoff::Backoff;
+use backoff::ExponentialBackoff;
 use solana_sdk::pubkey::{Pubkey, PubkeyError};
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use time::OffsetDateTime;
-use tokio::sync::mpsc;
-use tokio::time::{interval, Duration};
-use tracing::{debug, error, info};
+use tokio::runtime::Handle;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{interval, Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+/// Minimum delay between WAL replay attempts while the database is down
+const WAL_REPLAY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Failed replays of the oldest spooled batch, with the circuit breaker
+/// closed, before it is dead-lettered
+const WAL_REPLAY_ATTEMPTS: u32 = 10;
+
+/// Vaults written to Redis per pipeline during cache warmup
+const WARMUP_CHUNK: usize = 1000;
 
 /// High-performance indexer for vault state
+///
+/// Updates are hash-partitioned by pubkey across `num_workers` shard tasks.
+/// Each shard owns its own batches, so an account's writes never race
+/// across shards while separate shards flush in parallel. Within a shard,
+/// live updates take priority over the startup snapshot backlog, so a
+/// snapshot write can land after a newer live one; upserts keep the newest
+/// `(slot, write_version)` whatever order writes land in.
 pub struct Indexer {
     database: Arc<Database>,
+    /// Where vault states and checkpoints are written and read; the
+    /// database unless another store was plugged in
+    store: Arc<dyn VaultStore>,
     cache: Option<Arc<RedisCache>>,
+    /// Vault states kept in process, checked before Redis
+    local_cache: Option<Arc<LocalCache>>,
+    /// Store reads after cache misses, coalesced per vault
+    vault_reads: SingleFlight<Option<VaultState>>,
     vault_program_id: Pubkey,
-    batch_size: usize,
-    update_tx: mpsc::UnboundedSender<AccountUpdate>,
+    /// Vaults whose closures are let into the pipeline
+    known_vaults: Arc<KnownVaults>,
+    mode: IndexerMode,
+    /// Live lane batch size, shared with the shard workers so a reload
+    /// reaches them
+    batch_size: Arc<AtomicUsize>,
+    /// Shard workers size their batches themselves, ignoring `batch_size`
+    adaptive_batching: bool,
+    shards: Vec<ShardSender>,
+    stats: Arc<PipelineStats>,
+    /// How far the shards have flushed, for `min_slot` reads
+    watermark: Arc<FlushWatermark>,
+    breaker: Option<Arc<CircuitBreaker>>,
+    memory: Option<Arc<MemoryBudget>>,
+    writer: Option<Arc<WriterElection>>,
+    health: Arc<DatabaseHealth>,
+    /// Shard checkpoints as they were when this instance started
+    resume_checkpoints: Vec<ShardCheckpoint>,
+    dedup: UpdateDeduplicator,
+    readiness: Arc<ReadinessGate>,
+    cache_warmup: CacheWarmupConfig,
+    runtime: Handle,
+    degradation: Option<DegradationController>,
+    tvl: TvlCache,
+    events: broadcast::Sender<VaultEvent>,
+    /// Live vault updates for Yellowstone subscribers, with
+    /// `yellowstone.enabled`
+    account_updates: Option<broadcast::Sender<Arc<AccountUpdate>>>,
+    /// Keys the query servers check, with `auth.enabled`
+    api_keys: Option<Arc<ApiKeys>>,
+    /// Client buckets of the query servers, with `rate_limit.enabled`
+    rate_limits: Option<Arc<RateLimits>>,
+    /// `[backfill]`, for backfills started through the admin API
+    backfill: BackfillConfig,
+    /// Set while a backfill started through the admin API runs
+    backfill_running: Arc<AtomicBool>,
+    /// Re-reads the config file for `POST /admin/reload`, once installed
+    reloader: OnceLock<Arc<ConfigReloader>>,
 }
 
 impl Indexer {
@@ -25,111 +128,845 @@ impl Indexer {
     pub async fn new(
         database: Database,
         cache: Option<RedisCache>,
-        vault_program_id: &str,
-        batch_size: usize,
+        config: &PluginConfig,
     ) -> Result<Self> {
-        let vault_program_id = Pubkey::from_str(vault_program_id)?;
-        
-        let (tx, mut rx) = mpsc::unbounded_channel();
+        let database = Arc::new(database);
+        Self::with_store(database.clone(), database, cache, config).await
+    }
+    
+    /// Create an indexer that keeps vault states and checkpoints in `store`
+    ///
+    /// `database` still holds everything else: history, raw updates, dead
+    /// letters, tags, jobs and the background maintenance tables.
+    pub async fn with_store(
+        database: Arc<Database>,
+        store: Arc<dyn VaultStore>,
+        cache: Option<RedisCache>,
+        config: &PluginConfig,
+    ) -> Result<Self> {
+        let vault_program_id = Pubkey::from_str(&config.vault_program_id)?;
+        let num_workers = config.num_workers.max(1);
         
-        let db = Arc::new(database);
+        let db = database;
         let cache_arc = cache.map(Arc::new);
+        let local_cache = match cache_arc.as_ref().filter(|_| config.local_cache.enabled) {
+            Some(cache) => {
+                // Entries are only dropped when their change is announced
+                if !config.redis.publish_changes {
+                    anyhow::bail!("[local_cache] is invalidated through redis.publish_changes, which is off");
+                }
+                Some(LocalCache::start(&config.local_cache, &config.redis, cache.changes_channel())?)
+            }
+            None => None,
+        };
         
-        // Spawn indexing task
-        let db_clone = db.clone();
-        let cache_clone = cache_arc.clone();
+        let resume_checkpoints = store.get_shard_checkpoints().await?;
+        if let Some(slot) = resume_checkpoints.iter().map(|checkpoint| checkpoint.slot).min() {
+            info!("Resuming indexing after checkpointed slot {}", slot);
+            
+            // Rows of shards that no longer exist would never advance again;
+            // every shard takes over from the lowest one instead
+            if config.mode == IndexerMode::Geyser
+                && resume_checkpoints.iter().any(|checkpoint| checkpoint.shard >= num_workers)
+            {
+                info!("Shard count changed to {}, resetting shard checkpoints to slot {}", num_workers, slot);
+                store.reset_shard_checkpoints(num_workers, slot).await?;
+            }
+        }
         
-        tokio::spawn(async move {
-            let mut batch = Vec::new();
-            let mut flush_interval = interval(Duration::from_millis(100));
-            
-            loop {
-                tokio::select! {
-                    update = rx.recv() => {
-                        if let Some(update) = update {
-                            batch.push(update);
-                            
-                            if batch.len() >= batch_size {
-                                if let Err(e) = Self::process_batch(
-                                    &db_clone,
-                                    cache_clone.as_ref(),
-                                    &batch,
-                                ).await {
-                                    error!("Error processing batch: {}", e);
-                                }
-                                batch.clear();
-                            }
-                        } else {
-                            // Channel closed
-                            break;
-                        }
-                    }
-                    _ = flush_interval.tick() => {
-                        if !batch.is_empty() {
-                            if let Err(e) = Self::process_batch(
-                                &db_clone,
-                                cache_clone.as_ref(),
-                                &batch,
-                            ).await {
-                                error!("Error processing batch: {}", e);
-                            }
-                            batch.clear();
-                        }
-                    }
-                }
+        // Replicas never route Geyser updates, so they needn't know vaults
+        let known_vaults = if config.mode == IndexerMode::Geyser {
+            let addresses = store.get_active_vault_addresses().await?;
+            info!("Recognizing closures of {} known vaults", addresses.len());
+            KnownVaults::new(&addresses)
+        } else {
+            KnownVaults::default()
+        };
+        
+        let stats = Arc::new(PipelineStats::new());
+        let watermark = Arc::new(FlushWatermark::new(num_workers));
+        let breaker = config.circuit_breaker.enabled
+            .then(|| Arc::new(CircuitBreaker::new(&config.circuit_breaker)));
+        let memory = config.memory_budget.enabled
+            .then(|| Arc::new(MemoryBudget::new(&config.memory_budget)));
+        let writer = if config.coordination.enabled && config.mode == IndexerMode::Geyser {
+            // The writer lock lives as long as its session, which PgBouncer
+            // doesn't pin to one server connection
+            if config.database.pgbouncer {
+                anyhow::bail!("[coordination] needs session-level advisory locks and can't run with database.pgbouncer");
             }
-        });
+            Some(WriterElection::start(&config.coordination, db.clone()).await)
+        } else {
+            None
+        };
+        
+        let health = DatabaseHealth::start(
+            db.clone(),
+            Duration::from_secs(config.database.health_check_interval_secs),
+            Duration::from_secs(config.database.connection_timeout_seconds.max(1)),
+        );
+        
+        let (events, _) = broadcast::channel(config.events.channel_capacity.max(1));
+        if config.events.enabled {
+            tokio::spawn(EventWatcher::new(&config.events, db.clone(), events.clone()).await?.run());
+        }
+        let account_updates = config
+            .yellowstone
+            .enabled
+            .then(|| broadcast::channel(config.yellowstone.channel_capacity.max(1)).0);
+        let api_keys = if config.auth.enabled {
+            Some(ApiKeys::start(&config.auth, db.clone()).await?)
+        } else {
+            None
+        };
+        
+        // Spawn one indexing task per shard
+        let batch_size = Arc::new(AtomicUsize::new(config.batch_size.max(1)));
+        let mut shards = Vec::with_capacity(num_workers);
+        for shard in 0..num_workers {
+            let (live_tx, live_rx) = mpsc::unbounded_channel();
+            let (startup_tx, startup_rx) = mpsc::unbounded_channel();
+            let startup_pending = Arc::new(AtomicUsize::new(0));
+            shards.push(ShardSender {
+                live: live_tx,
+                startup: startup_tx,
+                startup_pending: startup_pending.clone(),
+            });
+            
+            let wal = if config.wal.enabled {
+                Some(WriteAheadLog::open(&config.wal.dir, shard, config.wal.max_bytes)?)
+            } else {
+                None
+            };
+            // Batches spooled by the previous run hold back every slot
+            // until they are replayed
+            if wal.as_ref().is_some_and(|wal| !wal.is_empty()) {
+                watermark.record_spooled(shard, 0);
+            }
+            
+            let sizer = config.adaptive_batching.enabled
+                .then(|| AdaptiveBatchSizer::new(&config.adaptive_batching, config.batch_size));
+            
+            let worker = ShardWorker {
+                shard,
+                database: db.clone(),
+                store: store.clone(),
+                cache: cache_arc.clone(),
+                events: config.events.enabled.then(|| events.clone()),
+                batch_size: batch_size.clone(),
+                sizer,
+                stats: stats.clone(),
+                watermark: watermark.clone(),
+                retry: config.retry.clone(),
+                breaker: breaker.clone(),
+                memory: memory.clone(),
+                writer: writer.clone(),
+                startup_mode: if config.bulk_startup.enabled {
+                    WriteMode::Copy
+                } else {
+                    WriteMode::Upsert
+                },
+                bulk_batch_size: config.bulk_startup.batch_size.max(1),
+                startup_pending,
+                flushed_slot: 0,
+                dead_lettered_slot: None,
+                wal,
+                next_wal_replay: Instant::now(),
+                wal_head_attempts: 0,
+            };
+            
+            tokio::spawn(worker.run(
+                live_rx,
+                startup_rx,
+                Duration::from_millis(config.flush_interval_ms.max(1)),
+            ));
+        }
+        
+        info!("Indexer started with {} shard workers", num_workers);
+        
+        // Replicas never receive a startup snapshot
+        let initial_readiness = if config.mode == IndexerMode::Replica {
+            Readiness::CaughtUp
+        } else {
+            Readiness::Ingesting
+        };
         
         Ok(Self {
-            database: db,
+            database: db.clone(),
+            store,
             cache: cache_arc,
+            local_cache,
+            vault_reads: SingleFlight::new(),
             vault_program_id,
+            known_vaults: Arc::new(known_vaults),
+            mode: config.mode,
             batch_size,
-            update_tx: tx,
+            adaptive_batching: config.adaptive_batching.enabled,
+            shards,
+            stats,
+            watermark,
+            breaker,
+            memory,
+            writer,
+            health,
+            resume_checkpoints,
+            dedup: UpdateDeduplicator::new(num_workers),
+            readiness: Arc::new(ReadinessGate::new(&config.readiness, initial_readiness)),
+            cache_warmup: config.cache_warmup.clone(),
+            runtime: Handle::current(),
+            degradation: config.degradation.enabled
+                .then(|| DegradationController::new(&config.degradation)),
+            tvl: TvlCache::new(&config.aggregates, db),
+            events,
+            account_updates,
+            api_keys,
+            rate_limits: config.rate_limit.enabled.then(|| RateLimits::start(&config.rate_limit)),
+            backfill: config.backfill.clone(),
+            backfill_running: Arc::new(AtomicBool::new(false)),
+            reloader: OnceLock::new(),
         })
     }
     
+    /// Build database, cache and indexer from plugin configuration
+    ///
+    /// In replica mode the indexer also starts following the primary's
+    /// vault_states via logical replication and ignores Geyser updates.
+    pub async fn from_config(config: &PluginConfig) -> Result<Self> {
+        // Initialize database
+        let pool_settings = PoolSettings::from_config(&config.database);
+        let mut database = Database::connect(
+            &config.database.connection_string(),
+            &pool_settings,
+            Namespace::from_config(&config.database)?,
+            config.database.migrates_at_startup(),
+            config.database.effective_schema_check(),
+        ).await?;
+        
+        if !config.database.read_urls.is_empty() {
+            database.attach_read_replicas(
+                &config.database.read_urls,
+                &pool_settings,
+                Duration::from_millis(config.database.max_replica_lag_ms),
+            ).await?;
+        }
+        
+        if let Some(cipher) = ColumnCipher::from_config(&config.encryption)? {
+            database.set_column_cipher(cipher);
+        }
+        
+        if config.timescale.enabled {
+            if !config.database.least_privilege {
+                database.setup_timescale(config.timescale.compress_after_epochs).await?;
+            } else if !database.timescale_ready().await? {
+                anyhow::bail!(
+                    "[timescale] is enabled but the tables aren't hypertables yet; run the migrate subcommand"
+                );
+            }
+        }
+        
+        // Initialize Redis cache if enabled
+        let cache = if config.enable_cache {
+            Some(RedisCache::new(&config.redis, &config.vault_program_id).await?)
+        } else {
+            None
+        };
+        
+        let database = Arc::new(database);
+        let store = Self::open_store(config, &pool_settings, &database).await?;
+        let indexer = Self::with_store(database, store, cache, config).await?;
+        
+        if let Some(cache) = indexer.cache.as_ref().filter(|_| config.redis.leaderboard) {
+            cache.seed_leaderboard(&indexer.store.get_vault_balances().await?).await?;
+        }
+        
+        if config.retention.enabled {
+            let pruner = RetentionPruner::new(
+                &config.retention,
+                indexer.database.clone(),
+                !config.database.least_privilege,
+                config.timescale.enabled,
+            );
+            tokio::spawn(pruner.run());
+        }
+        if config.aggregates.enabled {
+            tokio::spawn(AggregateRefresher::new(&config.aggregates, indexer.database.clone()).run());
+        }
+        
+        if config.mode == IndexerMode::Replica {
+            // Replicas are ready from the start, so warm up before returning
+            if let Err(e) = indexer.warm_cache().await {
+                warn!("Cache warmup failed: {}", e);
+            }
+            indexer.start_replication(&config.replication).await?;
+        } else {
+            // Hypertable chunks are created by Timescale as rows arrive
+            if !config.timescale.enabled {
+                let maintainer = PartitionMaintainer::new(
+                    &config.partitioning,
+                    indexer.database.clone(),
+                    indexer.stats.clone(),
+                );
+                tokio::spawn(maintainer.run());
+            }
+            
+            // A standby's backfill would race the writer's
+            if config.backfill.on_startup && indexer.is_writer() {
+                tokio::spawn(backfill::run_in_background(indexer.backfiller(&config.backfill)));
+            }
+            if config.gap_detection.enabled {
+                let tracker = SlotCoverageTracker::new(
+                    &config.gap_detection,
+                    indexer.database.clone(),
+                    indexer.stats.clone(),
+                    indexer.backfiller(&config.backfill),
+                );
+                tokio::spawn(tracker.run());
+            }
+            if config.consistency.enabled {
+                let checker = ConsistencyChecker::new(
+                    &config.consistency,
+                    &config.backfill.rpc_url,
+                    indexer.vault_program_id,
+                    indexer.database.clone(),
+                    indexer.cache.clone(),
+                );
+                tokio::spawn(checker.run());
+            }
+        }
+        
+        Ok(indexer)
+    }
+    
+    /// The configured `[store]`, `database` itself unless another backend
+    /// is selected
+    async fn open_store(
+        config: &PluginConfig,
+        settings: &PoolSettings,
+        database: &Arc<Database>,
+    ) -> Result<Arc<dyn VaultStore>> {
+        match config.store.backend {
+            StoreBackend::Postgres => Ok(database.clone()),
+            StoreBackend::Sqlite => {
+                // Replication applies the primary's vault_states to Postgres
+                if config.mode == IndexerMode::Replica {
+                    anyhow::bail!("replica mode needs store.backend = \"postgres\"");
+                }
+                Self::open_sqlite_store(config, settings).await
+            }
+        }
+    }
+    
+    #[cfg(feature = "sqlite")]
+    async fn open_sqlite_store(config: &PluginConfig, settings: &PoolSettings) -> Result<Arc<dyn VaultStore>> {
+        let store = SqliteStore::connect(
+            &config.store.sqlite_path,
+            settings,
+            config.database.migrates_at_startup(),
+            config.database.effective_schema_check(),
+        ).await?;
+        
+        Ok(Arc::new(store))
+    }
+    
+    #[cfg(not(feature = "sqlite"))]
+    async fn open_sqlite_store(_config: &PluginConfig, _settings: &PoolSettings) -> Result<Arc<dyn VaultStore>> {
+        anyhow::bail!("store.backend = \"sqlite\" needs a build with the sqlite feature")
+    }
+    
+    /// Follow the primary instance's vault_states via logical replication
+    pub async fn start_replication(&self, config: &ReplicationConfig) -> Result<()> {
+        let consumer = ReplicationConsumer::new(
+            config.clone(),
+            self.database.clone(),
+            self.cache.clone(),
+        ).await?;
+        
+        tokio::spawn(consumer.run());
+        Ok(())
+    }
+    
+    /// Seed the store with every program account from RPC
+    pub async fn backfill(&self, config: &BackfillConfig) -> Result<BackfillReport> {
+        self.backfiller(config).run().await
+    }
+    
+    /// Start a `[backfill]` in the background on request; `Ok(false)` if
+    /// one started this way is still running
+    ///
+    /// Refused on replicas and standbys, whose backfill would race the
+    /// writer's.
+    pub fn start_backfill(&self) -> Result<bool> {
+        if self.mode == IndexerMode::Replica || !self.is_writer() {
+            anyhow::bail!("Only the writing instance backfills");
+        }
+        if self.backfill_running.swap(true, Ordering::AcqRel) {
+            return Ok(false);
+        }
+        
+        let backfiller = self.backfiller(&self.backfill);
+        let running = self.backfill_running.clone();
+        tokio::spawn(async move {
+            backfill::run_in_background(backfiller).await;
+            running.store(false, Ordering::Release);
+        });
+        Ok(true)
+    }
+    
+    fn backfiller(&self, config: &BackfillConfig) -> Backfiller {
+        Backfiller::new(
+            config,
+            self.vault_program_id,
+            self.batch_size.load(Ordering::Relaxed),
+            self.resume_slot(),
+            self.database.clone(),
+            self.store.clone(),
+            self.cache.clone(),
+            self.known_vaults.clone(),
+        )
+    }
+    
     /// Process account update
     pub fn process_update(&self, update: AccountUpdate) -> Result<()> {
-        // Check if this is a vault account
-        if update.owner != self.vault_program_id {
+        let slot = update.slot;
+        let result = self.route_update(update);
+        
+        // Only once it is queued, so a read waiting for this slot waits for
+        // it; a dropped update must not pass for a flushed one
+        if result.is_ok() {
+            self.watermark.observe(slot);
+        }
+        result
+    }
+    
+    fn route_update(&self, update: AccountUpdate) -> Result<()> {
+        // Replicas receive state from the primary, not from Geyser
+        if self.mode == IndexerMode::Replica {
+            return Ok(());
+        }
+        
+        // Check if this is a vault account; closed accounts are handed back
+        // to the system program, so a live zero-lamport write is a vault
+        // closing if it is to a known vault
+        let owned = update.owner == self.vault_program_id;
+        let closing = update.lamports == 0
+            && !update.is_startup
+            && (owned || self.known_vaults.contains(&update.pubkey));
+        if !owned && !closing {
             return Ok(()); // Not a vault account, skip
         }
+        if !closing {
+            self.known_vaults.insert(update.pubkey);
+        }
+        
+        // Hot standbys leave writes to the instance holding the writer lock
+        if !self.is_writer() {
+            metrics::counter!("indexer_standby_skipped_total").increment(1);
+            return Ok(());
+        }
+        
+        // Tighten filters according to the degradation ladder when backlogged
+        if let Some(degradation) = &self.degradation {
+            let pending = self.stats.updates_received()
+                .saturating_sub(self.stats.updates_done());
+            degradation.observe_backlog(pending);
+            
+            if !degradation.admit(&update) {
+                return Ok(());
+            }
+        }
+        
+        // Snapshot and live copies of the same version are written once
+        if !self.dedup.admit(&update, shard_for(&update.pubkey, self.shards.len())) {
+            return Ok(());
+        }
+        
+        self.republish(&update);
+        let address = update.pubkey;
+        self.enqueue(update)?;
+        if closing {
+            self.known_vaults.remove(&address);
+        }
+        Ok(())
+    }
+    
+    /// Hand a live update to Yellowstone subscribers, if any are connected;
+    /// like Yellowstone, the startup snapshot isn't streamed
+    fn republish(&self, update: &AccountUpdate) {
+        if update.is_startup {
+            return;
+        }
+        if let Some(sender) = self.account_updates.as_ref().filter(|sender| sender.receiver_count() > 0) {
+            // No receivers left is fine; the update is simply dropped
+            let _ = sender.send(Arc::new(update.clone()));
+        }
+    }
+    
+    /// Record a block's time so timestamps can be mapped to slots
+    ///
+    /// Written in the background; blocks without a time are skipped, and a
+    /// failed write only loses that block's entry.
+    pub fn process_block_metadata(
+        &self,
+        slot: u64,
+        blockhash: &str,
+        block_height: Option<u64>,
+        block_time: Option<i64>,
+    ) {
+        if self.mode == IndexerMode::Replica || !self.is_writer() {
+            return;
+        }
+        let Some(block_time) = block_time
+            .and_then(|time| OffsetDateTime::from_unix_timestamp(time).ok())
+        else {
+            return;
+        };
+        
+        let database = self.database.clone();
+        let blockhash = blockhash.to_string();
+        self.runtime.spawn(async move {
+            if let Err(e) = database.insert_block_metadata(slot, &blockhash, block_height, block_time).await {
+                warn!("Failed to record block time for slot {}: {}", slot, e);
+            }
+        });
+    }
+    
+    /// Wait until the memory budget has room for `update`, without
+    /// blocking the thread; async producers call this before
+    /// `process_update`, which doesn't wait on a runtime thread
+    pub async fn wait_for_memory(&self, update: &AccountUpdate) {
+        if let Some(budget) = &self.memory {
+            budget.wait_for_room(MemoryBudget::cost(update)).await;
+        }
+    }
+    
+    /// Hand an update to its shard worker
+    fn enqueue(&self, update: AccountUpdate) -> Result<()> {
+        let cost = MemoryBudget::cost(&update);
+        if let Some(budget) = &self.memory {
+            if !budget.reserve(cost) {
+                anyhow::bail!(
+                    "Memory budget exhausted ({} bytes in flight), dropping update for {}",
+                    budget.used(),
+                    update.pubkey
+                );
+            }
+        }
+        
+        self.stats.record_received();
         
-        self.update_tx.send(update)
-            .map_err(|e| anyhow::anyhow!("Failed to send update: {}", e))?;
+        let index = shard_for(&update.pubkey, self.shards.len());
+        let shard = &self.shards[index];
+        let startup = update.is_startup;
+        let lane = if startup { &shard.startup } else { &shard.live };
+        self.watermark.record_queued(index, startup);
+        if startup {
+            shard.startup_pending.fetch_add(1, Ordering::AcqRel);
+        }
+        lane.send(update).map_err(|e| {
+            if let Some(budget) = &self.memory {
+                budget.release(cost);
+            }
+            self.stats.record_discarded(1);
+            self.watermark.record_unqueued(index, startup);
+            if startup {
+                shard.startup_pending.fetch_sub(1, Ordering::AcqRel);
+            }
+            anyhow::anyhow!("Failed to send update: {}", e)
+        })?;
         
         Ok(())
     }
     
+    /// Called once the validator has delivered its whole startup snapshot
+    ///
+    /// Queries are considered caught up once every update received so far
+    /// has been flushed and the cache warmup, if enabled, has run. Updates
+    /// spooled to the WAL hold this back until they are replayed; those
+    /// dead-lettered or left behind by a demoted writer don't, since only
+    /// an operator can replay them.
+    pub fn end_of_startup(&self) {
+        self.dedup.finish_startup();
+        
+        let target = self.stats.updates_received();
+        let stats = self.stats.clone();
+        let readiness = self.readiness.clone();
+        let warmup = self.cache.clone().filter(|_| self.cache_warmup.enabled).map(|cache| {
+            (cache, self.store.clone(), self.database.clone(), self.cache_warmup.clone())
+        });
+        
+        self.runtime.spawn(async move {
+            let mut ticker = interval(Duration::from_millis(100));
+            while stats.updates_settled() < target {
+                ticker.tick().await;
+            }
+            if stats.updates_dead_lettered() > 0 {
+                warn!(
+                    "Caught up with {} updates dead-lettered; queries miss them until they are replayed",
+                    stats.updates_dead_lettered()
+                );
+            }
+            if let Some((cache, store, database, config)) = warmup {
+                if let Err(e) = Self::load_warmup_vaults(&cache, store.as_ref(), &database, &config).await {
+                    warn!("Cache warmup failed: {}", e);
+                }
+            }
+            readiness.mark_caught_up();
+        });
+    }
+    
+    /// Load the `[cache_warmup]` vaults into Redis, e.g. after a Redis
+    /// flush; returns how many were loaded
+    ///
+    /// Runs by itself once startup ingestion has caught up.
+    pub async fn warm_cache(&self) -> Result<usize> {
+        match &self.cache {
+            Some(cache) if self.cache_warmup.enabled => {
+                Self::load_warmup_vaults(cache, self.store.as_ref(), &self.database, &self.cache_warmup).await
+            }
+            _ => Ok(0),
+        }
+    }
+    
+    async fn load_warmup_vaults(
+        cache: &RedisCache,
+        store: &dyn VaultStore,
+        database: &Database,
+        config: &CacheWarmupConfig,
+    ) -> Result<usize> {
+        let start = Instant::now();
+        let mut states = database.get_recently_updated_vaults(config.recent_vaults).await?;
+        let listed: Vec<String> = config.vault_addresses.iter().chain(cache.pinned_vaults()).cloned().collect();
+        if !listed.is_empty() {
+            states.extend(store.get_vault_states(&listed).await?);
+        }
+        
+        for chunk in states.chunks(WARMUP_CHUNK) {
+            cache.batch_set(chunk).await?;
+        }
+        
+        info!("Warmed the cache with {} vaults in {:?}", states.len(), start.elapsed());
+        Ok(states.len())
+    }
+    
+    /// Whether startup ingestion has caught up
+    pub fn readiness(&self) -> Readiness {
+        self.readiness.state()
+    }
+    
+    /// Whether this instance can serve: startup ingestion has caught up
+    /// and the last database health check passed
+    pub fn is_ready(&self) -> bool {
+        self.readiness.is_ready() && self.health.is_healthy()
+    }
+    
+    /// Result of the last background database health check
+    pub fn database_healthy(&self) -> bool {
+        self.health.is_healthy()
+    }
+    
+    /// Dead-lettered updates not yet replayed, oldest first
+    pub async fn list_dead_letters(&self, limit: i64) -> Result<Vec<DeadLetter>> {
+        self.database.list_dead_letters(limit, false).await
+    }
+    
+    /// Write dead-lettered updates again; returns how many were written
+    ///
+    /// Each letter is written by itself, skipping the shard queues, so the
+    /// call returns once they have landed. Writes are ordered by slot and
+    /// write version, so a letter older than the stored state changes
+    /// nothing. Only written letters are marked replayed; the others stay
+    /// pending for the next replay.
+    pub async fn replay_dead_letters(&self, ids: &[i64]) -> Result<usize> {
+        let letters = self.database.get_dead_letters(ids).await?;
+        
+        let mut replayed = Vec::new();
+        for letter in letters.into_iter().filter(|letter| letter.replayed_at.is_none()) {
+            let written = ShardWorker::guarded_write(
+                self.store.as_ref(),
+                &self.database,
+                self.cache.as_deref(),
+                None,
+                self.breaker.as_deref(),
+                std::slice::from_ref(&letter.update),
+                WriteMode::Upsert,
+            ).await;
+            match written {
+                Ok(()) => replayed.push(letter.id),
+                Err(e) => warn!("Failed to replay dead letter {}: {}", letter.id, e),
+            }
+        }
+        
+        self.database.mark_dead_letters_replayed(&replayed).await?;
+        info!("Replayed {} dead-lettered updates", replayed.len());
+        
+        // Letters left unreplayed keep the hold
+        if self.database.list_dead_letters(1, false).await?.is_empty() {
+            self.watermark.record_dead_letters_replayed();
+        }
+        
+        Ok(replayed.len())
+    }
+    
+    /// Active degradation level (0 = normal operation)
+    pub fn degradation_level(&self) -> usize {
+        self.degradation
+            .as_ref()
+            .map(DegradationController::active_level)
+            .unwrap_or(0)
+    }
+    
+    /// Whether this instance writes to the database (always true without
+    /// coordination)
+    pub fn is_writer(&self) -> bool {
+        self.writer
+            .as_ref()
+            .map(|writer| writer.is_leader())
+            .unwrap_or(true)
+    }
+    
+    /// State of the Postgres write circuit breaker (always closed if disabled)
+    pub fn circuit_state(&self) -> CircuitState {
+        self.breaker
+            .as_ref()
+            .map(|breaker| breaker.state())
+            .unwrap_or(CircuitState::Closed)
+    }
+    
+    /// Slot indexing resumed from: the lowest checkpoint across shards at
+    /// startup, or `None` on a fresh database
+    ///
+    /// Every update at or below this slot was flushed by the previous run:
+    /// a shard doesn't checkpoint past updates still in its startup lane or
+    /// WAL, or past those it dead-lettered.
+    pub fn resume_slot(&self) -> Option<u64> {
+        self.resume_checkpoints.iter().map(|checkpoint| checkpoint.slot).min()
+    }
+    
+    /// Per-shard checkpoints as they were when this instance started
+    pub fn resume_checkpoints(&self) -> &[ShardCheckpoint] {
+        &self.resume_checkpoints
+    }
+    
+    /// Current per-shard checkpoints
+    pub async fn shard_checkpoints(&self) -> Result<Vec<ShardCheckpoint>> {
+        self.store.get_shard_checkpoints().await
+    }
+    
+    /// Shared pipeline counters and latency windows
+    pub fn pipeline_stats(&self) -> Arc<PipelineStats> {
+        self.stats.clone()
+    }
+    
+    /// Indexing statistics since startup
+    pub fn get_stats(&self) -> IndexingStats {
+        self.stats.snapshot()
+    }
+    
     /// Process batch of updates
-    async fn process_batch(
+    ///
+    /// Updates that fail to parse are dead-lettered individually; an error
+    /// is only returned when the database write itself fails.
+    pub(crate) async fn process_batch(
+        store: &dyn VaultStore,
         database: &Database,
         cache: Option<&RedisCache>,
+        events: Option<&broadcast::Sender<VaultEvent>>,
         updates: &[AccountUpdate],
+        mode: WriteMode,
     ) -> Result<()> {
         let start = std::time::Instant::now();
         
+        // Collapse repeated writes to the same account within this flush window
+        let updates = Self::dedup_latest(updates);
+        
         let mut vault_states = Vec::new();
+        let mut live_updates = Vec::new();
+        let mut closing = Vec::new();
         
         for update in updates {
+            if update.lamports == 0 {
+                closing.push(update);
+                continue;
+            }
+            
             // Parse vault state from account data
-            if let Some(state) = Self::parse_vault_state(update)? {
-                vault_states.push(state);
+            match Self::parse_vault_state(update) {
+                Ok(Some(state)) => {
+                    vault_states.push(state);
+                    if !update.is_startup {
+                        live_updates.push(update);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Failed to parse vault {}: {}", update.pubkey, e);
+                    if let Err(dlq_err) = database
+                        .insert_dead_letters(&[update], DeadLetterStage::Parse, &e.to_string(), 1)
+                        .await
+                    {
+                        error!("Failed to dead-letter vault {}: {}", update.pubkey, dlq_err);
+                    }
+                }
             }
         }
         
-        if vault_states.is_empty() {
-            return Ok(());
+        if !closing.is_empty() {
+            Self::close_vaults(store, database, cache, events, &closing).await?;
         }
         
-        // Write to database
-        database.batch_upsert_vault_states(&vault_states).await?;
+        // Drop states whose contents match what's stored; the write would
+        // only bump the version, so neither the database nor the cache needs
+        // it. Their raw writes are still recorded below.
+        if !vault_states.is_empty() {
+            let addresses: Vec<String> = vault_states
+                .iter()
+                .map(|state| state.vault_address.to_string())
+                .collect();
+            let stored = store.get_state_hashes(&addresses).await?;
+            
+            let mut unchanged = HashSet::new();
+            for (state, address) in vault_states.iter().zip(&addresses) {
+                let current = state.content_hash()?;
+                if stored.get(address).is_some_and(|hash| hash[..] == current) {
+                    unchanged.insert(state.vault_address);
+                }
+            }
+            
+            if !unchanged.is_empty() {
+                vault_states.retain(|state| !unchanged.contains(&state.vault_address));
+                metrics::counter!("indexer_unchanged_states_skipped_total").increment(unchanged.len() as u64);
+            }
+        }
         
-        // Update cache
-        if let Some(cache) = cache {
-            cache.batch_set(&vault_states).await?;
+        // Write to database; versions older than the stored row are skipped
+        if !vault_states.is_empty() {
+            let applied = match mode {
+                WriteMode::Upsert => store.batch_upsert_vault_states(&vault_states).await?,
+                WriteMode::Copy => store.copy_upsert_vault_states(&vault_states).await?,
+            };
+            vault_states = vault_states
+                .into_iter()
+                .zip(applied)
+                .filter_map(|(state, applied)| applied.then_some(state))
+                .collect();
+        }
+        
+        // Keep the raw writes so versions can be traced to their transactions;
+        // like the cache this is secondary to the vault state itself
+        if !live_updates.is_empty() {
+            if let Err(e) = database.copy_account_updates(&live_updates).await {
+                warn!("Failed to record account updates for batch: {}", e);
+            }
+        }
+        
+        // Update cache; the database is the source of truth, so a cache
+        // failure must not dead-letter updates that were already written
+        if let Some(cache) = cache.filter(|_| !vault_states.is_empty()) {
+            if let Err(e) = cache.batch_set_changes(&vault_states).await {
+                warn!("Failed to update cache for batch: {}", e);
+            }
         }
+        Self::announce_updates(events, &vault_states);
         
         let elapsed = start.elapsed();
         debug!("Processed {} vault states in {:?}", vault_states.len(), elapsed);
@@ -137,8 +974,91 @@ impl Indexer {
         Ok(())
     }
     
+    /// Tombstone the vaults closed by zero-lamport writes
+    ///
+    /// Most such writes are for unrelated accounts and match no vault. The
+    /// cache keeps the tombstone, like the store, and closed vaults leave
+    /// their owners' indexes.
+    async fn close_vaults(
+        store: &dyn VaultStore,
+        database: &Database,
+        cache: Option<&RedisCache>,
+        events: Option<&broadcast::Sender<VaultEvent>>,
+        updates: &[&AccountUpdate],
+    ) -> Result<()> {
+        let closures: Vec<VaultClosure> = updates
+            .iter()
+            .map(|update| VaultClosure {
+                vault_address: update.pubkey,
+                slot: update.slot,
+                write_version: update.write_version,
+            })
+            .collect();
+        let closed = store.close_vaults(&closures).await?;
+        if closed.is_empty() {
+            return Ok(());
+        }
+        metrics::counter!("indexer_vaults_closed_total").increment(closed.len() as u64);
+        
+        let closed_addresses: HashSet<Pubkey> = closed.iter().map(|state| state.vault_address).collect();
+        let closing_updates: Vec<&AccountUpdate> = updates
+            .iter()
+            .copied()
+            .filter(|update| closed_addresses.contains(&update.pubkey))
+            .collect();
+        if let Err(e) = database.copy_account_updates(&closing_updates).await {
+            warn!("Failed to record account updates for closed vaults: {}", e);
+        }
+        
+        if let Some(cache) = cache {
+            if let Err(e) = cache.batch_set_changes(&closed).await {
+                warn!("Failed to update cache for closed vaults: {}", e);
+            }
+        }
+        Self::announce_updates(events, &closed);
+        
+        Ok(())
+    }
+    
+    /// Broadcast written vault states to event subscribers, if any
+    fn announce_updates(events: Option<&broadcast::Sender<VaultEvent>>, states: &[VaultState]) {
+        let Some(events) = events.filter(|events| events.receiver_count() > 0) else {
+            return;
+        };
+        
+        for state in states {
+            // Subscribers may leave between the check and the send
+            let _ = events.send(VaultEvent::Updated(state.clone()));
+        }
+    }
+    
+    /// Keep only the newest (slot, write_version) update per pubkey
+    fn dedup_latest(updates: &[AccountUpdate]) -> Vec<&AccountUpdate> {
+        let mut latest: HashMap<Pubkey, &AccountUpdate> = HashMap::with_capacity(updates.len());
+        
+        for update in updates {
+            match latest.entry(update.pubkey) {
+                Entry::Occupied(mut entry) => {
+                    let current = entry.get();
+                    if (update.slot, update.write_version) >= (current.slot, current.write_version) {
+                        entry.insert(update);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(update);
+                }
+            }
+        }
+        
+        if latest.len() < updates.len() {
+            debug!("Deduplicated batch from {} to {} updates", updates.len(), latest.len());
+        }
+        
+        latest.into_values().collect()
+    }
+    
     /// Parse vault state from account update
-    fn parse_vault_state(update: &AccountUpdate) -> Result<Option<VaultState>> {
+    pub(crate) fn parse_vault_state(update: &AccountUpdate) -> Result<Option<VaultState>> {
         // In a real implementation, we'd parse the account data according to
         // the vault program's account structure
         // This is a simplified version
@@ -177,30 +1097,901 @@ impl Indexer {
             last_updated: OffsetDateTime::now_utc(),
             slot: update.slot,
             write_version: update.write_version,
+            status: VaultStatus::Active,
+            closed_at_slot: None,
         };
         
         Ok(Some(state))
     }
     
     /// Get vault state (with cache lookup)
+    ///
+    /// Concurrent misses for the same vault share one store read. Subject
+    /// to the readiness policy while the startup snapshot is being
+    /// ingested.
     pub async fn get_vault_state(&self, vault_address: &str) -> Result<Option<VaultState>> {
+        self.readiness.check().await?;
+        
+        // The local cache learns of writes after they land, and a store
+        // read in flight may have started before
+        let own_writes = watermark::reads_own_writes();
+        if let Some(local) = self.local_cache.as_ref().filter(|_| !own_writes) {
+            if let Some(state) = local.get(vault_address).await {
+                return Ok(Some(state));
+            }
+        }
+        
         // Try cache first
         if let Some(cache) = &self.cache {
             if let Some(state) = cache.get(vault_address).await? {
+                self.cache_locally(&state).await;
                 return Ok(Some(state));
             }
         }
         
-        // Fallback to database
-        let state = self.database.get_vault_state(vault_address).await?;
+        // Fallback to the store, once per vault however many readers missed
+        let load = || async {
+            let state = self.store.get_vault_state(vault_address).await?;
+            
+            // Update cache if found
+            if let Some(ref state) = state {
+                if let Some(cache) = &self.cache {
+                    cache.set(state).await?;
+                }
+                self.cache_locally(state).await;
+            }
+            
+            Ok(state)
+        };
+        if own_writes {
+            return load().await;
+        }
+        self.vault_reads.run(vault_address, load).await
+    }
+    
+    /// Run `read` once every update up to `min_slot` has been flushed,
+    /// waiting at most `timeout`, so a client sees its own transaction
+    ///
+    /// The read then skips the local cache, coalesced reads and read
+    /// replicas, which may trail the flush. Only the instance writing the
+    /// index knows how far it got, so replicas and standbys refuse with
+    /// `SlotNotFlushed`, as does a wait that times out, whether on a slow
+    /// flush or on failed writes awaiting replay.
+    pub async fn read_at_slot<T, F>(&self, min_slot: Option<u64>, timeout: Duration, read: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let Some(min_slot) = min_slot else {
+            return read.await;
+        };
+        if self.mode == IndexerMode::Replica || !self.is_writer() {
+            return Err(SlotNotFlushed::NotWriter.into());
+        }
+        
+        self.watermark.wait_for(min_slot, timeout).await?;
+        watermark::read_your_writes(read).await
+    }
+    
+    async fn cache_locally(&self, state: &VaultState) {
+        if let Some(local) = &self.local_cache {
+            local.insert(state).await;
+        }
+    }
+    
+    /// Vault states for several addresses, in the order given; unknown
+    /// addresses are left out
+    ///
+    /// Cache hits come from one `MGET` and the misses from one store query,
+    /// which then fills the cache. Subject to the readiness policy like
+    /// `get_vault_state`.
+    pub async fn get_vault_states<S: AsRef<str>>(&self, vault_addresses: &[S]) -> Result<Vec<VaultState>> {
+        self.readiness.check().await?;
+        
+        let Some(cache) = &self.cache else {
+            let addresses: Vec<String> = vault_addresses.iter().map(|address| address.as_ref().to_string()).collect();
+            let mut states: HashMap<String, VaultState> = self
+                .store
+                .get_vault_states(&addresses)
+                .await?
+                .into_iter()
+                .map(|state| (state.vault_address.to_string(), state))
+                .collect();
+            return Ok(addresses.iter().filter_map(|address| states.remove(address)).collect());
+        };
+        
+        Ok(Self::cached_vault_states(cache, self.store.as_ref(), vault_addresses)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+    
+    /// Vault states for `addresses` in order, from the cache where
+    /// possible and from the store for the misses, which are then cached;
+    /// `None` for addresses the store doesn't know either
+    async fn cached_vault_states<S: AsRef<str>>(
+        cache: &RedisCache,
+        store: &dyn VaultStore,
+        addresses: &[S],
+    ) -> Result<Vec<Option<VaultState>>> {
+        let mut states = cache.get_many(addresses).await?;
+        let missing: Vec<String> = addresses
+            .iter()
+            .zip(&states)
+            .filter(|(_, state)| state.is_none())
+            .map(|(address, _)| address.as_ref().to_string())
+            .collect();
+        if missing.is_empty() {
+            return Ok(states);
+        }
+        
+        let loaded = store.get_vault_states(&missing).await?;
+        cache.batch_set(&loaded).await?;
+        let mut loaded: HashMap<String, VaultState> = loaded
+            .into_iter()
+            .map(|state| (state.vault_address.to_string(), state))
+            .collect();
+        for (address, state) in addresses.iter().zip(states.iter_mut()) {
+            if state.is_none() {
+                *state = loaded.remove(address.as_ref());
+            }
+        }
+        
+        Ok(states)
+    }
+    
+    /// One page of the vaults owned by `owner`, ordered by address and
+    /// starting after the address `after`
+    ///
+    /// Served from the Redis owner index when it's cached and still
+    /// accurate, otherwise from Postgres, which then repopulates the index.
+    /// Subject to the readiness policy like `get_vault_state`.
+    pub async fn get_vaults_by_owner(&self, owner: &str, after: Option<&str>, limit: i64) -> Result<Vec<VaultState>> {
+        self.readiness.check().await?;
+        
+        let Some(cache) = &self.cache else {
+            return self.store.get_vaults_by_owner(owner, after, limit).await;
+        };
+        
+        if let Some(addresses) = cache.get_owner_vaults(owner, after, limit).await? {
+            let states = Self::cached_vault_states(cache, self.store.as_ref(), &addresses).await?;
+            
+            // A vault that's missing from the store, or was closed or
+            // changed hands since the index was built, invalidates it
+            let stale = states.iter().any(|state| {
+                !state
+                    .as_ref()
+                    .is_some_and(|state| state.owner.to_string() == owner && state.status == VaultStatus::Active)
+            });
+            if !stale {
+                return Ok(states.into_iter().flatten().collect());
+            }
+            cache.delete_owner_vaults(owner).await?;
+        }
+        
+        // Started before the store is read, so vaults written meanwhile
+        // are added to the index by their writes
+        let rebuild = cache.begin_owner_rebuild(owner).await?;
+        let states = self.store.get_vaults_by_owner(owner, after, limit).await?;
+        if !states.is_empty() {
+            cache.batch_set(&states).await?;
+        }
+        let addresses = self.store.get_vault_addresses_by_owners(&[owner.to_string()]).await?;
+        cache.set_owner_vaults(owner, &rebuild, &addresses).await?;
         
-        // Update cache if found
-        if let Some(ref state) = state {
+        Ok(states)
+    }
+    
+    /// Drop vaults and owner indexes from Redis and this process's local
+    /// cache, so their next reads go to the store
+    ///
+    /// Other instances' local caches keep their copies until they expire.
+    pub async fn evict_cached(&self, vault_addresses: &[String], owners: &[String]) -> Result<()> {
+        for address in vault_addresses {
+            if let Some(local) = &self.local_cache {
+                local.invalidate(address).await;
+            }
             if let Some(cache) = &self.cache {
-                cache.set(state).await?;
+                cache.delete(address).await?;
+            }
+        }
+        if let Some(cache) = &self.cache {
+            for owner in owners {
+                cache.delete_owner_vaults(owner).await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// One page of the vaults matching `filter`, largest `sort_by` first
+    ///
+    /// Always read from the store. Subject to the readiness policy like
+    /// `get_vault_state`.
+    pub async fn list_vaults(
+        &self,
+        filter: &VaultFilter,
+        sort_by: VaultSort,
+        cursor: Option<&VaultCursor>,
+        limit: i64,
+    ) -> Result<VaultPage> {
+        self.readiness.check().await?;
+        self.store.list_vaults(filter, sort_by, cursor, limit).await
+    }
+    
+    /// The `n` largest active vaults by lamport balance
+    ///
+    /// Read off the Redis leaderboard when it's enabled, otherwise sorted
+    /// by the store. Subject to the readiness policy like `get_vault_state`.
+    pub async fn get_top_vaults(&self, n: i64) -> Result<Vec<VaultState>> {
+        self.readiness.check().await?;
+        
+        if let Some(cache) = &self.cache {
+            if let Some(addresses) = cache.top_vaults(n).await? {
+                return Ok(Self::cached_vault_states(cache, self.store.as_ref(), &addresses)
+                    .await?
+                    .into_iter()
+                    .flatten()
+                    .collect());
+            }
+        }
+        
+        self.store.get_top_vaults_by_balance(n, None).await
+    }
+    
+    /// Total value locked across all vaults, cached for
+    /// `aggregates.tvl_cache_secs`
+    ///
+    /// Subject to the readiness policy like `get_vault_state`.
+    pub async fn get_total_value_locked(&self) -> Result<TotalValueLocked> {
+        self.readiness.check().await?;
+        self.tvl.get().await
+    }
+    
+    /// Vaults updated after `since`, for consumers polling for changes
+    ///
+    /// Subject to the readiness policy: the startup snapshot is written at
+    /// old slots while live updates move ahead, so a cursor taken during
+    /// ingestion could skip snapshot vaults.
+    pub async fn get_vaults_updated_since(&self, since: &SyncCursor, limit: i64) -> Result<SyncPage> {
+        self.readiness.check().await?;
+        self.database.get_vaults_updated_since(since, limit).await
+    }
+    
+    /// Owner changes and recorded vault states after `since`, for
+    /// consumers processing every change exactly once
+    ///
+    /// Subject to the readiness policy, as `get_vaults_updated_since`.
+    pub async fn get_changes_since(&self, since: &ChangeCursor, limit: i64) -> Result<ChangePage> {
+        self.readiness.check().await?;
+        self.database.get_changes_since(since, limit).await
+    }
+    
+    /// Recorded versions of a vault within an inclusive slot range, oldest
+    /// first and starting after the `(slot, write_version)` in `after`,
+    /// each with the transaction that produced it when known
+    pub async fn get_vault_history(
+        &self,
+        vault_address: &str,
+        from_slot: u64,
+        to_slot: u64,
+        after: Option<(u64, u64)>,
+        limit: i64,
+    ) -> Result<Vec<VaultStateVersion>> {
+        self.database
+            .get_vault_history_with_signatures(vault_address, from_slot, to_slot, after, limit)
+            .await
+    }
+    
+    /// The recorded account write that produced `state`, as the validator
+    /// delivered it; `None` for snapshot and backfill writes, which aren't
+    /// recorded, and once retention has removed it
+    pub async fn get_raw_vault_account(&self, state: &VaultState) -> Result<Option<RawAccount>> {
+        let writes = self
+            .database
+            .get_raw_account(&state.vault_address.to_string(), state.slot)
+            .await?;
+        Ok(writes.into_iter().find(|raw| raw.write_version == state.write_version))
+    }
+    
+    /// Owner transitions of a vault, oldest first
+    pub async fn get_owner_history(&self, vault_address: &str) -> Result<Vec<OwnerChange>> {
+        self.database.get_owner_history(vault_address).await
+    }
+    
+    /// Receive vault events, such as owner changes and written states,
+    /// recorded from now on
+    ///
+    /// Nothing is delivered with `events.enabled` off.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<VaultEvent> {
+        self.events.subscribe()
+    }
+    
+    /// Receive live vault account updates as Geyser delivered them, from
+    /// now on; `None` with `yellowstone.enabled` off
+    ///
+    /// Updates are published on receipt, before they are written, and
+    /// include zero-lamport writes of closing accounts of any owner.
+    pub fn subscribe_account_updates(&self) -> Option<broadcast::Receiver<Arc<AccountUpdate>>> {
+        self.account_updates.as_ref().map(broadcast::Sender::subscribe)
+    }
+    
+    /// Keys the query servers require; `None` with `auth.enabled` off,
+    /// when every query is let through
+    pub fn api_keys(&self) -> Option<Arc<ApiKeys>> {
+        self.api_keys.clone()
+    }
+    
+    /// Live lane batch size of the shard workers from their next batch on;
+    /// `false` with `adaptive_batching`, whose controller keeps choosing
+    /// its own
+    pub fn set_batch_size(&self, batch_size: usize) -> bool {
+        if self.adaptive_batching {
+            return false;
+        }
+        self.batch_size.store(batch_size.max(1), Ordering::Relaxed);
+        true
+    }
+    
+    /// Apply `ttl_seconds`, `ttl_jitter_percent` and `hot_key_ttl_seconds`
+    /// to entries cached from now on; `false` without a cache
+    pub fn set_cache_ttls(&self, config: &RedisConfig) -> bool {
+        match &self.cache {
+            Some(cache) => {
+                cache.set_ttls(config);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Replace the degradation ladder's levels; `false` when `[degradation]`
+    /// was off at startup
+    pub fn set_degradation_levels(&self, config: &DegradationConfig) -> bool {
+        match &self.degradation {
+            Some(degradation) => {
+                degradation.set_levels(config);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Install what `POST /admin/reload` re-reads the config with; only the
+    /// first call has an effect
+    pub fn set_config_reloader(&self, reloader: Arc<ConfigReloader>) {
+        let _ = self.reloader.set(reloader);
+    }
+    
+    /// Reloader of the config file, if the indexer was started from one
+    pub fn config_reloader(&self) -> Option<Arc<ConfigReloader>> {
+        self.reloader.get().cloned()
+    }
+    
+    /// Buckets the query servers charge requests to; `None` with
+    /// `rate_limit.enabled` off
+    pub fn rate_limits(&self) -> Option<Arc<RateLimits>> {
+        self.rate_limits.clone()
+    }
+    
+    /// The program whose vaults are indexed
+    pub fn vault_program_id(&self) -> &Pubkey {
+        &self.vault_program_id
+    }
+    
+    /// Resolve a saved filter into the matching vault addresses
+    ///
+    /// Shared by subscriptions, webhooks and exports so per-customer vault
+    /// lists live in one place.
+    pub async fn resolve_saved_filter(&self, name: &str) -> Result<Vec<String>> {
+        let saved = self.database.get_saved_filter(name).await?
+            .ok_or_else(|| anyhow::anyhow!("Unknown saved filter '{}'", name))?;
+        
+        let mut candidates = saved.filter.vault_addresses.clone();
+        if candidates.is_empty() {
+            if let Some(tag) = saved.filter.tags.first() {
+                candidates = self.database.get_vaults_by_tag(tag).await?;
+            } else if !saved.filter.owners.is_empty() {
+                candidates = self.store
+                    .get_vault_addresses_by_owners(&saved.filter.owners)
+                    .await?;
+            } else {
+                anyhow::bail!(
+                    "Saved filter '{}' must list vault addresses, owners or tags",
+                    name
+                );
+            }
+        }
+        
+        let mut matched = Vec::new();
+        for address in candidates {
+            if let Some(state) = self.get_vault_state(&address).await? {
+                let tags = self.database.get_vault_tags(&address).await?;
+                if saved.filter.matches(&state, &tags) {
+                    matched.push(address);
+                }
+            }
+        }
+        
+        Ok(matched)
+    }
+}
+
+/// How a batch of vault states reaches Postgres
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum WriteMode {
+    /// Row-at-a-time upserts in one transaction
+    #[default]
+    Upsert,
+    /// COPY into a staging table, then a single merge; for bulk ingestion
+    Copy,
+}
+
+/// Channel pair feeding one shard worker
+struct ShardSender {
+    live: mpsc::UnboundedSender<AccountUpdate>,
+    startup: mpsc::UnboundedSender<AccountUpdate>,
+    /// Startup updates sent and not yet flushed or given up on
+    startup_pending: Arc<AtomicUsize>,
+}
+
+/// Pending updates for one priority lane
+#[derive(Default)]
+struct Lane {
+    updates: Vec<AccountUpdate>,
+    /// Arrival time of the oldest update in the lane
+    started: Option<Instant>,
+    /// Memory budget reserved for the updates in the lane
+    bytes: u64,
+    mode: WriteMode,
+    /// Whether this is the startup backlog lane
+    startup: bool,
+}
+
+impl Lane {
+    fn push(&mut self, update: AccountUpdate) {
+        self.started.get_or_insert_with(Instant::now);
+        self.bytes += MemoryBudget::cost(&update);
+        self.updates.push(update);
+    }
+    
+    fn len(&self) -> usize {
+        self.updates.len()
+    }
+    
+    fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+}
+
+/// Batch loop state for a single shard
+///
+/// Live updates and the startup snapshot backlog arrive on separate lanes.
+/// The live lane is always polled first and flushed on every tick; the
+/// backlog is only flushed on a tick when no live updates are waiting, so
+/// real-time writes never queue behind millions of snapshot accounts.
+/// Each lane writes in arrival order, but the lanes don't keep order
+/// between them: an account's snapshot write may land after its live one,
+/// and the version check in the upserts discards it.
+struct ShardWorker {
+    shard: usize,
+    database: Arc<Database>,
+    store: Arc<dyn VaultStore>,
+    cache: Option<Arc<RedisCache>>,
+    /// Where written states are broadcast, with `events.enabled`
+    events: Option<broadcast::Sender<VaultEvent>>,
+    batch_size: Arc<AtomicUsize>,
+    sizer: Option<AdaptiveBatchSizer>,
+    stats: Arc<PipelineStats>,
+    watermark: Arc<FlushWatermark>,
+    retry: RetryConfig,
+    breaker: Option<Arc<CircuitBreaker>>,
+    memory: Option<Arc<MemoryBudget>>,
+    writer: Option<Arc<WriterElection>>,
+    /// How the startup backlog lane is written
+    startup_mode: WriteMode,
+    bulk_batch_size: usize,
+    /// Startup updates sent to this shard and not yet done with, in the
+    /// channel or the backlog lane
+    startup_pending: Arc<AtomicUsize>,
+    /// Newest slot written, checkpointed once nothing older is pending
+    flushed_slot: u64,
+    /// Oldest slot of the updates this run dead-lettered after failed
+    /// writes; the checkpoint stays below it, so a restart reconciles them
+    dead_lettered_slot: Option<u64>,
+    wal: Option<WriteAheadLog>,
+    /// Earliest time the next WAL replay may be attempted
+    next_wal_replay: Instant,
+    /// Failed replays of the oldest batch in the WAL
+    wal_head_attempts: u32,
+}
+
+impl ShardWorker {
+    async fn run(
+        mut self,
+        mut live_rx: mpsc::UnboundedReceiver<AccountUpdate>,
+        mut startup_rx: mpsc::UnboundedReceiver<AccountUpdate>,
+        flush_every: Duration,
+    ) {
+        let mut live = Lane::default();
+        let mut backlog = Lane {
+            mode: self.startup_mode,
+            startup: true,
+            ..Lane::default()
+        };
+        let mut live_open = true;
+        let mut startup_open = true;
+        let mut flush_interval = interval(flush_every);
+        
+        while live_open || startup_open {
+            tokio::select! {
+                biased;
+                
+                update = live_rx.recv(), if live_open => {
+                    match update {
+                        Some(update) => {
+                            live.push(update);
+                            if live.len() >= self.target_batch_size(&live) {
+                                self.flush(&mut live).await;
+                            }
+                        }
+                        None => live_open = false,
+                    }
+                }
+                _ = flush_interval.tick() => {
+                    if !live.is_empty() {
+                        self.flush(&mut live).await;
+                    } else if self.has_wal_backlog() && self.is_writer() && self.replay_wal().await {
+                        self.save_checkpoint().await;
+                    }
+                    if !backlog.is_empty() && live_rx.is_empty() {
+                        self.flush(&mut backlog).await;
+                    }
+                }
+                update = startup_rx.recv(), if startup_open => {
+                    match update {
+                        Some(update) => {
+                            backlog.push(update);
+                            if backlog.len() >= self.target_batch_size(&backlog) {
+                                self.flush(&mut backlog).await;
+                            }
+                        }
+                        None => startup_open = false,
+                    }
+                }
+            }
+        }
+        
+        // Drain whatever is left once both lanes are closed
+        for lane in [&mut live, &mut backlog] {
+            if !lane.is_empty() {
+                self.flush(lane).await;
+            }
+        }
+        
+        debug!("Shard {} worker stopped", self.shard);
+    }
+    
+    fn target_batch_size(&self, lane: &Lane) -> usize {
+        if lane.mode == WriteMode::Copy {
+            return self.bulk_batch_size;
+        }
+        
+        self.sizer
+            .as_ref()
+            .map(AdaptiveBatchSizer::batch_size)
+            .unwrap_or_else(|| self.batch_size.load(Ordering::Relaxed))
+    }
+    
+    async fn flush(&mut self, lane: &mut Lane) {
+        // Demoted writers leave what they have queued to the new writer
+        if !self.is_writer() {
+            metrics::counter!("indexer_standby_skipped_total").increment(lane.len() as u64);
+            self.stats.record_discarded(lane.len());
+            self.watermark.record_done(self.shard, lane.startup, lane.len(), None);
+            self.discard(lane);
+            return;
+        }
+        
+        let start = Instant::now();
+        
+        // Spooled batches are older than anything in the lane, so they must
+        // land first; if they can't, the lane joins them in the WAL
+        let result = if self.replay_wal().await {
+            self.write_with_retry(&lane.updates, lane.mode).await
+        } else {
+            Err((anyhow::anyhow!("database unavailable, WAL replay pending"), 0))
+        };
+        
+        let max_slot = lane.updates.iter().map(|update| update.slot).max().unwrap_or(0);
+        let elapsed = start.elapsed();
+        let oldest_age = lane.started.take()
+            .map(|started| started.elapsed())
+            .unwrap_or(elapsed);
+        let flushed_slot = match result {
+            Ok(()) => {
+                self.flushed_slot = self.flushed_slot.max(max_slot);
+                self.stats.record_flush(lane.len(), max_slot, elapsed, oldest_age);
+                Some(max_slot)
+            }
+            Err((e, attempts)) => {
+                self.handle_failed_batch(&lane.updates, e, attempts).await;
+                None
+            }
+        };
+        
+        self.watermark.record_done(self.shard, lane.startup, lane.len(), flushed_slot);
+        self.discard(lane);
+        if flushed_slot.is_some() {
+            self.save_checkpoint().await;
+        }
+        
+        // Bulk flushes are deliberately large and would skew the sizer
+        if let Some(sizer) = self.sizer.as_mut().filter(|_| lane.mode == WriteMode::Upsert) {
+            sizer.record_flush(elapsed);
+        }
+    }
+    
+    /// Checkpoint the newest slot written, unless older updates are still
+    /// in the startup lane or the WAL, or were dead-lettered
+    async fn save_checkpoint(&self) {
+        if self.startup_pending.load(Ordering::Acquire) > 0 || self.has_wal_backlog() {
+            return;
+        }
+        let slot = match self.dead_lettered_slot {
+            Some(lost) => self.flushed_slot.min(lost.saturating_sub(1)),
+            None => self.flushed_slot,
+        };
+        if slot == 0 {
+            return;
+        }
+        
+        if let Err(e) = self.store.upsert_shard_checkpoint(self.shard, slot).await {
+            warn!("Shard {}: failed to checkpoint slot {}: {}", self.shard, slot, e);
+        }
+    }
+    
+    /// Empty a lane and return its memory reservation
+    fn discard(&self, lane: &mut Lane) {
+        if lane.startup {
+            self.startup_pending.fetch_sub(lane.len(), Ordering::AcqRel);
+        }
+        lane.updates.clear();
+        lane.started = None;
+        
+        if let Some(budget) = &self.memory {
+            budget.release(std::mem::take(&mut lane.bytes));
+        }
+    }
+    
+    fn is_writer(&self) -> bool {
+        self.writer
+            .as_ref()
+            .map(|writer| writer.is_leader())
+            .unwrap_or(true)
+    }
+    
+    /// Write a batch through the circuit breaker, if one is configured
+    async fn guarded_write(
+        store: &dyn VaultStore,
+        database: &Database,
+        cache: Option<&RedisCache>,
+        events: Option<&broadcast::Sender<VaultEvent>>,
+        breaker: Option<&CircuitBreaker>,
+        updates: &[AccountUpdate],
+        mode: WriteMode,
+    ) -> Result<()> {
+        let Some(breaker) = breaker else {
+            return Indexer::process_batch(store, database, cache, events, updates, mode).await;
+        };
+        if !breaker.allow() {
+            anyhow::bail!("circuit breaker open, database writes paused");
+        }
+        
+        let result = Indexer::process_batch(store, database, cache, events, updates, mode).await;
+        match &result {
+            Ok(()) => breaker.record_success(),
+            Err(_) => breaker.record_failure(),
+        }
+        result
+    }
+    
+    /// Write a batch, retrying with exponential backoff and jitter
+    ///
+    /// On failure returns the last error and the number of attempts made.
+    async fn write_with_retry(
+        &self,
+        updates: &[AccountUpdate],
+        mode: WriteMode,
+    ) -> Result<(), (anyhow::Error, u32)> {
+        let mut backoff = ExponentialBackoff {
+            initial_interval: Duration::from_millis(self.retry.initial_backoff_ms),
+            max_interval: Duration::from_millis(self.retry.max_backoff_ms),
+            max_elapsed_time: Some(Duration::from_millis(self.retry.max_elapsed_ms)),
+            ..ExponentialBackoff::default()
+        };
+        let mut attempts = 0;
+        
+        loop {
+            attempts += 1;
+            let e = match Self::guarded_write(
+                self.store.as_ref(),
+                &self.database,
+                self.cache.as_deref(),
+                self.events.as_ref(),
+                self.breaker.as_deref(),
+                updates,
+                mode,
+            ).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            
+            let delay = match backoff.next_backoff() {
+                Some(delay) if attempts < self.retry.max_attempts => delay,
+                _ => return Err((e, attempts)),
+            };
+            
+            warn!(
+                "Shard {}: batch of {} updates failed (attempt {}), retrying in {:?}: {}",
+                self.shard,
+                updates.len(),
+                attempts,
+                delay,
+                e
+            );
+            metrics::counter!("indexer_flush_retries_total").increment(1);
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    /// Spool a failed batch to the WAL, or dead-letter it if there is none
+    async fn handle_failed_batch(&mut self, updates: &[AccountUpdate], e: anyhow::Error, attempts: u32) {
+        error!(
+            "Shard {}: error processing batch after {} attempts: {}",
+            self.shard, attempts, e
+        );
+        
+        if let Some(wal) = self.wal.as_mut() {
+            match wal.append(updates) {
+                Ok(()) => {
+                    self.stats.record_spooled(updates.len());
+                    if let Some(oldest) = updates.iter().map(|update| update.slot).min() {
+                        self.watermark.record_spooled(self.shard, oldest);
+                    }
+                    warn!(
+                        "Shard {}: spooled {} updates to WAL ({} bytes pending)",
+                        self.shard,
+                        updates.len(),
+                        wal.size()
+                    );
+                    return;
+                }
+                Err(wal_err) => error!("Shard {}: failed to spool batch: {}", self.shard, wal_err),
             }
         }
         
-        Ok(state)
+        // Whether or not the letters land, these updates are gone from the
+        // pipeline
+        self.stats.record_dead_lettered(updates.len());
+        self.hold_dead_lettered(updates);
+        
+        let failed: Vec<&AccountUpdate> = updates.iter().collect();
+        if let Err(dlq_err) = self.database
+            .insert_dead_letters(&failed, DeadLetterStage::Write, &e.to_string(), attempts)
+            .await
+        {
+            error!(
+                "Shard {}: failed to dead-letter {} updates: {}",
+                self.shard,
+                failed.len(),
+                dlq_err
+            );
+        }
+    }
+    
+    /// Keep the checkpoint and the watermark below dead-lettered `updates`
+    fn hold_dead_lettered(&mut self, updates: &[AccountUpdate]) {
+        if let Some(oldest) = updates.iter().map(|update| update.slot).min() {
+            self.dead_lettered_slot = Some(self.dead_lettered_slot.map_or(oldest, |slot| slot.min(oldest)));
+            self.watermark.record_dead_lettered(self.shard, oldest);
+        }
+    }
+    
+    /// Dead-letter a spooled batch that keeps failing to replay, so the
+    /// batches behind it aren't held up; false if the letters didn't land,
+    /// in which case it stays in the WAL
+    async fn dead_letter_spooled(&mut self, updates: &[AccountUpdate], e: &anyhow::Error) -> bool {
+        let failed: Vec<&AccountUpdate> = updates.iter().collect();
+        if let Err(dlq_err) = self.database
+            .insert_dead_letters(&failed, DeadLetterStage::Write, &e.to_string(), WAL_REPLAY_ATTEMPTS)
+            .await
+        {
+            error!("Shard {}: failed to dead-letter spooled batch: {}", self.shard, dlq_err);
+            return false;
+        }
+        
+        error!(
+            "Shard {}: dead-lettered {} spooled updates after {} failed replays: {}",
+            self.shard,
+            updates.len(),
+            WAL_REPLAY_ATTEMPTS,
+            e
+        );
+        self.stats.record_spool_dead_lettered(updates.len());
+        self.hold_dead_lettered(updates);
+        true
+    }
+    
+    fn has_wal_backlog(&self) -> bool {
+        self.wal.as_ref().is_some_and(|wal| !wal.is_empty())
+    }
+    
+    /// Replay spooled batches in order; returns true once the WAL is empty
+    ///
+    /// A batch that fails `WAL_REPLAY_ATTEMPTS` times while the circuit
+    /// breaker lets writes through is dead-lettered and skipped.
+    async fn replay_wal(&mut self) -> bool {
+        let Some(wal) = self.wal.as_mut() else {
+            return true;
+        };
+        if wal.is_empty() {
+            return true;
+        }
+        if Instant::now() < self.next_wal_replay {
+            return false;
+        }
+        
+        let batches = match wal.read_batches() {
+            Ok(batches) => batches,
+            Err(e) => {
+                error!("Shard {}: failed to read WAL: {}", self.shard, e);
+                self.next_wal_replay = Instant::now() + WAL_REPLAY_BACKOFF;
+                return false;
+            }
+        };
+        
+        for (index, batch) in batches.iter().enumerate() {
+            // An open breaker refuses the write without trying the batch
+            let breaker_open = self.breaker.as_ref().is_some_and(|breaker| breaker.state() == CircuitState::Open);
+            if let Err(e) = Self::guarded_write(
+                self.store.as_ref(),
+                &self.database,
+                self.cache.as_deref(),
+                self.events.as_ref(),
+                self.breaker.as_deref(),
+                batch,
+                WriteMode::Upsert,
+            ).await {
+                if !breaker_open {
+                    self.wal_head_attempts += 1;
+                }
+                if self.wal_head_attempts >= WAL_REPLAY_ATTEMPTS && self.dead_letter_spooled(batch, &e).await {
+                    self.wal_head_attempts = 0;
+                    continue;
+                }
+                warn!("Shard {}: WAL replay paused, database still failing: {}", self.shard, e);
+                
+                if let Some(Err(e)) = self.wal.as_mut().map(|wal| wal.rewrite(&batches[index..])) {
+                    error!("Shard {}: failed to compact WAL: {}", self.shard, e);
+                }
+                self.next_wal_replay = Instant::now() + WAL_REPLAY_BACKOFF;
+                return false;
+            }
+            
+            self.wal_head_attempts = 0;
+            if let Some(slot) = batch.iter().map(|update| update.slot).max() {
+                self.flushed_slot = self.flushed_slot.max(slot);
+                self.stats.record_replayed(batch.len(), slot);
+            }
+        }
+        
+        if let Some(Err(e)) = self.wal.as_mut().map(WriteAheadLog::clear) {
+            error!("Shard {}: failed to clear WAL: {}", self.shard, e);
+        }
+        self.watermark.record_spool_drained(self.shard);
+        info!("Shard {}: replayed {} spooled batches from WAL", self.shard, batches.len());
+        
+        true
     }
 }
@@ -0,0 +1,46 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// Addresses of the vaults the indexer knows of
+///
+/// A closed account is handed back to the system program, so its closing
+/// write no longer names the vault program; this set tells a vault closing
+/// apart from the chain's other zero-lamport writes. It is seeded with the
+/// store's active vaults and grows with every vault routed or backfilled
+/// since; a closed vault leaves it until it is written again. Each address
+/// costs about 50 bytes.
+#[derive(Default)]
+pub struct KnownVaults {
+    addresses: RwLock<HashSet<Pubkey>>,
+}
+
+impl KnownVaults {
+    /// Set of `addresses`, skipping any that don't parse
+    pub fn new<S: AsRef<str>>(addresses: &[S]) -> Self {
+        Self {
+            addresses: RwLock::new(
+                addresses
+                    .iter()
+                    .filter_map(|address| Pubkey::from_str(address.as_ref()).ok())
+                    .collect(),
+            ),
+        }
+    }
+    
+    pub fn contains(&self, address: &Pubkey) -> bool {
+        self.addresses.read().unwrap().contains(address)
+    }
+    
+    pub fn insert(&self, address: Pubkey) {
+        // Nearly every vault write is to a vault already in the set
+        if !self.contains(&address) {
+            self.addresses.write().unwrap().insert(address);
+        }
+    }
+    
+    pub fn remove(&self, address: &Pubkey) {
+        self.addresses.write().unwrap().remove(address);
+    }
+}
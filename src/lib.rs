@@ -20,12 +20,59 @@
 //! // Configuration via config file
 //! ```
 
+pub mod account_data;
+pub mod admin;
+pub mod api;
+pub mod aggregates;
+pub mod auth;
+pub mod backfill;
+pub mod batching;
+pub mod change_feed;
+pub mod change_stream;
+pub mod circuit_breaker;
 pub mod config;
+pub mod consistency;
+pub mod coordination;
 pub mod database;
+pub mod dedup;
+pub mod degradation;
+pub mod encryption;
+pub mod events;
+pub mod gaps;
 pub mod geyser_plugin;
+pub mod graphql;
+pub mod grpc;
+pub mod health;
+pub mod hot_keys;
 pub mod indexer;
+pub mod loadgen;
+pub mod known_vaults;
+pub mod local_cache;
+pub mod memory;
+pub mod namespace;
+pub mod openapi;
+pub mod partitions;
+pub mod projection;
+pub mod rate_limit;
+pub mod readiness;
 pub mod redis_cache;
+pub mod reload;
+pub mod replicas;
+pub mod replication;
+pub mod retention;
+pub mod rpc;
+pub mod single_flight;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod sse;
+pub mod stats;
+pub mod store;
+pub mod tls;
 pub mod types;
 pub mod utils;
+pub mod wal;
+pub mod watermark;
+pub mod ws;
+pub mod yellowstone;
 
 pub use geyser_plugin::GeyserIndexerPlugin;
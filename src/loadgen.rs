@@ -0,0 +1,257 @@
+use crate::indexer::Indexer;
+use crate::stats::LatencyPercentiles;
+use crate::types::AccountUpdate;
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio::time::{interval, sleep, MissedTickBehavior};
+use tracing::{info, warn};
+
+/// How often the driver releases a slice of the configured rate
+const TICK: Duration = Duration::from_millis(10);
+
+/// Approximate Solana slot time, used to advance synthetic slots
+const SLOT_TIME: Duration = Duration::from_millis(400);
+
+/// Synthetic load parameters
+#[derive(Debug, Clone)]
+pub struct LoadGenConfig {
+    /// Number of distinct vault accounts
+    pub accounts: usize,
+    /// Target updates per second
+    pub rate_per_sec: u64,
+    /// Account data size in bytes (at least 40)
+    pub data_size: usize,
+    /// Zipf exponent for account selection; 0.0 is uniform, ~1.0 is very hot-keyed
+    pub hot_key_skew: f64,
+    pub duration: Duration,
+    pub seed: u64,
+}
+
+impl Default for LoadGenConfig {
+    fn default() -> Self {
+        Self {
+            accounts: 10_000,
+            rate_per_sec: 10_000,
+            data_size: 256,
+            hot_key_skew: 0.8,
+            duration: Duration::from_secs(30),
+            seed: 42,
+        }
+    }
+}
+
+/// Deterministic vault update stream
+///
+/// The same config and seed always produce the same sequence of pubkeys,
+/// owners, balances and payloads, so runs are comparable across changes.
+pub struct LoadGenerator {
+    config: LoadGenConfig,
+    program_id: Pubkey,
+    accounts: Vec<Pubkey>,
+    owners: Vec<Pubkey>,
+    /// Cumulative Zipf distribution over `accounts`
+    cdf: Vec<f64>,
+    rng: SplitMix64,
+    write_version: u64,
+}
+
+impl LoadGenerator {
+    pub fn new(config: LoadGenConfig, program_id: Pubkey) -> Self {
+        let count = config.accounts.max(1);
+        let mut rng = SplitMix64::new(config.seed);
+        
+        let accounts = (0..count).map(|_| rng.next_pubkey()).collect();
+        let owners = (0..count).map(|_| rng.next_pubkey()).collect();
+        
+        let mut cdf = Vec::with_capacity(count);
+        let mut total = 0.0;
+        for rank in 1..=count {
+            total += 1.0 / (rank as f64).powf(config.hot_key_skew.max(0.0));
+            cdf.push(total);
+        }
+        for weight in &mut cdf {
+            *weight /= total;
+        }
+        
+        Self {
+            config,
+            program_id,
+            accounts,
+            owners,
+            cdf,
+            rng,
+            write_version: 0,
+        }
+    }
+    
+    /// Produce the next synthetic update at `slot`
+    pub fn next_update(&mut self, slot: u64) -> AccountUpdate {
+        let sample = self.rng.next_f64();
+        let index = self.cdf.partition_point(|&weight| weight < sample).min(self.accounts.len() - 1);
+        
+        self.write_version += 1;
+        
+        let mut data = vec![0u8; self.config.data_size.max(40)];
+        data[0..32].copy_from_slice(self.owners[index].as_ref());
+        data[32..40].copy_from_slice(&self.rng.next_u64().to_le_bytes());
+        for byte in data[40..].iter_mut() {
+            *byte = self.rng.next_u64() as u8;
+        }
+        
+        AccountUpdate {
+            pubkey: self.accounts[index],
+            lamports: 1_000_000,
+            owner: self.program_id,
+            executable: false,
+            rent_epoch: 0,
+            data,
+            write_version: self.write_version,
+            slot,
+            is_startup: false,
+            txn_signature: None,
+        }
+    }
+}
+
+/// Results of a benchmark run
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub updates_sent: u64,
+    pub updates_flushed: u64,
+    pub elapsed: Duration,
+    pub flush_latency: LatencyPercentiles,
+    pub end_to_end_latency: LatencyPercentiles,
+}
+
+impl BenchReport {
+    pub fn throughput(&self) -> f64 {
+        self.updates_flushed as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "updates sent:      {}", self.updates_sent)?;
+        writeln!(f, "updates flushed:   {}", self.updates_flushed)?;
+        writeln!(f, "elapsed:           {:.2?}", self.elapsed)?;
+        writeln!(f, "throughput:        {:.0} updates/s", self.throughput())?;
+        writeln!(f, "flush latency:     {}", DisplayPercentiles(&self.flush_latency))?;
+        write!(f, "end-to-end latency: {}", DisplayPercentiles(&self.end_to_end_latency))
+    }
+}
+
+struct DisplayPercentiles<'a>(&'a LatencyPercentiles);
+
+impl fmt::Display for DisplayPercentiles<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p50 {:.2?}  p90 {:.2?}  p99 {:.2?}  max {:.2?}",
+            self.0.p50, self.0.p90, self.0.p99, self.0.max
+        )
+    }
+}
+
+/// Drive synthetic load through the real pipeline and report results
+///
+/// The indexer must have been built against real backends; only the Geyser
+/// feed is simulated.
+pub async fn run_benchmark(
+    indexer: &Indexer,
+    program_id: Pubkey,
+    config: LoadGenConfig,
+) -> Result<BenchReport> {
+    let stats = indexer.pipeline_stats();
+    let flushed_before = stats.updates_flushed();
+    let done_before = stats.updates_done();
+    let duration = config.duration;
+    let per_tick = (config.rate_per_sec as f64 * TICK.as_secs_f64()).max(1.0);
+    
+    info!(
+        "Benchmark: {} accounts, {} updates/s, {} byte payloads, skew {}, {:?}",
+        config.accounts, config.rate_per_sec, config.data_size, config.hot_key_skew, duration
+    );
+    
+    let mut generator = LoadGenerator::new(config, program_id);
+    let mut ticker = interval(TICK);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    
+    let start = Instant::now();
+    let mut sent = 0u64;
+    let mut budget = 0.0;
+    
+    while start.elapsed() < duration {
+        ticker.tick().await;
+        budget += per_tick;
+        
+        let slot = (start.elapsed().as_millis() / SLOT_TIME.as_millis()) as u64 + 1;
+        while budget >= 1.0 {
+            let update = generator.next_update(slot);
+            indexer.wait_for_memory(&update).await;
+            indexer.process_update(update)?;
+            sent += 1;
+            budget -= 1.0;
+        }
+    }
+    
+    // Wait for the tail of the stream to drain, giving up once the pipeline
+    // stops making progress (e.g. failing flushes)
+    let target = done_before + sent;
+    let mut last_done = stats.updates_done();
+    while last_done < target {
+        sleep(Duration::from_millis(500)).await;
+        let done = stats.updates_done();
+        if done == last_done {
+            break;
+        }
+        last_done = done;
+    }
+    
+    let elapsed = start.elapsed();
+    let updates_flushed = stats.updates_flushed() - flushed_before;
+    if updates_flushed == 0 {
+        warn!("No updates were flushed; check backend connectivity");
+    }
+    
+    Ok(BenchReport {
+        updates_sent: sent,
+        updates_flushed,
+        elapsed,
+        flush_latency: stats.flush_latency(),
+        end_to_end_latency: stats.end_to_end_latency(),
+    })
+}
+
+/// Small, fast, seedable PRNG (SplitMix64)
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    
+    /// Uniform sample in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    
+    fn next_pubkey(&mut self) -> Pubkey {
+        let mut bytes = [0u8; 32];
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes());
+        }
+        Pubkey::new_from_array(bytes)
+    }
+}
@@ -0,0 +1,106 @@
+use crate::redis_cache::RedisCache;
+use crate::types::{LocalCacheConfig, RedisConfig, VaultChange, VaultState};
+use anyhow::Result;
+use moka::future::Cache;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use tracing::{debug, warn};
+
+/// Wait before resubscribing after the change channel drops
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// In-process copies of vault states, checked before Redis
+///
+/// Entries live for `ttl_ms` at most and are dropped as soon as the
+/// vault's change is announced on the Redis change channel, which this
+/// cache subscribes to. Messages missed while the subscription is down
+/// can't be replayed, so every entry is dropped when it drops and again
+/// once it is back; entries cached in between rely on the TTL.
+pub struct LocalCache {
+    entries: Cache<String, VaultState>,
+}
+
+impl LocalCache {
+    /// Create the cache and start following `channel`; the subscription
+    /// ends once the cache is dropped
+    pub fn start(config: &LocalCacheConfig, redis: &RedisConfig, channel: String) -> Result<Arc<Self>> {
+        let cache = Arc::new(Self {
+            entries: Cache::builder()
+                .max_capacity(config.max_entries)
+                .time_to_live(Duration::from_millis(config.ttl_ms.max(1)))
+                .build(),
+        });
+        
+        let client = RedisCache::client(redis)?;
+        tokio::spawn(follow_changes(Arc::downgrade(&cache), client, channel));
+        Ok(cache)
+    }
+    
+    pub async fn get(&self, vault_address: &str) -> Option<VaultState> {
+        let state = self.entries.get(vault_address).await;
+        if state.is_some() {
+            metrics::counter!("indexer_local_cache_hits_total").increment(1);
+        } else {
+            metrics::counter!("indexer_local_cache_misses_total").increment(1);
+        }
+        state
+    }
+    
+    pub async fn insert(&self, state: &VaultState) {
+        self.entries.insert(state.vault_address.to_string(), state.clone()).await;
+    }
+    
+    pub async fn invalidate(&self, vault_address: &str) {
+        self.entries.invalidate(vault_address).await;
+    }
+    
+    fn clear(&self) {
+        self.entries.invalidate_all();
+    }
+}
+
+/// Drop entries as their vaults' changes are announced, resubscribing
+/// whenever the subscription drops
+async fn follow_changes(cache: Weak<LocalCache>, client: redis::Client, channel: String) {
+    loop {
+        if let Err(e) = subscribe(&cache, &client, &channel).await {
+            warn!("Local cache lost the change channel {}: {}", channel, e);
+        }
+        
+        match cache.upgrade() {
+            Some(cache) => cache.clear(),
+            None => return,
+        }
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+async fn subscribe(cache: &Weak<LocalCache>, client: &redis::Client, channel: &str) -> Result<()> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(channel).await?;
+    match cache.upgrade() {
+        Some(cache) => cache.clear(),
+        None => return Ok(()),
+    }
+    debug!("Local cache following {}", channel);
+    
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let Some(cache) = cache.upgrade() else {
+            return Ok(());
+        };
+        
+        let payload: String = message.get_payload()?;
+        match serde_json::from_str::<VaultChange>(&payload) {
+            Ok(change) => cache.invalidate(&change.vault_address).await,
+            Err(e) => {
+                // Can't tell which vault changed, so none can be trusted
+                warn!("Unreadable message on {}: {}", channel, e);
+                cache.clear();
+            }
+        }
+    }
+    
+    anyhow::bail!("subscription closed")
+}
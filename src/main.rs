@@ -1,8 +1,249 @@
 // Main entry point for testing/development
 // The plugin is loaded by Solana validator via the Geyser interface
+//
+// `vaulta-geyser-indexer replica <config.toml>` runs a secondary instance
+// that follows the primary's database via logical replication instead of
+// the Geyser feed.
+//
+// `vaulta-geyser-indexer bench <config.toml> [options]` pushes a
+// deterministic synthetic update stream through the real pipeline and
+// reports throughput and latency percentiles.
+//
+// `vaulta-geyser-indexer backfill <config.toml>` seeds the database from
+// RPC getProgramAccounts using the [backfill] settings.
+//
+// `vaulta-geyser-indexer migrate <config.toml>` applies pending schema
+// migrations, for deployments that set `database.run_migrations = false`,
+// and converts the history tables to hypertables when [timescale] is on.
+//
+// `vaulta-geyser-indexer dlq list <config.toml> [limit]` and
+// `vaulta-geyser-indexer dlq replay <config.toml> <id>...` inspect and
+// replay dead-lettered updates.
 
-fn main() {
-    println!("Vaulta Geyser Indexer Plugin");
-    println!("This plugin is loaded by Solana validator via Geyser interface");
-    println!("See README.md for configuration and usage instructions");
+use std::sync::Arc;
+use std::time::Duration;
+use vaulta_geyser_indexer::api::ApiServer;
+use vaulta_geyser_indexer::config::Config;
+use vaulta_geyser_indexer::database::{Database, PoolSettings};
+use vaulta_geyser_indexer::grpc::GrpcServer;
+use vaulta_geyser_indexer::indexer::Indexer;
+use vaulta_geyser_indexer::loadgen::{run_benchmark, LoadGenConfig};
+use vaulta_geyser_indexer::namespace::Namespace;
+use vaulta_geyser_indexer::reload::ConfigReloader;
+use vaulta_geyser_indexer::types::{IndexerMode, SchemaCheck};
+use vaulta_geyser_indexer::utils::parse_pubkey;
+
+const DLQ_USAGE: &str = "usage: vaulta-geyser-indexer dlq list <config.toml> [limit]\n       \
+vaulta-geyser-indexer dlq replay <config.toml> <id>...";
+
+const BENCH_USAGE: &str = "usage: vaulta-geyser-indexer bench <config.toml> \
+[--accounts N] [--rate N] [--data-size BYTES] [--skew S] [--duration SECS] [--seed N]";
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    
+    match args.get(1).map(String::as_str) {
+        Some("replica") => {
+            let config_path = args
+                .get(2)
+                .ok_or_else(|| anyhow::anyhow!("usage: vaulta-geyser-indexer replica <config.toml>"))?;
+            run_replica(config_path)
+        }
+        Some("dlq") => {
+            let (Some(action), Some(config_path)) = (args.get(2), args.get(3)) else {
+                anyhow::bail!(DLQ_USAGE);
+            };
+            run_dlq(action, config_path, &args[4..])
+        }
+        Some("backfill") => {
+            let config_path = args
+                .get(2)
+                .ok_or_else(|| anyhow::anyhow!("usage: vaulta-geyser-indexer backfill <config.toml>"))?;
+            run_backfill(config_path)
+        }
+        Some("migrate") => {
+            let config_path = args
+                .get(2)
+                .ok_or_else(|| anyhow::anyhow!("usage: vaulta-geyser-indexer migrate <config.toml>"))?;
+            run_migrate(config_path)
+        }
+        Some("bench") => {
+            let config_path = args.get(2).ok_or_else(|| anyhow::anyhow!(BENCH_USAGE))?;
+            let load = parse_bench_args(&args[3..])?;
+            run_bench(config_path, load)
+        }
+        _ => {
+            println!("Vaulta Geyser Indexer Plugin");
+            println!("This plugin is loaded by Solana validator via Geyser interface");
+            println!("See README.md for configuration and usage instructions");
+            Ok(())
+        }
+    }
+}
+
+/// Run a replica instance until interrupted
+fn run_replica(config_path: &str) -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+    
+    let file_config = Config::from_file(config_path)?.inner().clone();
+    let mut config = file_config.clone();
+    config.mode = IndexerMode::Replica;
+    
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let indexer = Arc::new(Indexer::from_config(&config).await?);
+        // Logging follows RUST_LOG here, so log_level isn't reloaded
+        indexer.set_config_reloader(Arc::new(ConfigReloader::new(config_path, &file_config, None)));
+        if config.api.enabled {
+            tokio::spawn(ApiServer::new(&config.api, indexer.clone()).run());
+        }
+        if config.grpc.enabled {
+            tokio::spawn(GrpcServer::new(&config.grpc, indexer.clone()).run());
+        }
+        println!("Replica running; press Ctrl+C to stop");
+        tokio::signal::ctrl_c().await?;
+        Ok(())
+    })
+}
+
+/// Run a one-off backfill from RPC and exit
+fn run_backfill(config_path: &str) -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+    
+    let mut config = Config::from_file(config_path)?.inner().clone();
+    // The backfill below runs in the foreground instead
+    config.backfill.on_startup = false;
+    
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let indexer = Indexer::from_config(&config).await?;
+        match indexer.resume_slot() {
+            Some(slot) => println!("Live indexing is checkpointed through slot {}", slot),
+            None => println!("No checkpoints recorded; database is fresh"),
+        }
+        
+        let report = indexer.backfill(&config.backfill).await?;
+        println!(
+            "Backfill job {}: {} accounts at slot {} in {:.2?}",
+            report.job_id, report.accounts, report.slot, report.elapsed
+        );
+        Ok(())
+    })
+}
+
+/// Apply pending schema migrations and exit
+fn run_migrate(config_path: &str) -> anyhow::Result<()> {
+    let config = Config::from_file(config_path)?.inner().clone();
+    
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let database = Database::connect(
+            &config.database.connection_string(),
+            &PoolSettings {
+                max_connections: 1,
+                ..PoolSettings::from_config(&config.database)
+            },
+            Namespace::from_config(&config.database)?,
+            true,
+            SchemaCheck::Strict,
+        ).await?;
+        
+        let version = database.schema_version().await?;
+        println!("Schema is at version {}", version.database.unwrap_or(0));
+        
+        if config.timescale.enabled {
+            database.setup_timescale(config.timescale.compress_after_epochs).await?;
+            println!("TimescaleDB hypertables are in place");
+        }
+        Ok(())
+    })
+}
+
+/// Parse `--flag value` pairs for the bench subcommand
+fn parse_bench_args(args: &[String]) -> anyhow::Result<LoadGenConfig> {
+    let mut load = LoadGenConfig::default();
+    let mut iter = args.iter();
+    
+    while let Some(flag) = iter.next() {
+        let value = iter
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("missing value for {}\n{}", flag, BENCH_USAGE))?;
+        
+        match flag.as_str() {
+            "--accounts" => load.accounts = value.parse()?,
+            "--rate" => load.rate_per_sec = value.parse()?,
+            "--data-size" => load.data_size = value.parse()?,
+            "--skew" => load.hot_key_skew = value.parse()?,
+            "--duration" => load.duration = Duration::from_secs(value.parse()?),
+            "--seed" => load.seed = value.parse()?,
+            _ => anyhow::bail!("unknown option {}\n{}", flag, BENCH_USAGE),
+        }
+    }
+    
+    Ok(load)
+}
+
+/// Run the load-generation benchmark against the configured backends
+fn run_bench(config_path: &str, load: LoadGenConfig) -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+    
+    let config = Config::from_file(config_path)?.inner().clone();
+    let program_id = parse_pubkey(&config.vault_program_id)?;
+    
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let indexer = Indexer::from_config(&config).await?;
+        let report = run_benchmark(&indexer, program_id, load).await?;
+        println!("{}", report);
+        Ok(())
+    })
+}
+
+/// Inspect or replay dead-lettered updates
+fn run_dlq(action: &str, config_path: &str, rest: &[String]) -> anyhow::Result<()> {
+    let config = Config::from_file(config_path)?.inner().clone();
+    
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let indexer = Indexer::from_config(&config).await?;
+        
+        match action {
+            "list" => {
+                let limit = rest.first().map(|l| l.parse()).transpose()?.unwrap_or(100);
+                for letter in indexer.list_dead_letters(limit).await? {
+                    println!(
+                        "{}\t{}\t{}\tslot={}\twv={}\tattempts={}\t{}",
+                        letter.id,
+                        letter.stage.as_str(),
+                        letter.update.pubkey,
+                        letter.update.slot,
+                        letter.update.write_version,
+                        letter.attempts,
+                        letter.error,
+                    );
+                }
+            }
+            "replay" => {
+                let ids = rest
+                    .iter()
+                    .map(|id| id.parse())
+                    .collect::<Result<Vec<i64>, _>>()?;
+                if ids.is_empty() {
+                    anyhow::bail!(DLQ_USAGE);
+                }
+                
+                let replayed = indexer.replay_dead_letters(&ids).await?;
+                println!("Replayed {} of {} updates", replayed, ids.len());
+            }
+            _ => anyhow::bail!(DLQ_USAGE),
+        }
+        
+        Ok(())
+    })
 }
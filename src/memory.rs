@@ -0,0 +1,108 @@
+use crate::types::{AccountUpdate, MemoryBudgetConfig};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+
+/// How often a blocked producer re-checks the budget
+const BACKPRESSURE_POLL: Duration = Duration::from_millis(5);
+
+/// Byte budget for updates held between Geyser and Postgres
+///
+/// Bytes are reserved when an update is enqueued and released once its
+/// shard has flushed it, so the counter covers channels and open batches.
+pub struct MemoryBudget {
+    max_bytes: u64,
+    max_block: Duration,
+    used: AtomicU64,
+}
+
+impl MemoryBudget {
+    pub fn new(config: &MemoryBudgetConfig) -> Self {
+        metrics::gauge!("indexer_pipeline_bytes").set(0.0);
+        
+        Self {
+            max_bytes: config.max_bytes,
+            max_block: Duration::from_millis(config.max_block_ms),
+            used: AtomicU64::new(0),
+        }
+    }
+    
+    /// Approximate heap and inline footprint of an update
+    pub fn cost(update: &AccountUpdate) -> u64 {
+        (std::mem::size_of::<AccountUpdate>() + update.data.len()) as u64
+    }
+    
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+    
+    /// Reserve `bytes`, blocking the caller while the budget is exhausted
+    ///
+    /// Blocking pushes back on the validator's Geyser thread. Returns false
+    /// if space didn't free up within `max_block_ms`. On a Tokio runtime
+    /// thread it never blocks, which would stall the tasks flushing the
+    /// pipeline; async producers wait with `wait_for_room` first.
+    pub fn reserve(&self, bytes: u64) -> bool {
+        let deadline = Instant::now() + self.max_block;
+        let may_block = Handle::try_current().is_err();
+        
+        loop {
+            if self.try_reserve(bytes) {
+                return true;
+            }
+            if !may_block || Instant::now() >= deadline {
+                metrics::counter!("indexer_memory_budget_rejections_total").increment(1);
+                return false;
+            }
+            
+            metrics::counter!("indexer_memory_backpressure_waits_total").increment(1);
+            std::thread::sleep(BACKPRESSURE_POLL);
+        }
+    }
+    
+    /// Wait without blocking the thread until `bytes` fit, at most
+    /// `max_block_ms`; returns whether they do
+    ///
+    /// Nothing is reserved, so a producer racing others may still be
+    /// refused by `reserve`.
+    pub async fn wait_for_room(&self, bytes: u64) -> bool {
+        let deadline = tokio::time::Instant::now() + self.max_block;
+        
+        while !self.has_room(bytes) {
+            if tokio::time::Instant::now() >= deadline {
+                return false;
+            }
+            metrics::counter!("indexer_memory_backpressure_waits_total").increment(1);
+            tokio::time::sleep(BACKPRESSURE_POLL).await;
+        }
+        true
+    }
+    
+    fn try_reserve(&self, bytes: u64) -> bool {
+        let reserved = self.used.fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+            Self::fits(used, bytes, self.max_bytes).then_some(used + bytes)
+        });
+        
+        match reserved {
+            Ok(used) => {
+                metrics::gauge!("indexer_pipeline_bytes").set((used + bytes) as f64);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+    
+    fn has_room(&self, bytes: u64) -> bool {
+        Self::fits(self.used(), bytes, self.max_bytes)
+    }
+    
+    fn fits(used: u64, bytes: u64, max_bytes: u64) -> bool {
+        // A single oversized update is admitted into an empty pipeline
+        used == 0 || used + bytes <= max_bytes
+    }
+    
+    pub fn release(&self, bytes: u64) {
+        let used = self.used.fetch_sub(bytes, Ordering::AcqRel).saturating_sub(bytes);
+        metrics::gauge!("indexer_pipeline_bytes").set(used as f64);
+    }
+}
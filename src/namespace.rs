@@ -0,0 +1,134 @@
+use crate::types::DatabaseConfig;
+use anyhow::Result;
+use sqlx::postgres::PgConnectOptions;
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// Identifiers owned by the indexer: its tables, views, indexes and
+/// functions. Any SQL identifier starting with one of these gets the table
+/// prefix, which also covers derived names such as `account_updates_p<n>`,
+/// `account_updates_id_seq` and `vault_states_staging`.
+const OWNED_IDENTIFIERS: [&str; 23] = [
+    "account_updates",
+    "api_keys",
+    "backfill_jobs",
+    "block_metadata",
+    "dead_letter",
+    "ensure_account_updates_partition",
+    "idx_",
+    "indexed_slot_ranges",
+    "mv_",
+    "owner_changes",
+    "refresh_aggregate_view",
+    "saved_filters",
+    "shard_checkpoints",
+    "slot_gaps",
+    "timescale_",
+    "vault_assets",
+    "vault_notes",
+    "vault_permissions",
+    "vault_repairs",
+    "vault_state_history",
+    "vault_states",
+    "vault_tags",
+    "_sqlx_migrations",
+];
+
+/// Where an indexer instance keeps its tables
+///
+/// The schema is applied through the connection's `search_path`, so queries
+/// stay unqualified; the prefix is applied to the SQL text by `render`.
+/// Several instances can share a database by using different schemas,
+/// different prefixes, or both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespace {
+    schema: String,
+    prefix: String,
+}
+
+impl Default for Namespace {
+    fn default() -> Self {
+        Self {
+            schema: "public".to_string(),
+            prefix: String::new(),
+        }
+    }
+}
+
+impl Namespace {
+    /// Both names must be lowercase identifiers, so they never need quoting
+    pub fn new(schema: &str, prefix: &str) -> Result<Self> {
+        if schema.is_empty() || !Self::is_identifier(schema) {
+            anyhow::bail!("invalid database schema name '{}'", schema);
+        }
+        if !Self::is_identifier(prefix) {
+            anyhow::bail!("invalid table prefix '{}'", prefix);
+        }
+        
+        Ok(Self {
+            schema: schema.to_string(),
+            prefix: prefix.to_string(),
+        })
+    }
+    
+    pub fn from_config(config: &DatabaseConfig) -> Result<Self> {
+        Self::new(&config.schema, &config.table_prefix)
+    }
+    
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+    
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+    
+    /// Name of an indexer table within the schema, e.g. `vault_states`
+    pub fn table(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+    
+    /// Schema-qualified name of an indexer table
+    pub fn qualified_table(&self, name: &str) -> String {
+        format!("{}.{}", self.schema, self.table(name))
+    }
+    
+    /// Connection options for `connection_string` with the schema first
+    /// on the search path
+    pub fn connect_options(&self, connection_string: &str) -> Result<PgConnectOptions> {
+        Ok(PgConnectOptions::from_str(connection_string)?
+            .options([("search_path", self.schema.as_str())]))
+    }
+    
+    /// Apply the table prefix to every indexer-owned identifier in `sql`
+    pub fn render<'a>(&self, sql: &'a str) -> Cow<'a, str> {
+        if self.prefix.is_empty() {
+            return Cow::Borrowed(sql);
+        }
+        
+        let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let mut rendered = String::with_capacity(sql.len() + 64);
+        let mut rest = sql;
+        
+        while let Some(start) = rest.find(is_word) {
+            rendered.push_str(&rest[..start]);
+            rest = &rest[start..];
+            
+            let end = rest.find(|c: char| !is_word(c)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            if OWNED_IDENTIFIERS.iter().any(|owned| word.starts_with(owned)) {
+                rendered.push_str(&self.prefix);
+            }
+            rendered.push_str(word);
+            rest = &rest[end..];
+        }
+        rendered.push_str(rest);
+        
+        Cow::Owned(rendered)
+    }
+    
+    fn is_identifier(name: &str) -> bool {
+        !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    }
+}
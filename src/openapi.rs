@@ -0,0 +1,147 @@
+use crate::api;
+use std::collections::HashMap;
+use utoipa::{OpenApi, ToSchema};
+
+/// OpenAPI 3 description of the REST routes, served at `/openapi.json`
+///
+/// Generated from the handler annotations in `api.rs`. Vault bodies are
+/// rendered by `FieldSelection` rather than serialized from a type, so
+/// their shape is described by the schemas below.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Vaulta Geyser Indexer API",
+        description = "Read-only queries over indexed vault accounts"
+    ),
+    paths(
+        api::get_vault,
+        api::get_vaults_batch,
+        api::get_owner_vaults,
+        api::get_changes,
+        api::get_stats,
+        api::stream_events
+    ),
+    components(schemas(
+        Vault,
+        AssetBalance,
+        Permission,
+        VaultPage,
+        ChangeLogPage,
+        ChangeEvent,
+        api::BatchRequest,
+        BatchResponse,
+        Stats,
+        ErrorBody
+    )),
+    tags(
+        (name = "vaults", description = "Vault state lookups"),
+        (name = "stats", description = "Indexing progress")
+    )
+)]
+pub struct ApiDoc;
+
+/// A vault state; with `fields`, only the selected properties are present
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(crate) struct Vault {
+    vault_address: String,
+    owner: String,
+    /// Lamports
+    balance: u64,
+    /// Token balances keyed by mint
+    assets: HashMap<String, AssetBalance>,
+    permissions: Vec<Permission>,
+    /// RFC 3339 timestamp
+    last_updated: String,
+    slot: u64,
+    write_version: u64,
+    /// `active` or `closed`
+    status: String,
+    /// Slot the account was closed at; null while active
+    closed_at_slot: Option<u64>,
+}
+
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(crate) struct AssetBalance {
+    mint: String,
+    amount: u64,
+    decimals: u8,
+}
+
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(crate) struct Permission {
+    pubkey: String,
+    /// `Owner`, `Admin`, `Operator` or `Viewer`
+    permission_type: String,
+    /// RFC 3339 timestamp
+    granted_at: String,
+}
+
+/// One page of a listing
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(crate) struct VaultPage {
+    vaults: Vec<Vault>,
+    /// Opaque cursor for the next page, passed back as `cursor`; null on
+    /// the last page
+    next_cursor: Option<String>,
+}
+
+/// Events of the change log after a cursor
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(crate) struct ChangeLogPage {
+    events: Vec<ChangeEvent>,
+    /// Opaque cursor the next poll continues from, passed back as `cursor`;
+    /// present even when no events were found
+    next_cursor: String,
+}
+
+/// A change log event: `update` carries `vault`, `owner_changed` the other
+/// properties
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(crate) struct ChangeEvent {
+    /// `update` or `owner_changed`
+    r#type: String,
+    vault: Option<Vault>,
+    vault_address: Option<String>,
+    previous_owner: Option<String>,
+    new_owner: Option<String>,
+    slot: Option<u64>,
+    write_version: Option<u64>,
+    /// RFC 3339 timestamp
+    changed_at: Option<String>,
+}
+
+/// Answer of `POST /vaults/batch`
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(crate) struct BatchResponse {
+    /// Vaults found, in address order
+    found: Vec<Vault>,
+    /// Requested addresses the indexer has no vault for
+    missing: Vec<String>,
+}
+
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(crate) struct Stats {
+    total_accounts_indexed: u64,
+    accounts_per_second: f64,
+    average_latency_ms: f64,
+    cache_hit_rate: f64,
+    last_indexed_slot: u64,
+    uptime_seconds: u64,
+    /// Whether startup ingestion is done and the database is healthy
+    ready: bool,
+}
+
+/// Body of every error response
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub(crate) struct ErrorBody {
+    error: String,
+}
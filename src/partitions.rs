@@ -0,0 +1,61 @@
+use crate::database::{Database, ACCOUNT_UPDATES_PARTITION_SLOTS};
+use crate::stats::PipelineStats;
+use crate::types::PartitioningConfig;
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{debug, error};
+
+/// Keeps `account_updates` partitions created ahead of the indexed slot
+///
+/// `account_updates` has no default partition, so the partition for a slot
+/// must exist before rows for it arrive. On every check the partition
+/// holding the latest slot and the next `partitions_ahead` are created if
+/// missing.
+pub struct PartitionMaintainer {
+    database: Arc<Database>,
+    stats: Arc<PipelineStats>,
+    partitions_ahead: u64,
+    check_interval: Duration,
+}
+
+impl PartitionMaintainer {
+    pub fn new(config: &PartitioningConfig, database: Arc<Database>, stats: Arc<PipelineStats>) -> Self {
+        Self {
+            database,
+            stats,
+            partitions_ahead: config.partitions_ahead as u64,
+            check_interval: Duration::from_secs(config.check_interval_secs.max(1)),
+        }
+    }
+    
+    pub async fn run(self) {
+        let mut ticker = interval(self.check_interval);
+        
+        loop {
+            ticker.tick().await;
+            
+            if let Err(e) = self.ensure_partitions().await {
+                error!("Failed to create account_updates partitions: {}", e);
+            }
+        }
+    }
+    
+    async fn ensure_partitions(&self) -> Result<()> {
+        // Before the first flush, fall back to what earlier runs indexed
+        let slot = match self.stats.last_indexed_slot() {
+            0 => self.database.get_latest_slot().await?,
+            slot => slot,
+        };
+        
+        for ahead in 0..=self.partitions_ahead {
+            let name = self.database
+                .ensure_account_updates_partition(slot + ahead * ACCOUNT_UPDATES_PARTITION_SLOTS)
+                .await?;
+            debug!("account_updates partition {} is in place", name);
+        }
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use crate::types::{VaultEvent, VaultState};
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::str::FromStr;
+use time::format_description::well_known::Rfc3339;
+
+/// A top-level field of a vault state response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VaultField {
+    VaultAddress,
+    Owner,
+    Balance,
+    Assets,
+    Permissions,
+    LastUpdated,
+    Slot,
+    WriteVersion,
+    Status,
+    ClosedAtSlot,
+}
+
+impl VaultField {
+    pub const ALL: [VaultField; 10] = [
+        VaultField::VaultAddress,
+        VaultField::Owner,
+        VaultField::Balance,
+        VaultField::Assets,
+        VaultField::Permissions,
+        VaultField::LastUpdated,
+        VaultField::Slot,
+        VaultField::WriteVersion,
+        VaultField::Status,
+        VaultField::ClosedAtSlot,
+    ];
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            VaultField::VaultAddress => "vault_address",
+            VaultField::Owner => "owner",
+            VaultField::Balance => "balance",
+            VaultField::Assets => "assets",
+            VaultField::Permissions => "permissions",
+            VaultField::LastUpdated => "last_updated",
+            VaultField::Slot => "slot",
+            VaultField::WriteVersion => "write_version",
+            VaultField::Status => "status",
+            VaultField::ClosedAtSlot => "closed_at_slot",
+        }
+    }
+    
+    /// This field of `state` as rendered in responses
+    pub fn value(&self, state: &VaultState) -> Value {
+        match self {
+            VaultField::VaultAddress => json!(state.vault_address.to_string()),
+            VaultField::Owner => json!(state.owner.to_string()),
+            VaultField::Balance => json!(state.balance),
+            VaultField::Assets => Value::Object(
+                state
+                    .assets
+                    .iter()
+                    .map(|(key, asset)| {
+                        (
+                            key.clone(),
+                            json!({
+                                "mint": asset.mint.to_string(),
+                                "amount": asset.amount,
+                                "decimals": asset.decimals,
+                            }),
+                        )
+                    })
+                    .collect(),
+            ),
+            VaultField::Permissions => Value::Array(
+                state
+                    .permissions
+                    .iter()
+                    .map(|permission| {
+                        json!({
+                            "pubkey": permission.pubkey.to_string(),
+                            "permission_type": format!("{:?}", permission.permission_type),
+                            "granted_at": format_timestamp(permission.granted_at),
+                        })
+                    })
+                    .collect(),
+            ),
+            VaultField::LastUpdated => json!(format_timestamp(state.last_updated)),
+            VaultField::Slot => json!(state.slot),
+            VaultField::WriteVersion => json!(state.write_version),
+            VaultField::Status => json!(state.status.as_str()),
+            VaultField::ClosedAtSlot => json!(state.closed_at_slot),
+        }
+    }
+}
+
+impl FromStr for VaultField {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        VaultField::ALL
+            .iter()
+            .copied()
+            .find(|field| field.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown vault field '{}'", s))
+    }
+}
+
+impl fmt::Display for VaultField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Set of fields a client asked for (`fields=balance,slot`)
+///
+/// Only selected fields are rendered, so clients that need a balance never
+/// pay for serializing large asset maps and permission lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSelection {
+    fields: Vec<VaultField>,
+}
+
+impl FieldSelection {
+    /// Every field, used when the client doesn't pass a selection
+    pub fn all() -> Self {
+        Self {
+            fields: VaultField::ALL.to_vec(),
+        }
+    }
+    
+    /// Parse a comma-separated field list; `None` or empty selects everything
+    pub fn parse(fields: Option<&str>) -> Result<Self> {
+        let Some(fields) = fields.map(str::trim).filter(|f| !f.is_empty()) else {
+            return Ok(Self::all());
+        };
+        
+        let mut selected = Vec::new();
+        for name in fields.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let field = name.parse()?;
+            if !selected.contains(&field) {
+                selected.push(field);
+            }
+        }
+        
+        Ok(Self { fields: selected })
+    }
+    
+    pub fn contains(&self, field: VaultField) -> bool {
+        self.fields.contains(&field)
+    }
+    
+    pub fn fields(&self) -> &[VaultField] {
+        &self.fields
+    }
+    
+    /// Render only the selected fields of a vault state as JSON
+    pub fn project(&self, state: &VaultState) -> Value {
+        let mut object = Map::with_capacity(self.fields.len());
+        
+        for field in &self.fields {
+            object.insert(field.name().to_string(), field.value(state));
+        }
+        
+        Value::Object(object)
+    }
+}
+
+impl Default for FieldSelection {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// A vault event as sent to stream subscribers: `{"type": "update",
+/// "vault": {...}}` with every field, or `{"type": "owner_changed", ...}`
+pub fn render_event(event: &VaultEvent) -> Value {
+    match event {
+        VaultEvent::Updated(state) => json!({ "type": "update", "vault": FieldSelection::all().project(state) }),
+        VaultEvent::OwnerChanged(change) => json!({
+            "type": "owner_changed",
+            "vault_address": change.vault_address,
+            "previous_owner": change.previous_owner,
+            "new_owner": change.new_owner,
+            "slot": change.slot,
+            "write_version": change.write_version,
+            "changed_at": format_timestamp(change.changed_at),
+        }),
+    }
+}
+
+pub(crate) fn format_timestamp(timestamp: time::OffsetDateTime) -> String {
+    timestamp
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| timestamp.unix_timestamp().to_string())
+}
@@ -0,0 +1,203 @@
+use crate::auth::{self, ApiKeys};
+use crate::types::{ApiScope, RateLimitConfig, RateLimitQuota};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultDirectRateLimiter, DefaultKeyedRateLimiter, Quota, RateLimiter};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::{interval, MissedTickBehavior};
+
+/// How often buckets of clients that went quiet are dropped
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Who a request is charged to
+#[derive(Debug, Clone, Copy)]
+pub enum Client<'a> {
+    /// The name of the API key it presented
+    Key(&'a str),
+    Ip(IpAddr),
+}
+
+/// Token buckets of the query servers' clients
+///
+/// Shared by the HTTP and gRPC servers, so a client's requests draw from
+/// one bucket whichever protocol they use. Keys named in `keys` get their
+/// own quota; other keys and IPs get a bucket each on first use, dropped
+/// again once full. Requests refused for their key are charged to their
+/// IP, which is refused before its key is checked once out of tokens.
+pub struct RateLimits {
+    per_ip: DefaultKeyedRateLimiter<IpAddr>,
+    per_key: DefaultKeyedRateLimiter<String>,
+    named_keys: HashMap<String, DefaultDirectRateLimiter>,
+    /// IPs out of tokens for presenting bad keys, until they refill
+    penalized: Mutex<HashMap<IpAddr, Instant>>,
+    client_ip_header: Option<String>,
+    trusted_proxies: usize,
+    clock: DefaultClock,
+}
+
+impl RateLimits {
+    /// Build the buckets and prune idle ones in the background
+    pub fn start(config: &RateLimitConfig) -> Arc<Self> {
+        let limits = Arc::new(Self {
+            per_ip: RateLimiter::keyed(quota(&config.per_ip)),
+            per_key: RateLimiter::keyed(quota(&config.per_key)),
+            named_keys: config
+                .keys
+                .iter()
+                .map(|(name, limit)| (name.clone(), RateLimiter::direct(quota(limit))))
+                .collect(),
+            penalized: Mutex::new(HashMap::new()),
+            client_ip_header: config.client_ip_header.as_ref().map(|header| header.to_ascii_lowercase()),
+            trusted_proxies: config.trusted_proxies.max(1),
+            clock: DefaultClock::default(),
+        });
+        tokio::spawn(limits.clone().prune());
+        
+        limits
+    }
+    
+    /// Take a token for `client`, or say how long until one is available
+    pub fn check(&self, client: Client<'_>) -> Result<(), Duration> {
+        let (result, kind) = match client {
+            Client::Key(name) => match self.named_keys.get(name) {
+                Some(limiter) => (limiter.check(), "key"),
+                None => (self.per_key.check_key(&name.to_string()), "key"),
+            },
+            Client::Ip(ip) => (self.per_ip.check_key(&ip), "ip"),
+        };
+        
+        result.map_err(|not_until| {
+            metrics::counter!("indexer_rate_limited_total", "client" => kind).increment(1);
+            not_until.wait_time_from(self.clock.now())
+        })
+    }
+    
+    /// Refuse `ip` before its key is checked while it is out of tokens for
+    /// presenting bad keys
+    pub fn check_penalty(&self, ip: IpAddr) -> Result<(), Duration> {
+        let mut penalized = self.penalized.lock().unwrap();
+        let Some(&until) = penalized.get(&ip) else {
+            return Ok(());
+        };
+        
+        let now = Instant::now();
+        if until > now {
+            metrics::counter!("indexer_rate_limited_total", "client" => "ip").increment(1);
+            return Err(until - now);
+        }
+        penalized.remove(&ip);
+        Ok(())
+    }
+    
+    /// Charge a request refused for its key to the bucket of `ip`; once
+    /// that is empty, `ip` is refused ahead of authentication until it
+    /// refills, so keys can't be guessed at any rate
+    pub fn charge_rejected(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.check(Client::Ip(ip)).inspect_err(|wait| {
+            self.penalized.lock().unwrap().insert(ip, Instant::now() + *wait);
+        })
+    }
+    
+    /// The client IP of a request: the address the outermost trusted proxy
+    /// appended to `client_ip_header` when set, else the peer; `header`
+    /// looks a header up by name
+    ///
+    /// Entries left of those the proxies appended come from the client and
+    /// could name any address, so they are never used. A request without
+    /// enough entries didn't pass every proxy and is charged to its peer.
+    pub fn client_ip<'a>(
+        &self,
+        header: impl Fn(&str) -> Option<&'a str>,
+        peer: Option<SocketAddr>,
+    ) -> Option<IpAddr> {
+        self.client_ip_header
+            .as_ref()
+            .and_then(|name| header(name))
+            .and_then(|value| value.rsplit(',').nth(self.trusted_proxies - 1))
+            .and_then(|ip| ip.trim().parse().ok())
+            .or_else(|| peer.map(|peer| peer.ip()))
+    }
+    
+    async fn prune(self: Arc<Self>) {
+        let mut ticker = interval(PRUNE_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        
+        loop {
+            ticker.tick().await;
+            self.per_ip.retain_recent();
+            self.per_ip.shrink_to_fit();
+            self.per_key.retain_recent();
+            self.per_key.shrink_to_fit();
+            
+            let now = Instant::now();
+            self.penalized.lock().unwrap().retain(|_, until| *until > now);
+        }
+    }
+}
+
+/// Whole seconds to put in `Retry-After`, rounded up
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    (wait.as_secs() + u64::from(wait.subsec_nanos() > 0)).max(1)
+}
+
+/// Refuse a gRPC call without a key holding `scope`, or over its client's
+/// limit; `None` keys or limits skip that check
+///
+/// Calls refused for their key are charged to their IP, so keys can't be
+/// guessed faster than `per_ip` allows.
+pub fn admit_grpc<T>(
+    keys: Option<&ApiKeys>,
+    limits: Option<&RateLimits>,
+    request: &tonic::Request<T>,
+    scope: ApiScope,
+) -> Result<(), tonic::Status> {
+    let metadata = request.metadata();
+    let client_ip = limits.and_then(|limits| {
+        limits.client_ip(
+            |name| metadata.get(name).and_then(|value| value.to_str().ok()),
+            request.remote_addr(),
+        )
+    });
+    
+    if let (Some(limits), Some(ip)) = (limits, client_ip) {
+        limits.check_penalty(ip).map_err(exhausted)?;
+    }
+    let key = match auth::authorize_grpc(keys, metadata, scope) {
+        Ok(key) => key,
+        Err(status) => {
+            if let (Some(limits), Some(ip)) = (limits, client_ip) {
+                limits.charge_rejected(ip).map_err(exhausted)?;
+            }
+            return Err(status);
+        }
+    };
+    
+    let Some(limits) = limits else {
+        return Ok(());
+    };
+    let client = match (&key, client_ip) {
+        (Some(name), _) => Client::Key(name),
+        (None, Some(ip)) => Client::Ip(ip),
+        // Nothing to charge it to, as over a Unix socket
+        (None, None) => return Ok(()),
+    };
+    limits.check(client).map_err(exhausted)
+}
+
+/// `RESOURCE_EXHAUSTED` with how long to wait in `retry-after`
+fn exhausted(wait: Duration) -> tonic::Status {
+    let mut status = tonic::Status::resource_exhausted("Rate limit exceeded");
+    if let Ok(value) = retry_after_secs(wait).to_string().parse() {
+        status.metadata_mut().insert("retry-after", value);
+    }
+    status
+}
+
+fn quota(limit: &RateLimitQuota) -> Quota {
+    let rate = NonZeroU32::new(limit.requests_per_second).unwrap_or(NonZeroU32::MIN);
+    let burst = NonZeroU32::new(limit.burst).unwrap_or(rate);
+    Quota::per_second(rate).allow_burst(burst)
+}
@@ -0,0 +1,75 @@
+use crate::types::{ReadinessConfig, ReadinessPolicy};
+use anyhow::Result;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+
+/// Query-side view of startup ingestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Readiness {
+    /// The startup snapshot is still being ingested; state may be partial
+    Ingesting,
+    /// End-of-startup was signalled and every snapshot update is flushed
+    CaughtUp,
+}
+
+/// Returned by queries under the `error` policy while ingesting
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("indexer is warming up: startup snapshot is still being ingested")]
+pub struct WarmingUp;
+
+/// Readiness state machine plus the configured query policy
+pub struct ReadinessGate {
+    state: watch::Sender<Readiness>,
+    policy: ReadinessPolicy,
+    block_timeout: Duration,
+}
+
+impl ReadinessGate {
+    pub fn new(config: &ReadinessConfig, initial: Readiness) -> Self {
+        metrics::gauge!("indexer_ready").set(if initial == Readiness::CaughtUp { 1.0 } else { 0.0 });
+        
+        Self {
+            state: watch::channel(initial).0,
+            policy: config.policy,
+            block_timeout: Duration::from_millis(config.block_timeout_ms),
+        }
+    }
+    
+    pub fn state(&self) -> Readiness {
+        *self.state.borrow()
+    }
+    
+    pub fn is_ready(&self) -> bool {
+        self.state() == Readiness::CaughtUp
+    }
+    
+    pub fn mark_caught_up(&self) {
+        if self.state.send_replace(Readiness::CaughtUp) == Readiness::Ingesting {
+            info!("Startup snapshot ingested, serving queries");
+            metrics::gauge!("indexer_ready").set(1.0);
+        }
+    }
+    
+    /// Apply the query policy: wait, fail with `WarmingUp`, or let the
+    /// caller through to flag its response via `is_ready`
+    pub async fn check(&self) -> Result<()> {
+        if self.is_ready() {
+            return Ok(());
+        }
+        
+        match self.policy {
+            ReadinessPolicy::Flag => Ok(()),
+            ReadinessPolicy::Error => Err(WarmingUp.into()),
+            ReadinessPolicy::Block => {
+                let mut state = self.state.subscribe();
+                let caught_up = state.wait_for(|state| *state == Readiness::CaughtUp);
+                
+                match tokio::time::timeout(self.block_timeout, caught_up).await {
+                    Ok(Ok(_)) => Ok(()),
+                    _ => Err(WarmingUp.into()),
+                }
+            }
+        }
+    }
+}
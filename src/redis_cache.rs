@@ -1,47 +1,355 @@
-use crate::types::{CacheEntry, VaultState};
-use anyhow::Result;
-use redis::AsyncCommands;
+use crate::change_stream::VAULT_CHANGE_STREAM;
+use crate::hot_keys::HotKeys;
+use crate::projection::VaultField;
+use crate::types::{CacheCodec, CacheEntry, RedisConfig, VaultChange, VaultState};
+use anyhow::{Context, Result};
+use redis::aio::ConnectionManager;
+use redis::sentinel::{Sentinel, SentinelNodeConnectionInfo};
+use redis::streams::StreamMaxlen;
+use rand::Rng;
+use redis::{AsyncCommands, ClientTlsConfig, ConnectionAddr, IntoConnectionInfo, TlsCertificates, TlsMode};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock, Weak};
 use std::time::Duration;
 use time::OffsetDateTime;
-use tracing::debug;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+/// First byte of a bincode entry; JSON entries start with `{`, so both
+/// can be read whichever codec is configured
+const BINCODE_FORMAT_V1: u8 = 1;
+
+/// First byte of a zstd-compressed entry, followed by the compressed JSON
+/// or bincode entry
+const ZSTD_FORMAT_V1: u8 = 2;
+
+/// zstd level for large entries; cache writes are on the flush path
+const ZSTD_LEVEL: i32 = 3;
+
+/// Write a vault's entry unless a newer version of it is cached, and
+/// update its field hash, owner index and leaderboard to match; returns
+/// 1 if written
+///
+/// Versions are `(slot, write_version)` as 40 zero-padded digits, which
+/// compare correctly as strings. The indexes are only added to when they
+/// are already cached: one created by a write would look complete while
+/// missing the members written before it. The owner index is also added
+/// to while it's being rebuilt, so a vault written after the rebuild read
+/// the store isn't left out of it.
+///
+/// KEYS: entry, version, owner index, leaderboard, field hash, owner index
+///       rebuild marker
+/// ARGV: entry data, TTL (0 for none), version, vault address, status,
+///       balance, whether the leaderboard is kept, whether the field hash
+///       is kept, then the field hash as field and value pairs
+const CACHE_WRITE: &str = r#"
+local stored = redis.call('GET', KEYS[2])
+if stored and stored > ARGV[3] then
+    return 0
+end
+
+if ARGV[2] == '0' then
+    redis.call('SET', KEYS[1], ARGV[1])
+    redis.call('SET', KEYS[2], ARGV[3])
+else
+    redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+    redis.call('SET', KEYS[2], ARGV[3], 'EX', ARGV[2])
+end
+
+if ARGV[8] == '1' then
+    redis.call('DEL', KEYS[5])
+    redis.call('HSET', KEYS[5], unpack(ARGV, 9))
+    if ARGV[2] ~= '0' then
+        redis.call('EXPIRE', KEYS[5], ARGV[2])
+    end
+end
+
+local indexes = {{KEYS[3], 0, KEYS[6]}}
+if ARGV[7] == '1' then
+    table.insert(indexes, {KEYS[4], ARGV[6]})
+end
+for _, index in ipairs(indexes) do
+    if ARGV[5] ~= 'active' then
+        redis.call('ZREM', index[1], ARGV[4])
+    elseif redis.call('EXISTS', index[1]) == 1
+        or (index[3] and redis.call('EXISTS', index[3]) == 1) then
+        redis.call('ZADD', index[1], index[2], ARGV[4])
+    end
+end
+return 1
+"#;
+
+/// Finish an owner index rebuild with the addresses read from the store;
+/// returns 1 if the index was stored
+///
+/// Writes since the rebuild started have already added their vaults, so
+/// the addresses are merged in rather than replacing the index. Nothing is
+/// stored if another rebuild has since taken over, and the index is
+/// dropped if the marker expired, since writes after that skipped it.
+///
+/// KEYS: owner index, rebuild marker
+/// ARGV: rebuild token, TTL (0 for none), then the vault addresses
+const OWNER_REBUILD_FINISH: &str = r#"
+local marker = redis.call('GET', KEYS[2])
+if marker ~= ARGV[1] then
+    if not marker then
+        redis.call('DEL', KEYS[1])
+    end
+    return 0
+end
+
+redis.call('DEL', KEYS[2])
+for i = 3, #ARGV do
+    redis.call('ZADD', KEYS[1], 0, ARGV[i])
+end
+if ARGV[2] ~= '0' and redis.call('EXISTS', KEYS[1]) == 1 then
+    redis.call('EXPIRE', KEYS[1], ARGV[2])
+end
+return 1
+"#;
+
+/// Seconds an owner index rebuild may take before it's abandoned
+const OWNER_REBUILD_TTL_SECS: u64 = 30;
+
+/// Fields kept in a vault's field hash with `field_hashes`
+const HASHED_FIELDS: [VaultField; 6] = [
+    VaultField::Owner,
+    VaultField::Balance,
+    VaultField::Slot,
+    VaultField::WriteVersion,
+    VaultField::Status,
+    VaultField::Assets,
+];
+
+/// Members added to the leaderboard per ZADD while seeding it
+const LEADERBOARD_SEED_CHUNK: usize = 1000;
 
 /// Redis cache for sub-100ms vault state queries
+///
+/// Holds `max_connections` multiplexed connections opened at startup and
+/// hands them out round-robin, so a query never pays for a handshake.
+/// Each connection pipelines concurrent commands and reconnects by itself
+/// after a dropped link.
+///
+/// With Sentinel endpoints configured, the master is looked up through
+/// Sentinel instead of `url`, and polled for every
+/// `sentinel_poll_interval_ms`; after a failover the connections are
+/// reopened against the new master.
+///
+/// Keys are namespaced by `key_prefix` and the vault program ID, e.g.
+/// `vaulta:<program id>:vault:<address>`, so indexers of different
+/// programs or clusters can share a Redis.
 pub struct RedisCache {
-    client: redis::Client,
-    ttl_seconds: u64,
+    connections: Arc<RwLock<Vec<ConnectionManager>>>,
+    next: AtomicUsize,
+    /// TTLs are atomics so a config reload can change them in place
+    ttl_seconds: AtomicU64,
+    /// Percentage of `ttl_seconds` entry TTLs are randomly moved by
+    ttl_jitter_percent: AtomicU8,
+    /// `<key_prefix>:<program id>:`, prepended to every key
+    key_namespace: String,
+    codec: CacheCodec,
+    /// Entries larger than this many bytes are compressed
+    compress_above_bytes: Option<usize>,
+    publish_changes: bool,
+    /// Cap of the change stream when `stream_changes` is set
+    stream_max_len: Option<usize>,
+    leaderboard: bool,
+    /// Vaults cached with no expiry
+    pinned_vaults: HashSet<String>,
+    /// Read counts of cached vaults, when hot keys get a longer TTL
+    hot_keys: Option<HotKeys>,
+    hot_key_ttl_seconds: AtomicU64,
+    /// Keep a field hash beside each entry
+    field_hashes: bool,
 }
 
 impl RedisCache {
-    /// Create a new Redis cache client
-    pub async fn new(url: &str, ttl_seconds: u64) -> Result<Self> {
-        let client = redis::Client::open(url)?;
+    /// Connect to Redis, failing if a connection can't be opened within
+    /// `connection_timeout_seconds`
+    pub async fn new(config: &RedisConfig, vault_program_id: &str) -> Result<Self> {
+        if config.sentinel_urls.is_empty() {
+            let client = Self::client(config)?;
+            let connections = Self::open_connections(&client, config).await?;
+            return Ok(Self::with_connections(connections, config, vault_program_id));
+        }
         
-        // Test connection
-        let mut conn = client.get_async_connection().await?;
-        redis::cmd("PING").query_async::<_, String>(&mut conn).await?;
+        let mut sentinel = Sentinel::build(config.sentinel_urls.clone())?;
+        let client = Self::resolve_master(&mut sentinel, config).await?;
+        let master = client.get_connection_info().addr.to_string();
+        info!("Redis master is {}", master);
         
-        Ok(Self {
-            client,
-            ttl_seconds,
-        })
+        let connections = Self::open_connections(&client, config).await?;
+        let cache = Self::with_connections(connections, config, vault_program_id);
+        tokio::spawn(Self::follow_master(
+            sentinel,
+            config.clone(),
+            master,
+            Arc::downgrade(&cache.connections),
+        ));
+        Ok(cache)
     }
     
-    /// Get vault state from cache
-    pub async fn get(&self, vault_address: &str) -> Result<Option<VaultState>> {
-        let mut conn = self.client.get_async_connection().await?;
+    fn with_connections(connections: Vec<ConnectionManager>, config: &RedisConfig, vault_program_id: &str) -> Self {
+        Self {
+            connections: Arc::new(RwLock::new(connections)),
+            next: AtomicUsize::new(0),
+            ttl_seconds: AtomicU64::new(config.ttl_seconds),
+            ttl_jitter_percent: AtomicU8::new(config.ttl_jitter_percent.min(100)),
+            key_namespace: Self::namespace_for(config, vault_program_id),
+            codec: config.codec,
+            compress_above_bytes: config.compress_above_bytes,
+            publish_changes: config.publish_changes,
+            stream_max_len: config.stream_changes.then_some(config.stream_max_len),
+            leaderboard: config.leaderboard,
+            pinned_vaults: config.pinned_vaults.iter().cloned().collect(),
+            hot_keys: config.hot_key_reads_per_minute.map(HotKeys::new),
+            hot_key_ttl_seconds: AtomicU64::new(config.hot_key_ttl_seconds),
+            field_hashes: config.field_hashes,
+        }
+    }
+    
+    /// Client for `url` with the configured ACL credentials and TLS
+    /// certificates
+    pub(crate) fn client(config: &RedisConfig) -> Result<redis::Client> {
+        let mut info = config.url.as_str().into_connection_info()?;
+        if let Some(username) = &config.username {
+            info.redis.username = Some(username.clone());
+        }
+        if let Some(password) = &config.password {
+            info.redis.password = Some(password.clone());
+        }
+        
+        let tls_configured = config.tls_ca_cert.is_some() || config.tls_client_cert.is_some() || config.tls_insecure;
+        match &mut info.addr {
+            ConnectionAddr::TcpTls { insecure, .. } => *insecure |= config.tls_insecure,
+            _ if tls_configured => anyhow::bail!("[redis] TLS options need a rediss:// url"),
+            _ => {}
+        }
         
-        let key = format!("vault:{}", vault_address);
-        let data: Option<String> = conn.get(&key).await?;
+        let client_tls = match (&config.tls_client_cert, &config.tls_client_key) {
+            (Some(cert), Some(key)) => Some(ClientTlsConfig {
+                client_cert: read_pem(cert)?,
+                client_key: read_pem(key)?,
+            }),
+            (None, None) => None,
+            _ => anyhow::bail!("tls_client_cert and tls_client_key must be set together"),
+        };
+        let root_cert = config.tls_ca_cert.as_deref().map(read_pem).transpose()?;
+        if client_tls.is_none() && root_cert.is_none() {
+            return Ok(redis::Client::open(info)?);
+        }
         
-        if let Some(data) = data {
-            let entry: CacheEntry = serde_json::from_str(&data)?;
+        Ok(redis::Client::build_with_tls(info, TlsCertificates { client_tls, root_cert })?)
+    }
+    
+    /// `<key_prefix>:<program id>:`, prepended to every key
+    pub(crate) fn namespace_for(config: &RedisConfig, vault_program_id: &str) -> String {
+        format!("{}:{}:", config.key_prefix, vault_program_id)
+    }
+    
+    async fn open_connections(client: &redis::Client, config: &RedisConfig) -> Result<Vec<ConnectionManager>> {
+        let timeout = Duration::from_secs(config.connection_timeout_seconds);
+        
+        let mut connections = Vec::with_capacity(config.max_connections.max(1) as usize);
+        for _ in 0..config.max_connections.max(1) {
+            let mut conn = tokio::time::timeout(timeout, ConnectionManager::new(client.clone()))
+                .await
+                .context("Timed out connecting to Redis")??;
+            redis::cmd("PING").query_async::<_, String>(&mut conn).await?;
+            connections.push(conn);
+        }
+        
+        Ok(connections)
+    }
+    
+    /// Client for the current master of `sentinel_master_name`; it takes
+    /// the credentials, database and TLS mode of `url`, but verifies the
+    /// master against the system CAs rather than `tls_ca_cert`
+    async fn resolve_master(sentinel: &mut Sentinel, config: &RedisConfig) -> Result<redis::Client> {
+        let master_name = config
+            .sentinel_master_name
+            .as_deref()
+            .context("[redis] sentinel_urls needs sentinel_master_name")?;
+        let info = Self::client(config)?.get_connection_info().clone();
+        let node = SentinelNodeConnectionInfo {
+            tls_mode: match info.addr {
+                ConnectionAddr::TcpTls { insecure: true, .. } => Some(TlsMode::Insecure),
+                ConnectionAddr::TcpTls { .. } => Some(TlsMode::Secure),
+                _ => None,
+            },
+            redis_connection_info: Some(info.redis),
+        };
+        
+        let timeout = Duration::from_secs(config.connection_timeout_seconds);
+        Ok(tokio::time::timeout(timeout, sentinel.async_master_for(master_name, Some(&node)))
+            .await
+            .context("Timed out asking Redis Sentinel for the master")??)
+    }
+    
+    /// Poll Sentinel for the master and reconnect when it moves; stops
+    /// once the cache is dropped
+    async fn follow_master(
+        mut sentinel: Sentinel,
+        config: RedisConfig,
+        mut master: String,
+        connections: Weak<RwLock<Vec<ConnectionManager>>>,
+    ) {
+        let mut ticker = interval(Duration::from_millis(config.sentinel_poll_interval_ms.max(100)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        
+        loop {
+            ticker.tick().await;
+            if connections.strong_count() == 0 {
+                return;
+            }
             
-            // Check if expired
-            let now = OffsetDateTime::now_utc();
-            let age = (now - entry.cached_at).whole_seconds() as u64;
+            let client = match Self::resolve_master(&mut sentinel, &config).await {
+                Ok(client) => client,
+                Err(e) => {
+                    warn!("Failed to look up the Redis master through Sentinel: {}", e);
+                    continue;
+                }
+            };
+            let address = client.get_connection_info().addr.to_string();
+            if address == master {
+                continue;
+            }
             
-            if age < entry.ttl_seconds {
+            match Self::open_connections(&client, &config).await {
+                Ok(reopened) => {
+                    let Some(connections) = connections.upgrade() else {
+                        return;
+                    };
+                    *connections.write().unwrap() = reopened;
+                    info!("Redis master moved from {} to {}", master, address);
+                    metrics::counter!("indexer_redis_failovers_total").increment(1);
+                    master = address;
+                }
+                Err(e) => warn!("Failed to connect to the new Redis master {}: {}", address, e),
+            }
+        }
+    }
+    
+    /// Next connection in turn; a clone shares the underlying connection
+    fn conn(&self) -> ConnectionManager {
+        let connections = self.connections.read().unwrap();
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % connections.len();
+        connections[index].clone()
+    }
+    
+    /// Get vault state from cache
+    pub async fn get(&self, vault_address: &str) -> Result<Option<VaultState>> {
+        let mut conn = self.conn();
+        
+        let key = self.vault_key(vault_address);
+        let data: Option<Vec<u8>> = conn.get(&key).await?;
+        
+        if let Some(entry) = data.and_then(|data| Self::decode(&data)) {
+            if Self::is_fresh(&entry, OffsetDateTime::now_utc()) {
                 debug!("Cache hit for vault: {}", vault_address);
+                self.record_hits([&entry.vault_state]).await?;
                 return Ok(Some(entry.vault_state));
             } else {
                 debug!("Cache expired for vault: {}", vault_address);
@@ -55,53 +363,492 @@ impl RedisCache {
     
     /// Set vault state in cache
     pub async fn set(&self, state: &VaultState) -> Result<()> {
-        let mut conn = self.client.get_async_connection().await?;
+        let mut conn = self.conn();
         
-        let key = format!("vault:{}", state.vault_address);
-        let entry = CacheEntry {
-            vault_state: state.clone(),
-            cached_at: OffsetDateTime::now_utc(),
-            ttl_seconds: self.ttl_seconds,
-        };
-        
-        let data = serde_json::to_string(&entry)?;
-        conn.set_ex(&key, data, self.ttl_seconds as usize).await?;
+        let mut pipe = redis::pipe();
+        self.write_entry(&mut pipe, state)?;
+        let (written,): (bool,) = pipe.query_async(&mut conn).await?;
         
-        debug!("Cached vault state: {}", state.vault_address);
+        if written {
+            debug!("Cached vault state: {}", state.vault_address);
+        } else {
+            metrics::counter!("indexer_cache_stale_writes_total").increment(1);
+        }
         Ok(())
     }
     
     /// Delete vault state from cache
     pub async fn delete(&self, vault_address: &str) -> Result<()> {
-        let mut conn = self.client.get_async_connection().await?;
-        let key = format!("vault:{}", vault_address);
-        let _: () = conn.del(&key).await?;
+        let mut conn = self.conn();
+        let keys = [self.vault_key(vault_address), self.fields_key(vault_address)];
+        let _: () = conn.del(&keys).await?;
         Ok(())
     }
     
+    /// One field of a cached vault, as stored in its field hash; `None` if
+    /// the vault isn't cached, `field_hashes` is off or the field isn't
+    /// one of those hashed
+    ///
+    /// Cheaper than `get` for consumers that only need, say, the balance:
+    /// a single HGET of a short string, with no entry to decode.
+    pub async fn get_field(&self, vault_address: &str, field: VaultField) -> Result<Option<String>> {
+        let mut conn = self.conn();
+        Ok(conn.hget(self.fields_key(vault_address), field.name()).await?)
+    }
+    
+    /// Several fields of a cached vault in one HMGET, in the order given;
+    /// `None` if the vault isn't cached
+    pub async fn get_fields(&self, vault_address: &str, fields: &[VaultField]) -> Result<Option<Vec<String>>> {
+        if fields.is_empty() {
+            return Ok(Some(Vec::new()));
+        }
+        
+        let mut conn = self.conn();
+        let names: Vec<&str> = fields.iter().map(|field| field.name()).collect();
+        let values: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(self.fields_key(vault_address))
+            .arg(&names)
+            .query_async(&mut conn)
+            .await?;
+        
+        // The hash is written whole, so a missing field means no hash
+        Ok(values.into_iter().collect())
+    }
+    
+    /// Cached lamport balance of a vault, from its field hash
+    pub async fn get_balance(&self, vault_address: &str) -> Result<Option<u64>> {
+        match self.get_field(vault_address, VaultField::Balance).await? {
+            Some(balance) => Ok(Some(balance.parse()?)),
+            None => Ok(None),
+        }
+    }
+    
     /// Batch set vault states
     pub async fn batch_set(&self, states: &[VaultState]) -> Result<()> {
-        let mut conn = self.client.get_async_connection().await?;
+        self.write_entries(states, false).await
+    }
+    
+    /// Cache vault states the indexer just wrote, announcing them to
+    /// subscribers; reads filling the cache use `batch_set` instead
+    pub async fn batch_set_changes(&self, states: &[VaultState]) -> Result<()> {
+        self.write_entries(states, true).await
+    }
+    
+    async fn write_entries(&self, states: &[VaultState], changed: bool) -> Result<()> {
+        let mut conn = self.conn();
         let mut pipe = redis::pipe();
         
         for state in states {
-            let key = format!("vault:{}", state.vault_address);
-            let entry = CacheEntry {
-                vault_state: state.clone(),
-                cached_at: OffsetDateTime::now_utc(),
-                ttl_seconds: self.ttl_seconds,
-            };
-            let data = serde_json::to_string(&entry)?;
-            pipe.set_ex(&key, data, self.ttl_seconds as usize);
+            self.write_entry(&mut pipe, state)?;
+            if changed {
+                self.announce_change(&mut pipe, state)?;
+            }
+        }
+        
+        let written: Vec<bool> = pipe.query_async(&mut conn).await?;
+        let stale = written.iter().filter(|written| !**written).count();
+        if stale > 0 {
+            metrics::counter!("indexer_cache_stale_writes_total").increment(stale as u64);
+        }
+        Ok(())
+    }
+    
+    /// Cached vault states for several addresses in one `MGET`, in order;
+    /// `None` for misses and expired entries
+    pub async fn get_many<S: AsRef<str>>(&self, vault_addresses: &[S]) -> Result<Vec<Option<VaultState>>> {
+        if vault_addresses.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let mut conn = self.conn();
+        let keys: Vec<String> = vault_addresses
+            .iter()
+            .map(|address| self.vault_key(address.as_ref()))
+            .collect();
+        let data: Vec<Option<Vec<u8>>> = redis::cmd("MGET").arg(&keys).query_async(&mut conn).await?;
+        
+        let now = OffsetDateTime::now_utc();
+        let states: Vec<Option<VaultState>> = data
+            .into_iter()
+            .map(|data| {
+                let entry = Self::decode(&data?)?;
+                Self::is_fresh(&entry, now).then_some(entry.vault_state)
+            })
+            .collect();
+        
+        self.record_hits(states.iter().flatten()).await?;
+        Ok(states)
+    }
+    
+    /// An entry cached with TTL 0 is pinned and never expires
+    fn is_fresh(entry: &CacheEntry, now: OffsetDateTime) -> bool {
+        let age = (now - entry.cached_at).whole_seconds() as u64;
+        entry.ttl_seconds == 0 || age < entry.ttl_seconds
+    }
+    
+    /// Count reads of cached vaults, rewriting those that just turned hot
+    /// so they take the longer TTL
+    async fn record_hits<'a>(&self, states: impl IntoIterator<Item = &'a VaultState>) -> Result<()> {
+        let Some(hot_keys) = &self.hot_keys else {
+            return Ok(());
+        };
+        
+        let heated: Vec<VaultState> = states
+            .into_iter()
+            .filter(|state| hot_keys.record(&state.vault_address.to_string()))
+            .cloned()
+            .collect();
+        if !heated.is_empty() {
+            debug!("{} cached vaults turned hot", heated.len());
+            metrics::counter!("indexer_cache_hot_keys_total").increment(heated.len() as u64);
+            self.batch_set(&heated).await?;
+        }
+        Ok(())
+    }
+    
+    /// Vaults kept in the cache with no expiry
+    pub fn pinned_vaults(&self) -> impl Iterator<Item = &String> {
+        self.pinned_vaults.iter()
+    }
+    
+    /// TTL of a new entry: `ttl_seconds` moved randomly by up to the
+    /// jitter either way, so entries cached together don't all expire
+    /// together and send their reads to the database at once
+    fn entry_ttl(&self) -> u64 {
+        self.jittered(self.ttl_seconds.load(Ordering::Relaxed))
+    }
+    
+    /// Take `ttl_seconds`, `ttl_jitter_percent` and `hot_key_ttl_seconds`
+    /// from `config` for entries written from now on; cached entries keep
+    /// the TTL they were written with
+    pub fn set_ttls(&self, config: &RedisConfig) {
+        self.ttl_seconds.store(config.ttl_seconds, Ordering::Relaxed);
+        self.ttl_jitter_percent
+            .store(config.ttl_jitter_percent.min(100), Ordering::Relaxed);
+        self.hot_key_ttl_seconds.store(config.hot_key_ttl_seconds, Ordering::Relaxed);
+    }
+    
+    /// TTL of a vault's entry: none if it's pinned, `hot_key_ttl_seconds`
+    /// while it's hot, and the usual TTL otherwise
+    fn vault_ttl(&self, vault_address: &str) -> u64 {
+        if self.pinned_vaults.contains(vault_address) {
+            return 0;
+        }
+        
+        match &self.hot_keys {
+            Some(hot_keys) if hot_keys.is_hot(vault_address) => {
+                self.jittered(self.hot_key_ttl_seconds.load(Ordering::Relaxed))
+            }
+            _ => self.entry_ttl(),
+        }
+    }
+    
+    fn jittered(&self, ttl_seconds: u64) -> u64 {
+        let jitter = f64::from(self.ttl_jitter_percent.load(Ordering::Relaxed)) / 100.0;
+        if jitter == 0.0 {
+            return ttl_seconds;
+        }
+        
+        let spread = ttl_seconds as f64 * jitter;
+        let ttl = ttl_seconds as f64 + rand::thread_rng().gen_range(-spread..=spread);
+        (ttl.round() as u64).max(1)
+    }
+    
+    /// Cache entry for `state` in the configured codec
+    fn encode(&self, state: &VaultState, ttl_seconds: u64) -> Result<Vec<u8>> {
+        let entry = CacheEntry {
+            vault_state: state.clone(),
+            cached_at: OffsetDateTime::now_utc(),
+            ttl_seconds,
+        };
+        
+        let data = match self.codec {
+            CacheCodec::Json => serde_json::to_vec(&entry)?,
+            CacheCodec::Bincode => {
+                let mut data = vec![BINCODE_FORMAT_V1];
+                bincode::serialize_into(&mut data, &entry)?;
+                data
+            }
+        };
+        
+        let Some(threshold) = self.compress_above_bytes else {
+            return Ok(data);
+        };
+        metrics::counter!("indexer_cache_entries_encoded_total").increment(1);
+        if data.len() <= threshold {
+            return Ok(data);
         }
         
-        pipe.query_async(&mut conn).await?;
+        let mut compressed = vec![ZSTD_FORMAT_V1];
+        compressed.extend(zstd::bulk::compress(&data, ZSTD_LEVEL)?);
+        metrics::counter!("indexer_cache_entries_compressed_total").increment(1);
+        metrics::counter!("indexer_cache_compressed_bytes_saved_total")
+            .increment(data.len().saturating_sub(compressed.len()) as u64);
+        Ok(compressed)
+    }
+    
+    /// A cache entry in either codec; an entry of an unknown format, or
+    /// one that doesn't decode, is treated as a miss and gets overwritten
+    fn decode(data: &[u8]) -> Option<CacheEntry> {
+        let entry = match data.first() {
+            Some(b'{') => serde_json::from_slice(data).map_err(anyhow::Error::from),
+            Some(&BINCODE_FORMAT_V1) => bincode::deserialize(&data[1..]).map_err(anyhow::Error::from),
+            Some(&ZSTD_FORMAT_V1) => match zstd::stream::decode_all(&data[1..]) {
+                Ok(inner) if inner.first() != Some(&ZSTD_FORMAT_V1) => return Self::decode(&inner),
+                Ok(_) => Err(anyhow::anyhow!("nested compressed cache entry")),
+                Err(e) => Err(e.into()),
+            },
+            _ => Err(anyhow::anyhow!("unknown cache entry format")),
+        };
+        
+        match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                debug!("Ignoring undecodable cache entry: {}", e);
+                metrics::counter!("indexer_cache_undecodable_entries_total").increment(1);
+                None
+            }
+        }
+    }
+    
+    /// One page of an owner's vault addresses after the address `after`
+    /// from the owner index, or `None` if the index for `owner` isn't cached
+    /// or is being rebuilt
+    ///
+    /// The index is a sorted set with equal scores, so it's ordered
+    /// bytewise by address and a page is a lexicographic range. Writes
+    /// keep a cached index current: an active vault is added and a closed
+    /// one removed. A vault moving to another owner is left in the old
+    /// owner's index, since the write doesn't name the old owner, so
+    /// callers must check ownership of what they load.
+    pub async fn get_owner_vaults(&self, owner: &str, after: Option<&str>, limit: i64) -> Result<Option<Vec<String>>> {
+        let mut conn = self.conn();
+        let key = self.owner_key(owner);
+        let min = after.map_or_else(|| "-".to_string(), |address| format!("({}", address));
+        
+        let (exists, rebuilding, addresses): (bool, bool, Vec<String>) = redis::pipe()
+            .exists(&key)
+            .exists(self.owner_rebuild_key(owner))
+            .zrangebylex_limit(&key, min, "+", 0, limit as isize)
+            .query_async(&mut conn)
+            .await?;
+        
+        let hit = exists && !rebuilding;
+        if hit {
+            debug!("Owner index hit for: {}", owner);
+        }
+        Ok(hit.then_some(addresses))
+    }
+    
+    /// Start rebuilding the owner index for `owner`, before its addresses
+    /// are read from the store; returns the token to pass to
+    /// `set_owner_vaults`
+    ///
+    /// Drops the index and sets a rebuild marker, under which writes add
+    /// their vaults to the index even though it isn't cached.
+    pub async fn begin_owner_rebuild(&self, owner: &str) -> Result<String> {
+        let mut conn = self.conn();
+        let token = uuid::Uuid::new_v4().to_string();
+        
+        let _: () = redis::pipe()
+            .atomic()
+            .del(self.owner_key(owner))
+            .cmd("SET")
+            .arg(self.owner_rebuild_key(owner))
+            .arg(&token)
+            .arg("EX")
+            .arg(OWNER_REBUILD_TTL_SECS)
+            .query_async(&mut conn)
+            .await?;
+        
+        Ok(token)
+    }
+    
+    /// Finish the owner index rebuild started with `token`, adding all of
+    /// `owner`'s vault addresses as read from the store
+    pub async fn set_owner_vaults(&self, owner: &str, token: &str, vault_addresses: &[String]) -> Result<()> {
+        let mut conn = self.conn();
+        
+        let stored: i64 = redis::cmd("EVAL")
+            .arg(OWNER_REBUILD_FINISH)
+            .arg(2)
+            .arg(self.owner_key(owner))
+            .arg(self.owner_rebuild_key(owner))
+            .arg(token)
+            .arg(self.entry_ttl())
+            .arg(vault_addresses)
+            .query_async(&mut conn)
+            .await?;
+        if stored == 0 {
+            debug!("Owner index rebuild for {} was superseded or expired", owner);
+        }
+        
+        Ok(())
+    }
+    
+    /// Drop the owner index for `owner`, e.g. after finding it stale
+    pub async fn delete_owner_vaults(&self, owner: &str) -> Result<()> {
+        let mut conn = self.conn();
+        let _: () = conn.del(self.owner_key(owner)).await?;
+        Ok(())
+    }
+    
+    /// Channel cached vault writes are announced on when `publish_changes`
+    /// is set, e.g. `vaulta:<program id>:vault_changes`
+    pub fn changes_channel(&self) -> String {
+        format!("{}vault_changes", self.key_namespace)
+    }
+    
+    /// Announce a written vault on the channel and the change stream, as
+    /// configured, in the same pipeline as the cache write so a consumer
+    /// never sees the old entry after the announcement
+    fn announce_change(&self, pipe: &mut redis::Pipeline, state: &VaultState) -> Result<()> {
+        let change = VaultChange {
+            vault_address: state.vault_address.to_string(),
+            slot: state.slot,
+            write_version: state.write_version,
+        };
+        
+        if self.publish_changes {
+            pipe.publish(self.changes_channel(), serde_json::to_string(&change)?).ignore();
+        }
+        if let Some(max_len) = self.stream_max_len {
+            pipe.xadd_maxlen(
+                format!("{}{}", self.key_namespace, VAULT_CHANGE_STREAM),
+                StreamMaxlen::Approx(max_len),
+                "*",
+                &[
+                    ("vault_address", change.vault_address),
+                    ("slot", change.slot.to_string()),
+                    ("write_version", change.write_version.to_string()),
+                ],
+            )
+            .ignore();
+        }
+        Ok(())
+    }
+    
+    /// Queue the write of a vault's entry, with its owner index and
+    /// leaderboard position
+    ///
+    /// The entry is only replaced by a state at least as new as the cached
+    /// one, so a slow writer can't put back an older state; the indexes
+    /// follow the entry. Adds one reply, whether it was written.
+    fn write_entry(&self, pipe: &mut redis::Pipeline, state: &VaultState) -> Result<()> {
+        let address = state.vault_address.to_string();
+        let ttl = self.vault_ttl(&address);
+        
+        let owner = state.owner.to_string();
+        
+        let eval = pipe.cmd("EVAL")
+            .arg(CACHE_WRITE)
+            .arg(6)
+            .arg(self.vault_key(&address))
+            .arg(self.version_key(&address))
+            .arg(self.owner_key(&owner))
+            .arg(self.leaderboard_key())
+            .arg(self.fields_key(&address))
+            .arg(self.owner_rebuild_key(&owner))
+            .arg(self.encode(state, ttl)?)
+            .arg(ttl)
+            .arg(format!("{:020}{:020}", state.slot, state.write_version))
+            .arg(&address)
+            .arg(state.status.as_str())
+            .arg(state.balance)
+            .arg(if self.leaderboard { "1" } else { "0" })
+            .arg(if self.field_hashes { "1" } else { "0" });
+        if self.field_hashes {
+            // Values as rendered in responses; strings go in unquoted
+            for field in HASHED_FIELDS {
+                match field.value(state) {
+                    serde_json::Value::String(value) => eval.arg(field.name()).arg(value),
+                    value => eval.arg(field.name()).arg(value.to_string()),
+                };
+            }
+        }
+        Ok(())
+    }
+    
+    /// Replace the balance leaderboard with the balances of all active
+    /// vaults, which writes keep current from then on
+    ///
+    /// Seed it before writes start: a write racing the seed could be
+    /// overwritten by an older balance.
+    pub async fn seed_leaderboard(&self, balances: &[(String, u64)]) -> Result<()> {
+        let mut conn = self.conn();
+        let key = self.leaderboard_key();
+        let staging = format!("{}:seed", key);
+        
+        let mut pipe = redis::pipe();
+        pipe.del(&staging).ignore();
+        for chunk in balances.chunks(LEADERBOARD_SEED_CHUNK) {
+            let members: Vec<(u64, &String)> = chunk.iter().map(|(address, balance)| (*balance, address)).collect();
+            pipe.zadd_multiple(&staging, &members).ignore();
+        }
+        if balances.is_empty() {
+            pipe.del(&key).ignore();
+        } else {
+            pipe.rename(&staging, &key).ignore();
+        }
+        let _: () = pipe.query_async(&mut conn).await?;
+        
+        info!("Seeded the vault leaderboard with {} vaults", balances.len());
         Ok(())
     }
     
+    /// Addresses of the `n` largest active vaults by lamport balance, or
+    /// `None` if the leaderboard isn't seeded
+    ///
+    /// Ties are ordered by address, descending, like
+    /// `Database::get_top_vaults_by_balance`.
+    pub async fn top_vaults(&self, n: i64) -> Result<Option<Vec<String>>> {
+        if !self.leaderboard || n <= 0 {
+            return Ok(None);
+        }
+        
+        let mut conn = self.conn();
+        let key = self.leaderboard_key();
+        let (exists, addresses): (bool, Vec<String>) = redis::pipe()
+            .exists(&key)
+            .zrevrange(&key, 0, (n - 1) as isize)
+            .query_async(&mut conn)
+            .await?;
+        
+        Ok(exists.then_some(addresses))
+    }
+    
+    fn leaderboard_key(&self) -> String {
+        format!("{}balances", self.key_namespace)
+    }
+    
+    fn vault_key(&self, vault_address: &str) -> String {
+        format!("{}vault:{}", self.key_namespace, vault_address)
+    }
+    
+    fn owner_key(&self, owner: &str) -> String {
+        format!("{}owner:{}", self.key_namespace, owner)
+    }
+    
+    /// Marker set while the owner index is being rebuilt, holding the
+    /// rebuild's token
+    fn owner_rebuild_key(&self, owner: &str) -> String {
+        format!("{}owner_rebuild:{}", self.key_namespace, owner)
+    }
+    
+    /// Version of the cached entry, kept apart from it so the write
+    /// script can compare without decoding; deleting the entry leaves it,
+    /// so an older state can't be put back after an invalidation either
+    fn version_key(&self, vault_address: &str) -> String {
+        format!("{}version:{}", self.key_namespace, vault_address)
+    }
+    
+    fn fields_key(&self, vault_address: &str) -> String {
+        format!("{}fields:{}", self.key_namespace, vault_address)
+    }
+    
     /// Get cache statistics
     pub async fn get_stats(&self) -> Result<CacheStats> {
-        let mut conn = self.client.get_async_connection().await?;
+        let mut conn = self.conn();
         
         let info: String = redis::cmd("INFO")
             .arg("stats")
@@ -141,6 +888,10 @@ impl RedisCache {
     }
 }
 
+fn read_pem(path: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Failed to read {}", path))
+}
+
 #[derive(Debug, Clone)]
 pub struct CacheStats {
     pub keyspace_hits: u64,
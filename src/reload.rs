@@ -0,0 +1,168 @@
+use crate::config::Config;
+use crate::indexer::Indexer;
+use crate::types::PluginConfig;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::info;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::reload::Handle;
+use tracing_subscriber::{EnvFilter, Registry};
+
+/// Handle to the log filter installed by `init_logging`
+pub type LogFilter = Handle<EnvFilter, Registry>;
+
+/// Sections compared setting by setting, since some of their settings can
+/// be applied in place; any other changed section needs a restart as a
+/// whole
+const SPLIT_SECTIONS: [&str; 2] = ["redis", "degradation"];
+
+/// Settings a reload found changed
+#[derive(Debug, Default, Serialize)]
+pub struct ReloadReport {
+    /// Now in effect
+    pub applied: Vec<String>,
+    /// Left as they were until the next restart
+    pub restart_required: Vec<String>,
+}
+
+/// Re-reads the config file and applies what can change under a running
+/// indexer
+///
+/// Hot-safe are `batch_size` (with `adaptive_batching` off), the Redis
+/// `ttl_seconds`, `ttl_jitter_percent` and `hot_key_ttl_seconds` (with a
+/// cache), the degradation `levels` (with `[degradation]` on since
+/// startup) and `log_level` (when the plugin installed the log
+/// subscriber). Other changes are reported by
+/// name, as `section` or `section.setting`, and keep being reported until a
+/// restart applies them. Values are never reported, since some are secrets.
+pub struct ConfigReloader {
+    path: PathBuf,
+    /// The settings in effect
+    running: Mutex<PluginConfig>,
+    log_filter: Option<LogFilter>,
+}
+
+impl ConfigReloader {
+    /// Reloader of the file at `path`, which `config` was read from
+    pub fn new(path: impl AsRef<Path>, config: &PluginConfig, log_filter: Option<LogFilter>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            running: Mutex::new(config.clone()),
+            log_filter,
+        }
+    }
+    
+    /// Re-read the file and apply its hot-safe changes to `indexer`
+    ///
+    /// A file that fails to parse, or sets an invalid `log_level`, changes
+    /// nothing.
+    pub fn reload(&self, indexer: &Indexer) -> Result<ReloadReport> {
+        let file = Config::from_file(&self.path)?.inner().clone();
+        let mut running = self.running.lock().unwrap();
+        let mut log_filter = match &self.log_filter {
+            Some(_) if file.log_level != running.log_level => Some(
+                EnvFilter::try_new(&file.log_level)
+                    .with_context(|| format!("Invalid log_level '{}'", file.log_level))?,
+            ),
+            _ => None,
+        };
+        
+        let mut report = ReloadReport::default();
+        for setting in changed_settings(&running, &file)? {
+            let applied = match setting.as_str() {
+                "batch_size" => {
+                    let applied = indexer.set_batch_size(file.batch_size);
+                    if applied {
+                        running.batch_size = file.batch_size;
+                    }
+                    applied
+                }
+                "redis.ttl_seconds" | "redis.ttl_jitter_percent" | "redis.hot_key_ttl_seconds" => {
+                    // Without a cache there is nothing to apply them to
+                    let applied = indexer.set_cache_ttls(&file.redis);
+                    if applied {
+                        running.redis.ttl_seconds = file.redis.ttl_seconds;
+                        running.redis.ttl_jitter_percent = file.redis.ttl_jitter_percent;
+                        running.redis.hot_key_ttl_seconds = file.redis.hot_key_ttl_seconds;
+                    }
+                    applied
+                }
+                "degradation.levels" => {
+                    let applied = indexer.set_degradation_levels(&file.degradation);
+                    if applied {
+                        running.degradation.levels = file.degradation.levels.clone();
+                    }
+                    applied
+                }
+                "log_level" => match (&self.log_filter, log_filter.take()) {
+                    (Some(handle), Some(filter)) => {
+                        handle.reload(filter).context("Failed to set the log level")?;
+                        running.log_level = file.log_level.clone();
+                        true
+                    }
+                    _ => false,
+                },
+                _ => false,
+            };
+            if applied {
+                report.applied.push(setting);
+            } else {
+                report.restart_required.push(setting);
+            }
+        }
+        
+        info!(
+            "Reloaded {}: applied {:?}, restart required for {:?}",
+            self.path.display(),
+            report.applied,
+            report.restart_required
+        );
+        Ok(report)
+    }
+}
+
+/// Install the log subscriber of the plugin, filtered by `level` as an
+/// `EnvFilter` directive such as `info` or `info,vaulta_geyser_indexer=debug`;
+/// `None` if the process already has a subscriber
+pub fn init_logging(level: &str) -> Option<LogFilter> {
+    let filter = EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = tracing_subscriber::reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .try_init()
+        .ok()?;
+    
+    Some(handle)
+}
+
+/// Names of the settings that differ between `running` and `file`
+fn changed_settings(running: &PluginConfig, file: &PluginConfig) -> Result<Vec<String>> {
+    let (Value::Object(running), Value::Object(file)) = (serde_json::to_value(running)?, serde_json::to_value(file)?)
+    else {
+        anyhow::bail!("Config is not a table");
+    };
+    
+    let mut changed = Vec::new();
+    for (name, value) in &file {
+        let previous = running.get(name).unwrap_or(&Value::Null);
+        if previous == value {
+            continue;
+        }
+        match (previous, value) {
+            (Value::Object(previous), Value::Object(value)) if SPLIT_SECTIONS.contains(&name.as_str()) => {
+                changed.extend(
+                    value
+                        .iter()
+                        .filter(|(key, setting)| previous.get(*key) != Some(*setting))
+                        .map(|(key, _)| format!("{}.{}", name, key)),
+                );
+            }
+            _ => changed.push(name.clone()),
+        }
+    }
+    Ok(changed)
+}
@@ -0,0 +1,135 @@
+use crate::database::PoolSettings;
+use crate::namespace::Namespace;
+use anyhow::Result;
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval, timeout, MissedTickBehavior};
+use tracing::{info, warn};
+
+/// How often replica lag is measured
+const LAG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Replica {
+    pool: PgPool,
+    lag_ms: AtomicU64,
+    available: AtomicBool,
+}
+
+/// Read-only replicas that read queries are spread across
+///
+/// Each replica's replay lag is measured every second. Reads go round-robin
+/// to replicas that answered the last check and lag by at most `max_lag`;
+/// when none qualifies the caller falls back to the primary.
+pub struct ReadReplicas {
+    replicas: Vec<Replica>,
+    max_lag: Duration,
+    next: AtomicUsize,
+}
+
+impl ReadReplicas {
+    /// Connect to every replica and start measuring their lag
+    pub async fn connect(
+        urls: &[String],
+        settings: &PoolSettings,
+        namespace: &Namespace,
+        max_lag: Duration,
+    ) -> Result<Arc<Self>> {
+        let mut replicas = Vec::with_capacity(urls.len());
+        for url in urls {
+            let pool = sqlx::postgres::PgPoolOptions::new()
+                .max_connections(settings.max_connections)
+                .min_connections(settings.min_connections)
+                .acquire_timeout(settings.acquire_timeout)
+                .idle_timeout(settings.idle_timeout)
+                .test_before_acquire(settings.test_before_acquire)
+                .connect_with(settings.connect_options(namespace, url)?)
+                .await?;
+            
+            replicas.push(Replica {
+                pool,
+                lag_ms: AtomicU64::new(u64::MAX),
+                available: AtomicBool::new(false),
+            });
+        }
+        info!("Connected to {} read replicas", replicas.len());
+        
+        let set = Arc::new(Self {
+            replicas,
+            max_lag,
+            next: AtomicUsize::new(0),
+        });
+        set.check_all().await;
+        tokio::spawn(set.clone().run());
+        
+        Ok(set)
+    }
+    
+    /// A replica fresh enough to read from, if any
+    pub fn pick(&self) -> Option<&PgPool> {
+        let max_lag_ms = self.max_lag.as_millis() as u64;
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        
+        (0..self.replicas.len())
+            .map(|offset| &self.replicas[(start + offset) % self.replicas.len()])
+            .find(|replica| {
+                replica.available.load(Ordering::Acquire)
+                    && replica.lag_ms.load(Ordering::Acquire) <= max_lag_ms
+            })
+            .map(|replica| &replica.pool)
+    }
+    
+    async fn run(self: Arc<Self>) {
+        let mut ticker = interval(LAG_CHECK_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+        
+        loop {
+            ticker.tick().await;
+            self.check_all().await;
+        }
+    }
+    
+    async fn check_all(&self) {
+        for (index, replica) in self.replicas.iter().enumerate() {
+            let label = index.to_string();
+            
+            match timeout(LAG_CHECK_INTERVAL, Self::replay_lag(&replica.pool)).await {
+                Ok(Ok(lag)) => {
+                    replica.lag_ms.store(lag.as_millis() as u64, Ordering::Release);
+                    if !replica.available.swap(true, Ordering::AcqRel) {
+                        info!("Read replica {} is available", index);
+                    }
+                    metrics::gauge!("indexer_replica_lag_seconds", "replica" => label).set(lag.as_secs_f64());
+                }
+                result => {
+                    if replica.available.swap(false, Ordering::AcqRel) {
+                        match result {
+                            Ok(Err(e)) => warn!("Read replica {} is unavailable: {}", index, e),
+                            _ => warn!("Read replica {} timed out", index),
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Time since the replica last replayed a transaction, or zero when it
+    /// has replayed everything it received (an idle primary isn't lag)
+    async fn replay_lag(pool: &PgPool) -> Result<Duration> {
+        let seconds: f64 = sqlx::query_scalar(
+            r#"
+            SELECT CASE
+                WHEN NOT pg_is_in_recovery() THEN 0
+                WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0
+                ELSE COALESCE(EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp()), 0)
+            END::float8
+            "#
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        Ok(Duration::from_secs_f64(seconds.max(0.0)))
+    }
+}
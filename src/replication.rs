@@ -0,0 +1,198 @@
+use crate::database::Database;
+use crate::redis_cache::RedisCache;
+use crate::types::ReplicationConfig;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Row};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, error, info};
+
+/// Logical replication consumer for replica-mode instances
+///
+/// Peeks vault_states changes from a wal2json replication slot on the
+/// primary, applies them to the local database and cache, and only then
+/// advances the slot, so a crash mid-apply replays changes instead of
+/// losing them.
+pub struct ReplicationConsumer {
+    primary: PgPool,
+    database: Arc<Database>,
+    cache: Option<Arc<RedisCache>>,
+    config: ReplicationConfig,
+}
+
+/// One row change in wal2json format-version 2
+#[derive(Debug, Deserialize)]
+struct Wal2JsonChange {
+    action: String,
+    #[serde(default)]
+    columns: Vec<Wal2JsonColumn>,
+    #[serde(default)]
+    identity: Vec<Wal2JsonColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Wal2JsonColumn {
+    name: String,
+    value: serde_json::Value,
+}
+
+impl Wal2JsonChange {
+    /// vault_address of the changed row (new tuple for I/U, identity for D)
+    fn vault_address(&self) -> Option<&str> {
+        let columns = if self.columns.is_empty() { &self.identity } else { &self.columns };
+        
+        columns
+            .iter()
+            .find(|column| column.name == "vault_address")
+            .and_then(|column| column.value.as_str())
+    }
+}
+
+impl ReplicationConsumer {
+    /// Connect to the primary and make sure the replication slot exists
+    pub async fn new(
+        config: ReplicationConfig,
+        database: Arc<Database>,
+        cache: Option<Arc<RedisCache>>,
+    ) -> Result<Self> {
+        if config.primary_url.is_empty() {
+            anyhow::bail!("replication.primary_url must be set in replica mode");
+        }
+        
+        let options = database.namespace().connect_options(&config.primary_url)?;
+        let primary = PgPoolOptions::new()
+            .max_connections(2)
+            .connect_with(options)
+            .await
+            .context("Failed to connect to primary database")?;
+        
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM pg_replication_slots WHERE slot_name = $1)"
+        )
+        .bind(&config.slot_name)
+        .fetch_one(&primary)
+        .await?;
+        
+        if !exists {
+            sqlx::query("SELECT pg_create_logical_replication_slot($1, 'wal2json')")
+                .bind(&config.slot_name)
+                .execute(&primary)
+                .await
+                .context("Failed to create logical replication slot on primary")?;
+            info!("Created replication slot '{}' on primary", config.slot_name);
+        }
+        
+        Ok(Self {
+            primary,
+            database,
+            cache,
+            config,
+        })
+    }
+    
+    /// Consume changes until the task is dropped
+    pub async fn run(self) {
+        info!("Replication consumer following slot '{}'", self.config.slot_name);
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms.max(1));
+        
+        loop {
+            match self.poll_once().await {
+                // Keep draining without sleeping while the slot is backlogged
+                Ok(applied) if applied >= self.config.max_changes_per_poll as usize => continue,
+                Ok(_) => {}
+                Err(e) => error!("Replication poll failed: {}", e),
+            }
+            
+            sleep(poll_interval).await;
+        }
+    }
+    
+    /// Apply one page of changes, returning the number of changes consumed
+    async fn poll_once(&self) -> Result<usize> {
+        let rows = sqlx::query(
+            r#"
+            SELECT lsn::text, data
+            FROM pg_logical_slot_peek_changes(
+                $1, NULL, $2,
+                'format-version', '2',
+                'include-transaction', 'false',
+                'add-tables', $3
+            )
+            "#
+        )
+        .bind(&self.config.slot_name)
+        .bind(self.config.max_changes_per_poll as i32)
+        .bind(self.database.namespace().qualified_table("vault_states"))
+        .fetch_all(&self.primary)
+        .await?;
+        
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        
+        let mut upserted = BTreeSet::new();
+        let mut deleted = BTreeSet::new();
+        let mut last_lsn = String::new();
+        
+        for row in &rows {
+            last_lsn = row.try_get(0)?;
+            let data: String = row.try_get(1)?;
+            let change: Wal2JsonChange = serde_json::from_str(&data)
+                .with_context(|| format!("Invalid wal2json change: {}", data))?;
+            
+            let Some(vault_address) = change.vault_address() else {
+                continue;
+            };
+            
+            match change.action.as_str() {
+                "I" | "U" => {
+                    deleted.remove(vault_address);
+                    upserted.insert(vault_address.to_string());
+                }
+                "D" => {
+                    upserted.remove(vault_address);
+                    deleted.insert(vault_address.to_string());
+                }
+                _ => {}
+            }
+        }
+        
+        // Changes only tell us which vaults moved; read their current rows
+        // from the primary so the replica gets fully typed state.
+        let addresses: Vec<String> = upserted.into_iter().collect();
+        if !addresses.is_empty() {
+            let primary = Database::from_pool(self.primary.clone(), self.database.namespace().clone());
+            let states = primary.get_vault_states(&addresses).await?;
+            
+            self.database.batch_upsert_vault_states(&states).await?;
+            if let Some(cache) = &self.cache {
+                cache.batch_set(&states).await?;
+            }
+        }
+        
+        for vault_address in &deleted {
+            self.database.delete_vault_state(vault_address).await?;
+            if let Some(cache) = &self.cache {
+                cache.delete(vault_address).await?;
+            }
+        }
+        
+        sqlx::query("SELECT pg_replication_slot_advance($1, $2::pg_lsn)")
+            .bind(&self.config.slot_name)
+            .bind(&last_lsn)
+            .execute(&self.primary)
+            .await?;
+        
+        debug!(
+            "Replicated {} upserts and {} deletes up to LSN {}",
+            addresses.len(),
+            deleted.len(),
+            last_lsn
+        );
+        
+        Ok(rows.len())
+    }
+}
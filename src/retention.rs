@@ -0,0 +1,120 @@
+use crate::database::Database;
+use crate::types::RetentionConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{error, info, warn};
+
+/// Periodically prunes rows older than their configured retention, and
+/// moves old history versions to the archive table
+///
+/// Removed rows are counted in `indexer_retention_pruned_rows_total`,
+/// labelled by table, and archived ones in
+/// `indexer_history_archived_rows_total`.
+pub struct RetentionPruner {
+    database: Arc<Database>,
+    prune_interval: Duration,
+    account_updates_days: u32,
+    history_days: u32,
+    archive_history_days: u32,
+    batch_size: i64,
+    drop_partitions: bool,
+    timescale: bool,
+}
+
+impl RetentionPruner {
+    /// `drop_partitions` is off when the role may not run DDL; expired
+    /// partitions are then emptied row by row instead. With `timescale`,
+    /// `account_updates` is a hypertable and expired chunks are dropped.
+    pub fn new(
+        config: &RetentionConfig,
+        database: Arc<Database>,
+        drop_partitions: bool,
+        timescale: bool,
+    ) -> Self {
+        if config.archive_history_days > 0
+            && config.history_days > 0
+            && config.history_days <= config.archive_history_days
+        {
+            warn!(
+                "retention.history_days ({}) prunes history before it is archived after {} days",
+                config.history_days, config.archive_history_days
+            );
+        }
+        
+        Self {
+            database,
+            prune_interval: Duration::from_secs(config.interval_secs.max(1)),
+            account_updates_days: config.account_updates_days,
+            history_days: config.history_days,
+            archive_history_days: config.archive_history_days,
+            batch_size: config.batch_size.max(1),
+            drop_partitions,
+            timescale,
+        }
+    }
+    
+    pub async fn run(self) {
+        let mut ticker = interval(self.prune_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        
+        loop {
+            ticker.tick().await;
+            
+            if self.account_updates_days > 0 {
+                let before = Self::cutoff(self.account_updates_days);
+                let result = self.prune_account_updates(before).await;
+                Self::report("account_updates", result);
+            }
+            
+            if self.archive_history_days > 0 {
+                let before = Self::cutoff(self.archive_history_days);
+                match self.database.archive_vault_state_history(before, self.batch_size).await {
+                    Ok(0) => {}
+                    Ok(archived) => {
+                        info!("Archived {} vault_state_history rows", archived);
+                        metrics::counter!("indexer_history_archived_rows_total").increment(archived);
+                    }
+                    Err(e) => error!("Archiving of vault_state_history failed: {}", e),
+                }
+            }
+            
+            if self.history_days > 0 {
+                let before = Self::cutoff(self.history_days);
+                let result = self.database.prune_vault_state_history(before, self.batch_size).await;
+                Self::report("vault_state_history", result);
+            }
+        }
+    }
+    
+    async fn prune_account_updates(&self, before: OffsetDateTime) -> anyhow::Result<u64> {
+        if !self.timescale {
+            return self.database
+                .prune_account_updates(before, self.batch_size, self.drop_partitions)
+                .await;
+        }
+        
+        let dropped = if self.drop_partitions {
+            self.database.drop_account_updates_chunks(before).await?
+        } else {
+            0
+        };
+        Ok(dropped + self.database.prune_account_updates(before, self.batch_size, false).await?)
+    }
+    
+    fn cutoff(days: u32) -> OffsetDateTime {
+        OffsetDateTime::now_utc() - time::Duration::days(days as i64)
+    }
+    
+    fn report(table: &'static str, result: anyhow::Result<u64>) {
+        match result {
+            Ok(0) => {}
+            Ok(pruned) => {
+                info!("Retention pruned {} rows from {}", pruned, table);
+                metrics::counter!("indexer_retention_pruned_rows_total", "table" => table).increment(pruned);
+            }
+            Err(e) => error!("Retention pruning of {} failed: {}", table, e),
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use crate::indexer::Indexer;
+use crate::readiness::WarmingUp;
+use crate::types::{RawAccount, VaultState, VaultStatus};
+use crate::utils::parse_pubkey;
+use base58::{FromBase58, ToBase58};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tracing::error;
+
+/// JSON-RPC error codes, numbered as the Solana RPC numbers them
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const SERVER_ERROR: i64 = -32000;
+const NODE_UNHEALTHY: i64 = -32005;
+const MIN_CONTEXT_SLOT_NOT_REACHED: i64 = -32016;
+
+/// Most calls answered from one batch request
+const MAX_BATCH: usize = 100;
+
+/// Longest account data the RPC still returns base58 encoded
+const MAX_BASE58_BYTES: usize = 128;
+
+/// Answer a Solana JSON-RPC request body, a single call or a batch
+///
+/// Serves `getAccountInfo`, `getMultipleAccounts` and `getProgramAccounts`
+/// for vault accounts from the index, shaped as the validator RPC shapes
+/// them, so client SDKs can read vaults from the indexer unchanged. Data
+/// comes from the `account_updates` write behind each vault's current
+/// state; accounts this indexer doesn't hold read as missing, and
+/// `getProgramAccounts` needs a `memcmp` filter on the vault owner at
+/// offset 0. `commitment` is accepted and ignored, since the index has
+/// one view.
+pub(crate) async fn handle(indexer: &Indexer, max_accounts: usize, body: &[u8]) -> Value {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return error_response(Value::Null, RpcError(PARSE_ERROR, e.to_string())),
+    };
+    
+    match request {
+        Value::Array(calls) if calls.is_empty() => {
+            error_response(Value::Null, RpcError(INVALID_REQUEST, "Empty batch".to_string()))
+        }
+        Value::Array(calls) if calls.len() > MAX_BATCH => error_response(
+            Value::Null,
+            RpcError(INVALID_REQUEST, format!("Batches are limited to {} calls", MAX_BATCH)),
+        ),
+        Value::Array(calls) => {
+            let mut responses = Vec::with_capacity(calls.len());
+            for call in calls {
+                responses.push(answer(indexer, max_accounts, call).await);
+            }
+            Value::Array(responses)
+        }
+        call => answer(indexer, max_accounts, call).await,
+    }
+}
+
+/// A failed call: JSON-RPC error code and message
+struct RpcError(i64, String);
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        if e.is::<WarmingUp>() {
+            return RpcError(NODE_UNHEALTHY, e.to_string());
+        }
+        
+        error!("JSON-RPC call failed: {:#}", e);
+        RpcError(INTERNAL_ERROR, "Internal error".to_string())
+    }
+}
+
+fn invalid_params(message: impl std::fmt::Display) -> RpcError {
+    RpcError(INVALID_PARAMS, format!("Invalid params: {}", message))
+}
+
+#[derive(Deserialize)]
+struct Call {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+}
+
+/// Options object shared by the account methods
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountOptions {
+    encoding: Option<String>,
+    data_slice: Option<DataSlice>,
+    min_context_slot: Option<u64>,
+    #[serde(default)]
+    filters: Vec<Filter>,
+    #[serde(default)]
+    with_context: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataSlice {
+    offset: usize,
+    length: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Filter {
+    Memcmp(Memcmp),
+    DataSize(usize),
+}
+
+#[derive(Debug, Deserialize)]
+struct Memcmp {
+    offset: usize,
+    bytes: String,
+    encoding: Option<String>,
+}
+
+impl Memcmp {
+    fn decode(&self) -> Result<Vec<u8>, RpcError> {
+        match self.encoding.as_deref().unwrap_or("base58") {
+            "base58" => self.bytes.from_base58().map_err(|_| invalid_params("memcmp bytes are not base58")),
+            "base64" => BASE64.decode(&self.bytes).map_err(|_| invalid_params("memcmp bytes are not base64")),
+            other => Err(invalid_params(format!("unknown memcmp encoding '{}'", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Encoding {
+    /// Legacy default: a bare base58 string
+    Binary,
+    Base58,
+    Base64,
+    Base64Zstd,
+}
+
+impl Encoding {
+    fn parse(encoding: Option<&str>) -> Result<Self, RpcError> {
+        match encoding.unwrap_or("binary") {
+            "binary" => Ok(Encoding::Binary),
+            "base58" => Ok(Encoding::Base58),
+            // Vault data has no parser the RPC knows, so it falls back to
+            // base64 as the validator does for unknown programs
+            "base64" | "jsonParsed" => Ok(Encoding::Base64),
+            "base64+zstd" => Ok(Encoding::Base64Zstd),
+            other => Err(invalid_params(format!("unknown encoding '{}'", other))),
+        }
+    }
+    
+    fn encode(&self, data: &[u8]) -> Result<Value, RpcError> {
+        if matches!(self, Encoding::Binary | Encoding::Base58) && data.len() > MAX_BASE58_BYTES {
+            return Err(invalid_params(format!(
+                "encoded binary (base 58) data should be less than {} bytes, please use Base64 encoding",
+                MAX_BASE58_BYTES
+            )));
+        }
+        
+        Ok(match self {
+            Encoding::Binary => json!(data.to_base58()),
+            Encoding::Base58 => json!([data.to_base58(), "base58"]),
+            Encoding::Base64 => json!([BASE64.encode(data), "base64"]),
+            Encoding::Base64Zstd => {
+                let compressed = zstd::bulk::compress(data, 0).map_err(anyhow::Error::from)?;
+                json!([BASE64.encode(compressed), "base64+zstd"])
+            }
+        })
+    }
+}
+
+async fn answer(indexer: &Indexer, max_accounts: usize, call: Value) -> Value {
+    let call: Call = match serde_json::from_value(call) {
+        Ok(call) => call,
+        Err(e) => return error_response(Value::Null, RpcError(INVALID_REQUEST, e.to_string())),
+    };
+    
+    let result = match call.method.as_str() {
+        "getAccountInfo" => get_account_info(indexer, &call.params).await,
+        "getMultipleAccounts" => get_multiple_accounts(indexer, max_accounts, &call.params).await,
+        "getProgramAccounts" => get_program_accounts(indexer, max_accounts, &call.params).await,
+        _ => Err(RpcError(METHOD_NOT_FOUND, "Method not found".to_string())),
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": call.id }),
+        Err(e) => error_response(call.id, e),
+    }
+}
+
+fn error_response(id: Value, RpcError(code, message): RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "error": { "code": code, "message": message }, "id": id })
+}
+
+async fn get_account_info(indexer: &Indexer, params: &[Value]) -> Result<Value, RpcError> {
+    let address = pubkey_param(params.first())?;
+    let options = options_param(params.get(1))?;
+    let encoding = Encoding::parse(options.encoding.as_deref())?;
+    let slot = context_slot(indexer, &options)?;
+    
+    let value = match indexer.get_vault_state(&address).await? {
+        Some(state) => render_account(indexer, &state, encoding, &options).await?,
+        None => Value::Null,
+    };
+    Ok(with_context(slot, value))
+}
+
+async fn get_multiple_accounts(indexer: &Indexer, max_accounts: usize, params: &[Value]) -> Result<Value, RpcError> {
+    let addresses: Vec<String> = params
+        .first()
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(invalid_params)?
+        .ok_or_else(|| invalid_params("expected an array of account addresses"))?;
+    if addresses.len() > max_accounts {
+        return Err(invalid_params(format!("too many accounts provided; max {}", max_accounts)));
+    }
+    for address in &addresses {
+        parse_pubkey(address).map_err(invalid_params)?;
+    }
+    let options = options_param(params.get(1))?;
+    let encoding = Encoding::parse(options.encoding.as_deref())?;
+    let slot = context_slot(indexer, &options)?;
+    
+    let mut states: HashMap<String, VaultState> = indexer
+        .get_vault_states(&addresses)
+        .await?
+        .into_iter()
+        .map(|state| (state.vault_address.to_string(), state))
+        .collect();
+    let mut accounts = Vec::with_capacity(addresses.len());
+    for address in &addresses {
+        accounts.push(match states.remove(address) {
+            Some(state) => render_account(indexer, &state, encoding, &options).await?,
+            None => Value::Null,
+        });
+    }
+    Ok(with_context(slot, Value::Array(accounts)))
+}
+
+async fn get_program_accounts(indexer: &Indexer, max_accounts: usize, params: &[Value]) -> Result<Value, RpcError> {
+    let program_id = pubkey_param(params.first())?;
+    if program_id != indexer.vault_program_id().to_string() {
+        return Err(invalid_params(format!(
+            "this indexer only serves accounts of program {}",
+            indexer.vault_program_id()
+        )));
+    }
+    let options = options_param(params.get(1))?;
+    let encoding = Encoding::parse(options.encoding.as_deref())?;
+    let slot = context_slot(indexer, &options)?;
+    
+    // Vault data starts with the owner, so a memcmp at offset 0 selects an
+    // owner's vaults; anything broader would read the whole index
+    let mut owner = None;
+    for filter in &options.filters {
+        if let Filter::Memcmp(memcmp) = filter {
+            let bytes = memcmp.decode()?;
+            if memcmp.offset == 0 && bytes.len() >= 32 {
+                owner = Some(bytes[..32].to_base58());
+            }
+        }
+    }
+    let owner = owner.ok_or_else(|| {
+        invalid_params("getProgramAccounts needs a memcmp filter on the vault owner at offset 0")
+    })?;
+    
+    let limit = i64::try_from(max_accounts).unwrap_or(i64::MAX).saturating_add(1);
+    let states = indexer.get_vaults_by_owner(&owner, None, limit).await?;
+    if states.len() > max_accounts {
+        return Err(RpcError(
+            SERVER_ERROR,
+            format!("more than {} accounts match; narrow the filters", max_accounts),
+        ));
+    }
+    
+    let mut accounts = Vec::new();
+    for state in &states {
+        let raw = load_account(indexer, state).await?;
+        if !matches_filters(&raw.data, &options.filters)? {
+            continue;
+        }
+        accounts.push(json!({
+            "pubkey": state.vault_address.to_string(),
+            "account": account_json(&raw, encoding, &options)?,
+        }));
+    }
+    
+    let accounts = Value::Array(accounts);
+    Ok(if options.with_context { with_context(slot, accounts) } else { accounts })
+}
+
+/// The account behind a vault state, or null for a closed vault, which no
+/// longer exists on chain
+async fn render_account(
+    indexer: &Indexer,
+    state: &VaultState,
+    encoding: Encoding,
+    options: &AccountOptions,
+) -> Result<Value, RpcError> {
+    if state.status == VaultStatus::Closed {
+        return Ok(Value::Null);
+    }
+    let raw = load_account(indexer, state).await?;
+    account_json(&raw, encoding, options)
+}
+
+/// The recorded write behind a vault state
+///
+/// Snapshot and backfill writes aren't recorded and retention removes old
+/// ones, so a vault that hasn't changed live since can't be served.
+async fn load_account(indexer: &Indexer, state: &VaultState) -> Result<RawAccount, RpcError> {
+    indexer
+        .get_raw_vault_account(state)
+        .await?
+        .filter(|raw| raw.lamports.is_some() && raw.owner.is_some())
+        .ok_or_else(|| {
+            RpcError(
+                SERVER_ERROR,
+                format!("account data for {} at slot {} is not retained", state.vault_address, state.slot),
+            )
+        })
+}
+
+fn account_json(raw: &RawAccount, encoding: Encoding, options: &AccountOptions) -> Result<Value, RpcError> {
+    let data = match &options.data_slice {
+        Some(slice) => {
+            let start = slice.offset.min(raw.data.len());
+            let end = start.saturating_add(slice.length).min(raw.data.len());
+            &raw.data[start..end]
+        }
+        None => &raw.data[..],
+    };
+    
+    Ok(json!({
+        "data": encoding.encode(data)?,
+        "executable": raw.executable.unwrap_or(false),
+        "lamports": raw.lamports,
+        "owner": raw.owner.map(|owner| owner.to_string()),
+        "rentEpoch": raw.rent_epoch,
+        "space": raw.data.len(),
+    }))
+}
+
+fn matches_filters(data: &[u8], filters: &[Filter]) -> Result<bool, RpcError> {
+    for filter in filters {
+        let matched = match filter {
+            Filter::DataSize(size) => data.len() == *size,
+            Filter::Memcmp(memcmp) => {
+                let bytes = memcmp.decode()?;
+                data.get(memcmp.offset..).map_or(false, |rest| rest.starts_with(&bytes))
+            }
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Slot reported as the response context: the newest indexed slot
+fn context_slot(indexer: &Indexer, options: &AccountOptions) -> Result<u64, RpcError> {
+    let slot = indexer.get_stats().last_indexed_slot;
+    if let Some(min_context_slot) = options.min_context_slot {
+        if slot < min_context_slot {
+            return Err(RpcError(
+                MIN_CONTEXT_SLOT_NOT_REACHED,
+                format!("Minimum context slot has not been reached: context slot {}", slot),
+            ));
+        }
+    }
+    Ok(slot)
+}
+
+fn with_context(slot: u64, value: Value) -> Value {
+    json!({ "context": { "slot": slot }, "value": value })
+}
+
+fn pubkey_param(param: Option<&Value>) -> Result<String, RpcError> {
+    let address = param
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid_params("expected an account address"))?;
+    parse_pubkey(address).map_err(invalid_params)?;
+    Ok(address.to_string())
+}
+
+fn options_param(param: Option<&Value>) -> Result<AccountOptions, RpcError> {
+    match param {
+        None | Some(Value::Null) => Ok(AccountOptions::default()),
+        Some(options) => serde_json::from_value(options.clone()).map_err(invalid_params),
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+type Flight<T> = Arc<OnceCell<Result<T, Arc<anyhow::Error>>>>;
+
+/// Coalesces concurrent loads of the same key into one
+///
+/// When a hot vault drops out of the cache, every reader misses at once.
+/// The first to ask for a key runs the load; readers arriving while it
+/// runs wait for its result instead of querying the database themselves.
+/// If the loading reader is cancelled, a waiting one takes over. The key
+/// is released as soon as the load finishes, so later reads start fresh.
+pub struct SingleFlight<T> {
+    inflight: Mutex<HashMap<String, Flight<T>>>,
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Result of `load` for `key`, shared with every concurrent caller
+    /// for the same key; only one of them runs `load`
+    pub async fn run<F, Fut>(&self, key: &str, load: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let flight = {
+            let mut inflight = self.inflight.lock().unwrap();
+            match inflight.get(key) {
+                Some(flight) => {
+                    metrics::counter!("indexer_coalesced_reads_total").increment(1);
+                    flight.clone()
+                }
+                None => {
+                    let flight: Flight<T> = Arc::new(OnceCell::new());
+                    inflight.insert(key.to_string(), flight.clone());
+                    flight
+                }
+            }
+        };
+        
+        let result = flight
+            .get_or_init(|| async move { load().await.map_err(Arc::new) })
+            .await
+            .clone();
+        
+        let mut inflight = self.inflight.lock().unwrap();
+        if inflight.get(key).is_some_and(|current| Arc::ptr_eq(current, &flight)) {
+            inflight.remove(key);
+        }
+        drop(inflight);
+        
+        result.map_err(|e| anyhow::anyhow!("{:#}", e))
+    }
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
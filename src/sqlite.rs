@@ -0,0 +1,693 @@
+use crate::database::{PoolSettings, QueryTimer};
+use crate::store::VaultStore;
+use crate::types::{
+    AssetBalance, Permission, SchemaCheck, SchemaVersion, ShardCheckpoint, VaultClosure, VaultCursor, VaultFilter,
+    VaultPage, VaultSort, VaultState, VaultStatus,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::types::Json;
+use sqlx::{QueryBuilder, Row, Sqlite, SqliteConnection};
+use std::collections::HashMap;
+use std::time::Duration;
+use time::{OffsetDateTime, UtcOffset};
+use tracing::warn;
+
+/// Column list matching `SqliteStore::vault_state_from_row`
+const VAULT_STATE_COLUMNS: &str = "vault_address, owner, balance, assets, permissions, \
+    last_updated, slot, write_version, status, closed_at_slot";
+
+/// Current time in the format written by `timestamp`
+const NOW: &str = "strftime('%Y-%m-%dT%H:%M:%f000000Z', 'now')";
+
+/// Vault store backed by a single SQLite file, for local development
+///
+/// Selected with `[store] backend = "sqlite"` in a build with the `sqlite`
+/// feature, so parser changes can be run against a local file. It only
+/// holds vault states and shard checkpoints: history, tags, dead letters
+/// and the Postgres-only features stay on `Database`.
+///
+/// SQLite allows one writer at a time, so shard flushes queue on the
+/// database lock; expect far lower throughput than Postgres.
+pub struct SqliteStore {
+    pool: SqlitePool,
+    slow_query_threshold: Option<Duration>,
+}
+
+impl SqliteStore {
+    /// Open the database file at `path`, creating it if missing
+    ///
+    /// The journal is switched to WAL so readers don't block the writer,
+    /// and writers wait up to the acquire timeout for the database lock.
+    /// Pending migrations are applied if `run_migrations` is set, and
+    /// `schema_check` decides what a mismatched schema does afterwards.
+    pub async fn connect(
+        path: &str,
+        settings: &PoolSettings,
+        run_migrations: bool,
+        schema_check: SchemaCheck,
+    ) -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(settings.acquire_timeout);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(settings.max_connections)
+            .min_connections(settings.min_connections)
+            .acquire_timeout(settings.acquire_timeout)
+            .idle_timeout(settings.idle_timeout)
+            .connect_with(options)
+            .await?;
+        
+        let store = Self {
+            pool,
+            slow_query_threshold: settings.slow_query_threshold,
+        };
+        store.prepare_schema(run_migrations, schema_check).await?;
+        
+        Ok(store)
+    }
+    
+    /// Timer for one call, reported when dropped; `binds` summarizes the
+    /// call's parameters and is only evaluated for slow calls
+    fn time_query<F: FnOnce() -> String>(&self, query: &'static str, binds: F) -> QueryTimer<F> {
+        QueryTimer::new(query, binds, self.slow_query_threshold)
+    }
+    
+    /// Schema version recorded in the database and the one this binary's
+    /// SQLite migrations reach
+    pub async fn schema_version(&self) -> Result<SchemaVersion> {
+        let _timer = self.time_query("schema_version", String::new);
+        let binary = Self::migrator()
+            .iter()
+            .map(|migration| migration.version)
+            .max()
+            .unwrap_or(0);
+        
+        let tracked: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let database = if tracked {
+            sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success")
+                .fetch_one(&self.pool)
+                .await?
+        } else {
+            None
+        };
+        
+        Ok(SchemaVersion { database, binary })
+    }
+    
+    /// Embedded SQLite migrations, numbered independently of the Postgres
+    /// ones
+    fn migrator() -> Migrator {
+        let mut migrator = sqlx::migrate!("./migrations_sqlite");
+        migrator.set_ignore_missing(true);
+        migrator
+    }
+    
+    /// Bring the schema up to date and apply the version policy
+    async fn prepare_schema(&self, run_migrations: bool, check: SchemaCheck) -> Result<()> {
+        let mut version = self.schema_version().await?;
+        
+        if run_migrations && !version.is_ahead() {
+            Self::migrator().run(&self.pool).await?;
+            version = self.schema_version().await?;
+        }
+        
+        let problem = if version.is_ahead() {
+            format!(
+                "database schema version {} is ahead of this binary ({}); upgrade the indexer",
+                version.database.unwrap_or(0),
+                version.binary
+            )
+        } else if version.is_behind() {
+            format!(
+                "database schema version {} is behind this binary ({}); run migrations",
+                version.database.unwrap_or(0),
+                version.binary
+            )
+        } else {
+            return Ok(());
+        };
+        
+        match check {
+            SchemaCheck::Strict => anyhow::bail!(problem),
+            SchemaCheck::Warn => {
+                warn!("{}", problem);
+                Ok(())
+            }
+        }
+    }
+    
+    /// Fixed-width UTC text, so stored timestamps compare and sort as
+    /// strings
+    fn timestamp(time: OffsetDateTime) -> String {
+        let time = time.to_offset(UtcOffset::UTC);
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+            time.year(),
+            time.month() as u8,
+            time.day(),
+            time.hour(),
+            time.minute(),
+            time.second(),
+            time.nanosecond()
+        )
+    }
+    
+    /// JSON array of strings, for `json_each` in place of Postgres arrays
+    fn json_list<T: serde::Serialize>(values: &[T]) -> Result<String> {
+        Ok(serde_json::to_string(values)?)
+    }
+    
+    /// Rewrite the `vault_assets` rows of the given vaults from their
+    /// states; closed vaults keep none
+    async fn replace_vault_assets(&self, conn: &mut SqliteConnection, states: &[&VaultState]) -> Result<()> {
+        for state in states {
+            let address = state.vault_address.to_string();
+            sqlx::query("DELETE FROM vault_assets WHERE vault_address = $1")
+                .bind(&address)
+                .execute(&mut *conn)
+                .await?;
+            
+            if state.status == VaultStatus::Closed {
+                continue;
+            }
+            for (mint, asset) in &state.assets {
+                sqlx::query("INSERT INTO vault_assets (vault_address, mint, amount, decimals) VALUES ($1, $2, $3, $4)")
+                    .bind(&address)
+                    .bind(mint)
+                    .bind(asset.amount as i64)
+                    .bind(asset.decimals as i16)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Rewrite the `vault_permissions` rows of the given vaults from their
+    /// states; closed vaults keep none
+    async fn replace_vault_permissions(&self, conn: &mut SqliteConnection, states: &[&VaultState]) -> Result<()> {
+        for state in states {
+            let address = state.vault_address.to_string();
+            sqlx::query("DELETE FROM vault_permissions WHERE vault_address = $1")
+                .bind(&address)
+                .execute(&mut *conn)
+                .await?;
+            
+            if state.status == VaultStatus::Closed {
+                continue;
+            }
+            for permission in &state.permissions {
+                sqlx::query(
+                    r#"
+                    INSERT INTO vault_permissions (vault_address, pubkey, role, role_rank)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT DO NOTHING
+                    "#
+                )
+                .bind(&address)
+                .bind(&permission.pubkey.to_bytes()[..])
+                .bind(permission.permission_type.as_str())
+                .bind(permission.permission_type.rank())
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn vault_state_from_row(row: &SqliteRow) -> Result<VaultState> {
+        let vault_address_str: String = row.try_get(0)?;
+        let owner_str: String = row.try_get(1)?;
+        let balance: i64 = row.try_get(2)?;
+        let Json(assets): Json<HashMap<String, AssetBalance>> = row.try_get(3)?;
+        let Json(permissions): Json<Vec<Permission>> = row.try_get(4)?;
+        let last_updated: OffsetDateTime = row.try_get(5)?;
+        let slot: i64 = row.try_get(6)?;
+        let write_version: i64 = row.try_get(7)?;
+        let status: String = row.try_get(8)?;
+        let closed_at_slot: Option<i64> = row.try_get(9)?;
+        
+        Ok(VaultState {
+            vault_address: vault_address_str.parse()?,
+            owner: owner_str.parse()?,
+            balance: balance as u64,
+            assets,
+            permissions,
+            last_updated,
+            slot: slot as u64,
+            write_version: write_version as u64,
+            status: status.parse()?,
+            closed_at_slot: closed_at_slot.map(|slot| slot as u64),
+        })
+    }
+}
+
+#[async_trait]
+impl VaultStore for SqliteStore {
+    async fn upsert_vault_state(&self, state: &VaultState) -> Result<()> {
+        let _timer = self.time_query("upsert_vault_state", || {
+            format!("vault_address={}", state.vault_address)
+        });
+        let mut tx = self.pool.begin().await?;
+        
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO vault_states (
+                vault_address, owner, balance, assets, permissions,
+                last_updated, slot, write_version, state_hash, status, closed_at_slot, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, {now})
+            ON CONFLICT (vault_address) DO UPDATE SET
+                owner = excluded.owner,
+                balance = excluded.balance,
+                assets = excluded.assets,
+                permissions = excluded.permissions,
+                last_updated = excluded.last_updated,
+                slot = excluded.slot,
+                write_version = excluded.write_version,
+                state_hash = excluded.state_hash,
+                status = excluded.status,
+                closed_at_slot = excluded.closed_at_slot,
+                updated_at = {now}
+            "#,
+            now = NOW
+        ))
+        .bind(state.vault_address.to_string())
+        .bind(state.owner.to_string())
+        .bind(state.balance as i64)
+        .bind(Json(&state.assets))
+        .bind(Json(&state.permissions))
+        .bind(Self::timestamp(state.last_updated))
+        .bind(state.slot as i64)
+        .bind(state.write_version as i64)
+        .bind(&state.content_hash()?[..])
+        .bind(state.status.as_str())
+        .bind(state.closed_at_slot.map(|slot| slot as i64))
+        .execute(&mut *tx)
+        .await?;
+        
+        self.replace_vault_assets(&mut tx, &[state]).await?;
+        self.replace_vault_permissions(&mut tx, &[state]).await?;
+        
+        tx.commit().await?;
+        Ok(())
+    }
+    
+    async fn batch_upsert_vault_states(&self, states: &[VaultState]) -> Result<Vec<bool>> {
+        let _timer = self.time_query("batch_upsert_vault_states", || {
+            format!("states=[{} items]", states.len())
+        });
+        let mut tx = self.pool.begin().await?;
+        let mut applied = Vec::with_capacity(states.len());
+        
+        for state in states {
+            let row = sqlx::query(&format!(
+                r#"
+                INSERT INTO vault_states (
+                    vault_address, owner, balance, assets, permissions,
+                    last_updated, slot, write_version, state_hash, status, closed_at_slot, updated_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, {now})
+                ON CONFLICT (vault_address) DO UPDATE SET
+                    owner = excluded.owner,
+                    balance = excluded.balance,
+                    assets = excluded.assets,
+                    permissions = excluded.permissions,
+                    last_updated = excluded.last_updated,
+                    slot = excluded.slot,
+                    write_version = excluded.write_version,
+                    state_hash = excluded.state_hash,
+                    status = excluded.status,
+                    closed_at_slot = excluded.closed_at_slot,
+                    updated_at = {now}
+                WHERE (vault_states.slot, vault_states.write_version)
+                    < (excluded.slot, excluded.write_version)
+                RETURNING 1
+                "#,
+                now = NOW
+            ))
+            .bind(state.vault_address.to_string())
+            .bind(state.owner.to_string())
+            .bind(state.balance as i64)
+            .bind(Json(&state.assets))
+            .bind(Json(&state.permissions))
+            .bind(Self::timestamp(state.last_updated))
+            .bind(state.slot as i64)
+            .bind(state.write_version as i64)
+            .bind(&state.content_hash()?[..])
+            .bind(state.status.as_str())
+            .bind(state.closed_at_slot.map(|slot| slot as i64))
+            .fetch_optional(&mut *tx)
+            .await?;
+            
+            applied.push(row.is_some());
+        }
+        
+        let applied_states: Vec<&VaultState> = states
+            .iter()
+            .zip(&applied)
+            .filter_map(|(state, applied)| applied.then_some(state))
+            .collect();
+        self.replace_vault_assets(&mut tx, &applied_states).await?;
+        self.replace_vault_permissions(&mut tx, &applied_states).await?;
+        
+        tx.commit().await?;
+        Ok(applied)
+    }
+    
+    /// Closed vaults are left out, so a recreated vault is always written
+    async fn get_state_hashes(&self, vault_addresses: &[String]) -> Result<HashMap<String, Vec<u8>>> {
+        let _timer = self.time_query("get_state_hashes", || {
+            format!("vault_addresses=[{} items]", vault_addresses.len())
+        });
+        let rows: Vec<(String, Vec<u8>)> = sqlx::query_as(
+            "SELECT vault_address, state_hash FROM vault_states \
+             WHERE vault_address IN (SELECT value FROM json_each($1)) AND state_hash IS NOT NULL \
+               AND status = 'active'"
+        )
+        .bind(Self::json_list(vault_addresses)?)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows.into_iter().collect())
+    }
+    
+    /// A closure only applies to an active vault whose stored version is
+    /// older, so a late closure can't hide a vault that was recreated
+    async fn close_vaults(&self, closures: &[VaultClosure]) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("close_vaults", || {
+            format!("closures=[{} items]", closures.len())
+        });
+        let mut tx = self.pool.begin().await?;
+        let mut closed = Vec::new();
+        
+        for closure in closures {
+            let row = sqlx::query(&format!(
+                r#"
+                UPDATE vault_states SET
+                    status = 'closed',
+                    closed_at_slot = $2,
+                    slot = $2,
+                    write_version = $3,
+                    last_updated = {now},
+                    updated_at = {now}
+                WHERE vault_address = $1
+                  AND status = 'active'
+                  AND (slot, write_version) < ($2, $3)
+                RETURNING {columns}
+                "#,
+                now = NOW,
+                columns = VAULT_STATE_COLUMNS
+            ))
+            .bind(closure.vault_address.to_string())
+            .bind(closure.slot as i64)
+            .bind(closure.write_version as i64)
+            .fetch_optional(&mut *tx)
+            .await?;
+            
+            if let Some(row) = row {
+                closed.push(Self::vault_state_from_row(&row)?);
+            }
+        }
+        
+        let closed_refs: Vec<&VaultState> = closed.iter().collect();
+        self.replace_vault_assets(&mut tx, &closed_refs).await?;
+        self.replace_vault_permissions(&mut tx, &closed_refs).await?;
+        
+        tx.commit().await?;
+        Ok(closed)
+    }
+    
+    async fn get_vault_state(&self, vault_address: &str) -> Result<Option<VaultState>> {
+        let _timer = self.time_query("get_vault_state", || {
+            format!("vault_address={}", vault_address)
+        });
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM vault_states WHERE vault_address = $1",
+            VAULT_STATE_COLUMNS
+        ))
+        .bind(vault_address)
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        row.map(|row| Self::vault_state_from_row(&row)).transpose()
+    }
+    
+    async fn get_vault_states(&self, vault_addresses: &[String]) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_vault_states", || {
+            format!("vault_addresses=[{} items]", vault_addresses.len())
+        });
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM vault_states WHERE vault_address IN (SELECT value FROM json_each($1))",
+            VAULT_STATE_COLUMNS
+        ))
+        .bind(Self::json_list(vault_addresses)?)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    async fn get_active_vault_addresses(&self) -> Result<Vec<String>> {
+        let _timer = self.time_query("get_active_vault_addresses", String::new);
+        let addresses = sqlx::query_scalar(
+            "SELECT vault_address FROM vault_states WHERE status = 'active'"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(addresses)
+    }
+    
+    async fn get_vault_balances(&self) -> Result<Vec<(String, u64)>> {
+        let _timer = self.time_query("get_vault_balances", String::new);
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT vault_address, balance FROM vault_states WHERE status = 'active'"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(rows.into_iter().map(|(address, balance)| (address, balance as u64)).collect())
+    }
+    
+    /// SQLite compares text bytewise, matching the Redis owner index
+    async fn get_vaults_by_owner(&self, owner: &str, after: Option<&str>, limit: i64) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_vaults_by_owner", || {
+            format!("owner={}, after={:?}, limit={}", owner, after, limit)
+        });
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {} FROM vault_states
+            WHERE owner = $1 AND status = 'active' AND vault_address > $2
+            ORDER BY vault_address
+            LIMIT $3
+            "#,
+            VAULT_STATE_COLUMNS
+        ))
+        .bind(owner)
+        .bind(after.unwrap_or_default())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    async fn get_vault_addresses_by_owners(&self, owners: &[String]) -> Result<Vec<String>> {
+        let _timer = self.time_query("get_vault_addresses_by_owners", || {
+            format!("owners=[{} items]", owners.len())
+        });
+        let addresses = sqlx::query_scalar(
+            "SELECT vault_address FROM vault_states \
+             WHERE owner IN (SELECT value FROM json_each($1)) AND status = 'active' \
+             ORDER BY vault_address"
+        )
+        .bind(Self::json_list(owners)?)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(addresses)
+    }
+    
+    async fn get_top_vaults_by_balance(&self, n: i64, mint: Option<&str>) -> Result<Vec<VaultState>> {
+        let _timer = self.time_query("get_top_vaults_by_balance", || {
+            format!("n={}, mint={:?}", n, mint)
+        });
+        let rows = match mint {
+            None => {
+                sqlx::query(&format!(
+                    "SELECT {} FROM vault_states WHERE status = 'active' \
+                     ORDER BY balance DESC, vault_address DESC LIMIT $1",
+                    VAULT_STATE_COLUMNS
+                ))
+                .bind(n)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            Some(mint) => {
+                sqlx::query(&format!(
+                    r#"
+                    SELECT {} FROM vault_states
+                    JOIN (
+                        SELECT vault_address, amount FROM vault_assets
+                        WHERE mint = $1
+                        ORDER BY amount DESC
+                        LIMIT $2
+                    ) top USING (vault_address)
+                    ORDER BY top.amount DESC, vault_address
+                    "#,
+                    VAULT_STATE_COLUMNS
+                ))
+                .bind(mint)
+                .bind(n)
+                .fetch_all(&self.pool)
+                .await?
+            }
+        };
+        
+        rows.iter().map(Self::vault_state_from_row).collect()
+    }
+    
+    /// Tags are kept by `Database`, so filtering on them isn't supported
+    async fn list_vaults(
+        &self,
+        filter: &VaultFilter,
+        sort_by: VaultSort,
+        cursor: Option<&VaultCursor>,
+        limit: i64,
+    ) -> Result<VaultPage> {
+        let _timer = self.time_query("list_vaults", || {
+            format!("sort_by={}, cursor={:?}, limit={}", sort_by.as_str(), cursor, limit)
+        });
+        let mut query = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT {} FROM vault_states WHERE TRUE",
+            VAULT_STATE_COLUMNS
+        ));
+        
+        if !filter.include_closed {
+            query.push(" AND status = 'active'");
+        }
+        if !filter.vault_addresses.is_empty() {
+            query.push(" AND vault_address IN (SELECT value FROM json_each(")
+                .push_bind(Self::json_list(&filter.vault_addresses)?)
+                .push("))");
+        }
+        if !filter.owners.is_empty() {
+            query.push(" AND owner IN (SELECT value FROM json_each(")
+                .push_bind(Self::json_list(&filter.owners)?)
+                .push("))");
+        }
+        if !filter.tags.is_empty() {
+            anyhow::bail!("filtering vaults by tag needs the Postgres store");
+        }
+        if let Some(min_balance) = filter.min_balance {
+            query.push(" AND balance >= ").push_bind(min_balance as i64);
+        }
+        
+        if let Some(cursor) = cursor {
+            if cursor.sort_by() != sort_by {
+                anyhow::bail!(
+                    "cursor continues a listing sorted by {}, not {}",
+                    cursor.sort_by().as_str(),
+                    sort_by.as_str()
+                );
+            }
+            query.push(format!(" AND ({}, vault_address) < (", sort_by.as_str()));
+            match cursor {
+                VaultCursor::Balance { balance, .. } => query.push_bind(*balance as i64),
+                VaultCursor::LastUpdated { last_updated, .. } => query.push_bind(Self::timestamp(*last_updated)),
+                VaultCursor::Slot { slot, .. } => query.push_bind(*slot as i64),
+            };
+            query.push(", ").push_bind(cursor.vault_address().to_string()).push(")");
+        }
+        
+        query.push(format!(" ORDER BY {} DESC, vault_address DESC LIMIT ", sort_by.as_str()))
+            .push_bind(limit);
+        
+        let rows = query.build().fetch_all(&self.pool).await?;
+        let vaults = rows
+            .iter()
+            .map(Self::vault_state_from_row)
+            .collect::<Result<Vec<_>>>()?;
+        let next_cursor = if vaults.len() as i64 == limit {
+            vaults.last().map(|last| VaultCursor::after(sort_by, last))
+        } else {
+            None
+        };
+        
+        Ok(VaultPage { vaults, next_cursor })
+    }
+    
+    async fn upsert_shard_checkpoint(&self, shard: usize, slot: u64) -> Result<()> {
+        let _timer = self.time_query("upsert_shard_checkpoint", || {
+            format!("shard={}, slot={}", shard, slot)
+        });
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO shard_checkpoints (shard, slot)
+            VALUES ($1, $2)
+            ON CONFLICT (shard) DO UPDATE SET
+                slot = MAX(shard_checkpoints.slot, excluded.slot),
+                updated_at = {}
+            "#,
+            NOW
+        ))
+        .bind(shard as i32)
+        .bind(slot as i64)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    async fn get_shard_checkpoints(&self) -> Result<Vec<ShardCheckpoint>> {
+        let _timer = self.time_query("get_shard_checkpoints", String::new);
+        let rows = sqlx::query("SELECT shard, slot, updated_at FROM shard_checkpoints ORDER BY shard")
+            .fetch_all(&self.pool)
+            .await?;
+        
+        rows.iter()
+            .map(|row| {
+                let shard: i32 = row.try_get(0)?;
+                let slot: i64 = row.try_get(1)?;
+                
+                Ok(ShardCheckpoint {
+                    shard: shard as usize,
+                    slot: slot as u64,
+                    updated_at: row.try_get(2)?,
+                })
+            })
+            .collect()
+    }
+    
+    /// Replaces the checkpoints in one transaction
+    async fn reset_shard_checkpoints(&self, shards: usize, slot: u64) -> Result<()> {
+        let _timer = self.time_query("reset_shard_checkpoints", || {
+            format!("shards={}, slot={}", shards, slot)
+        });
+        let mut tx = self.pool.begin().await?;
+        
+        sqlx::query("DELETE FROM shard_checkpoints")
+            .execute(&mut *tx)
+            .await?;
+        for shard in 0..shards {
+            sqlx::query("INSERT INTO shard_checkpoints (shard, slot) VALUES ($1, $2)")
+                .bind(shard as i32)
+                .bind(slot as i64)
+                .execute(&mut *tx)
+                .await?;
+        }
+        
+        tx.commit().await?;
+        Ok(())
+    }
+}
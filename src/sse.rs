@@ -0,0 +1,111 @@
+use crate::database::SYNC_SETTLE_SLOTS;
+use crate::events::{self, EventFilter};
+use crate::indexer::Indexer;
+use crate::projection::render_event;
+use crate::types::VaultEvent;
+use anyhow::{Context, Result};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::debug;
+use utoipa::IntoParams;
+
+/// Events queued per stream while the client reads slowly
+const STREAM_BUFFER: usize = 256;
+
+pub type EventStream = Sse<ReceiverStream<Result<Event, Infallible>>>;
+
+/// Query of `GET /events`: comma-separated vault addresses and owners
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct EventsQuery {
+    /// Comma-separated vault addresses to follow
+    vault: Option<String>,
+    /// Comma-separated owners whose vaults to follow
+    owner: Option<String>,
+}
+
+/// Stream the vault events matching `query` as server-sent events
+///
+/// Each event is named after its `type` (`update` or `owner_changed`),
+/// carries the same JSON as a WebSocket message and has the id
+/// `slot:write_version`. A client resuming with `Last-Event-ID` first gets
+/// the current state of every matching vault written since that slot, less
+/// `SYNC_SETTLE_SLOTS` because events aren't strictly ordered across
+/// shards. A client that falls behind the broadcast channel is
+/// disconnected, so it resumes the same way.
+pub fn stream(indexer: Arc<Indexer>, query: EventsQuery, last_event_id: Option<&str>) -> Result<EventStream> {
+    let filter = EventFilter::for_subscription(split(query.vault), split(query.owner))?;
+    let resume_slot = last_event_id.map(parse_event_id).transpose()?;
+    
+    let receiver = indexer.subscribe_events();
+    let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(async move {
+        metrics::gauge!("indexer_sse_streams").increment(1.0);
+        if let Err(e) = forward(&indexer, &filter, resume_slot, receiver, &tx).await {
+            debug!("Event stream ended: {}", e);
+        }
+        metrics::gauge!("indexer_sse_streams").decrement(1.0);
+    });
+    
+    Ok(Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default()))
+}
+
+async fn forward(
+    indexer: &Indexer,
+    filter: &EventFilter,
+    resume_slot: Option<u64>,
+    mut receiver: broadcast::Receiver<VaultEvent>,
+    tx: &mpsc::Sender<Result<Event, Infallible>>,
+) -> Result<()> {
+    if let Some(slot) = resume_slot {
+        for state in events::states_since(indexer, filter, slot.saturating_sub(SYNC_SETTLE_SLOTS)).await? {
+            send(tx, &VaultEvent::Updated(state)).await?;
+        }
+    }
+    
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Ok(event) if filter.matches(&event) => send(tx, &event).await?,
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    metrics::counter!("indexer_sse_events_skipped_total").increment(skipped);
+                    anyhow::bail!("fell {} events behind", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            _ = tx.closed() => return Ok(()),
+        }
+    }
+}
+
+async fn send(tx: &mpsc::Sender<Result<Event, Infallible>>, event: &VaultEvent) -> Result<()> {
+    let (name, slot, write_version) = match event {
+        VaultEvent::Updated(state) => ("update", state.slot, state.write_version),
+        VaultEvent::OwnerChanged(change) => ("owner_changed", change.slot, change.write_version),
+    };
+    let event = Event::default()
+        .event(name)
+        .id(format!("{}:{}", slot, write_version))
+        .data(render_event(event).to_string());
+    
+    tx.send(Ok(event)).await.map_err(|_| anyhow::anyhow!("client disconnected"))
+}
+
+/// Slot of a `slot:write_version` event id
+fn parse_event_id(id: &str) -> Result<u64> {
+    let slot = id.split(':').next().unwrap_or_default();
+    slot.parse().with_context(|| format!("Invalid Last-Event-ID '{}'", id))
+}
+
+fn split(list: Option<String>) -> Vec<String> {
+    list.iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
@@ -0,0 +1,189 @@
+use crate::batching::LatencyWindow;
+use crate::types::IndexingStats;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Number of recent flushes kept for latency percentiles
+const LATENCY_WINDOW: usize = 4096;
+
+/// Counters shared by the indexer front-end and its shard workers
+pub struct PipelineStats {
+    started_at: Instant,
+    updates_received: AtomicU64,
+    updates_flushed: AtomicU64,
+    /// Updates of batches that failed to write and were spooled to the WAL
+    updates_spooled: AtomicU64,
+    /// Updates of batches that failed to write and were dead-lettered
+    updates_dead_lettered: AtomicU64,
+    /// Spooled updates dead-lettered after failing to replay
+    spool_dead_lettered: AtomicU64,
+    /// Spooled updates written by WAL replays
+    updates_replayed: AtomicU64,
+    updates_discarded: AtomicU64,
+    batches_flushed: AtomicU64,
+    last_indexed_slot: AtomicU64,
+    total_flush_micros: AtomicU64,
+    flush_latency: Mutex<LatencyWindow>,
+    end_to_end_latency: Mutex<LatencyWindow>,
+}
+
+/// Point-in-time latency percentiles
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatencyPercentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl PipelineStats {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            updates_received: AtomicU64::new(0),
+            updates_flushed: AtomicU64::new(0),
+            updates_spooled: AtomicU64::new(0),
+            updates_dead_lettered: AtomicU64::new(0),
+            spool_dead_lettered: AtomicU64::new(0),
+            updates_replayed: AtomicU64::new(0),
+            updates_discarded: AtomicU64::new(0),
+            batches_flushed: AtomicU64::new(0),
+            last_indexed_slot: AtomicU64::new(0),
+            total_flush_micros: AtomicU64::new(0),
+            flush_latency: Mutex::new(LatencyWindow::new(LATENCY_WINDOW)),
+            end_to_end_latency: Mutex::new(LatencyWindow::new(LATENCY_WINDOW)),
+        }
+    }
+    
+    pub fn record_received(&self) {
+        self.updates_received.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a flush that was written
+    ///
+    /// `oldest_age` is how long the oldest update in the batch waited from
+    /// arrival until the flush finished, i.e. the worst end-to-end latency.
+    pub fn record_flush(&self, updates: usize, max_slot: u64, flush: Duration, oldest_age: Duration) {
+        self.updates_flushed.fetch_add(updates as u64, Ordering::Relaxed);
+        self.batches_flushed.fetch_add(1, Ordering::Relaxed);
+        self.last_indexed_slot.fetch_max(max_slot, Ordering::Relaxed);
+        self.total_flush_micros.fetch_add(flush.as_micros() as u64, Ordering::Relaxed);
+        
+        self.flush_latency.lock().unwrap().record(flush);
+        self.end_to_end_latency.lock().unwrap().record(oldest_age);
+    }
+    
+    /// Record a flush that failed to write and was spooled to the WAL
+    pub fn record_spooled(&self, updates: usize) {
+        self.updates_spooled.fetch_add(updates as u64, Ordering::Relaxed);
+    }
+    
+    /// Record a flush that failed to write and was dead-lettered
+    pub fn record_dead_lettered(&self, updates: usize) {
+        self.updates_dead_lettered.fetch_add(updates as u64, Ordering::Relaxed);
+    }
+    
+    /// Record spooled updates dead-lettered after failing to replay
+    pub fn record_spool_dead_lettered(&self, updates: usize) {
+        self.spool_dead_lettered.fetch_add(updates as u64, Ordering::Relaxed);
+    }
+    
+    /// Record spooled updates written by a WAL replay
+    pub fn record_replayed(&self, updates: usize, max_slot: u64) {
+        self.updates_replayed.fetch_add(updates as u64, Ordering::Relaxed);
+        self.last_indexed_slot.fetch_max(max_slot, Ordering::Relaxed);
+    }
+    
+    /// Count updates dropped after being received, which no one will write
+    pub fn record_discarded(&self, updates: usize) {
+        self.updates_discarded.fetch_add(updates as u64, Ordering::Relaxed);
+    }
+    
+    pub fn updates_received(&self) -> u64 {
+        self.updates_received.load(Ordering::Relaxed)
+    }
+    
+    /// Updates written as they were flushed, leaving out WAL replays
+    pub fn updates_flushed(&self) -> u64 {
+        self.updates_flushed.load(Ordering::Relaxed)
+    }
+    
+    /// Updates that failed to write, spooled or dead-lettered
+    pub fn updates_failed(&self) -> u64 {
+        self.updates_spooled.load(Ordering::Relaxed) + self.updates_dead_lettered.load(Ordering::Relaxed)
+    }
+    
+    /// Updates dead-lettered, as they failed or after a WAL replay did
+    pub fn updates_dead_lettered(&self) -> u64 {
+        self.updates_dead_lettered.load(Ordering::Relaxed) + self.spool_dead_lettered.load(Ordering::Relaxed)
+    }
+    
+    /// Updates that have left the queues: flushed, failed or discarded
+    pub fn updates_done(&self) -> u64 {
+        self.updates_flushed() + self.updates_failed() + self.updates_discarded()
+    }
+    
+    /// Updates nothing more will happen to without an operator: written,
+    /// live or from the WAL, discarded or dead-lettered. A spooled update
+    /// counts once the WAL replays it.
+    pub fn updates_settled(&self) -> u64 {
+        self.updates_written() + self.updates_discarded() + self.updates_dead_lettered()
+    }
+    
+    fn updates_written(&self) -> u64 {
+        self.updates_flushed() + self.updates_replayed.load(Ordering::Relaxed)
+    }
+    
+    fn updates_discarded(&self) -> u64 {
+        self.updates_discarded.load(Ordering::Relaxed)
+    }
+    
+    /// Highest slot written by any shard
+    pub fn last_indexed_slot(&self) -> u64 {
+        self.last_indexed_slot.load(Ordering::Relaxed)
+    }
+    
+    pub fn flush_latency(&self) -> LatencyPercentiles {
+        Self::percentiles(&self.flush_latency.lock().unwrap())
+    }
+    
+    pub fn end_to_end_latency(&self) -> LatencyPercentiles {
+        Self::percentiles(&self.end_to_end_latency.lock().unwrap())
+    }
+    
+    /// Snapshot in the shape of `IndexingStats`
+    pub fn snapshot(&self) -> IndexingStats {
+        let uptime = self.started_at.elapsed();
+        let flushed = self.updates_written();
+        let batches = self.batches_flushed.load(Ordering::Relaxed);
+        
+        IndexingStats {
+            total_accounts_indexed: flushed,
+            accounts_per_second: flushed as f64 / uptime.as_secs_f64().max(1e-9),
+            average_latency_ms: if batches > 0 {
+                self.total_flush_micros.load(Ordering::Relaxed) as f64 / batches as f64 / 1000.0
+            } else {
+                0.0
+            },
+            cache_hit_rate: 0.0,
+            last_indexed_slot: self.last_indexed_slot(),
+            uptime_seconds: uptime.as_secs(),
+        }
+    }
+    
+    fn percentiles(window: &LatencyWindow) -> LatencyPercentiles {
+        LatencyPercentiles {
+            p50: window.percentile(0.50).unwrap_or_default(),
+            p90: window.percentile(0.90).unwrap_or_default(),
+            p99: window.percentile(0.99).unwrap_or_default(),
+            max: window.percentile(1.0).unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for PipelineStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,157 @@
+use crate::database::Database;
+use crate::types::{ShardCheckpoint, VaultClosure, VaultCursor, VaultFilter, VaultPage, VaultSort, VaultState};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// Storage for current vault state and shard checkpoints
+///
+/// This is the part of the storage layer the indexing pipeline writes
+/// through and serves vault reads from, so another backend can take over
+/// the hot path by implementing it and being passed to
+/// `Indexer::with_store`. `Database` is the default; with the `sqlite`
+/// feature, `SqliteStore` keeps vault state in a local file instead.
+/// History, dead letters, raw account updates and the other auxiliary
+/// tables stay on `Database`.
+#[async_trait]
+pub trait VaultStore: Send + Sync {
+    /// Write one vault state unconditionally
+    async fn upsert_vault_state(&self, state: &VaultState) -> Result<()>;
+    
+    /// Write vault states, skipping any older than the stored
+    /// `(slot, write_version)`; returns whether each state was applied
+    async fn batch_upsert_vault_states(&self, states: &[VaultState]) -> Result<Vec<bool>>;
+    
+    /// Bulk variant of `batch_upsert_vault_states` for the startup snapshot
+    /// and backfill, with the same result
+    async fn copy_upsert_vault_states(&self, states: &[VaultState]) -> Result<Vec<bool>> {
+        self.batch_upsert_vault_states(states).await
+    }
+    
+    /// Content hashes of stored vault states, keyed by address, used to
+    /// skip writes that wouldn't change anything; a store that keeps none
+    /// returns an empty map and every state is written
+    async fn get_state_hashes(&self, _vault_addresses: &[String]) -> Result<HashMap<String, Vec<u8>>> {
+        Ok(HashMap::new())
+    }
+    
+    /// Keep vaults closed by newer writes as tombstones; returns the states
+    /// of the vaults that were closed
+    async fn close_vaults(&self, closures: &[VaultClosure]) -> Result<Vec<VaultState>>;
+    
+    async fn get_vault_state(&self, vault_address: &str) -> Result<Option<VaultState>>;
+    
+    /// Vault states for several addresses; unknown addresses are left out
+    async fn get_vault_states(&self, vault_addresses: &[String]) -> Result<Vec<VaultState>>;
+    
+    /// Addresses of every active vault
+    async fn get_active_vault_addresses(&self) -> Result<Vec<String>>;
+    
+    /// Address and lamport balance of every active vault, to seed the
+    /// cache leaderboard
+    async fn get_vault_balances(&self) -> Result<Vec<(String, u64)>>;
+    
+    /// One page of the active vaults owned by `owner`, ordered by address,
+    /// starting after the address `after`
+    async fn get_vaults_by_owner(&self, owner: &str, after: Option<&str>, limit: i64) -> Result<Vec<VaultState>>;
+    
+    /// Addresses of the active vaults owned by any of `owners`, ordered by
+    /// address
+    async fn get_vault_addresses_by_owners(&self, owners: &[String]) -> Result<Vec<String>>;
+    
+    /// The `n` largest active vaults by lamport balance, or by their amount
+    /// of `mint`
+    async fn get_top_vaults_by_balance(&self, n: i64, mint: Option<&str>) -> Result<Vec<VaultState>>;
+    
+    /// One page of the vaults matching `filter`, largest `sort_by` first
+    async fn list_vaults(
+        &self,
+        filter: &VaultFilter,
+        sort_by: VaultSort,
+        cursor: Option<&VaultCursor>,
+        limit: i64,
+    ) -> Result<VaultPage>;
+    
+    /// Advance a shard's checkpoint; never moves it backwards
+    async fn upsert_shard_checkpoint(&self, shard: usize, slot: u64) -> Result<()>;
+    
+    /// All shard checkpoints, by shard
+    async fn get_shard_checkpoints(&self) -> Result<Vec<ShardCheckpoint>>;
+    
+    /// Replace every checkpoint with one at `slot` for each of `shards`
+    /// shards, after the number of shards changed
+    async fn reset_shard_checkpoints(&self, shards: usize, slot: u64) -> Result<()>;
+}
+
+#[async_trait]
+impl VaultStore for Database {
+    async fn upsert_vault_state(&self, state: &VaultState) -> Result<()> {
+        Database::upsert_vault_state(self, state).await
+    }
+    
+    async fn batch_upsert_vault_states(&self, states: &[VaultState]) -> Result<Vec<bool>> {
+        Database::batch_upsert_vault_states(self, states).await
+    }
+    
+    async fn copy_upsert_vault_states(&self, states: &[VaultState]) -> Result<Vec<bool>> {
+        Database::copy_upsert_vault_states(self, states).await
+    }
+    
+    async fn get_state_hashes(&self, vault_addresses: &[String]) -> Result<HashMap<String, Vec<u8>>> {
+        Database::get_state_hashes(self, vault_addresses).await
+    }
+    
+    async fn close_vaults(&self, closures: &[VaultClosure]) -> Result<Vec<VaultState>> {
+        Database::close_vaults(self, closures).await
+    }
+    
+    async fn get_vault_state(&self, vault_address: &str) -> Result<Option<VaultState>> {
+        Database::get_vault_state(self, vault_address).await
+    }
+    
+    async fn get_vault_states(&self, vault_addresses: &[String]) -> Result<Vec<VaultState>> {
+        Database::get_vault_states(self, vault_addresses).await
+    }
+    
+    async fn get_active_vault_addresses(&self) -> Result<Vec<String>> {
+        Database::get_active_vault_addresses(self).await
+    }
+    
+    async fn get_vault_balances(&self) -> Result<Vec<(String, u64)>> {
+        Database::get_vault_balances(self).await
+    }
+    
+    async fn get_vaults_by_owner(&self, owner: &str, after: Option<&str>, limit: i64) -> Result<Vec<VaultState>> {
+        Database::get_vaults_by_owner(self, owner, after, limit).await
+    }
+    
+    async fn get_vault_addresses_by_owners(&self, owners: &[String]) -> Result<Vec<String>> {
+        Database::get_vault_addresses_by_owners(self, owners).await
+    }
+    
+    async fn get_top_vaults_by_balance(&self, n: i64, mint: Option<&str>) -> Result<Vec<VaultState>> {
+        Database::get_top_vaults_by_balance(self, n, mint).await
+    }
+    
+    async fn list_vaults(
+        &self,
+        filter: &VaultFilter,
+        sort_by: VaultSort,
+        cursor: Option<&VaultCursor>,
+        limit: i64,
+    ) -> Result<VaultPage> {
+        Database::list_vaults(self, filter, sort_by, cursor, limit).await
+    }
+    
+    async fn upsert_shard_checkpoint(&self, shard: usize, slot: u64) -> Result<()> {
+        Database::upsert_shard_checkpoint(self, shard, slot).await
+    }
+    
+    async fn get_shard_checkpoints(&self) -> Result<Vec<ShardCheckpoint>> {
+        Database::get_shard_checkpoints(self).await
+    }
+    
+    async fn reset_shard_checkpoints(&self, shards: usize, slot: u64) -> Result<()> {
+        Database::reset_shard_checkpoints(self, shards, slot).await
+    }
+}
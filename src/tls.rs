@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use axum_server::tls_rustls::RustlsConfig;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{info, warn};
+
+/// PEM certificate chain and private key served by the HTTP API
+///
+/// The files are polled for changes, so a rotated certificate is picked up
+/// without a restart. A reload that fails, as when the certificate was
+/// replaced before its key, keeps the previous pair and is retried on the
+/// next poll.
+pub struct TlsFiles {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl TlsFiles {
+    pub fn new(cert_path: &str, key_path: &str) -> Self {
+        Self {
+            cert_path: PathBuf::from(cert_path),
+            key_path: PathBuf::from(key_path),
+        }
+    }
+    
+    /// Load the pair and keep reloading it in the background every
+    /// `reload_interval` when either file changed
+    pub async fn load(self, reload_interval: Duration) -> Result<RustlsConfig> {
+        let loaded = self.modified().await;
+        let config = RustlsConfig::from_pem_file(&self.cert_path, &self.key_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load TLS certificate {} and key {}",
+                    self.cert_path.display(),
+                    self.key_path.display()
+                )
+            })?;
+        
+        tokio::spawn(self.watch(config.clone(), loaded, reload_interval.max(Duration::from_secs(1))));
+        Ok(config)
+    }
+    
+    async fn watch(self, config: RustlsConfig, mut loaded: Option<(SystemTime, SystemTime)>, every: Duration) {
+        let mut ticker = interval(every);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+        
+        loop {
+            ticker.tick().await;
+            let modified = self.modified().await;
+            if modified.is_none() || modified == loaded {
+                continue;
+            }
+            
+            match config.reload_from_pem_file(&self.cert_path, &self.key_path).await {
+                Ok(()) => {
+                    info!("Reloaded TLS certificate {}", self.cert_path.display());
+                    metrics::counter!("indexer_api_tls_reloads_total").increment(1);
+                    loaded = modified;
+                }
+                Err(e) => warn!("Failed to reload TLS certificate, keeping the previous one: {}", e),
+            }
+        }
+    }
+    
+    /// Modification times of the certificate and key
+    async fn modified(&self) -> Option<(SystemTime, SystemTime)> {
+        let cert = tokio::fs::metadata(&self.cert_path).await.ok()?.modified().ok()?;
+        let key = tokio::fs::metadata(&self.key_path).await.ok()?.modified().ok()?;
+        Some((cert, key))
+    }
+}
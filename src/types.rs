@@ -1,6 +1,9 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
-use std::collections::HashMap;
+use solana_sdk::signature::Signature;
+use std::collections::{BTreeMap, HashMap};
 use time::OffsetDateTime;
 
 /// Vault account state
@@ -9,11 +12,71 @@ pub struct VaultState {
     pub vault_address: Pubkey,
     pub owner: Pubkey,
     pub balance: u64,
+    /// Keyed by mint address
     pub assets: HashMap<String, AssetBalance>,
     pub permissions: Vec<Permission>,
     pub last_updated: OffsetDateTime,
     pub slot: u64,
     pub write_version: u64,
+    /// Closed vaults keep their last state as a tombstone
+    #[serde(default)]
+    pub status: VaultStatus,
+    /// Slot the account was closed at; `None` while active
+    #[serde(default)]
+    pub closed_at_slot: Option<u64>,
+}
+
+impl VaultState {
+    /// Hash of the parsed contents, stored as `vault_states.state_hash`
+    ///
+    /// Covers owner, balance, assets and permissions but not the version
+    /// fields or `last_updated`, so a write that leaves the vault unchanged
+    /// hashes the same. Assets are hashed in mint order.
+    pub fn content_hash(&self) -> anyhow::Result<[u8; 32]> {
+        let assets: BTreeMap<&String, &AssetBalance> = self.assets.iter().collect();
+        let contents = bincode::serialize(&(&self.owner, self.balance, assets, &self.permissions))?;
+        
+        Ok(*blake3::hash(&contents).as_bytes())
+    }
+}
+
+/// Whether a vault account still exists on chain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultStatus {
+    #[default]
+    Active,
+    /// The account was closed; the row is a tombstone
+    Closed,
+}
+
+impl VaultStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VaultStatus::Active => "active",
+            VaultStatus::Closed => "closed",
+        }
+    }
+}
+
+impl std::str::FromStr for VaultStatus {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "active" => Ok(VaultStatus::Active),
+            "closed" => Ok(VaultStatus::Closed),
+            other => anyhow::bail!("Unknown vault status '{}'", other),
+        }
+    }
+}
+
+/// A vault account closed by a write at `(slot, write_version)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VaultClosure {
+    pub vault_address: Pubkey,
+    pub slot: u64,
+    pub write_version: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +86,15 @@ pub struct AssetBalance {
     pub decimals: u8,
 }
 
+/// One row of the normalized `vault_assets` table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultAsset {
+    pub vault_address: String,
+    pub mint: String,
+    pub amount: u64,
+    pub decimals: u8,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Permission {
     pub pubkey: Pubkey,
@@ -30,7 +102,7 @@ pub struct Permission {
     pub granted_at: OffsetDateTime,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PermissionType {
     Owner,
     Admin,
@@ -38,8 +110,366 @@ pub enum PermissionType {
     Viewer,
 }
 
-/// Account update event from Geyser
+impl PermissionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionType::Owner => "Owner",
+            PermissionType::Admin => "Admin",
+            PermissionType::Operator => "Operator",
+            PermissionType::Viewer => "Viewer",
+        }
+    }
+    
+    /// Position from Viewer (1) to Owner (4); stored as
+    /// `vault_permissions.role_rank`
+    pub fn rank(&self) -> i16 {
+        match self {
+            PermissionType::Owner => 4,
+            PermissionType::Admin => 3,
+            PermissionType::Operator => 2,
+            PermissionType::Viewer => 1,
+        }
+    }
+}
+
+/// Reusable vault selection criteria
+///
+/// Empty lists match everything; non-empty lists must each match. Used by
+/// saved filters so subscriptions, webhooks and exports share one definition
+/// of "this customer's vaults".
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VaultFilter {
+    #[serde(default)]
+    pub vault_addresses: Vec<String>,
+    #[serde(default)]
+    pub owners: Vec<String>,
+    /// Vault must carry every listed tag
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub min_balance: Option<u64>,
+    /// Also match closed vaults, which are left out by default
+    #[serde(default)]
+    pub include_closed: bool,
+}
+
+impl VaultFilter {
+    /// Check a vault state (and its tags) against the filter
+    pub fn matches(&self, state: &VaultState, tags: &[String]) -> bool {
+        if !self.include_closed && state.status == VaultStatus::Closed {
+            return false;
+        }
+        
+        if !self.vault_addresses.is_empty()
+            && !self.vault_addresses.contains(&state.vault_address.to_string())
+        {
+            return false;
+        }
+        
+        if !self.owners.is_empty() && !self.owners.contains(&state.owner.to_string()) {
+            return false;
+        }
+        
+        if !self.tags.iter().all(|tag| tags.contains(tag)) {
+            return false;
+        }
+        
+        if let Some(min_balance) = self.min_balance {
+            if state.balance < min_balance {
+                return false;
+            }
+        }
+        
+        true
+    }
+}
+
+/// Column a vault listing is ordered by, largest first; ties are broken by
+/// address
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultSort {
+    #[default]
+    Balance,
+    LastUpdated,
+    Slot,
+}
+
+impl VaultSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VaultSort::Balance => "balance",
+            VaultSort::LastUpdated => "last_updated",
+            VaultSort::Slot => "slot",
+        }
+    }
+}
+
+/// Keyset position in a vault listing: the sort key and address of the
+/// last vault already returned
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "sort_by", rename_all = "snake_case")]
+pub enum VaultCursor {
+    Balance { balance: u64, vault_address: String },
+    LastUpdated { last_updated: OffsetDateTime, vault_address: String },
+    Slot { slot: u64, vault_address: String },
+}
+
+impl VaultCursor {
+    /// Cursor continuing a listing sorted by `sort_by` after `state`
+    pub fn after(sort_by: VaultSort, state: &VaultState) -> Self {
+        let vault_address = state.vault_address.to_string();
+        match sort_by {
+            VaultSort::Balance => VaultCursor::Balance { balance: state.balance, vault_address },
+            VaultSort::LastUpdated => VaultCursor::LastUpdated {
+                last_updated: state.last_updated,
+                vault_address,
+            },
+            VaultSort::Slot => VaultCursor::Slot { slot: state.slot, vault_address },
+        }
+    }
+    
+    pub fn sort_by(&self) -> VaultSort {
+        match self {
+            VaultCursor::Balance { .. } => VaultSort::Balance,
+            VaultCursor::LastUpdated { .. } => VaultSort::LastUpdated,
+            VaultCursor::Slot { .. } => VaultSort::Slot,
+        }
+    }
+    
+    pub fn vault_address(&self) -> &str {
+        match self {
+            VaultCursor::Balance { vault_address, .. }
+            | VaultCursor::LastUpdated { vault_address, .. }
+            | VaultCursor::Slot { vault_address, .. } => vault_address,
+        }
+    }
+}
+
+/// Opaque position in one of the API listings, handed to clients as
+/// `next_cursor`
+///
+/// Every REST, gRPC and GraphQL listing encodes its cursor the same way, as
+/// URL-safe base64 of this JSON, and hands one out only when its page came
+/// back full; the change log always hands one out, to poll from. A cursor
+/// continues only the listing it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "listing", rename_all = "snake_case")]
+pub enum PageCursor {
+    /// Filtered vault listing, in its sort order
+    Vaults(VaultCursor),
+    /// An owner's vaults, ordered by address
+    OwnerVaults { vault_address: String },
+    /// A vault's recorded versions, oldest first
+    History { slot: u64, write_version: u64 },
+    /// The change log
+    Changes(ChangeCursor),
+}
+
+impl PageCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+    
+    /// Encoded cursor continuing an owner listing after `page`, which was
+    /// asked for `limit` vaults
+    pub fn next_owner_vaults(page: &[VaultState], limit: i64) -> Option<String> {
+        let last = page.last().filter(|_| page.len() as i64 == limit)?;
+        Some(
+            PageCursor::OwnerVaults {
+                vault_address: last.vault_address.to_string(),
+            }
+            .encode(),
+        )
+    }
+    
+    /// Encoded cursor continuing a vault's history after `page`, which was
+    /// asked for `limit` versions
+    pub fn next_history(page: &[VaultStateVersion], limit: i64) -> Option<String> {
+        let last = page.last().filter(|_| page.len() as i64 == limit)?;
+        Some(
+            PageCursor::History {
+                slot: last.state.slot,
+                write_version: last.state.write_version,
+            }
+            .encode(),
+        )
+    }
+    
+    /// Position in a filtered vault listing
+    pub fn decode_vaults(cursor: &str) -> anyhow::Result<VaultCursor> {
+        match Self::decode(cursor)? {
+            PageCursor::Vaults(cursor) => Ok(cursor),
+            _ => anyhow::bail!("Cursor continues another listing"),
+        }
+    }
+    
+    /// Address of the last vault already returned in an owner listing
+    pub fn decode_owner_vaults(cursor: &str) -> anyhow::Result<String> {
+        match Self::decode(cursor)? {
+            PageCursor::OwnerVaults { vault_address } => Ok(vault_address),
+            _ => anyhow::bail!("Cursor continues another listing"),
+        }
+    }
+    
+    /// `(slot, write_version)` of the last history version already returned
+    pub fn decode_history(cursor: &str) -> anyhow::Result<(u64, u64)> {
+        match Self::decode(cursor)? {
+            PageCursor::History { slot, write_version } => Ok((slot, write_version)),
+            _ => anyhow::bail!("Cursor continues another listing"),
+        }
+    }
+    
+    /// Position in the change log
+    pub fn decode_changes(cursor: &str) -> anyhow::Result<ChangeCursor> {
+        match Self::decode(cursor)? {
+            PageCursor::Changes(cursor) => Ok(cursor),
+            _ => anyhow::bail!("Cursor continues another listing"),
+        }
+    }
+    
+    fn decode(cursor: &str) -> anyhow::Result<Self> {
+        let json = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| anyhow::anyhow!("Invalid cursor"))?;
+        serde_json::from_slice(&json).map_err(|_| anyhow::anyhow!("Invalid cursor"))
+    }
+}
+
+/// One page of a vault listing
+#[derive(Debug, Clone)]
+pub struct VaultPage {
+    pub vaults: Vec<VaultState>,
+    /// Cursor for the next page; `None` on the last page
+    pub next_cursor: Option<VaultCursor>,
+}
+
+/// Position in the stream of vault updates, ordered by `(slot,
+/// write_version, vault_address)`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncCursor {
+    pub slot: u64,
+    pub write_version: u64,
+    pub vault_address: String,
+}
+
+impl SyncCursor {
+    /// Start of a sync: every vault last updated after `slot`
+    pub fn after_slot(slot: u64) -> Self {
+        // Write versions are stored as BIGINT, so none sorts after this
+        Self {
+            slot,
+            write_version: i64::MAX as u64,
+            vault_address: String::new(),
+        }
+    }
+    
+    /// Position just after `state`
+    pub fn after(state: &VaultState) -> Self {
+        Self {
+            slot: state.slot,
+            write_version: state.write_version,
+            vault_address: state.vault_address.to_string(),
+        }
+    }
+}
+
+/// Vaults updated after a `SyncCursor`
+#[derive(Debug, Clone)]
+pub struct SyncPage {
+    pub vaults: Vec<VaultState>,
+    /// Where the next poll continues from; the given cursor if nothing new
+    /// was found
+    pub next_cursor: SyncCursor,
+}
+
+/// Position in the change log, ordered by `(slot, write_version,
+/// vault_address)`; a write that changed a vault's owner yields its owner
+/// change first, then its state
+///
+/// The default cursor is the start of the log.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ChangeCursor {
+    pub slot: u64,
+    pub write_version: u64,
+    pub vault_address: String,
+    /// Past the state written at this position, not just its owner change
+    pub after_state: bool,
+}
+
+impl ChangeCursor {
+    /// Start of the log at every change after `slot`
+    pub fn after_slot(slot: u64) -> Self {
+        // Write versions are stored as BIGINT, so none sorts after this
+        Self {
+            slot,
+            write_version: i64::MAX as u64,
+            vault_address: String::new(),
+            after_state: true,
+        }
+    }
+    
+    /// Position just after `event`
+    pub fn after(event: &VaultEvent) -> Self {
+        match event {
+            VaultEvent::OwnerChanged(change) => Self {
+                slot: change.slot,
+                write_version: change.write_version,
+                vault_address: change.vault_address.clone(),
+                after_state: false,
+            },
+            VaultEvent::Updated(state) => Self {
+                slot: state.slot,
+                write_version: state.write_version,
+                vault_address: state.vault_address.to_string(),
+                after_state: true,
+            },
+        }
+    }
+}
+
+/// Vault events after a `ChangeCursor`, in log order
 #[derive(Debug, Clone)]
+pub struct ChangePage {
+    pub events: Vec<VaultEvent>,
+    /// Where the next poll continues from; the given cursor if nothing new
+    /// was found
+    pub next_cursor: ChangeCursor,
+}
+
+impl ChangePage {
+    /// Merge owner changes and recorded states, each already in log order
+    /// after `since`, into the first `limit` events
+    pub fn merge(since: &ChangeCursor, owner_changes: Vec<OwnerChange>, states: Vec<VaultState>, limit: i64) -> Self {
+        let mut events: Vec<(ChangeCursor, VaultEvent)> = owner_changes
+            .into_iter()
+            .map(VaultEvent::OwnerChanged)
+            .chain(states.into_iter().map(VaultEvent::Updated))
+            .map(|event| (ChangeCursor::after(&event), event))
+            .collect();
+        events.sort_by(|a, b| a.0.cmp(&b.0));
+        events.truncate(limit.max(0) as usize);
+        
+        let next_cursor = events.last().map(|(cursor, _)| cursor.clone()).unwrap_or_else(|| since.clone());
+        Self {
+            events: events.into_iter().map(|(_, event)| event).collect(),
+            next_cursor,
+        }
+    }
+}
+
+/// Named filter stored in the `saved_filters` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedFilter {
+    pub name: String,
+    pub description: Option<String>,
+    pub filter: VaultFilter,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+/// Account update event from Geyser
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountUpdate {
     pub pubkey: Pubkey,
     pub lamports: u64,
@@ -50,6 +480,212 @@ pub struct AccountUpdate {
     pub write_version: u64,
     pub slot: u64,
     pub is_startup: bool,
+    /// Transaction that produced the write, when the validator reports it;
+    /// the WAL reads spooled updates from before it was added
+    pub txn_signature: Option<Signature>,
+}
+
+/// A write recorded in `account_updates`, as the validator delivered it
+#[derive(Debug, Clone)]
+pub struct RawAccount {
+    pub pubkey: Pubkey,
+    pub slot: u64,
+    pub write_version: u64,
+    pub data: Vec<u8>,
+    /// Account metadata; `None` for writes recorded before it was stored
+    pub owner: Option<Pubkey>,
+    pub lamports: Option<u64>,
+    pub executable: Option<bool>,
+    pub rent_epoch: Option<u64>,
+    pub txn_signature: Option<Signature>,
+    pub received_at: OffsetDateTime,
+}
+
+impl RawAccount {
+    /// The live update this write came from, for re-running parsers; `None`
+    /// if the metadata wasn't recorded
+    pub fn to_account_update(&self) -> Option<AccountUpdate> {
+        Some(AccountUpdate {
+            pubkey: self.pubkey,
+            lamports: self.lamports?,
+            owner: self.owner?,
+            executable: self.executable?,
+            rent_epoch: self.rent_epoch?,
+            data: self.data.clone(),
+            write_version: self.write_version,
+            slot: self.slot,
+            is_startup: false,
+            txn_signature: self.txn_signature,
+        })
+    }
+}
+
+/// Pipeline stage at which an update was dead-lettered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterStage {
+    /// Account data could not be parsed into a vault state
+    Parse,
+    /// The batch containing the update could not be written
+    Write,
+}
+
+impl DeadLetterStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadLetterStage::Parse => "parse",
+            DeadLetterStage::Write => "write",
+        }
+    }
+}
+
+impl std::str::FromStr for DeadLetterStage {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "parse" => Ok(DeadLetterStage::Parse),
+            "write" => Ok(DeadLetterStage::Write),
+            other => anyhow::bail!("Unknown dead-letter stage '{}'", other),
+        }
+    }
+}
+
+/// Update persisted in the `dead_letter` table for later inspection/replay
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub update: AccountUpdate,
+    pub stage: DeadLetterStage,
+    pub error: String,
+    pub attempts: u32,
+    pub created_at: OffsetDateTime,
+    pub replayed_at: Option<OffsetDateTime>,
+}
+
+/// Lifecycle of a backfill job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackfillJobStatus {
+    Running,
+    /// Stopped on an error; resumed by the next backfill of the same program
+    Failed,
+    Completed,
+}
+
+impl BackfillJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackfillJobStatus::Running => "running",
+            BackfillJobStatus::Failed => "failed",
+            BackfillJobStatus::Completed => "completed",
+        }
+    }
+}
+
+impl std::str::FromStr for BackfillJobStatus {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "running" => Ok(BackfillJobStatus::Running),
+            "failed" => Ok(BackfillJobStatus::Failed),
+            "completed" => Ok(BackfillJobStatus::Completed),
+            other => anyhow::bail!("Unknown backfill job status '{}'", other),
+        }
+    }
+}
+
+/// Persisted progress of a paged backfill, stored in `backfill_jobs`
+#[derive(Debug, Clone)]
+pub struct BackfillJob {
+    pub id: i64,
+    pub program_id: String,
+    /// Data offset of the paging byte; part of the job's filter
+    pub prefix_offset: usize,
+    /// Next page (prefix byte value) to fetch; 256 once every page is done
+    pub next_prefix: u16,
+    pub pages_done: u32,
+    pub accounts_done: u64,
+    pub status: BackfillJobStatus,
+    pub error_count: u32,
+    pub last_error: Option<String>,
+    pub started_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+/// A recorded vault state version and the transaction that produced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultStateVersion {
+    pub state: VaultState,
+    /// `None` for snapshot, backfill and repair writes, or when the
+    /// validator didn't report a transaction
+    pub txn_signature: Option<String>,
+}
+
+/// An owner transition recorded in `owner_changes`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnerChange {
+    pub id: i64,
+    pub vault_address: String,
+    pub previous_owner: String,
+    pub new_owner: String,
+    /// Version of the vault state that carried the new owner
+    pub slot: u64,
+    pub write_version: u64,
+    pub changed_at: OffsetDateTime,
+}
+
+/// Payload of a `vault_changes` notification: a vault write that was applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultChange {
+    pub vault_address: String,
+    pub slot: u64,
+    pub write_version: u64,
+}
+
+/// Notable vault changes, delivered by `Indexer::subscribe_events`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum VaultEvent {
+    OwnerChanged(OwnerChange),
+    /// A vault state written by this indexer, including closures
+    Updated(VaultState),
+}
+
+/// Row of `mv_owner_totals`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnerTotals {
+    pub owner: String,
+    pub vault_count: u64,
+    pub total_balance: u128,
+}
+
+/// The single row of `mv_vault_counts`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultCounts {
+    pub vault_count: u64,
+    pub owner_count: u64,
+    pub total_balance: u128,
+    pub max_slot: u64,
+}
+
+/// Row of `mv_mint_totals`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MintTotals {
+    pub mint: String,
+    pub holder_count: u64,
+    pub total_amount: u128,
+}
+
+/// Value held across all indexed vaults
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotalValueLocked {
+    /// Sum of vault balances, in lamports
+    pub total_balance: u128,
+    /// Per-mint totals, largest first
+    pub mints: Vec<MintTotals>,
+    pub computed_at: OffsetDateTime,
 }
 
 /// Indexing statistics
@@ -71,6 +707,60 @@ pub struct CacheEntry {
     pub ttl_seconds: u64,
 }
 
+/// Serialization of cache entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheCodec {
+    /// Readable with `redis-cli`
+    #[default]
+    Json,
+    /// Smaller and faster to encode and decode
+    Bincode,
+}
+
+/// What to do when the database schema version doesn't match the binary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaCheck {
+    /// Refuse to start
+    #[default]
+    Strict,
+    /// Log a warning and start anyway
+    Warn,
+}
+
+/// TLS requirement for database connections, named as in libpq's `sslmode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    Disable,
+    Allow,
+    Prefer,
+    Require,
+    /// Require TLS and a server certificate signed by a trusted CA
+    VerifyCa,
+    /// As `VerifyCa`, and the certificate must match the host name
+    VerifyFull,
+}
+
+/// Latest migration applied to the database vs. embedded in the binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaVersion {
+    /// `None` if no migration has been applied
+    pub database: Option<i64>,
+    pub binary: i64,
+}
+
+impl SchemaVersion {
+    pub fn is_ahead(&self) -> bool {
+        self.database.is_some_and(|version| version > self.binary)
+    }
+    
+    pub fn is_behind(&self) -> bool {
+        self.database.unwrap_or(0) < self.binary
+    }
+}
+
 /// Database configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
@@ -81,6 +771,192 @@ pub struct DatabaseConfig {
     pub password: String,
     pub max_connections: u32,
     pub connection_timeout_seconds: u64,
+    /// Apply pending embedded migrations at startup
+    #[serde(default = "default_run_migrations")]
+    pub run_migrations: bool,
+    #[serde(default)]
+    pub schema_check: SchemaCheck,
+    /// Schema holding the indexer's tables; created on first migration
+    #[serde(default = "default_schema")]
+    pub schema: String,
+    /// Prepended to every table, view and index name, so several
+    /// instances can share one schema
+    #[serde(default)]
+    pub table_prefix: String,
+    /// Run without DDL privileges: never migrate at runtime, require the
+    /// schema version to match exactly, and never drop partitions
+    #[serde(default)]
+    pub least_privilege: bool,
+    /// Idle connections kept open so a burst doesn't pay for connecting
+    #[serde(default)]
+    pub min_connections: u32,
+    /// Idle connections above `min_connections` are closed after this long
+    #[serde(default = "default_idle_timeout_seconds")]
+    pub idle_timeout_seconds: u64,
+    /// Ping each connection as it's taken from the pool, so connections
+    /// dropped by Postgres or the network are replaced instead of failing
+    /// the next query
+    #[serde(default = "default_test_before_acquire")]
+    pub test_before_acquire: bool,
+    /// Attempts at the first connection, with exponential backoff between
+    /// them, before startup gives up
+    #[serde(default = "default_connect_attempts")]
+    pub connect_attempts: u32,
+    #[serde(default = "default_connect_max_backoff_ms")]
+    pub connect_max_backoff_ms: u64,
+    /// How often the background health check pings the database
+    #[serde(default = "default_health_check_interval_secs")]
+    pub health_check_interval_secs: u64,
+    /// Connection URLs of read replicas; vault lookups and listings are
+    /// served from them, writes always go to the primary
+    #[serde(default)]
+    pub read_urls: Vec<String>,
+    /// Replicas lagging the primary by more than this are skipped, and
+    /// reads fall back to the primary when none is fresh enough
+    #[serde(default = "default_max_replica_lag_ms")]
+    pub max_replica_lag_ms: u64,
+    /// Connect through PgBouncer in transaction pooling mode: no named
+    /// prepared statements and no session-level state
+    #[serde(default)]
+    pub pgbouncer: bool,
+    /// Longest wait for a pooled connection; defaults to
+    /// `connection_timeout_seconds`
+    #[serde(default)]
+    pub acquire_timeout_ms: Option<u64>,
+    /// Server-side cap on any single statement; 0 leaves the server
+    /// default. Not allowed with `pgbouncer`
+    #[serde(default)]
+    pub statement_timeout_ms: u64,
+    /// Server-side cap on waiting for a row or table lock; 0 leaves the
+    /// server default. Not allowed with `pgbouncer`
+    #[serde(default)]
+    pub lock_timeout_ms: u64,
+    /// Database calls slower than this are logged with their parameters
+    /// and counted; 0 disables the log
+    #[serde(default = "default_slow_query_threshold_ms")]
+    pub slow_query_threshold_ms: u64,
+    /// Announce applied vault writes with `pg_notify` for `ChangeFeed`
+    /// listeners
+    #[serde(default)]
+    pub notify_changes: bool,
+    /// Store `account_updates.data` zstd-compressed; rows written either
+    /// way stay readable after the setting changes
+    #[serde(default)]
+    pub compress_account_data: bool,
+    /// Store `account_updates.data` as a binary diff against the account's
+    /// previous write
+    #[serde(default)]
+    pub delta_account_data: bool,
+    /// With `delta_account_data`, store a full copy every this many writes
+    /// of an account, bounding the chain a read has to replay
+    #[serde(default = "default_account_data_keyframe_interval")]
+    pub account_data_keyframe_interval: u32,
+    /// TLS mode; unset keeps the connection default, `prefer`
+    #[serde(default)]
+    pub ssl_mode: Option<SslMode>,
+    /// PEM bundle of CAs trusted to sign the server certificate
+    #[serde(default)]
+    pub ssl_root_cert: Option<String>,
+    /// PEM client certificate, for servers requiring certificate auth
+    #[serde(default)]
+    pub ssl_client_cert: Option<String>,
+    /// PEM private key for `ssl_client_cert`
+    #[serde(default)]
+    pub ssl_client_key: Option<String>,
+}
+
+impl DatabaseConfig {
+    pub fn connection_string(&self) -> String {
+        format!(
+            "postgresql://{}:{}@{}:{}/{}",
+            self.username, self.password, self.host, self.port, self.database,
+        )
+    }
+    
+    /// Whether pending migrations are applied at startup
+    pub fn migrates_at_startup(&self) -> bool {
+        self.run_migrations && !self.least_privilege
+    }
+    
+    /// Least-privilege mode always verifies the schema strictly, since it
+    /// can't fix a mismatch itself
+    pub fn effective_schema_check(&self) -> SchemaCheck {
+        if self.least_privilege {
+            SchemaCheck::Strict
+        } else {
+            self.schema_check
+        }
+    }
+}
+
+/// Backend holding vault states and shard checkpoints
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreBackend {
+    /// The Postgres `Database`
+    #[default]
+    Postgres,
+    /// A local SQLite file, for development; needs the `sqlite` feature
+    Sqlite,
+}
+
+/// Where the pipeline keeps vault states and shard checkpoints; everything
+/// else stays in Postgres
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoreConfig {
+    pub backend: StoreBackend,
+    /// Database file for the `sqlite` backend, created if missing
+    pub sqlite_path: String,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: StoreBackend::Postgres,
+            sqlite_path: "vaulta_indexer.db".to_string(),
+        }
+    }
+}
+
+fn default_run_migrations() -> bool {
+    true
+}
+
+fn default_schema() -> String {
+    "public".to_string()
+}
+
+fn default_idle_timeout_seconds() -> u64 {
+    600
+}
+
+fn default_test_before_acquire() -> bool {
+    true
+}
+
+fn default_connect_attempts() -> u32 {
+    10
+}
+
+fn default_connect_max_backoff_ms() -> u64 {
+    10_000
+}
+
+fn default_health_check_interval_secs() -> u64 {
+    10
+}
+
+fn default_max_replica_lag_ms() -> u64 {
+    1000
+}
+
+fn default_slow_query_threshold_ms() -> u64 {
+    1000
+}
+
+fn default_account_data_keyframe_interval() -> u32 {
+    32
 }
 
 /// Redis configuration
@@ -88,26 +964,973 @@ pub struct DatabaseConfig {
 pub struct RedisConfig {
     pub url: String,
     pub ttl_seconds: u64,
+    /// Each entry's TTL is moved randomly by up to this percentage of
+    /// `ttl_seconds` either way
+    #[serde(default = "default_ttl_jitter_percent")]
+    pub ttl_jitter_percent: u8,
     pub max_connections: u32,
     pub connection_timeout_seconds: u64,
+    /// Sentinel endpoints; when set, the master of `sentinel_master_name`
+    /// is looked up through them and `url` only supplies credentials
+    #[serde(default)]
+    pub sentinel_urls: Vec<String>,
+    #[serde(default)]
+    pub sentinel_master_name: Option<String>,
+    /// How often Sentinel is asked whether the master moved
+    #[serde(default = "default_sentinel_poll_interval_ms")]
+    pub sentinel_poll_interval_ms: u64,
+    /// ACL user and password; override any given in `url`
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// PEM bundle of CAs trusted to sign the server certificate; TLS
+    /// itself is enabled by a `rediss://` url
+    #[serde(default)]
+    pub tls_ca_cert: Option<String>,
+    /// PEM client certificate, for servers requiring certificate auth
+    #[serde(default)]
+    pub tls_client_cert: Option<String>,
+    /// PEM private key for `tls_client_cert`
+    #[serde(default)]
+    pub tls_client_key: Option<String>,
+    /// Skip server certificate verification; for testing only
+    #[serde(default)]
+    pub tls_insecure: bool,
+    /// First part of every cache key, followed by the vault program ID;
+    /// set it per cluster when indexers of several clusters share a Redis
+    #[serde(default = "default_redis_key_prefix")]
+    pub key_prefix: String,
+    /// Encoding of new entries; entries in either encoding are read
+    #[serde(default)]
+    pub codec: CacheCodec,
+    /// zstd-compress entries larger than this many bytes; unset disables
+    /// compression
+    #[serde(default)]
+    pub compress_above_bytes: Option<usize>,
+    /// Publish every cached vault write on the `vault_changes` channel
+    /// under the key namespace
+    #[serde(default)]
+    pub publish_changes: bool,
+    /// Append every vault write to the `vault_change_stream` stream under
+    /// the key namespace
+    #[serde(default)]
+    pub stream_changes: bool,
+    /// Approximate number of entries the change stream keeps
+    #[serde(default = "default_stream_max_len")]
+    pub stream_max_len: usize,
+    /// Keep a sorted set of active vaults by balance for top-N queries,
+    /// seeded from the database at startup
+    #[serde(default)]
+    pub leaderboard: bool,
+    /// Vaults cached with no expiry, for the busiest known vaults
+    #[serde(default)]
+    pub pinned_vaults: Vec<String>,
+    /// Reads per minute that make a cached vault hot; unset to treat all
+    /// vaults alike
+    #[serde(default)]
+    pub hot_key_reads_per_minute: Option<u64>,
+    /// TTL of a hot vault's entry
+    #[serde(default = "default_hot_key_ttl_seconds")]
+    pub hot_key_ttl_seconds: u64,
+    /// Also keep each vault's main fields in a hash, for field-level reads
+    #[serde(default)]
+    pub field_hashes: bool,
+}
+
+fn default_ttl_jitter_percent() -> u8 {
+    10
+}
+
+fn default_sentinel_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_redis_key_prefix() -> String {
+    "vaulta".to_string()
+}
+
+fn default_stream_max_len() -> usize {
+    100_000
+}
+
+fn default_hot_key_ttl_seconds() -> u64 {
+    3600
+}
+
+/// Adaptive batch sizing configuration
+///
+/// When enabled, `batch_size` is only the starting point: each shard grows
+/// its batch while flushes are fast and shrinks it when the p99 flush
+/// latency exceeds `target_p99_flush_ms`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveBatchConfig {
+    pub enabled: bool,
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    pub target_p99_flush_ms: u64,
+    /// Number of recent flushes the p99 is computed over
+    pub window_size: usize,
+}
+
+impl Default for AdaptiveBatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_batch_size: 100,
+            max_batch_size: 10_000,
+            target_p99_flush_ms: 50,
+            window_size: 100,
+        }
+    }
+}
+
+/// One rung of the degradation ladder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegradationLevel {
+    /// Backlog (received but not yet flushed updates) that activates this level
+    pub min_pending_updates: u64,
+    /// Drop startup snapshot updates while active
+    #[serde(default)]
+    pub drop_startup: bool,
+    /// If non-empty, only accounts with one of these data lengths are kept
+    #[serde(default)]
+    pub essential_data_lens: Vec<usize>,
+    /// Keep one in every N updates per account (0 or 1 keeps all)
+    #[serde(default)]
+    pub sample_every: u32,
+}
+
+/// Emergency filtering applied when the pipeline is severely backlogged
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DegradationConfig {
+    pub enabled: bool,
+    pub levels: Vec<DegradationLevel>,
+}
+
+/// Highest slot a shard has fully flushed to Postgres
+#[derive(Debug, Clone)]
+pub struct ShardCheckpoint {
+    pub shard: usize,
+    pub slot: u64,
+    pub updated_at: OffsetDateTime,
+}
+
+/// A contiguous range of slots the indexer was running for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotRange {
+    pub id: i64,
+    pub start_slot: u64,
+    pub end_slot: u64,
+}
+
+/// Slots between two indexed ranges, when the indexer wasn't running
+#[derive(Debug, Clone)]
+pub struct SlotGap {
+    pub id: i64,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub detected_at: OffsetDateTime,
+    pub reconciled_at: Option<OffsetDateTime>,
+}
+
+/// One slot-range partition of `account_updates`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotPartition {
+    pub name: String,
+    pub start_slot: u64,
+    /// Exclusive
+    pub end_slot: u64,
+}
+
+/// Where an instance gets its vault-state changes from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexerMode {
+    /// Index account updates delivered by the validator's Geyser interface
+    #[default]
+    Geyser,
+    /// Follow the primary instance's vault_states via Postgres logical replication
+    Replica,
+}
+
+/// Logical replication consumer configuration (replica mode only)
+///
+/// The primary must run with `wal_level = logical` and have the wal2json
+/// output plugin available.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplicationConfig {
+    /// Connection string of the primary instance's database
+    pub primary_url: String,
+    pub slot_name: String,
+    pub poll_interval_ms: u64,
+    pub max_changes_per_poll: u32,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            primary_url: String::new(),
+            slot_name: "vaulta_indexer_replica".to_string(),
+            poll_interval_ms: 200,
+            max_changes_per_poll: 5000,
+        }
+    }
+}
+
+/// Retry policy for failed batch flushes
+///
+/// A failed batch is retried with exponential backoff and jitter while it
+/// stays within both budgets; after that it goes to the WAL or DLQ.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Total attempts including the first; 1 disables retries
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Upper bound on time a batch is held in memory for retries
+    pub max_elapsed_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 5000,
+            max_elapsed_ms: 30_000,
+        }
+    }
+}
+
+/// Slot coverage tracking and gap reconciliation
+///
+/// Reconciliation re-reads program accounts through the `[backfill]` RPC.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GapDetectionConfig {
+    pub enabled: bool,
+    /// How often the indexed slot range is persisted
+    pub checkpoint_interval_ms: u64,
+    /// Gaps shorter than this many slots are ignored
+    pub min_gap_slots: u64,
+}
+
+impl Default for GapDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            checkpoint_interval_ms: 5000,
+            min_gap_slots: 1,
+        }
+    }
+}
+
+/// Loading vaults into Redis before queries are served
+///
+/// Runs once startup ingestion has caught up, before readiness is
+/// reported, so the first queries after a restart don't all fall through
+/// to the database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheWarmupConfig {
+    pub enabled: bool,
+    /// Number of most recently written vaults to load
+    pub recent_vaults: i64,
+    /// Vaults to load regardless of when they were written
+    pub vault_addresses: Vec<String>,
+}
+
+impl Default for CacheWarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            recent_vaults: 10_000,
+            vault_addresses: Vec::new(),
+        }
+    }
+}
+
+/// In-process tier of vault states in front of Redis
+///
+/// Serves hot vaults without a Redis round trip. Needs the cache and
+/// `redis.publish_changes`, whose announcements drop changed entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalCacheConfig {
+    pub enabled: bool,
+    /// Vault states kept at most
+    pub max_entries: u64,
+    /// Longest an entry is served, bounding staleness if an announcement
+    /// is missed
+    pub ttl_ms: u64,
+}
+
+impl Default for LocalCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_entries: 10_000,
+            ttl_ms: 1000,
+        }
+    }
+}
+
+/// Embedded HTTP query API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    /// Address and port to listen on
+    pub bind_address: String,
+    /// Largest `limit` served by `/owners/{pubkey}/vaults`, largest
+    /// `first` served over GraphQL, and most accounts one JSON-RPC call
+    /// returns
+    pub max_page_size: i64,
+    /// Most addresses one `POST /vaults/batch` may look up
+    pub max_batch_size: usize,
+    /// Serve the GraphQL endpoint at `/graphql`
+    pub graphql: bool,
+    /// Deepest selection a GraphQL query may nest
+    pub graphql_max_depth: usize,
+    /// Most fields a GraphQL query may resolve, counting list fields once
+    /// per requested item
+    pub graphql_max_complexity: usize,
+    /// Serve Solana-style account methods over JSON-RPC at `/rpc`
+    pub json_rpc: bool,
+    /// Serve Swagger UI for the OpenAPI spec at `/docs`
+    pub swagger_ui: bool,
+    /// PEM certificate chain to serve HTTPS with; plain HTTP when unset
+    pub tls_cert_path: Option<String>,
+    /// PEM private key of `tls_cert_path`
+    pub tls_key_path: Option<String>,
+    /// How often the certificate and key are checked for rotation
+    pub tls_reload_interval_secs: u64,
+    /// Origins browsers may call the API from, such as
+    /// `https://explorer.vaultapay.app`, or `*` for any; no CORS headers
+    /// when empty
+    pub cors_allowed_origins: Vec<String>,
+    /// Longest a lookup given `min_slot` waits for that slot to be flushed
+    pub min_slot_timeout_ms: u64,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1:8080".to_string(),
+            max_page_size: 1000,
+            max_batch_size: 100,
+            graphql: false,
+            graphql_max_depth: 10,
+            graphql_max_complexity: 10_000,
+            json_rpc: false,
+            swagger_ui: false,
+            tls_cert_path: None,
+            tls_key_path: None,
+            tls_reload_interval_secs: 30,
+            cors_allowed_origins: Vec::new(),
+            min_slot_timeout_ms: 2000,
+        }
+    }
+}
+
+/// Embedded gRPC query service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GrpcConfig {
+    pub enabled: bool,
+    /// Address and port to listen on
+    pub bind_address: String,
+    /// Largest `limit` served by `ListVaultsByOwner`
+    pub max_page_size: i64,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1:50051".to_string(),
+            max_page_size: 1000,
+        }
+    }
+}
+
+/// Re-publishing of vault account updates over the Yellowstone gRPC protocol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct YellowstoneConfig {
+    pub enabled: bool,
+    /// Address and port to listen on
+    pub bind_address: String,
+    /// Updates buffered per subscriber; slower subscribers are disconnected
+    pub channel_capacity: usize,
+}
+
+impl Default for YellowstoneConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1:10000".to_string(),
+            channel_capacity: 10_000,
+        }
+    }
+}
+
+/// API key authentication of the HTTP, WebSocket and gRPC servers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    /// Keys accepted from the config file
+    pub keys: Vec<ApiKeyConfig>,
+    /// Also accept the keys stored in the `api_keys` table
+    pub database_keys: bool,
+    /// How often `api_keys` is reloaded, picking up new and revoked keys
+    pub refresh_interval_secs: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keys: Vec::new(),
+            database_keys: false,
+            refresh_interval_secs: 60,
+        }
+    }
+}
+
+/// One configured API key; exactly one of `key` and `key_sha256` is set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Shown in logs and metrics instead of the key
+    pub name: String,
+    /// The key itself
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Hex SHA-256 of the key, so the config file doesn't hold it
+    #[serde(default)]
+    pub key_sha256: Option<String>,
+    pub scopes: Vec<ApiScope>,
+}
+
+/// What an API key may query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    /// Vault and owner lookups, over REST and gRPC
+    Read,
+    /// Indexing statistics
+    Stats,
+    /// Vault change streams: WebSocket, server-sent events and gRPC
+    /// subscriptions, Yellowstone included
+    Stream,
+    /// The GraphQL endpoint
+    Graphql,
+    /// The Solana-style JSON-RPC endpoint
+    Rpc,
+    /// Operator routes under `/admin`: backfills, cache eviction and key
+    /// reloads. Granted only explicitly, never with the query scopes.
+    Admin,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::Stats => "stats",
+            ApiScope::Stream => "stream",
+            ApiScope::Graphql => "graphql",
+            ApiScope::Rpc => "rpc",
+            ApiScope::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for ApiScope {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "read" => Ok(ApiScope::Read),
+            "stats" => Ok(ApiScope::Stats),
+            "stream" => Ok(ApiScope::Stream),
+            "graphql" => Ok(ApiScope::Graphql),
+            "rpc" => Ok(ApiScope::Rpc),
+            "admin" => Ok(ApiScope::Admin),
+            other => anyhow::bail!("Unknown API scope '{}'", other),
+        }
+    }
+}
+
+/// Token-bucket rate limiting of the HTTP and gRPC query servers
+///
+/// Requests with an API key draw from that key's bucket, others from their
+/// client IP's.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Bucket of each client IP
+    pub per_ip: RateLimitQuota,
+    /// Bucket of each API key without its own entry in `keys`
+    pub per_key: RateLimitQuota,
+    /// Buckets of named API keys
+    pub keys: HashMap<String, RateLimitQuota>,
+    /// Header a trusted proxy puts the client IP in, such as
+    /// `x-forwarded-for`; the connection's peer address when unset
+    pub client_ip_header: Option<String>,
+    /// Proxies in front of the API that append to `client_ip_header`; the
+    /// client IP is the entry the outermost of them added
+    pub trusted_proxies: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            per_ip: RateLimitQuota {
+                requests_per_second: 10,
+                burst: 20,
+            },
+            per_key: RateLimitQuota {
+                requests_per_second: 50,
+                burst: 100,
+            },
+            keys: HashMap::new(),
+            client_ip_header: None,
+            trusted_proxies: 1,
+        }
+    }
+}
+
+/// Refill rate and size of one token bucket
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RateLimitQuota {
+    pub requests_per_second: u32,
+    /// Requests allowed at once after a quiet period
+    pub burst: u32,
+}
+
+/// An API key row of the `api_keys` table
+#[derive(Debug, Clone)]
+pub struct StoredApiKey {
+    pub name: String,
+    /// SHA-256 of the key
+    pub key_sha256: Vec<u8>,
+    pub scopes: Vec<ApiScope>,
+}
+
+/// How queries behave while the startup snapshot is being ingested
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessPolicy {
+    /// Wait for catch-up, up to `block_timeout_ms`
+    Block,
+    /// Fail immediately with a "warming up" error
+    #[default]
+    Error,
+    /// Serve possibly partial data; callers flag responses via `is_ready`
+    Flag,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReadinessConfig {
+    pub policy: ReadinessPolicy,
+    pub block_timeout_ms: u64,
+}
+
+impl Default for ReadinessConfig {
+    fn default() -> Self {
+        Self {
+            policy: ReadinessPolicy::default(),
+            block_timeout_ms: 5000,
+        }
+    }
+}
+
+/// Periodic sampling of indexed vaults against on-chain data
+///
+/// Uses the `[backfill]` RPC endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsistencyConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Vaults sampled per check
+    pub sample_size: u32,
+    /// Delay before re-reading mismatches, to let in-flight updates land
+    pub recheck_delay_ms: u64,
+    /// Rewrite confirmed divergences from RPC and audit them in vault_repairs
+    pub repair: bool,
+}
+
+impl Default for ConsistencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 300,
+            sample_size: 100,
+            recheck_delay_ms: 2000,
+            repair: false,
+        }
+    }
+}
+
+/// Single-writer coordination for redundant instances sharing a database
+///
+/// Only the instance holding the advisory lock writes; the others stay
+/// connected as hot standbys and take over when the writer's session ends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoordinationConfig {
+    pub enabled: bool,
+    /// Advisory lock key; instances contending for one writer role must share it
+    pub lock_key: i64,
+    /// How often a standby retries the lock and the writer checks its session
+    pub check_interval_ms: u64,
+}
+
+impl Default for CoordinationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lock_key: 0x7661_756c_7461,
+            check_interval_ms: 2000,
+        }
+    }
+}
+
+/// Automatic creation of `account_updates` slot partitions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartitioningConfig {
+    /// Partitions kept created beyond the one holding the latest slot
+    pub partitions_ahead: u32,
+    pub check_interval_secs: u64,
+}
+
+impl Default for PartitioningConfig {
+    fn default() -> Self {
+        Self {
+            partitions_ahead: 2,
+            check_interval_secs: 300,
+        }
+    }
+}
+
+/// TimescaleDB storage for the append-only tables
+///
+/// `vault_state_history` and `account_updates` become hypertables chunked
+/// by epoch, replacing the native `account_updates` partitions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimescaleConfig {
+    pub enabled: bool,
+    /// Chunks ending this many epochs behind the newest slot get compressed
+    pub compress_after_epochs: u32,
+}
+
+impl Default for TimescaleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            compress_after_epochs: 2,
+        }
+    }
+}
+
+/// Scheduled pruning of old rows
+///
+/// A retention of 0 days keeps a table forever.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    pub account_updates_days: u32,
+    pub history_days: u32,
+    /// `vault_state_history` versions older than this move to
+    /// `vault_state_history_archive`; 0 keeps them all in the hot table
+    pub archive_history_days: u32,
+    /// Rows deleted per statement, to keep lock and WAL bursts short
+    pub batch_size: i64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 3600,
+            account_updates_days: 7,
+            history_days: 90,
+            archive_history_days: 0,
+            batch_size: 10_000,
+        }
+    }
+}
+
+/// Application-level encryption of sensitive text columns
+///
+/// The 32-byte key is given base64-encoded, in the variable named by
+/// `key_env` or the file at `key_file`, never in the config itself.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+    pub key_env: Option<String>,
+    pub key_file: Option<String>,
+    /// Columns stored encrypted
+    pub columns: Vec<EncryptedColumn>,
+}
+
+/// A text column that can be stored encrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptedColumn {
+    /// `vault_notes.note`
+    VaultNotes,
+    /// `saved_filters.description`
+    SavedFilterDescriptions,
+}
+
+/// Scheduled refresh of the aggregate materialized views
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AggregatesConfig {
+    pub enabled: bool,
+    pub refresh_interval_secs: u64,
+    /// How long `Indexer::get_total_value_locked` reuses its last result;
+    /// independent of `enabled`, since TVL is computed from the base tables
+    pub tvl_cache_secs: u64,
+}
+
+impl Default for AggregatesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval_secs: 60,
+            tvl_cache_secs: 30,
+        }
+    }
+}
+
+/// Polling of recorded changes into `Indexer::subscribe_events`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    pub enabled: bool,
+    pub poll_interval_ms: u64,
+    /// Events buffered per subscriber; slower subscribers skip ahead
+    pub channel_capacity: usize,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_interval_ms: 1000,
+            channel_capacity: 1024,
+        }
+    }
+}
+
+/// Historical backfill from RPC `getProgramAccounts`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackfillConfig {
+    /// Backfill in the background whenever the indexer starts
+    pub on_startup: bool,
+    pub rpc_url: String,
+    /// Data offset of the byte used to split the program into pages
+    pub prefix_offset: usize,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            on_startup: false,
+            rpc_url: "http://localhost:8899".to_string(),
+            prefix_offset: 0,
+        }
+    }
+}
+
+/// Bulk ingestion path for the startup snapshot
+///
+/// Backlog lane batches are staged with COPY and merged in one statement
+/// instead of upserted row by row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BulkStartupConfig {
+    pub enabled: bool,
+    /// Backlog lane batch size while bulk loading
+    pub batch_size: usize,
+}
+
+impl Default for BulkStartupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            batch_size: 50_000,
+        }
+    }
+}
+
+/// Cap on bytes held in shard channels and open batches
+///
+/// When exhausted, `process_update` blocks the Geyser thread for up to
+/// `max_block_ms` waiting for flushes, then drops the update. Async
+/// producers wait with `Indexer::wait_for_memory` instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryBudgetConfig {
+    pub enabled: bool,
+    pub max_bytes: u64,
+    pub max_block_ms: u64,
+}
+
+impl Default for MemoryBudgetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_bytes: 2 * 1024 * 1024 * 1024,
+            max_block_ms: 1000,
+        }
+    }
+}
+
+/// Circuit breaker around Postgres writes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    pub enabled: bool,
+    /// Consecutive write failures before the breaker opens
+    pub failure_threshold: u32,
+    /// How long the breaker stays open before a half-open probe
+    pub open_ms: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: 5,
+            open_ms: 5000,
+        }
+    }
+}
+
+/// Disk spool for batches that fail to write while Postgres is down
+///
+/// Spooled batches are replayed in order before any newer batch on the same
+/// shard; once `max_bytes` is reached further failures go to the DLQ.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalConfig {
+    pub enabled: bool,
+    /// Directory holding one log file per shard
+    pub dir: String,
+    /// Per-shard size cap
+    pub max_bytes: u64,
+}
+
+impl Default for WalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: "wal".to_string(),
+            max_bytes: 1024 * 1024 * 1024,
+        }
+    }
 }
 
 /// Plugin configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginConfig {
     pub vault_program_id: String,
+    #[serde(default)]
+    pub mode: IndexerMode,
     pub database: DatabaseConfig,
+    #[serde(default)]
+    pub store: StoreConfig,
     pub redis: RedisConfig,
     pub batch_size: usize,
     pub flush_interval_ms: u64,
+    /// Number of parallel batch workers; updates are sharded by pubkey
+    #[serde(default = "default_num_workers")]
+    pub num_workers: usize,
+    #[serde(default)]
+    pub adaptive_batching: AdaptiveBatchConfig,
+    #[serde(default)]
+    pub degradation: DegradationConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub bulk_startup: BulkStartupConfig,
+    #[serde(default)]
+    pub readiness: ReadinessConfig,
+    #[serde(default)]
+    pub cache_warmup: CacheWarmupConfig,
+    #[serde(default)]
+    pub local_cache: LocalCacheConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+    #[serde(default)]
+    pub yellowstone: YellowstoneConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub backfill: BackfillConfig,
+    #[serde(default)]
+    pub gap_detection: GapDetectionConfig,
+    #[serde(default)]
+    pub consistency: ConsistencyConfig,
+    #[serde(default)]
+    pub memory_budget: MemoryBudgetConfig,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    #[serde(default)]
+    pub wal: WalConfig,
+    #[serde(default)]
+    pub coordination: CoordinationConfig,
+    #[serde(default)]
+    pub partitioning: PartitioningConfig,
+    #[serde(default)]
+    pub timescale: TimescaleConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub aggregates: AggregatesConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
     pub enable_cache: bool,
     pub log_level: String,
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+}
+
+fn default_num_workers() -> usize {
+    4
 }
 
 impl Default for PluginConfig {
     fn default() -> Self {
         Self {
             vault_program_id: "Vault1111111111111111111111111111111111111111".to_string(),
+            mode: IndexerMode::default(),
             database: DatabaseConfig {
                 host: "localhost".to_string(),
                 port: 5432,
@@ -116,17 +1939,92 @@ impl Default for PluginConfig {
                 password: "postgres".to_string(),
                 max_connections: 10,
                 connection_timeout_seconds: 30,
+                run_migrations: default_run_migrations(),
+                schema_check: SchemaCheck::default(),
+                schema: default_schema(),
+                table_prefix: String::new(),
+                least_privilege: false,
+                min_connections: 0,
+                idle_timeout_seconds: default_idle_timeout_seconds(),
+                test_before_acquire: default_test_before_acquire(),
+                connect_attempts: default_connect_attempts(),
+                connect_max_backoff_ms: default_connect_max_backoff_ms(),
+                health_check_interval_secs: default_health_check_interval_secs(),
+                read_urls: Vec::new(),
+                max_replica_lag_ms: default_max_replica_lag_ms(),
+                pgbouncer: false,
+                acquire_timeout_ms: None,
+                statement_timeout_ms: 0,
+                lock_timeout_ms: 0,
+                slow_query_threshold_ms: default_slow_query_threshold_ms(),
+                notify_changes: false,
+                compress_account_data: false,
+                delta_account_data: false,
+                account_data_keyframe_interval: default_account_data_keyframe_interval(),
+                ssl_mode: None,
+                ssl_root_cert: None,
+                ssl_client_cert: None,
+                ssl_client_key: None,
             },
+            store: StoreConfig::default(),
             redis: RedisConfig {
                 url: "redis://localhost:6379".to_string(),
                 ttl_seconds: 300,
+                ttl_jitter_percent: default_ttl_jitter_percent(),
                 max_connections: 10,
                 connection_timeout_seconds: 5,
+                sentinel_urls: Vec::new(),
+                sentinel_master_name: None,
+                sentinel_poll_interval_ms: default_sentinel_poll_interval_ms(),
+                username: None,
+                password: None,
+                tls_ca_cert: None,
+                tls_client_cert: None,
+                tls_client_key: None,
+                tls_insecure: false,
+                key_prefix: default_redis_key_prefix(),
+                codec: CacheCodec::default(),
+                compress_above_bytes: None,
+                publish_changes: false,
+                stream_changes: false,
+                stream_max_len: default_stream_max_len(),
+                leaderboard: false,
+                pinned_vaults: Vec::new(),
+                hot_key_reads_per_minute: None,
+                hot_key_ttl_seconds: default_hot_key_ttl_seconds(),
+                field_hashes: false,
             },
             batch_size: 1000,
             flush_interval_ms: 100,
+            num_workers: default_num_workers(),
+            adaptive_batching: AdaptiveBatchConfig::default(),
+            degradation: DegradationConfig::default(),
+            retry: RetryConfig::default(),
+            bulk_startup: BulkStartupConfig::default(),
+            readiness: ReadinessConfig::default(),
+            cache_warmup: CacheWarmupConfig::default(),
+            local_cache: LocalCacheConfig::default(),
+            api: ApiConfig::default(),
+            grpc: GrpcConfig::default(),
+            yellowstone: YellowstoneConfig::default(),
+            auth: AuthConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            backfill: BackfillConfig::default(),
+            gap_detection: GapDetectionConfig::default(),
+            consistency: ConsistencyConfig::default(),
+            memory_budget: MemoryBudgetConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            wal: WalConfig::default(),
+            coordination: CoordinationConfig::default(),
+            partitioning: PartitioningConfig::default(),
+            timescale: TimescaleConfig::default(),
+            retention: RetentionConfig::default(),
+            aggregates: AggregatesConfig::default(),
+            events: EventsConfig::default(),
+            encryption: EncryptionConfig::default(),
             enable_cache: true,
             log_level: "info".to_string(),
+            replication: ReplicationConfig::default(),
         }
     }
 }
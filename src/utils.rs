@@ -32,3 +32,17 @@ pub fn format_bytes(bytes: usize) -> String {
     
     format!("{:.2} {}", size, UNITS[unit_index])
 }
+
+/// Map a pubkey to one of `num_shards` worker shards
+///
+/// Pubkeys are uniformly distributed, so the leading 8 bytes are a good
+/// enough hash for partitioning.
+pub fn shard_for(pubkey: &Pubkey, num_shards: usize) -> usize {
+    if num_shards <= 1 {
+        return 0;
+    }
+    
+    let bytes = pubkey.to_bytes();
+    let prefix = u64::from_le_bytes(bytes[0..8].try_into().expect("pubkey has 32 bytes"));
+    (prefix % num_shards as u64) as usize
+}
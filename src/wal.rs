@@ -0,0 +1,350 @@
+use crate::types::AccountUpdate;
+use anyhow::{Context, Result};
+use bincode::Options;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Start of every log file, ahead of its frames
+const MAGIC: &[u8; 4] = b"VWAL";
+
+/// Layout of the batches in a frame; bump it with every change to
+/// `AccountUpdate`, keeping a decoder for the layouts before. Version 1 is
+/// `AccountUpdate` with `txn_signature`.
+const FRAME_VERSION: u8 = 1;
+
+/// Frame header: format version, payload length (u32 LE) + first 4 bytes
+/// of its blake3 hash
+const HEADER_LEN: usize = 9;
+
+/// Frame header of logs written before frames were versioned, which
+/// starts at the payload length
+const UNVERSIONED_HEADER_LEN: usize = 8;
+
+/// Append-only spool of batches that could not be written to Postgres
+///
+/// Each shard owns one log file, so replaying it in order preserves the
+/// per-account ordering the shard guarantees. Frames are versioned,
+/// length-prefixed and checksummed. A torn or corrupt frame ends the
+/// readable log: it and everything after it are moved to the shard's
+/// `.rejected` file, and the log is cut back to the last intact frame so
+/// new frames follow it. Intact frames that can't be decoded are moved
+/// there too, so one bad batch never holds up the rest.
+pub struct WriteAheadLog {
+    path: PathBuf,
+    size: u64,
+    max_bytes: u64,
+}
+
+/// Frames found by reading a log
+struct Scan {
+    /// Decoded batches, in append order
+    batches: Vec<Vec<AccountUpdate>>,
+    /// Intact frames that failed to decode, header included
+    undecodable: Vec<Vec<u8>>,
+    /// End of the last intact frame
+    intact_len: u64,
+    file_len: u64,
+    versioned: bool,
+}
+
+/// `AccountUpdate` as spooled before it carried `txn_signature`, which
+/// only unversioned logs hold
+#[derive(Deserialize)]
+struct UnsignedAccountUpdate {
+    pubkey: Pubkey,
+    lamports: u64,
+    owner: Pubkey,
+    executable: bool,
+    rent_epoch: u64,
+    data: Vec<u8>,
+    write_version: u64,
+    slot: u64,
+    is_startup: bool,
+}
+
+impl From<UnsignedAccountUpdate> for AccountUpdate {
+    fn from(update: UnsignedAccountUpdate) -> Self {
+        Self {
+            pubkey: update.pubkey,
+            lamports: update.lamports,
+            owner: update.owner,
+            executable: update.executable,
+            rent_epoch: update.rent_epoch,
+            data: update.data,
+            write_version: update.write_version,
+            slot: update.slot,
+            is_startup: update.is_startup,
+            txn_signature: None,
+        }
+    }
+}
+
+impl WriteAheadLog {
+    /// Open (or create) the log for a shard under `dir`, recovering it to
+    /// its intact frames
+    pub fn open<P: AsRef<Path>>(dir: P, shard: usize, max_bytes: u64) -> Result<Self> {
+        fs::create_dir_all(dir.as_ref())
+            .with_context(|| format!("Failed to create WAL directory: {:?}", dir.as_ref()))?;
+        
+        let path = dir.as_ref().join(format!("shard-{}.wal", shard));
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let mut wal = Self {
+            path,
+            size,
+            max_bytes,
+        };
+        
+        if size > 0 {
+            info!("Found {} bytes of spooled updates in {:?}", size, wal.path);
+            wal.read_batches()?;
+        }
+        
+        Ok(wal)
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+    
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    
+    /// Append one batch as a single frame and fsync it
+    pub fn append(&mut self, updates: &[AccountUpdate]) -> Result<()> {
+        let payload = bincode::serialize(updates)?;
+        let start = if self.size == 0 { MAGIC.len() as u64 } else { self.size };
+        let frame_len = (HEADER_LEN + payload.len()) as u64;
+        
+        if start + frame_len > self.max_bytes {
+            anyhow::bail!(
+                "WAL {:?} is full ({} of {} bytes)",
+                self.path,
+                self.size,
+                self.max_bytes
+            );
+        }
+        
+        let mut file = if self.size == 0 {
+            let mut file = File::create(&self.path)?;
+            file.write_all(MAGIC)?;
+            file
+        } else {
+            OpenOptions::new().append(true).open(&self.path)?
+        };
+        Self::write_frame(&mut file, &payload)?;
+        file.sync_data()?;
+        
+        self.size = start + frame_len;
+        Ok(())
+    }
+    
+    /// Read all intact batches in append order
+    ///
+    /// Frames are checked on every read, so damage done after `open` is
+    /// moved aside here as well. Logs from before frames were versioned are
+    /// rewritten in the current format.
+    pub fn read_batches(&mut self) -> Result<Vec<Vec<AccountUpdate>>> {
+        let Some(scan) = self.scan()? else {
+            self.size = 0;
+            return Ok(Vec::new());
+        };
+        
+        if scan.intact_len < scan.file_len {
+            warn!(
+                "Moving {} bytes of torn or corrupt frames from {:?} to {:?}",
+                scan.file_len - scan.intact_len,
+                self.path,
+                self.rejected_path()
+            );
+            self.reject_from(scan.intact_len)?;
+        }
+        for frame in &scan.undecodable {
+            self.reject(frame)?;
+        }
+        
+        if scan.versioned && scan.undecodable.is_empty() {
+            if scan.intact_len < scan.file_len {
+                let file = OpenOptions::new().write(true).open(&self.path)?;
+                file.set_len(scan.intact_len)?;
+                file.sync_data()?;
+            }
+            // A log holding nothing but its magic holds no batches
+            if scan.batches.is_empty() {
+                return self.clear().map(|()| Vec::new());
+            }
+            self.size = scan.intact_len;
+        } else {
+            self.rewrite(&scan.batches)?;
+        }
+        
+        Ok(scan.batches)
+    }
+    
+    /// Replace the log contents with the given batches (after partial replay)
+    pub fn rewrite(&mut self, batches: &[Vec<AccountUpdate>]) -> Result<()> {
+        if batches.is_empty() {
+            return self.clear();
+        }
+        
+        let tmp_path = self.path.with_extension("wal.tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(MAGIC)?;
+        let mut size = MAGIC.len() as u64;
+        
+        for batch in batches {
+            let payload = bincode::serialize(batch)?;
+            Self::write_frame(&mut writer, &payload)?;
+            size += (HEADER_LEN + payload.len()) as u64;
+        }
+        
+        writer.into_inner()?.sync_data()?;
+        fs::rename(&tmp_path, &self.path)?;
+        
+        self.size = size;
+        Ok(())
+    }
+    
+    /// Remove all spooled batches
+    pub fn clear(&mut self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        
+        self.size = 0;
+        Ok(())
+    }
+    
+    /// Read every frame up to the first torn or corrupt one; `None` if
+    /// there is no log
+    fn scan(&self) -> Result<Option<Scan>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        
+        let mut magic = [0u8; MAGIC.len()];
+        let versioned = reader.read_exact(&mut magic).is_ok() && &magic == MAGIC;
+        let (header_len, mut offset) = if versioned {
+            (HEADER_LEN, MAGIC.len() as u64)
+        } else {
+            reader.seek(SeekFrom::Start(0))?;
+            (UNVERSIONED_HEADER_LEN, 0)
+        };
+        
+        let mut scan = Scan {
+            batches: Vec::new(),
+            undecodable: Vec::new(),
+            intact_len: offset,
+            file_len,
+            versioned,
+        };
+        let mut header = [0u8; HEADER_LEN];
+        
+        loop {
+            let header = &mut header[..header_len];
+            match reader.read_exact(header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            
+            let (version, fields) = if versioned {
+                (Some(header[0]), &header[1..])
+            } else {
+                (None, &header[..])
+            };
+            let len = u32::from_le_bytes(fields[0..4].try_into().expect("4-byte slice")) as u64;
+            
+            // A length running past the end of the file is a torn frame or
+            // a corrupt header; either way it mustn't size an allocation
+            let end = offset + header_len as u64 + len;
+            if end > file_len {
+                break;
+            }
+            let mut payload = vec![0u8; len as usize];
+            reader.read_exact(&mut payload)?;
+            
+            if blake3::hash(&payload).as_bytes()[0..4] != fields[4..8] {
+                break;
+            }
+            
+            match Self::decode(version, &payload) {
+                Ok(batch) => scan.batches.push(batch),
+                Err(e) => {
+                    warn!("Moving aside undecodable frame at byte {} of {:?}: {}", offset, self.path, e);
+                    scan.undecodable.push([&header[..], &payload].concat());
+                }
+            }
+            offset = end;
+            scan.intact_len = end;
+        }
+        
+        Ok(Some(scan))
+    }
+    
+    /// Batch of a frame written in format `version`, `None` for frames
+    /// from before frames were versioned
+    fn decode(version: Option<u8>, payload: &[u8]) -> Result<Vec<AccountUpdate>> {
+        match version {
+            Some(FRAME_VERSION) => Ok(bincode::deserialize(payload)?),
+            // Unversioned frames hold either layout, and only the one they
+            // were written in reads the payload to its exact end
+            None => match Self::exact().deserialize::<Vec<AccountUpdate>>(payload) {
+                Ok(batch) => Ok(batch),
+                Err(_) => {
+                    let batch: Vec<UnsignedAccountUpdate> = Self::exact().deserialize(payload)?;
+                    Ok(batch.into_iter().map(AccountUpdate::from).collect())
+                }
+            },
+            Some(version) => anyhow::bail!("unknown WAL frame version {}", version),
+        }
+    }
+    
+    /// `bincode::deserialize`, but refusing payloads with bytes left over
+    fn exact() -> impl Options {
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .reject_trailing_bytes()
+    }
+    
+    /// Where frames that can't be replayed are kept for inspection
+    fn rejected_path(&self) -> PathBuf {
+        self.path.with_extension("rejected")
+    }
+    
+    fn reject(&self, bytes: &[u8]) -> Result<()> {
+        let mut rejected = OpenOptions::new().create(true).append(true).open(self.rejected_path())?;
+        rejected.write_all(bytes)?;
+        rejected.sync_data()?;
+        Ok(())
+    }
+    
+    /// Move everything in the log from `offset` on to the rejected file
+    fn reject_from(&self, offset: u64) -> Result<()> {
+        let mut log = File::open(&self.path)?;
+        log.seek(SeekFrom::Start(offset))?;
+        let mut tail = Vec::new();
+        log.read_to_end(&mut tail)?;
+        self.reject(&tail)
+    }
+    
+    fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> Result<()> {
+        let len = u32::try_from(payload.len()).context("WAL frame too large")?;
+        
+        writer.write_all(&[FRAME_VERSION])?;
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(&blake3::hash(payload).as_bytes()[0..4])?;
+        writer.write_all(payload)?;
+        Ok(())
+    }
+}
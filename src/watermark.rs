@@ -0,0 +1,202 @@
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+/// How often a waiting read checks the watermark again
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+tokio::task_local! {
+    /// Set while a read must see everything flushed before it started
+    static READ_YOUR_WRITES: ();
+}
+
+/// Marks a shard doesn't hold
+const NO_SLOT: u64 = u64::MAX;
+
+/// Returned by `min_slot` reads the index can't answer yet
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+pub enum SlotNotFlushed {
+    #[error("slot {0} isn't indexed yet; retry shortly")]
+    Pending(u64),
+    #[error("slot {0} is held back by failed writes awaiting replay")]
+    Failed(u64),
+    #[error("min_slot is only served by the instance writing the index")]
+    NotWriter,
+}
+
+/// Progress of one lane of a shard
+#[derive(Default)]
+struct LaneMark {
+    /// Updates sent to the lane
+    queued: AtomicU64,
+    /// Updates the lane has flushed or given up on
+    done: AtomicU64,
+    /// Highest slot the lane has flushed
+    flushed_slot: AtomicU64,
+}
+
+/// Progress of one shard
+struct ShardMark {
+    live: LaneMark,
+    startup: LaneMark,
+    /// Oldest slot spooled to the WAL, until it drains
+    spooled_slot: AtomicU64,
+    /// Oldest slot dead-lettered after a failed write, until dead letters
+    /// are replayed
+    dead_lettered_slot: AtomicU64,
+}
+
+impl ShardMark {
+    fn lane(&self, startup: bool) -> &LaneMark {
+        if startup {
+            &self.startup
+        } else {
+            &self.live
+        }
+    }
+    
+    /// Oldest slot the shard failed to write, if any
+    fn failed_slot(&self) -> Option<u64> {
+        let slot = self
+            .spooled_slot
+            .load(Ordering::SeqCst)
+            .min(self.dead_lettered_slot.load(Ordering::SeqCst));
+        (slot != NO_SLOT).then_some(slot)
+    }
+}
+
+/// Highest slot whose updates are all flushed
+///
+/// A lane with nothing queued holds back no slot the indexer has seen; a
+/// busy one holds back everything after the newest slot it flushed. Slots
+/// are seen in the order Geyser delivers them, so an update for a slot the
+/// indexer has moved past is already queued. A shard that failed to write
+/// a batch holds back that batch's slots and everything after them while
+/// the batch sits in the WAL, or once dead-lettered, until dead letters are
+/// replayed.
+pub struct FlushWatermark {
+    /// Highest slot of any account update routed so far, vault or not
+    seen_slot: AtomicU64,
+    shards: Vec<ShardMark>,
+}
+
+impl FlushWatermark {
+    pub fn new(shards: usize) -> Self {
+        Self {
+            seen_slot: AtomicU64::new(0),
+            shards: (0..shards)
+                .map(|_| ShardMark {
+                    live: LaneMark::default(),
+                    startup: LaneMark::default(),
+                    spooled_slot: AtomicU64::new(NO_SLOT),
+                    dead_lettered_slot: AtomicU64::new(NO_SLOT),
+                })
+                .collect(),
+        }
+    }
+    
+    /// Note an account update once it has been routed without error,
+    /// queued or not
+    pub fn observe(&self, slot: u64) {
+        self.seen_slot.fetch_max(slot, Ordering::SeqCst);
+    }
+    
+    /// Count an update before it is sent to a lane of `shard`
+    pub fn record_queued(&self, shard: usize, startup: bool) {
+        self.shards[shard].lane(startup).queued.fetch_add(1, Ordering::SeqCst);
+    }
+    
+    /// Take back an update `shard` never received
+    pub fn record_unqueued(&self, shard: usize, startup: bool) {
+        self.shards[shard].lane(startup).queued.fetch_sub(1, Ordering::SeqCst);
+    }
+    
+    /// Count `updates` that a lane of `shard` is done with; `flushed_slot`
+    /// is the newest slot written, if they were written
+    pub fn record_done(&self, shard: usize, startup: bool, updates: usize, flushed_slot: Option<u64>) {
+        let lane = self.shards[shard].lane(startup);
+        if let Some(slot) = flushed_slot {
+            lane.flushed_slot.fetch_max(slot, Ordering::SeqCst);
+        }
+        lane.done.fetch_add(updates as u64, Ordering::SeqCst);
+    }
+    
+    /// Hold `shard` back from `slot` on while it has batches in the WAL
+    pub fn record_spooled(&self, shard: usize, slot: u64) {
+        self.shards[shard].spooled_slot.fetch_min(slot, Ordering::SeqCst);
+    }
+    
+    /// Release the hold of the WAL of `shard`, once it has drained
+    pub fn record_spool_drained(&self, shard: usize) {
+        self.shards[shard].spooled_slot.store(NO_SLOT, Ordering::SeqCst);
+    }
+    
+    /// Hold `shard` back from `slot` on until dead letters are replayed
+    pub fn record_dead_lettered(&self, shard: usize, slot: u64) {
+        self.shards[shard].dead_lettered_slot.fetch_min(slot, Ordering::SeqCst);
+    }
+    
+    /// Release every dead-letter hold, once the letters are written
+    pub fn record_dead_letters_replayed(&self) {
+        for mark in &self.shards {
+            mark.dead_lettered_slot.store(NO_SLOT, Ordering::SeqCst);
+        }
+    }
+    
+    /// Whether every update up to `slot` is flushed
+    pub fn reached(&self, slot: u64) -> bool {
+        // Read before the shards, so whatever it covers is counted below
+        let seen = self.seen_slot.load(Ordering::SeqCst);
+        self.shards.iter().all(|mark| {
+            if mark.failed_slot().is_some_and(|failed| failed <= slot) {
+                return false;
+            }
+            [&mark.live, &mark.startup].into_iter().all(|lane| {
+                // `done` first: an update queued in between only reads as busy
+                let done = lane.done.load(Ordering::SeqCst);
+                if done >= lane.queued.load(Ordering::SeqCst) {
+                    seen >= slot
+                } else {
+                    lane.flushed_slot.load(Ordering::SeqCst) >= slot
+                }
+            })
+        })
+    }
+    
+    /// Wait up to `timeout` for `slot` to be flushed
+    pub async fn wait_for(&self, slot: u64, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        while !self.reached(slot) {
+            if Instant::now() >= deadline {
+                let failed = self
+                    .shards
+                    .iter()
+                    .any(|mark| mark.failed_slot().is_some_and(|failed| failed <= slot));
+                let reason = if failed { "failed" } else { "pending" };
+                metrics::counter!("indexer_min_slot_timeouts_total", "reason" => reason).increment(1);
+                
+                return Err(if failed {
+                    SlotNotFlushed::Failed(slot)
+                } else {
+                    SlotNotFlushed::Pending(slot)
+                }
+                .into());
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+        Ok(())
+    }
+}
+
+/// Run `read` past everything that may trail a flush: the local cache,
+/// reads already in flight and read replicas
+pub async fn read_your_writes<F: Future>(read: F) -> F::Output {
+    READ_YOUR_WRITES.scope((), read).await
+}
+
+/// Whether the current read runs under `read_your_writes`
+pub fn reads_own_writes() -> bool {
+    READ_YOUR_WRITES.try_with(|_| ()).is_ok()
+}
@@ -0,0 +1,116 @@
+use crate::events::{self, EventFilter};
+use crate::indexer::Indexer;
+use crate::projection::render_event;
+use crate::types::VaultEvent;
+use anyhow::Result;
+use axum::extract::ws::{Message, WebSocket};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+/// How long a client has to send its subscription after connecting
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// First message of a WebSocket subscription
+#[derive(Debug, Deserialize)]
+struct Subscribe {
+    #[serde(default)]
+    vault_addresses: Vec<String>,
+    #[serde(default)]
+    owners: Vec<String>,
+    /// Replay the current state of matching vaults written after this
+    /// slot before live updates
+    from_slot: Option<u64>,
+}
+
+/// Serve one WebSocket subscription until the client disconnects
+///
+/// The client sends `{"vault_addresses": [...], "owners": [...]}` first,
+/// optionally with `from_slot`. The server then sends each matching event
+/// as a JSON message: `{"type": "update", "vault": {...}}` for written
+/// states and `{"type": "owner_changed", ...}` for owner changes. With
+/// `from_slot`, the current state of every matching vault written after
+/// it is sent first, followed by `{"type": "replayed", "vaults": n}`.
+/// Live events are subscribed to before the replay reads, so nothing is
+/// missed in between, but a vault may arrive twice; clients keep the
+/// highest `(slot, write_version)`.
+pub(crate) async fn serve(mut socket: WebSocket, indexer: Arc<Indexer>) {
+    let (filter, from_slot) = match read_subscription(&mut socket).await {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            let _ = send(&mut socket, json!({ "type": "error", "error": e.to_string() })).await;
+            return;
+        }
+    };
+    let mut events = indexer.subscribe_events();
+    metrics::gauge!("indexer_ws_subscriptions").increment(1.0);
+    
+    if let Err(e) = run(&mut socket, &indexer, &filter, from_slot, &mut events).await {
+        debug!("WebSocket subscription ended: {}", e);
+    }
+    metrics::gauge!("indexer_ws_subscriptions").decrement(1.0);
+}
+
+async fn run(
+    socket: &mut WebSocket,
+    indexer: &Indexer,
+    filter: &EventFilter,
+    from_slot: Option<u64>,
+    events: &mut broadcast::Receiver<VaultEvent>,
+) -> Result<()> {
+    if let Some(from_slot) = from_slot {
+        let replayed = replay(socket, indexer, filter, from_slot).await?;
+        send(socket, json!({ "type": "replayed", "vaults": replayed })).await?;
+    }
+    
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if filter.matches(&event) => send(socket, render_event(&event)).await?,
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    // The client should reconnect with `from_slot` to catch up
+                    send(socket, json!({ "type": "lagged", "skipped": skipped })).await?;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+            },
+        }
+    }
+}
+
+async fn read_subscription(socket: &mut WebSocket) -> Result<(EventFilter, Option<u64>)> {
+    let message = tokio::time::timeout(SUBSCRIBE_TIMEOUT, socket.recv())
+        .await
+        .map_err(|_| anyhow::anyhow!("No subscription received within {:?}", SUBSCRIBE_TIMEOUT))?;
+    let text = match message {
+        Some(Ok(Message::Text(text))) => text,
+        _ => anyhow::bail!("Expected a JSON subscription message"),
+    };
+    
+    let subscribe: Subscribe = serde_json::from_str(&text)?;
+    let filter = EventFilter::for_subscription(subscribe.vault_addresses, subscribe.owners)?;
+    Ok((filter, subscribe.from_slot))
+}
+
+/// Send the current state of subscribed vaults written after `from_slot`
+async fn replay(socket: &mut WebSocket, indexer: &Indexer, filter: &EventFilter, from_slot: u64) -> Result<usize> {
+    let states = events::states_since(indexer, filter, from_slot).await?;
+    let replayed = states.len();
+    for state in states {
+        send(socket, render_event(&VaultEvent::Updated(state))).await?;
+    }
+    Ok(replayed)
+}
+
+async fn send(socket: &mut WebSocket, message: Value) -> Result<()> {
+    socket.send(Message::Text(message.to_string())).await?;
+    Ok(())
+}
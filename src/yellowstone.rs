@@ -0,0 +1,429 @@
+use crate::events::MAX_SUBSCRIPTION_KEYS;
+use crate::indexer::Indexer;
+use crate::rate_limit;
+use crate::types::{AccountUpdate, ApiScope, YellowstoneConfig};
+use crate::utils::parse_pubkey;
+use anyhow::{Context, Result};
+use base58::FromBase58;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{debug, error, info};
+
+/// Types and service generated from `proto/geyser/geyser.proto`
+pub mod proto {
+    tonic::include_proto!("geyser");
+}
+
+use proto::geyser_server::{Geyser, GeyserServer};
+use proto::subscribe_request_filter_accounts_filter::Filter as AccountsFilter;
+use proto::subscribe_request_filter_accounts_filter_lamports::Cmp as LamportsCmp;
+use proto::subscribe_request_filter_accounts_filter_memcmp::Data as MemcmpData;
+use proto::subscribe_update::UpdateOneof;
+
+/// Updates queued per subscription while the client reads slowly
+const SUBSCRIPTION_BUFFER: usize = 256;
+
+/// How often idle subscriptions are pinged, as Yellowstone does, so
+/// proxies and load balancers keep them open
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Yellowstone gRPC endpoint re-publishing the vault updates this plugin
+/// receives
+///
+/// Speaks the subset of the Yellowstone `geyser.Geyser` service in
+/// `proto/geyser/geyser.proto`: account subscriptions with the upstream
+/// filters, pings, `GetSlot` and `GetVersion`. Updates are streamed at
+/// processed commitment as they arrive, before they are written.
+/// Subscriptions for slots, transactions, blocks or entries, other
+/// commitments and `from_slot` replay are refused, and a subscriber that
+/// falls `channel_capacity` updates behind is disconnected.
+pub struct YellowstoneServer {
+    config: YellowstoneConfig,
+    indexer: Arc<Indexer>,
+}
+
+struct GeyserService {
+    indexer: Arc<Indexer>,
+}
+
+impl YellowstoneServer {
+    pub fn new(config: &YellowstoneConfig, indexer: Arc<Indexer>) -> Self {
+        Self {
+            config: config.clone(),
+            indexer,
+        }
+    }
+    
+    /// Serve until the process exits; a failure to bind is logged
+    pub async fn run(self) {
+        if let Err(e) = self.serve().await {
+            error!("Yellowstone server on {} stopped: {:#}", self.config.bind_address, e);
+        }
+    }
+    
+    async fn serve(&self) -> Result<()> {
+        let address: SocketAddr = self
+            .config
+            .bind_address
+            .parse()
+            .with_context(|| format!("Invalid [yellowstone] bind_address {}", self.config.bind_address))?;
+        let service = GeyserService {
+            indexer: self.indexer.clone(),
+        };
+        
+        info!("Yellowstone gRPC server listening on {}", address);
+        tonic::transport::Server::builder()
+            .add_service(GeyserServer::new(service))
+            .serve(address)
+            .await?;
+        Ok(())
+    }
+}
+
+impl GeyserService {
+    /// Every call streams or describes the update stream, so all need the
+    /// `stream` scope with `[auth]` on; `[rate_limit]` applies as to the
+    /// query service
+    fn admit<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        rate_limit::admit_grpc(
+            self.indexer.api_keys().as_deref(),
+            self.indexer.rate_limits().as_deref(),
+            request,
+            ApiScope::Stream,
+        )
+    }
+}
+
+#[tonic::async_trait]
+impl Geyser for GeyserService {
+    type SubscribeStream = ReceiverStream<Result<proto::SubscribeUpdate, Status>>;
+    
+    async fn subscribe(
+        &self,
+        request: Request<Streaming<proto::SubscribeRequest>>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        self.admit(&request)?;
+        let updates = self
+            .indexer
+            .subscribe_account_updates()
+            .ok_or_else(|| Status::unavailable("Account updates are not being published"))?;
+        let (tx, rx) = mpsc::channel(SUBSCRIPTION_BUFFER);
+        let vault_program_id = *self.indexer.vault_program_id();
+        let requests = request.into_inner();
+        tokio::spawn(async move {
+            metrics::gauge!("indexer_yellowstone_subscriptions").increment(1.0);
+            forward_updates(vault_program_id, requests, updates, tx).await;
+            metrics::gauge!("indexer_yellowstone_subscriptions").decrement(1.0);
+        });
+        
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+    
+    async fn ping(&self, request: Request<proto::PingRequest>) -> Result<Response<proto::PongResponse>, Status> {
+        self.admit(&request)?;
+        Ok(Response::new(proto::PongResponse {
+            count: request.into_inner().count,
+        }))
+    }
+    
+    async fn get_slot(
+        &self,
+        request: Request<proto::GetSlotRequest>,
+    ) -> Result<Response<proto::GetSlotResponse>, Status> {
+        self.admit(&request)?;
+        Ok(Response::new(proto::GetSlotResponse {
+            slot: self.indexer.get_stats().last_indexed_slot,
+        }))
+    }
+    
+    async fn get_version(
+        &self,
+        request: Request<proto::GetVersionRequest>,
+    ) -> Result<Response<proto::GetVersionResponse>, Status> {
+        self.admit(&request)?;
+        let version = serde_json::json!({
+            "package": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        Ok(Response::new(proto::GetVersionResponse {
+            version: version.to_string(),
+        }))
+    }
+}
+
+/// Stream a subscriber's matching updates until it goes away
+///
+/// Each request on the client stream replaces the filters, except one that
+/// only carries a ping, which is answered with a pong.
+async fn forward_updates(
+    vault_program_id: Pubkey,
+    mut requests: Streaming<proto::SubscribeRequest>,
+    mut updates: broadcast::Receiver<Arc<AccountUpdate>>,
+    tx: mpsc::Sender<Result<proto::SubscribeUpdate, Status>>,
+) {
+    let mut subscription = Subscription::default();
+    let mut ping = tokio::time::interval_at(tokio::time::Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    
+    loop {
+        let message = tokio::select! {
+            request = requests.message() => match request {
+                Ok(Some(request)) => {
+                    let pong = request.ping.as_ref().map(|ping| ping.id);
+                    if !is_ping_only(&request) {
+                        match Subscription::parse(&request, vault_program_id) {
+                            Ok(parsed) => subscription = parsed,
+                            Err(status) => {
+                                let _ = tx.send(Err(status)).await;
+                                return;
+                            }
+                        }
+                    }
+                    match pong {
+                        Some(id) => update(Vec::new(), UpdateOneof::Pong(proto::SubscribeUpdatePong { id })),
+                        None => continue,
+                    }
+                }
+                Ok(None) => return,
+                Err(e) => {
+                    debug!("Yellowstone subscriber stream ended: {}", e);
+                    return;
+                }
+            },
+            received = updates.recv() => match received {
+                Ok(account) => match subscription.matching(&account) {
+                    Some(names) => update(names, UpdateOneof::Account(subscription.render(&account))),
+                    None => continue,
+                },
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    metrics::counter!("indexer_yellowstone_updates_skipped_total").increment(skipped);
+                    let _ = tx
+                        .send(Err(Status::resource_exhausted(format!(
+                            "Subscriber fell {} updates behind",
+                            skipped
+                        ))))
+                        .await;
+                    return;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = ping.tick() => update(Vec::new(), UpdateOneof::Ping(proto::SubscribeUpdatePing {})),
+            _ = tx.closed() => return,
+        };
+        
+        if tx.send(Ok(message)).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn update(filters: Vec<String>, update: UpdateOneof) -> proto::SubscribeUpdate {
+    proto::SubscribeUpdate {
+        filters,
+        update_oneof: Some(update),
+    }
+}
+
+fn is_ping_only(request: &proto::SubscribeRequest) -> bool {
+    request.ping.is_some()
+        && request.accounts.is_empty()
+        && request.accounts_data_slice.is_empty()
+        && request.commitment.is_none()
+}
+
+/// A subscriber's named account filters and data slices
+#[derive(Default)]
+struct Subscription {
+    filters: HashMap<String, AccountFilter>,
+    data_slices: Vec<(usize, usize)>,
+    vault_program_id: Pubkey,
+}
+
+/// One named account filter; every set criterion must match
+struct AccountFilter {
+    accounts: HashSet<Pubkey>,
+    owners: HashSet<Pubkey>,
+    memcmp: Vec<(usize, Vec<u8>)>,
+    datasize: Option<u64>,
+    lamports: Vec<LamportsCmp>,
+    nonempty_txn_signature: Option<bool>,
+}
+
+impl Subscription {
+    fn parse(request: &proto::SubscribeRequest, vault_program_id: Pubkey) -> Result<Self, Status> {
+        if !request.slots.is_empty()
+            || !request.transactions.is_empty()
+            || !request.transactions_status.is_empty()
+            || !request.blocks.is_empty()
+            || !request.blocks_meta.is_empty()
+            || !request.entry.is_empty()
+        {
+            return Err(Status::invalid_argument("Only account subscriptions are served"));
+        }
+        if request.commitment.unwrap_or_default() != proto::CommitmentLevel::Processed as i32 {
+            return Err(Status::invalid_argument("Only processed commitment is served"));
+        }
+        if request.from_slot.is_some() {
+            return Err(Status::invalid_argument("from_slot replay is not supported"));
+        }
+        
+        let keys: usize = request
+            .accounts
+            .values()
+            .map(|filter| filter.account.len() + filter.owner.len())
+            .sum();
+        if keys > MAX_SUBSCRIPTION_KEYS {
+            return Err(Status::invalid_argument(format!(
+                "A subscription may list at most {} accounts and owners",
+                MAX_SUBSCRIPTION_KEYS
+            )));
+        }
+        
+        let filters = request
+            .accounts
+            .iter()
+            .map(|(name, filter)| Ok((name.clone(), AccountFilter::parse(filter)?)))
+            .collect::<Result<_, Status>>()?;
+        let data_slices = request
+            .accounts_data_slice
+            .iter()
+            .map(|slice| (slice.offset as usize, slice.length as usize))
+            .collect();
+        
+        Ok(Self {
+            filters,
+            data_slices,
+            vault_program_id,
+        })
+    }
+    
+    /// Names of the filters `account` matches; `None` if it matches none
+    fn matching(&self, account: &AccountUpdate) -> Option<Vec<String>> {
+        let names: Vec<String> = self
+            .filters
+            .iter()
+            .filter(|(_, filter)| filter.matches(account, &self.vault_program_id))
+            .map(|(name, _)| name.clone())
+            .collect();
+        (!names.is_empty()).then_some(names)
+    }
+    
+    fn render(&self, account: &AccountUpdate) -> proto::SubscribeUpdateAccount {
+        let data = if self.data_slices.is_empty() {
+            account.data.clone()
+        } else {
+            // Slices are concatenated, as Yellowstone does
+            self.data_slices
+                .iter()
+                .flat_map(|&(offset, length)| {
+                    let start = offset.min(account.data.len());
+                    let end = start.saturating_add(length).min(account.data.len());
+                    account.data[start..end].iter().copied()
+                })
+                .collect()
+        };
+        
+        proto::SubscribeUpdateAccount {
+            account: Some(proto::SubscribeUpdateAccountInfo {
+                pubkey: account.pubkey.to_bytes().to_vec(),
+                lamports: account.lamports,
+                owner: account.owner.to_bytes().to_vec(),
+                executable: account.executable,
+                rent_epoch: account.rent_epoch,
+                data,
+                write_version: account.write_version,
+                txn_signature: account.txn_signature.map(|signature| signature.as_ref().to_vec()),
+            }),
+            slot: account.slot,
+            is_startup: account.is_startup,
+        }
+    }
+}
+
+impl AccountFilter {
+    fn parse(filter: &proto::SubscribeRequestFilterAccounts) -> Result<Self, Status> {
+        let pubkeys = |keys: &[String]| {
+            keys.iter()
+                .map(|key| parse_pubkey(key).map_err(|e| Status::invalid_argument(e.to_string())))
+                .collect::<Result<HashSet<_>, _>>()
+        };
+        
+        let mut parsed = Self {
+            accounts: pubkeys(&filter.account)?,
+            owners: pubkeys(&filter.owner)?,
+            memcmp: Vec::new(),
+            datasize: None,
+            lamports: Vec::new(),
+            nonempty_txn_signature: filter.nonempty_txn_signature,
+        };
+        for data_filter in filter.filters.iter().filter_map(|data_filter| data_filter.filter.as_ref()) {
+            match data_filter {
+                AccountsFilter::Memcmp(memcmp) => {
+                    let bytes = match &memcmp.data {
+                        Some(MemcmpData::Bytes(bytes)) => bytes.clone(),
+                        Some(MemcmpData::Base58(encoded)) => encoded
+                            .from_base58()
+                            .map_err(|_| Status::invalid_argument("memcmp data is not base58"))?,
+                        Some(MemcmpData::Base64(encoded)) => BASE64
+                            .decode(encoded)
+                            .map_err(|_| Status::invalid_argument("memcmp data is not base64"))?,
+                        None => return Err(Status::invalid_argument("memcmp filter without data")),
+                    };
+                    parsed.memcmp.push((memcmp.offset as usize, bytes));
+                }
+                AccountsFilter::Datasize(size) => parsed.datasize = Some(*size),
+                AccountsFilter::Lamports(lamports) => {
+                    parsed.lamports.extend(lamports.cmp.clone());
+                }
+                AccountsFilter::TokenAccountState(_) => {
+                    return Err(Status::invalid_argument("token_account_state filters are not supported"));
+                }
+            }
+        }
+        
+        Ok(parsed)
+    }
+    
+    /// A filter listing no accounts or owners matches every vault account,
+    /// but not other programs' closing writes
+    fn matches(&self, account: &AccountUpdate, vault_program_id: &Pubkey) -> bool {
+        if self.accounts.is_empty() && self.owners.is_empty() && account.owner != *vault_program_id {
+            return false;
+        }
+        if !self.accounts.is_empty() && !self.accounts.contains(&account.pubkey) {
+            return false;
+        }
+        if !self.owners.is_empty() && !self.owners.contains(&account.owner) {
+            return false;
+        }
+        if let Some(required) = self.nonempty_txn_signature {
+            if account.txn_signature.is_some() != required {
+                return false;
+            }
+        }
+        if self.datasize.is_some_and(|size| account.data.len() as u64 != size) {
+            return false;
+        }
+        
+        let memcmp_matches = self.memcmp.iter().all(|(offset, bytes)| {
+            account
+                .data
+                .get(*offset..)
+                .is_some_and(|rest| rest.starts_with(bytes))
+        });
+        let lamports_match = self.lamports.iter().all(|cmp| match cmp {
+            LamportsCmp::Eq(value) => account.lamports == *value,
+            LamportsCmp::Ne(value) => account.lamports != *value,
+            LamportsCmp::Lt(value) => account.lamports < *value,
+            LamportsCmp::Gt(value) => account.lamports > *value,
+        });
+        memcmp_matches && lamports_match
+    }
+}